@@ -14,6 +14,7 @@ use futures_core::stream::Stream;
 
 use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
 use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
 use serde_json;
 use serde_json::{Map, Value};
 use std::collections::HashMap;
@@ -23,6 +24,7 @@ use crate::types::req_params::*;
 use crate::types::responses::*;
 use crate::types::types::*;
 
+use super::raw::RawIndex;
 use super::trait_param_meta::*;
 
 use chrono::{DateTime, Utc};
@@ -42,6 +44,8 @@ pub struct StripeClient {
     // Arc Mutex so that this client can be cloned but a single Stripe account for a process logs stats in a central place.
     // - Also allows external observation of current 429 status (different thread or async tasks).
     pub stats: Arc<RwLock<Stats>>,
+    // When set, the JSON of each response is kept (see `RawIndex`).
+    pub raw: Option<RawIndex>,
 }
 
 // Loosely means "Stripe dataset".
@@ -199,6 +203,19 @@ impl StripeClient {
                 running: 0,
                 req_log: vec![],
             })),
+            raw: None,
+        }
+    }
+
+    // Parses a 200 response, and keeps its JSON when `raw` is set.
+    async fn json<T: DeserializeOwned>(&self, res: Response) -> Result<T, UniErr> {
+        match &self.raw {
+            None => Ok(res.json::<T>().await?),
+            Some(raw) => {
+                let x = res.json::<Value>().await?;
+                raw.add(&x);
+                serde_json::from_value(x).map_err(UniErr::Json)
+            }
         }
     }
 
@@ -254,8 +271,8 @@ impl StripeClient {
     // - Assumptions:
     //      - Create a new client for each new Stripe secret key (do not mutate the secret key as it will not longer match this account).
     //      - Account will only need to be read once per process start up as it changes slowly/just the ID is needed.
-    pub async fn get_account_set_cache(&mut self) {
-        let (id, account) = self.get_account().await.unwrap();
+    pub async fn get_account_set_cache(&mut self) -> Result<(), UniErr> {
+        let (id, account) = self.get_account().await?;
 
         self.stripe_account = Some(StripeAccount {
             id,
@@ -263,6 +280,8 @@ impl StripeClient {
             is_test: self.config.is_test,
             account,
         });
+
+        Ok(())
     }
 
     fn set_headers(
@@ -355,7 +374,7 @@ impl StripeClient {
         //      - Assumes read-only requests.
         //
         // Note:
-        // - All will kill the process and assume no resolution after max retries.
+        // - All return the last failed response after max retries (assume no resolution); the caller maps it to an `Err`.
         // - Any combination of the above can occur in on request retry loop.
         // - @todo/low Only HTTP 200 is a success, other non-200 success codes not handled (E.g. a write would be 201, a cached response 304).
         loop {
@@ -375,7 +394,9 @@ impl StripeClient {
                     }
 
                     if net_err_count == 6 {
-                        panic!("Network issues. Retried request {} times.", net_err_count);
+                        warn!("Network issues. Retried request {} times, assuming no resolution.", net_err_count);
+                        resolved_429(self.stats.write().await);
+                        return a;
                     }
 
                     // a = get_req().send().await;
@@ -389,12 +410,12 @@ impl StripeClient {
                     match x {
                         StatusCode::TOO_MANY_REQUESTS => {
                             seen_429(self.stats.write().await);
+                            rate_429_err_count += 1;
 
                             if rate_429_err_count > 20 {
-                                panic!(
-                                    "Retried 429 HTTP error {} times, assuming no resolution.",
-                                    rate_429_err_count
-                                );
+                                warn!("Retried 429 HTTP error {} times, assuming no resolution.", rate_429_err_count - 1);
+                                resolved_429(self.stats.write().await);
+                                return a;
                             }
 
                             // Assumption: There can be 2-100 requests being awaited to the same central Stripe account (rate limits applied per Stripe account).
@@ -421,10 +442,9 @@ impl StripeClient {
                             non_429_err_count += 1;
 
                             if non_429_err_count > 2 {
-                                panic!(
-                                    "Retried non-429 HTTP error {} times, assuming no resolution.",
-                                    non_429_err_count
-                                );
+                                warn!("Retried non-429 HTTP error {} times, assuming no resolution.", non_429_err_count - 1);
+                                resolved_429(self.stats.write().await);
+                                return a;
                             }
 
                             delay_for(Duration::from_millis(2000)).await;
@@ -502,6 +522,8 @@ impl StripeClient {
 pub enum UniErr {
     Net(reqwest::Error),
     App(Error),
+    // A 200 response that does not match its type (only when `StripeClient.raw` is set; otherwise reqwest returns this as `Net`).
+    Json(serde_json::Error),
 }
 
 // Implement Error trait so it can be used with Rusts error handling.
@@ -510,6 +532,7 @@ impl std::error::Error for UniErr {
         match *self {
             UniErr::Net(ref netErr) => Some(netErr),
             UniErr::App(_) => None,
+            UniErr::Json(ref x) => Some(x),
         }
     }
 }
@@ -519,6 +542,7 @@ impl std::fmt::Display for UniErr {
         match *self {
             UniErr::Net(ref netErr) => netErr.fmt(f),
             UniErr::App(_) => write!(f, "Error object returned from server"),
+            UniErr::Json(ref x) => x.fmt(f),
         }
     }
 }
@@ -584,7 +608,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<ThreeDSecure>().await?;
+                let x = self.json::<ThreeDSecure>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -611,7 +635,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Account>().await?;
+                let x = self.json::<Account>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -645,7 +669,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<UniPolymorphic70BAFA>().await?;
+                let x = self.json::<UniPolymorphic70BAFA>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -675,7 +699,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<ListAccountCapability>().await?;
+                let x = self.json::<ListAccountCapability>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -709,7 +733,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<AccountCapability>().await?;
+                let x = self.json::<AccountCapability>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -742,7 +766,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<ExternalAccountListADE54B>().await?;
+                let x = self.json::<ExternalAccountListADE54B>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -776,7 +800,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<UniPolymorphic70BAFA>().await?;
+                let x = self.json::<UniPolymorphic70BAFA>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -806,7 +830,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetAccountPeopleRes>().await?;
+                let x = self.json::<GetAccountPeopleRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -861,7 +885,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Person>().await?;
+                let x = self.json::<Person>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -891,7 +915,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetAccountPeopleRes>().await?;
+                let x = self.json::<GetAccountPeopleRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -946,7 +970,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Person>().await?;
+                let x = self.json::<Person>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -976,7 +1000,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetAccountsRes>().await?;
+                let x = self.json::<GetAccountsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1028,7 +1052,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Account>().await?;
+                let x = self.json::<Account>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1063,7 +1087,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<UniPolymorphic70BAFA>().await?;
+                let x = self.json::<UniPolymorphic70BAFA>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1097,7 +1121,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<ListAccountCapability>().await?;
+                let x = self.json::<ListAccountCapability>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1132,7 +1156,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<AccountCapability>().await?;
+                let x = self.json::<AccountCapability>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1166,7 +1190,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<ExternalAccountListADE54B>().await?;
+                let x = self.json::<ExternalAccountListADE54B>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1201,7 +1225,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<UniPolymorphic70BAFA>().await?;
+                let x = self.json::<UniPolymorphic70BAFA>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1235,7 +1259,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetAccountPeopleRes>().await?;
+                let x = self.json::<GetAccountPeopleRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1292,7 +1316,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Person>().await?;
+                let x = self.json::<Person>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1326,7 +1350,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetAccountPeopleRes>().await?;
+                let x = self.json::<GetAccountPeopleRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1383,7 +1407,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Person>().await?;
+                let x = self.json::<Person>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1413,7 +1437,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<ApplePayDomainList>().await?;
+                let x = self.json::<ApplePayDomainList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1468,7 +1492,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<ApplePayDomain>().await?;
+                let x = self.json::<ApplePayDomain>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1498,7 +1522,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetApplicationFeesRes>().await?;
+                let x = self.json::<GetApplicationFeesRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1554,7 +1578,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<FeeRefund>().await?;
+                let x = self.json::<FeeRefund>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1585,7 +1609,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<PlatformFee>().await?;
+                let x = self.json::<PlatformFee>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1619,7 +1643,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<FeeRefundListFDC0D1>().await?;
+                let x = self.json::<FeeRefundListFDC0D1>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1668,7 +1692,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Balance>().await?;
+                let x = self.json::<Balance>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1698,7 +1722,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<BalanceTransactionsList>().await?;
+                let x = self.json::<BalanceTransactionsList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1750,7 +1774,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<BalanceTransaction>().await?;
+                let x = self.json::<BalanceTransaction>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1780,7 +1804,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<BalanceTransactionsList>().await?;
+                let x = self.json::<BalanceTransactionsList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1835,7 +1859,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<BalanceTransaction>().await?;
+                let x = self.json::<BalanceTransaction>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1865,7 +1889,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetBitcoinReceiversRes>().await?;
+                let x = self.json::<GetBitcoinReceiversRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1917,7 +1941,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<BitcoinReceiver>().await?;
+                let x = self.json::<BitcoinReceiver>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1951,7 +1975,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<BitcoinTransactionListC3C538>().await?;
+                let x = self.json::<BitcoinTransactionListC3C538>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2003,7 +2027,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<BitcoinTransactionListC3C538>().await?;
+                let x = self.json::<BitcoinTransactionListC3C538>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2054,7 +2078,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetChargesRes>().await?;
+                let x = self.json::<GetChargesRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2106,7 +2130,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Charge>().await?;
+                let x = self.json::<Charge>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2140,7 +2164,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Dispute>().await?;
+                let x = self.json::<Dispute>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2174,7 +2198,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<RefundListBBCF51>().await?;
+                let x = self.json::<RefundListBBCF51>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2231,7 +2255,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Refund>().await?;
+                let x = self.json::<Refund>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2261,7 +2285,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<PaymentPagesCheckoutSessionList>().await?;
+                let x = self.json::<PaymentPagesCheckoutSessionList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2316,7 +2340,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Session>().await?;
+                let x = self.json::<Session>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2405,7 +2429,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetCountrySpecsRes>().await?;
+                let x = self.json::<GetCountrySpecsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2460,7 +2484,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<CountrySpec>().await?;
+                let x = self.json::<CountrySpec>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2490,7 +2514,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetCouponsRes>().await?;
+                let x = self.json::<GetCouponsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2542,7 +2566,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Coupon>().await?;
+                let x = self.json::<Coupon>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2572,7 +2596,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<CreditNotesList>().await?;
+                let x = self.json::<CreditNotesList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2623,7 +2647,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<CreditNote>().await?;
+                let x = self.json::<CreditNote>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2656,7 +2680,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<CreditNoteLinesList34EE1C>().await?;
+                let x = self.json::<CreditNoteLinesList34EE1C>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2711,7 +2735,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<CreditNoteLinesList34EE1C>().await?;
+                let x = self.json::<CreditNoteLinesList34EE1C>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2764,7 +2788,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<CreditNote>().await?;
+                let x = self.json::<CreditNote>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2794,7 +2818,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetCustomersRes>().await?;
+                let x = self.json::<GetCustomersRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2846,7 +2870,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<UniGetCustomersCustomerRes>().await?;
+                let x = self.json::<UniGetCustomersCustomerRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2880,7 +2904,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<CustomerBalanceTransactionList>().await?;
+                let x = self.json::<CustomerBalanceTransactionList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2937,7 +2961,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<CustomerBalanceTransaction>().await?;
+                let x = self.json::<CustomerBalanceTransaction>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -2971,7 +2995,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<BankAccountList>().await?;
+                let x = self.json::<BankAccountList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3028,7 +3052,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<BankAccount>().await?;
+                let x = self.json::<BankAccount>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3062,7 +3086,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<CardList81180B>().await?;
+                let x = self.json::<CardList81180B>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3119,7 +3143,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Card>().await?;
+                let x = self.json::<Card>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3153,7 +3177,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Discount>().await?;
+                let x = self.json::<Discount>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3187,7 +3211,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<ApmsSourcesSourceListF0771E>().await?;
+                let x = self.json::<ApmsSourcesSourceListF0771E>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3222,7 +3246,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<UniPolymorphic>().await?;
+                let x = self.json::<UniPolymorphic>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3256,7 +3280,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<SubscriptionList5B5899>().await?;
+                let x = self.json::<SubscriptionList5B5899>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3313,7 +3337,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Subscription>().await?;
+                let x = self.json::<Subscription>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3348,7 +3372,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Discount>().await?;
+                let x = self.json::<Discount>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3382,7 +3406,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<TaxIDsListAFDA6E>().await?;
+                let x = self.json::<TaxIDsListAFDA6E>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3439,7 +3463,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<TaxId>().await?;
+                let x = self.json::<TaxId>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3469,7 +3493,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetDisputesRes>().await?;
+                let x = self.json::<GetDisputesRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3521,7 +3545,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Dispute>().await?;
+                let x = self.json::<Dispute>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3551,7 +3575,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<NotificationEventList>().await?;
+                let x = self.json::<NotificationEventList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3603,7 +3627,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<NotificationEvent>().await?;
+                let x = self.json::<NotificationEvent>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3633,7 +3657,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetExchangeRatesRes>().await?;
+                let x = self.json::<GetExchangeRatesRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3688,7 +3712,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<ExchangeRate>().await?;
+                let x = self.json::<ExchangeRate>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3718,7 +3742,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetFileLinksRes>().await?;
+                let x = self.json::<GetFileLinksRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3770,7 +3794,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<FileLink>().await?;
+                let x = self.json::<FileLink>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3797,7 +3821,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetFilesRes>().await?;
+                let x = self.json::<GetFilesRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3849,7 +3873,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<File>().await?;
+                let x = self.json::<File>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3879,7 +3903,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetInvoiceitemsRes>().await?;
+                let x = self.json::<GetInvoiceitemsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3934,7 +3958,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<InvoiceItem>().await?;
+                let x = self.json::<InvoiceItem>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -3964,7 +3988,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<InvoicesList>().await?;
+                let x = self.json::<InvoicesList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4015,7 +4039,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Invoice>().await?;
+                let x = self.json::<Invoice>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4045,7 +4069,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<InvoiceLinesList9B8534>().await?;
+                let x = self.json::<InvoiceLinesList9B8534>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4097,7 +4121,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Invoice>().await?;
+                let x = self.json::<Invoice>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4131,7 +4155,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<InvoiceLinesList9B8534>().await?;
+                let x = self.json::<InvoiceLinesList9B8534>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4183,7 +4207,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<RadarIssuerFraudRecordList>().await?;
+                let x = self.json::<RadarIssuerFraudRecordList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4238,7 +4262,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<IssuerFraudRecord>().await?;
+                let x = self.json::<IssuerFraudRecord>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4268,7 +4292,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetIssuingAuthorizationsRes>().await?;
+                let x = self.json::<GetIssuingAuthorizationsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4323,7 +4347,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<IssuingAuthorization>().await?;
+                let x = self.json::<IssuingAuthorization>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4353,7 +4377,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetIssuingCardholdersRes>().await?;
+                let x = self.json::<GetIssuingCardholdersRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4408,7 +4432,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<IssuingCardholder>().await?;
+                let x = self.json::<IssuingCardholder>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4438,7 +4462,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetIssuingCardsRes>().await?;
+                let x = self.json::<GetIssuingCardsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4490,7 +4514,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<IssuingCard>().await?;
+                let x = self.json::<IssuingCard>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4520,7 +4544,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<IssuingDisputeList>().await?;
+                let x = self.json::<IssuingDisputeList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4575,7 +4599,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<IssuingDispute>().await?;
+                let x = self.json::<IssuingDispute>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4605,7 +4629,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetIssuingSettlementsRes>().await?;
+                let x = self.json::<GetIssuingSettlementsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4660,7 +4684,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<IssuingSettlement>().await?;
+                let x = self.json::<IssuingSettlement>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4690,7 +4714,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetIssuingTransactionsRes>().await?;
+                let x = self.json::<GetIssuingTransactionsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4745,7 +4769,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<IssuingTransaction>().await?;
+                let x = self.json::<IssuingTransaction>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4776,7 +4800,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Mandate>().await?;
+                let x = self.json::<Mandate>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4806,7 +4830,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetOrderReturnsRes>().await?;
+                let x = self.json::<GetOrderReturnsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4858,7 +4882,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<OrderReturn>().await?;
+                let x = self.json::<OrderReturn>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4885,7 +4909,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetOrdersRes>().await?;
+                let x = self.json::<GetOrdersRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4937,7 +4961,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Order>().await?;
+                let x = self.json::<Order>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -4967,7 +4991,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<PaymentFlowsPaymentIntentList>().await?;
+                let x = self.json::<PaymentFlowsPaymentIntentList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5022,7 +5046,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<PaymentIntent>().await?;
+                let x = self.json::<PaymentIntent>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5052,7 +5076,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<PaymentFlowsPaymentMethodList>().await?;
+                let x = self.json::<PaymentFlowsPaymentMethodList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5107,7 +5131,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<PaymentMethod>().await?;
+                let x = self.json::<PaymentMethod>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5134,7 +5158,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<PayoutList>().await?;
+                let x = self.json::<PayoutList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5186,7 +5210,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Payout>().await?;
+                let x = self.json::<Payout>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5213,7 +5237,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<PlanList>().await?;
+                let x = self.json::<PlanList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5265,7 +5289,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Plan>().await?;
+                let x = self.json::<Plan>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5292,7 +5316,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<PriceList>().await?;
+                let x = self.json::<PriceList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5344,7 +5368,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Price>().await?;
+                let x = self.json::<Price>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5374,7 +5398,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetProductsRes>().await?;
+                let x = self.json::<GetProductsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5426,7 +5450,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Product>().await?;
+                let x = self.json::<Product>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5456,7 +5480,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetPromotionCodesRes>().await?;
+                let x = self.json::<GetPromotionCodesRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5511,7 +5535,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<PromotionCode>().await?;
+                let x = self.json::<PromotionCode>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5544,7 +5568,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<RadarEarlyFraudWarningList>().await?;
+                let x = self.json::<RadarEarlyFraudWarningList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5599,7 +5623,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<RadarEarlyFraudWarning>().await?;
+                let x = self.json::<RadarEarlyFraudWarning>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5629,7 +5653,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetRadarValueListItemsRes>().await?;
+                let x = self.json::<GetRadarValueListItemsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5684,7 +5708,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<RadarListListItem>().await?;
+                let x = self.json::<RadarListListItem>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5714,7 +5738,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetRadarValueListsRes>().await?;
+                let x = self.json::<GetRadarValueListsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5769,7 +5793,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<RadarListList>().await?;
+                let x = self.json::<RadarListList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5799,7 +5823,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetRecipientsRes>().await?;
+                let x = self.json::<GetRecipientsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5851,7 +5875,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<UniGetRecipientsIdRes>().await?;
+                let x = self.json::<UniGetRecipientsIdRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5881,7 +5905,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetRefundsRes>().await?;
+                let x = self.json::<GetRefundsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5933,7 +5957,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Refund>().await?;
+                let x = self.json::<Refund>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -5963,7 +5987,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetReportingReportRunsRes>().await?;
+                let x = self.json::<GetReportingReportRunsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6018,7 +6042,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<ReportingReportRun>().await?;
+                let x = self.json::<ReportingReportRun>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6084,7 +6108,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<ReportingReportType>().await?;
+                let x = self.json::<ReportingReportType>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6114,7 +6138,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetReviewsRes>().await?;
+                let x = self.json::<GetReviewsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6166,7 +6190,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<RadarReview>().await?;
+                let x = self.json::<RadarReview>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6249,7 +6273,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<PaymentFlowsSetupIntentList>().await?;
+                let x = self.json::<PaymentFlowsSetupIntentList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6301,7 +6325,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<SetupIntent>().await?;
+                let x = self.json::<SetupIntent>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6334,7 +6358,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetSigmaScheduledQueryRunsRes>().await?;
+                let x = self.json::<GetSigmaScheduledQueryRunsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6389,7 +6413,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<ScheduledQueryRun>().await?;
+                let x = self.json::<ScheduledQueryRun>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6416,7 +6440,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetSkusRes>().await?;
+                let x = self.json::<GetSkusRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6468,7 +6492,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<UniGetSkusIdRes>().await?;
+                let x = self.json::<UniGetSkusIdRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6499,7 +6523,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Source>().await?;
+                let x = self.json::<Source>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6534,7 +6558,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<SourceMandateNotification>().await?;
+                let x = self.json::<SourceMandateNotification>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6568,7 +6592,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<ApmsSourcesSourceTransactionList>().await?;
+                let x = self.json::<ApmsSourcesSourceTransactionList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6625,7 +6649,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<SourceTransaction>().await?;
+                let x = self.json::<SourceTransaction>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6655,7 +6679,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetSubscriptionItemsRes>().await?;
+                let x = self.json::<GetSubscriptionItemsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6710,7 +6734,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<SubscriptionItem>().await?;
+                let x = self.json::<SubscriptionItem>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6800,7 +6824,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetSubscriptionSchedulesRes>().await?;
+                let x = self.json::<GetSubscriptionSchedulesRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6855,7 +6879,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<SubscriptionSchedule>().await?;
+                let x = self.json::<SubscriptionSchedule>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6885,7 +6909,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetSubscriptionsRes>().await?;
+                let x = self.json::<GetSubscriptionsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6940,7 +6964,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Subscription>().await?;
+                let x = self.json::<Subscription>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -6970,7 +6994,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetTaxRatesRes>().await?;
+                let x = self.json::<GetTaxRatesRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -7022,7 +7046,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<TaxRate>().await?;
+                let x = self.json::<TaxRate>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -7052,7 +7076,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<TerminalLocationLocationList>().await?;
+                let x = self.json::<TerminalLocationLocationList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -7107,7 +7131,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<TerminalLocationLocation>().await?;
+                let x = self.json::<TerminalLocationLocation>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -7137,7 +7161,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<TerminalReaderRetrieveReader>().await?;
+                let x = self.json::<TerminalReaderRetrieveReader>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -7192,7 +7216,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<TerminalReaderReader>().await?;
+                let x = self.json::<TerminalReaderReader>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -7223,7 +7247,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Token>().await?;
+                let x = self.json::<Token>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -7250,7 +7274,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<TopupList>().await?;
+                let x = self.json::<TopupList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -7302,7 +7326,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Topup>().await?;
+                let x = self.json::<Topup>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -7332,7 +7356,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<TransferList>().await?;
+                let x = self.json::<TransferList>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -7387,7 +7411,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<TransferReversalList620BF1>().await?;
+                let x = self.json::<TransferReversalList620BF1>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -7440,7 +7464,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<Transfer>().await?;
+                let x = self.json::<Transfer>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -7475,7 +7499,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<TransferReversal>().await?;
+                let x = self.json::<TransferReversal>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -7505,7 +7529,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<GetWebhookEndpointsRes>().await?;
+                let x = self.json::<GetWebhookEndpointsRes>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -7560,7 +7584,7 @@ impl StripeClient {
 
         match res.status() {
             StatusCode::OK => {
-                let x = res.json::<NotificationWebhookEndpoint>().await?;
+                let x = self.json::<NotificationWebhookEndpoint>(res).await?;
                 Ok(x)
             }
             _ => {
@@ -1,4 +1,5 @@
 
             pub mod http;
+            pub mod raw;
             pub mod trait_param_meta;
         
\ No newline at end of file
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+
+
+// The JSON of API responses as it was returned, by (object type, id) (`StripeClient.raw`).
+// - Typed responses drop any field the client does not model; this keeps the original for callers that store it.
+// - Only top level objects are kept: each item of a list response, or the response object itself. Nested objects are read from their parent.
+// - Callers `take` each object when they use it, and `clear` objects they did not use (E.g. after each run).
+#[derive(Clone, Debug, Default)]
+pub struct RawIndex {
+    objects: Arc<Mutex<HashMap<(String, String), Value>>>,
+}
+
+// Some objects share ids (E.g. a plan and its price).
+fn get_key(x: &Value) -> Option<(String, String)> {
+    let object = x.get("object").and_then(|x| x.as_str())?;
+    let id = x.get("id").and_then(|x| x.as_str())?;
+    Some((object.to_string(), id.to_string()))
+}
+
+impl RawIndex {
+    pub fn add(&self, x: &Value) {
+        let mut objects = self.objects.lock().unwrap();
+
+        match x.get("object").and_then(|x| x.as_str()) {
+            Some("list") | Some("search_result") => {
+                if let Some(Value::Array(data)) = x.get("data") {
+                    for item in data {
+                        if let Some(k) = get_key(item) {
+                            objects.insert(k, item.clone());
+                        }
+                    }
+                }
+            }
+            _ => {
+                if let Some(k) = get_key(x) {
+                    objects.insert(k, x.clone());
+                }
+            }
+        }
+    }
+
+    pub fn take(&self, object: &str, id: &str) -> Option<Value> {
+        self.objects.lock().unwrap().remove(&(object.to_string(), id.to_string()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.objects.lock().unwrap().clear();
+    }
+}
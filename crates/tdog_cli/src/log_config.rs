@@ -7,6 +7,7 @@ use log::{Record, Level, Metadata};
 use std::collections::HashMap;
 use std::path::{Path, Component};
 use tdog_core::providers::stripe::schema::ToJSONKey;
+use tdog_core::util::get_job_label;
 use serde_json::Value;
 use atty::Stream;
 
@@ -18,6 +19,9 @@ struct ConsoleLogger {
     // Cached computed fields to avoid re-computing on every log entry.
     // Maximum level in any of the config's.
     max_level: log::Level,
+
+    // When stdout is used for data output (E.g. `options.cdc`).
+    to_stderr: bool,
 }
 
 impl ConsoleLogger {
@@ -80,11 +84,18 @@ impl ConsoleLogger {
             msg = format_json_at_end_of_string(msg.as_str())
         }
 
+        // When many jobs run in one process, label each line with the job that logged it.
+        let job = match get_job_label() {
+            Some(x) => format!(" {}", x),
+            None => "".to_string()
+        };
+
         format!(
-            "[{} {} {}] {}",
+            "[{} {} {}{}] {}",
             ms,
             level_to_string_color(record.level()),
             mod_key,
+            job,
             msg
         )
     }
@@ -158,6 +169,11 @@ impl log::Log for ConsoleLogger {
         if inc {
             // eprintln exists for errors/progress, outputs to stderr
 
+            if self.to_stderr {
+                eprintln!("{}", self.get_line(record));
+                return;
+            }
+
             // stdout
             println!("{}", self.get_line(record));
         }
@@ -351,12 +367,13 @@ impl From<&Level> for LevelString {
 //              - @ee https://cloud.google.com/monitoring/alerts/policies-in-json#json-metric-absence
 //          - Downstream processes.
 //              - Heartbeat SQL column (last ts of last check, regardless of events processed - 0 indicates no events to process but TD process is still operational/up to date).
-pub fn init_log(config: LogConfig) {
+pub fn init_log(config: LogConfig, to_stderr: bool) {
     let max_level = config.get_max_log_level().expect("Must provide at least one log level.");
 
     let cl = ConsoleLogger {
         config,
         max_level,
+        to_stderr,
     };
 
 
@@ -4,9 +4,13 @@ use log::{error, info};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use unicon::uc::{*};
-use tdog_core::{Cmd, FromAPI};
+use std::collections::HashSet;
+use tdog_core::{CdcTo, Cmd, Cmds, FromAPI, HttpServer};
+use tdog_core::http_server;
+use tdog_core::error::TdError;
+use tdog_core::providers::stripe::schema_graph::{get_dbt_sources, get_graph, GraphFormat};
 use tdog_core::providers::stripe::schema_meta::get_cli_version;
-use tdog_core::util::{Redact, REDACT_PLACEHOLDER, is_debug_build};
+use tdog_core::util::{Redact, REDACT_PLACEHOLDER, is_debug_build, parse_created_gte};
 use crate::log_config::{init_log, LevelString, LogConfig};
 
 mod test_parse_json;
@@ -15,51 +19,101 @@ pub mod log_config;
 #[derive(Serialize, Deserialize)]
 #[derive(Debug, Clone)]
 pub struct Config {
-    pub cmd: Cmd,
+    // A single job, or an array of jobs that run concurrently in this process.
+    pub cmd: Cmds,
 
     #[serde(default = "default_log")]
     pub log: LevelStringOrConfig,
+
+    // Serves `/metrics`, `/healthz` and `/readyz` for all jobs.
+    pub http_server: Option<HttpServer>,
 }
 
 impl Config {
     // Allow the user to pass a config that is missing keys (or keys are set to null).
     // - Serde does not allow you to configure defaults for structs defined in other crates.
     // - Some defaults depend on other values in the config (E.g. default target schema may depend on API source name).
-    pub fn set_defaults(&mut self) {
-        match &mut self.cmd {
-            Cmd::Download(ref mut dl) => {
-                let schema_name_default = match dl.from {
-                    FromAPI::Stripe(_) => "stripe".to_string()
-                };
-
-                // Validation.
-                match &dl.to {
-                    Engine::MySQL(x) => {
-                        if let Some(_db_name) = &x.db_name {
-                            error!("MySQL: Use `schema_name` instead of `db_name`.");
-                            panic!();
+    pub fn set_defaults(&mut self) -> Result<(), TdError> {
+        // Labels identify jobs in the shared log output, so they must be unique.
+        let all = self.cmd.get_all();
+        let labels: HashSet<String> = all.iter().enumerate().map(|(i, x)| x.get_label(i)).collect();
+        if labels.len() != all.len() {
+            error!("Each job in the `cmd` array must have a unique `options.label`.");
+            return Err(TdError::Config("Each job in the `cmd` array must have a unique `options.label`.".into()));
+        }
+
+        for cmd in self.cmd.get_all_mut() {
+            match cmd {
+                Cmd::Download(dl) => {
+                    let schema_name_default = match dl.from {
+                        FromAPI::Stripe(_) => "stripe".to_string()
+                    };
+
+                    if let Some(x) = &dl.options.created_gte {
+                        if parse_created_gte(x, chrono::Utc::now()).is_none() {
+                            error!("Invalid `options.created_gte` `{}`. Use a date (`2021-01-01`), an RFC 3339 date time (`2021-01-01T00:00:00Z`) or a duration (`90days`).", x);
+                            return Err(TdError::Config(format!("Invalid `options.created_gte` `{}`.", x)));
                         }
                     }
-                    _ => {}
-                }
 
-                // Set defaults.
-                match &mut dl.to {
-                    Engine::SQLite(_) => {}
-                    Engine::MySQL(x) => {
-                        if x.schema_name.is_none() {
-                            x.schema_name = Some(schema_name_default.clone())
-                        }
+                    set_engine_defaults(&mut dl.to, schema_name_default)?;
+                }
+                Cmd::Replay(rp) => {
+                    if rp.batch_size == 0 {
+                        error!("`batch_size` must be greater than 0.");
+                        return Err(TdError::Config("`batch_size` must be greater than 0.".into()));
                     }
-                    Engine::Postgres(x) => {
-                        if x.schema_name.is_none() {
-                            x.schema_name = Some(schema_name_default.clone())
-                        }
+
+                    // Events are always Stripe events.
+                    set_engine_defaults(&mut rp.to, "stripe".to_string())?;
+                }
+                Cmd::EventsExport(x) => set_engine_defaults(&mut x.db, "stripe".to_string())?,
+                Cmd::EventsImport(x) => set_engine_defaults(&mut x.to, "stripe".to_string())?,
+                Cmd::Snapshot(x) => {
+                    if parse_created_gte(&x.as_of, chrono::Utc::now()).is_none() {
+                        error!("Invalid `as_of` `{}`. Use a date (`2021-01-31`), an RFC 3339 date time (`2021-01-31T23:59:59Z`) or a duration (`30days`).", &x.as_of);
+                        return Err(TdError::Config(format!("Invalid `as_of` `{}`.", &x.as_of)));
                     }
+
+                    set_engine_defaults(&mut x.db, "stripe".to_string())?;
+                    set_engine_defaults(&mut x.to, "stripe".to_string())?;
                 }
+                Cmd::Check(x) => set_engine_defaults(&mut x.db, "stripe".to_string())?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn set_engine_defaults(to: &mut Engine, schema_name_default: String) -> Result<(), TdError> {
+    // Validation.
+    match &to {
+        Engine::MySQL(x) => {
+            if let Some(_db_name) = &x.db_name {
+                error!("MySQL: Use `schema_name` instead of `db_name`.");
+                return Err(TdError::Config("MySQL: Use `schema_name` instead of `db_name`.".into()));
+            }
+        }
+        _ => {}
+    }
+
+    // Set defaults.
+    match to {
+        Engine::SQLite(_) => {}
+        Engine::MySQL(x) => {
+            if x.schema_name.is_none() {
+                x.schema_name = Some(schema_name_default.clone())
+            }
+        }
+        Engine::Postgres(x) => {
+            if x.schema_name.is_none() {
+                x.schema_name = Some(schema_name_default.clone())
             }
         }
     }
+
+    Ok(())
 }
 
 fn default_log() -> LevelStringOrConfig {
@@ -81,15 +135,150 @@ pub enum LevelStringOrConfig {
 
 // Allow users to use native CLI args like this:
 // - `tdog --stripe-key abc --target db.sqlite --watch`
+// - `tdog replay --events events.jsonl --target db.sqlite`
+// - `tdog events export --target db.sqlite --dir /archive`
+// - `tdog snapshot --from db.sqlite --as-of 2021-01-31 --to snapshot.sqlite`
+// - `tdog check --target db.sqlite`
 // - Less messing around with JSON, easy to type.
 // - JSON config enables using JSON specification and more complicated configurations if needed.
-fn from_native_args() -> Option<String> {
+fn from_native_args() -> Result<Option<String>, TdError> {
     let args: Vec<String> = env::args().collect();
 
     let from = args.iter().position(|s| s == "--stripe-key");
     let to = args.iter().position(|s| s == "--target");
     let watch = args.iter().position(|s| s == "--watch").is_some();
 
+    // `tdog replay --events dir_or_jsonl --target db.sqlite [--stripe-key abc]`
+    if args.get(1).map(|x| x.as_str()) == Some("replay") {
+        let events = args.iter().position(|s| s == "--events").and_then(|i| args.get(i + 1));
+        let to_v = to.and_then(|i| args.get(i + 1));
+        let from_v = from.and_then(|i| args.get(i + 1));
+
+        if let (Some(events), Some(to_v)) = (events, to_v) {
+            let value = json!({
+                "cmd": {
+                    "fn": "replay",
+                    "args": {
+                        "events": events,
+                        "to": {
+                            "sqlite": {
+                                "file": to_v
+                            }
+                        },
+                        "from": from_v.map(|x| json!({
+                            "stripe": {
+                                "secret_key": x,
+                            }
+                        }))
+                    }
+                }
+            });
+            return Ok(serde_json::to_string(&value).ok());
+        }
+        return Ok(None);
+    }
+
+    // `tdog events export --target db.sqlite --dir /archive [--retention-days 90]`
+    // `tdog events import --dir /archive --target archive.sqlite`
+    if args.get(1).map(|x| x.as_str()) == Some("events") {
+        let dir = args.iter().position(|s| s == "--dir").and_then(|i| args.get(i + 1));
+        let to_v = to.and_then(|i| args.get(i + 1));
+        let retention_days = match args.iter().position(|s| s == "--retention-days").and_then(|i| args.get(i + 1)) {
+            Some(x) => Some(x.parse::<u32>().map_err(|_| TdError::Config(format!("Invalid `--retention-days` `{}`: use a number of days.", x)))?),
+            None => None
+        };
+        let sqlite = |file: &String| json!({
+            "sqlite": {
+                "file": file
+            }
+        });
+
+        if let (Some(dir), Some(to_v)) = (dir, to_v) {
+            let value = match args.get(2).map(|x| x.as_str()) {
+                Some("export") => json!({
+                    "cmd": {
+                        "fn": "events_export",
+                        "args": {
+                            "db": sqlite(to_v),
+                            "dir": dir,
+                            "retention_days": retention_days
+                        }
+                    }
+                }),
+                Some("import") => json!({
+                    "cmd": {
+                        "fn": "events_import",
+                        "args": {
+                            "dir": dir,
+                            "to": sqlite(to_v)
+                        }
+                    }
+                }),
+                _ => return Ok(None)
+            };
+            return Ok(serde_json::to_string(&value).ok());
+        }
+        return Ok(None);
+    }
+
+    // `tdog snapshot --from db.sqlite --as-of 2021-01-31 --to snapshot.sqlite [--events-dir /archive]`
+    // - `--from` is only read; `--to` is written.
+    if args.get(1).map(|x| x.as_str()) == Some("snapshot") {
+        let get = |flag: &str| args.iter().position(|s| s == flag).and_then(|i| args.get(i + 1));
+
+        if let (Some(db), Some(as_of), Some(to_v)) = (get("--from"), get("--as-of"), get("--to")) {
+            let value = json!({
+                "cmd": {
+                    "fn": "snapshot",
+                    "args": {
+                        "db": {
+                            "sqlite": {
+                                "file": db
+                            }
+                        },
+                        "as_of": as_of,
+                        "to": {
+                            "sqlite": {
+                                "file": to_v
+                            }
+                        },
+                        "events_dir": get("--events-dir")
+                    }
+                }
+            });
+            return Ok(serde_json::to_string(&value).ok());
+        }
+        return Ok(None);
+    }
+
+    // `tdog check --target db.sqlite [--repair --stripe-key abc]`
+    if args.get(1).map(|x| x.as_str()) == Some("check") {
+        let repair = args.iter().any(|s| s == "--repair");
+
+        if let Some(to_v) = to.and_then(|i| args.get(i + 1)) {
+            let value = json!({
+                "cmd": {
+                    "fn": "check",
+                    "args": {
+                        "db": {
+                            "sqlite": {
+                                "file": to_v
+                            }
+                        },
+                        "repair": repair,
+                        "from": from.and_then(|i| args.get(i + 1)).map(|x| json!({
+                            "stripe": {
+                                "secret_key": x,
+                            }
+                        }))
+                    }
+                }
+            });
+            return Ok(serde_json::to_string(&value).ok());
+        }
+        return Ok(None);
+    }
+
     if let (Some(from_i), Some(to_i)) = (from, to) {
         if let (Some(from_v), Some(to_v)) = (args.get(from_i + 1), args.get(to_i + 1)) {
             let value = json!({
@@ -112,10 +301,26 @@ fn from_native_args() -> Option<String> {
                     }
                 }
             });
-            return serde_json::to_string(&value).ok();
+            return Ok(serde_json::to_string(&value).ok());
         }
     }
-    None
+    Ok(None)
+}
+
+// `tdog schema graph [--format mermaid|dot|json]` and `tdog schema dbt [--schema stripe]` print to stdout (no config or DB needed).
+fn run_schema_cmd() -> Option<Result<String, TdError>> {
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|x| x.as_str()) != Some("schema") {
+        return None;
+    }
+
+    let get = |flag: &str| args.iter().position(|s| s == flag).and_then(|i| args.get(i + 1)).map(|x| x.as_str());
+
+    Some(match args.get(2).map(|x| x.as_str()) {
+        Some("graph") => get("--format").unwrap_or("mermaid").parse::<GraphFormat>().map(get_graph),
+        Some("dbt") => Ok(get_dbt_sources(get("--schema"))),
+        _ => Err(TdError::Config("Use `tdog schema graph --format mermaid|dot|json` or `tdog schema dbt [--schema stripe]`.".into()))
+    })
 }
 
 // Use a single JSON object for args:
@@ -130,7 +335,7 @@ fn from_native_args() -> Option<String> {
 fn get_json_str() -> Result<String> {
     let args: Vec<String> = env::args().collect();
 
-    if let Some(s) = from_native_args() {
+    if let Some(s) = from_native_args()? {
         return Ok(s);
     }
 
@@ -178,22 +383,49 @@ impl Config {
         // let redact = "****redacted****";
         // let re = Regex::new("^(.{14}).+?(.{4})$").unwrap();
 
-        match x.cmd {
-            Cmd::Download(ref mut x) => {
-                match x.from {
-                    FromAPI::Stripe(ref mut x) => {
-                        // x.secret_key = re.replace(x.secret_key.as_str(), format!("$1{}$2", &redact).as_str()).parse().unwrap();
-                        x.secret_key = x.secret_key.as_str().redact(14, 2);
+        let redact_from = |from: &mut FromAPI| {
+            match from {
+                FromAPI::Stripe(ref mut x) => {
+                    // x.secret_key = re.replace(x.secret_key.as_str(), format!("$1{}$2", &redact).as_str()).parse().unwrap();
+                    x.secret_key = x.secret_key.as_str().redact(14, 2);
+                }
+            }
+        };
+
+        let redact_to = |to: &mut Engine| {
+            match to {
+                Engine::SQLite(_) => {}
+                Engine::MySQL(ref mut x) | Engine::Postgres(ref mut x) => {
+                    if x.pass.is_some() {
+                        x.pass = Some(REDACT_PLACEHOLDER.to_string());
                     }
                 }
+            }
+        };
 
-                match x.to {
-                    Engine::SQLite(_) => {}
-                    Engine::MySQL(ref mut x) | Engine::Postgres(ref mut x) => {
-                        if x.pass.is_some() {
-                            x.pass = Some(REDACT_PLACEHOLDER.to_string());
-                        }
+        for cmd in x.cmd.get_all_mut() {
+            match cmd {
+                Cmd::Download(x) => {
+                    redact_from(&mut x.from);
+                    redact_to(&mut x.to);
+                }
+                Cmd::Replay(x) => {
+                    if let Some(from) = &mut x.from {
+                        redact_from(from);
                     }
+                    redact_to(&mut x.to);
+                }
+                Cmd::EventsExport(x) => redact_to(&mut x.db),
+                Cmd::EventsImport(x) => redact_to(&mut x.to),
+                Cmd::Snapshot(x) => {
+                    redact_to(&mut x.db);
+                    redact_to(&mut x.to);
+                }
+                Cmd::Check(x) => {
+                    if let Some(from) = &mut x.from {
+                        redact_from(from);
+                    }
+                    redact_to(&mut x.db);
                 }
             }
         }
@@ -202,6 +434,11 @@ impl Config {
     }
 
 
+    // Logs are written to stderr so that stdout only contains CDC lines.
+    fn is_stdout_used_for_cdc(&self) -> bool {
+        self.cmd.get_all().iter().any(|x| matches!(x.get_options().cdc, Some(CdcTo::Stdout(_))))
+    }
+
     fn get_log_config(&self) -> LogConfig {
         match &self.log {
             LevelStringOrConfig::AppLevel(app_level) => LogConfig::friendly_casual_logs(app_level.into()),
@@ -211,13 +448,41 @@ impl Config {
 }
 
 
+// Exit codes and the last line of output are for process supervisors (systemd, Kubernetes) to decide whether to restart.
+// - Stderr: `{"error": {"kind": "stripe_auth", "exit_code": 11, "retryable": false, "job": null, "msg": "..."}}`.
+// - @see `TdError::get_exit_code` and `readme.md`.
+fn exit_with_error(job: Option<String>, e: TdError) -> ! {
+    error!("Exiting with code {}: {}", e.get_exit_code(), e);
+    eprintln!("{}", e.to_json_line(job.as_deref()));
+    std::process::exit(e.get_exit_code());
+}
+
+
 // #[tokio::main(threaded_scheduler)]
 #[tokio::main]
 async fn main() -> Result<()> {
-    let mut x = Config::from_cli_args()?;
-    init_log(x.get_log_config());
+    if let Some(x) = run_schema_cmd() {
+        match x {
+            Ok(s) => {
+                print!("{}", s);
+                return Ok(());
+            }
+            Err(e) => exit_with_error(None, e)
+        }
+    }
 
-    x.set_defaults();
+    let mut x = match Config::from_cli_args() {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            exit_with_error(None, TdError::Config(format!("{:#}", e)));
+        }
+    };
+    init_log(x.get_log_config(), x.is_stdout_used_for_cdc());
+
+    if let Err(e) = x.set_defaults() {
+        exit_with_error(None, e);
+    }
 
     info!("CLI version: {}", get_cli_version());
     if is_debug_build() {
@@ -232,8 +497,16 @@ async fn main() -> Result<()> {
     //     warn!("Callback ran, license is {:?}", &x2.license);
     // }));
 
+    if let Some(s) = &x.http_server {
+        if let Err(e) = http_server::start(s, &x.cmd) {
+            exit_with_error(None, e);
+        }
+    }
+
     // - Issue: cannot have cyclic Cargo crates, so cannot pass Config down.
-    x.cmd.run().await;
+    if let Err((job, e)) = x.cmd.run().await {
+        exit_with_error(job, e);
+    }
 
     Ok(())
 }
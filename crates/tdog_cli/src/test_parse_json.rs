@@ -167,10 +167,10 @@ mod test {
             "#;
 
             let mut v: Config = serde_json::from_str(dl).expect("Ok");
-            v.set_defaults();
+            v.set_defaults().unwrap();
 
             let x = || {
-                match &v.cmd {
+                match v.cmd.get_all()[0] {
                     Cmd::Download(dl) => {
                         match &dl.to {
                             Engine::SQLite(_) => {}
@@ -180,6 +180,7 @@ mod test {
                             Engine::Postgres(_) => {}
                         }
                     }
+                    _ => unreachable!()
                 }
                 None
             };
@@ -190,4 +191,219 @@ mod test {
         }
     }
 
-}
\ No newline at end of file
+
+    #[test]
+    fn parse_json_many_jobs() {
+        {
+            // Array of jobs, each with its own (from, to) and options.
+            let dl = r#"
+            {
+                "cmd": [
+                    {
+                        "fn": "download",
+                        "args": {
+                            "from": {
+                                "stripe": {
+                                    "secret_key": "123"
+                                }
+                            },
+                            "to": {
+                                "sqlite": {
+                                    "file": "abc"
+                                }
+                            },
+                            "options": {
+                                "watch": true,
+                                "label": "acc_a",
+                                "restart": {
+                                    "max_restarts": 3
+                                }
+                            }
+                        }
+                    },
+                    {
+                        "fn": "download",
+                        "args": {
+                            "from": {
+                                "stripe": {
+                                    "secret_key": "456"
+                                }
+                            },
+                            "to": {
+                                "mysql": {
+                                    "addr": {
+                                        "socket": "/tmp/mysql.sock"
+                                    },
+                                    "user": "root",
+                                    "pass": "my-secret-pw"
+                                }
+                            }
+                        }
+                    }
+                ]
+            }
+            "#;
+
+            let mut v: Config = serde_json::from_str(dl).expect("Ok");
+            v.set_defaults().unwrap();
+
+            let all = v.cmd.get_all();
+            assert_eq!(all.len(), 2);
+            assert_eq!(all[0].get_label(0), "acc_a");
+            assert_eq!(all[1].get_label(1), "job_2");
+
+            match all[0] {
+                Cmd::Download(dl) => {
+                    let r = dl.options.restart.as_ref().unwrap();
+                    assert_eq!(r.max_restarts, Some(3));
+                    assert_eq!(r.backoff_ms, 5_000);
+                }
+                _ => unreachable!()
+            }
+
+            // Defaults are set for every job.
+            match all[1] {
+                Cmd::Download(dl) => {
+                    match &dl.to {
+                        Engine::MySQL(x) => assert!(x.schema_name.is_some()),
+                        _ => unreachable!()
+                    }
+                }
+                _ => unreachable!()
+            }
+        }
+    }
+
+
+    #[test]
+    fn parse_json_created_gte_invalid() {
+        let dl = r#"
+        {
+            "cmd": {
+                "fn": "download",
+                "args": {
+                    "from": {
+                        "stripe": {
+                            "secret_key": "123"
+                        }
+                    },
+                    "to": {
+                        "sqlite": {
+                            "file": "/tmp/td.sqlite"
+                        }
+                    },
+                    "options": {
+                        "created_gte": "last year"
+                    }
+                }
+            }
+        }
+        "#;
+
+        let mut v: Config = serde_json::from_str(dl).expect("Ok");
+        assert!(matches!(v.set_defaults(), Err(TdError::Config(_))));
+    }
+
+    #[test]
+    fn parse_json_replay() {
+        let rp = r#"
+        {
+            "cmd": {
+                "fn": "replay",
+                "args": {
+                    "events": "/tmp/events.jsonl",
+                    "to": {
+                        "postgres": {
+                            "addr": {
+                                "ip": "127.0.0.1",
+                                "port": 5432
+                            },
+                            "user": "postgres",
+                            "pass": "my-secret-pw"
+                        }
+                    }
+                }
+            }
+        }
+        "#;
+
+        let mut v: Config = serde_json::from_str(rp).expect("Ok");
+        v.set_defaults().unwrap();
+
+        match v.cmd.get_all()[0] {
+            Cmd::Replay(rp) => {
+                assert_eq!(rp.batch_size, 500);
+                assert!(rp.from.is_none());
+                match &rp.to {
+                    Engine::Postgres(x) => assert_eq!(x.schema_name.as_deref(), Some("stripe")),
+                    _ => unreachable!()
+                }
+            }
+            _ => unreachable!()
+        }
+    }
+
+    #[test]
+    fn parse_json_events_export() {
+        let x = r#"
+        {
+            "cmd": {
+                "fn": "events_export",
+                "args": {
+                    "db": {
+                        "sqlite": {
+                            "file": "/tmp/td.sqlite"
+                        }
+                    },
+                    "dir": "/tmp/archive",
+                    "retention_days": 90
+                }
+            }
+        }
+        "#;
+
+        let mut v: Config = serde_json::from_str(x).expect("Ok");
+        v.set_defaults().unwrap();
+
+        match v.cmd.get_all()[0] {
+            Cmd::EventsExport(x) => assert_eq!(x.retention_days, Some(90)),
+            _ => unreachable!()
+        }
+    }
+
+    #[test]
+    fn parse_json_snapshot() {
+        let x = r#"
+        {
+            "cmd": {
+                "fn": "snapshot",
+                "args": {
+                    "db": {
+                        "sqlite": {
+                            "file": "/tmp/td.sqlite"
+                        }
+                    },
+                    "as_of": "2021-01-31T23:59:59Z",
+                    "to": {
+                        "sqlite": {
+                            "file": "/tmp/td-2021-01.sqlite"
+                        }
+                    }
+                }
+            }
+        }
+        "#;
+
+        let mut v: Config = serde_json::from_str(x).expect("Ok");
+        v.set_defaults().unwrap();
+
+        match v.cmd.get_all()[0] {
+            Cmd::Snapshot(x) => assert!(x.events_dir.is_none()),
+            _ => unreachable!()
+        }
+
+        let mut v: Config = serde_json::from_str(&x.replace("2021-01-31T23:59:59Z", "end of January")).expect("Ok");
+        assert!(matches!(v.set_defaults(), Err(TdError::Config(_))));
+    }
+
+}
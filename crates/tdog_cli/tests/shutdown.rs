@@ -0,0 +1,89 @@
+// Graceful shutdown, with the signal sent to a real `tdog` process (signal handlers are process wide, so this cannot run inside the test process).
+#![cfg(unix)]
+
+use std::process::Command;
+
+use chrono::Utc;
+use rusqlite::Connection;
+use serde_json::{json, Map, Value};
+use stripe_client::http::http::StripeAccount;
+use tdog_core::providers::stripe::schema::Db;
+use tdog_core::providers::stripe::schema_meta::{TdMetadata, TdRun};
+use tdog_core::util::get_unique_id;
+use unicon::traits::{*};
+use unicon::uc::{ConMetaSQLite, SQLiteCreate, UniCon};
+
+
+fn get_temp_file(name: &str) -> String {
+    std::env::temp_dir().join(format!("{}-{}", get_unique_id(), name)).to_str().unwrap().to_string()
+}
+
+// A DB with an (empty) download, as if restored from a backup.
+fn create_downloaded_db(file: &str) {
+    let mut uc = UniCon::Rusqlite(ConMetaSQLite {
+        create: SQLiteCreate { file: file.to_string() },
+        c: Connection::open(file).unwrap(),
+    });
+    uc.ensure_schema_and_tables_exist_and_writable::<Db>();
+
+    TdMetadata::insert_cli_and_stripe_versions(&mut uc, &StripeAccount {
+        id: "acct_1".into(),
+        is_test: true,
+        account: Map::new(),
+    }, None);
+
+    TdRun {
+        run_id: None,
+        r#type: "download".into(),
+        start_ts: Some(Utc::now().into()),
+        end_ts: Some(Utc::now().into()),
+    }.insert_set_pk(&mut uc);
+}
+
+fn get_count(file: &str, sql: &str) -> i64 {
+    Connection::open(file).unwrap().query_row(sql, [], |r| r.get(0)).unwrap()
+}
+
+// SIGTERM is sent by the `on_apply_events` hook of the first batch: the committed batch is kept, the next batches are not started, and the process exits with 75 (`interrupted`).
+#[test]
+fn shutdown_sigterm_during_replay() {
+    let file = get_temp_file("shutdown.sqlite");
+    create_downloaded_db(&file);
+
+    let events: Map<String, Value> = serde_json::from_str(&std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/../tdog_core/src/tests/stripe/event_seq/data/customer_1/events.json")).unwrap()).unwrap();
+    let events = events.get("events").unwrap().as_array().unwrap();
+    assert_eq!(events.len(), 4);
+
+    let events_file = get_temp_file("events.jsonl");
+    let lines: Vec<String> = events.iter().map(|x| x.to_string()).collect();
+    std::fs::write(&events_file, lines.join("\n")).unwrap();
+
+    let config = json!({
+        "cmd": {
+            "fn": "replay",
+            "args": {
+                "events": events_file,
+                "to": {"sqlite": {"file": file}},
+                "batch_size": 1,
+                "options": {
+                    "hooks": {
+                        // `$PPID` is the `tdog` process; the sleep lets the signal handler run before the next batch.
+                        "on_apply_events": {"cmd": {"cmd": "kill -TERM $PPID; sleep 1"}}
+                    }
+                }
+            }
+        }
+    });
+
+    let out = Command::new(env!("CARGO_BIN_EXE_tdog_cli")).arg("--json").arg(config.to_string()).output().unwrap();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+
+    assert_eq!(out.status.code(), Some(75), "{}", stderr);
+    let last: Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert_eq!(last["error"]["kind"], "interrupted");
+
+    // Only the first batch was applied, and its tx was committed in full.
+    assert_eq!(get_count(&file, "select count(*) from td_stripe_apply_events"), 1);
+    assert_eq!(get_count(&file, "select count(*) from td_runs where type='apply_events' and end_ts is not null"), 1);
+    assert_eq!(get_count(&file, "select count(*) from td_runs"), 2);
+}
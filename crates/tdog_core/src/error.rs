@@ -0,0 +1,188 @@
+use std::any::Any;
+use std::fmt;
+
+use serde_json::{json, Value};
+use stripe_client::http::http::UniErr;
+use stripe_client::types::types::UniStrTypeA3B873;
+use unicon::error::UniConError;
+
+// Errors that stop a job, grouped by what a process supervisor (systemd, Kubernetes, a shell loop) should do about them.
+// - Each kind maps to a documented process exit code (see `readme.md`), so the supervisor can decide whether to restart without parsing logs.
+// - Bugs and invalid states still `panic!` ("fail and reboot"); `Cmd::run` maps a panic to `Internal`.
+#[derive(Debug, Clone)]
+pub enum TdError {
+    // Invalid JSON config. Restarting will not help.
+    Config(String),
+
+    // The Stripe secret key is invalid, revoked or missing permissions.
+    StripeAuth(String),
+
+    // Stripe responded with HTTP 429 after all retries, or `exit_on_429` is set.
+    RateLimit(String),
+
+    // Could not reach the Stripe API after all retries.
+    Network(String),
+
+    // The DB was written by a different CLI or Stripe API version.
+    VersionMismatch(String),
+
+    // The existing DB does not match the tables this CLI would create, or belongs to a different Stripe account.
+    SchemaConflict(String),
+
+    // Could not connect to, or write to the DB.
+    Db(String),
+
+    // A first download was stopped by SIGINT/SIGTERM; its tx was rolled back.
+    Interrupted(String),
+
+    // `check` found rows whose owners are missing for relations the Stripe API keeps consistent.
+    Integrity(String),
+
+    // Any other error (a panic, or an unexpected Stripe API error).
+    Internal(String),
+}
+
+impl TdError {
+    pub fn get_kind(&self) -> &'static str {
+        match self {
+            TdError::Config(_) => "config",
+            TdError::StripeAuth(_) => "stripe_auth",
+            TdError::RateLimit(_) => "rate_limit",
+            TdError::Network(_) => "network",
+            TdError::VersionMismatch(_) => "version_mismatch",
+            TdError::SchemaConflict(_) => "schema_conflict",
+            TdError::Db(_) => "db",
+            TdError::Interrupted(_) => "interrupted",
+            TdError::Integrity(_) => "integrity",
+            TdError::Internal(_) => "internal",
+        }
+    }
+
+    pub fn get_msg(&self) -> &str {
+        match self {
+            TdError::Config(x) |
+            TdError::StripeAuth(x) |
+            TdError::RateLimit(x) |
+            TdError::Network(x) |
+            TdError::VersionMismatch(x) |
+            TdError::SchemaConflict(x) |
+            TdError::Db(x) |
+            TdError::Interrupted(x) |
+            TdError::Integrity(x) |
+            TdError::Internal(x) => x
+        }
+    }
+
+    // Note: Do not re-number these; supervisors match on them.
+    // - 1 is `Internal`, which includes panics (`Cmd::run` catches them; an uncaught Rust panic would exit with 101).
+    // - 75 is `EX_TEMPFAIL` from `sysexits.h`.
+    pub fn get_exit_code(&self) -> i32 {
+        match self {
+            TdError::Internal(_) => 1,
+            TdError::Config(_) => 10,
+            TdError::StripeAuth(_) => 11,
+            TdError::RateLimit(_) => 12,
+            TdError::Network(_) => 13,
+            TdError::VersionMismatch(_) => 14,
+            TdError::SchemaConflict(_) => 15,
+            TdError::Db(_) => 16,
+            TdError::Integrity(_) => 17,
+            TdError::Interrupted(_) => 75,
+        }
+    }
+
+    // True when the same config may succeed if run again later (without a human changing the config, keys or DB).
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            TdError::RateLimit(_) |
+            TdError::Network(_) |
+            TdError::Db(_) |
+            TdError::Interrupted(_) => true,
+
+            // Panics may be caused by a temporary state (E.g. an object being mutated during the download), so "fail and reboot" still applies.
+            TdError::Internal(_) => true,
+
+            TdError::Config(_) |
+            TdError::StripeAuth(_) |
+            TdError::VersionMismatch(_) |
+            TdError::SchemaConflict(_) |
+            TdError::Integrity(_) => false
+        }
+    }
+
+    // A single JSON line written to stderr as the last output of the process.
+    // - `job` = the job label (`options.label`) when the config has many jobs.
+    pub fn to_json_line(&self, job: Option<&str>) -> String {
+        json!({
+            "error": self.to_json(job)
+        }).to_string()
+    }
+
+    // Also the summary for the `on_error` hook.
+    pub fn to_json(&self, job: Option<&str>) -> Value {
+        json!({
+            "kind": self.get_kind(),
+            "exit_code": self.get_exit_code(),
+            "retryable": self.is_retryable(),
+            "job": job,
+            "msg": self.get_msg(),
+        })
+    }
+
+    // Convert a caught panic to an error so that it can be reported with the same exit code/JSON line as other errors.
+    pub fn from_panic(x: Box<dyn Any + Send>) -> TdError {
+        let msg = if let Some(s) = x.downcast_ref::<String>() {
+            s.clone()
+        } else if let Some(s) = x.downcast_ref::<&str>() {
+            s.to_string()
+        } else {
+            "Panic with a non-string payload.".to_string()
+        };
+
+        TdError::Internal(msg)
+    }
+
+    // A failed commit of `what` (E.g. "download transaction").
+    // - With `options.foreign_keys=enforced`, a foreign key violation means rows reference owners that were not written; retrying will not add them.
+    pub fn from_commit(what: &str, e: UniConError) -> TdError {
+        match e {
+            UniConError::ForeignKey(x) => TdError::Integrity(format!("Could not commit the {}, rows reference missing owners: {}", what, x)),
+            e => TdError::Db(format!("Could not commit the {}: {}", what, e))
+        }
+    }
+}
+
+impl fmt::Display for TdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} error: {}", self.get_kind(), self.get_msg())
+    }
+}
+
+impl std::error::Error for TdError {}
+
+// Note: The Stripe client has already retried the request; this is the final response.
+impl From<UniErr> for TdError {
+    fn from(e: UniErr) -> Self {
+        match e {
+            UniErr::Net(x) => TdError::Network(format!("Stripe API request failed: {}", x)),
+            // The same as a response reqwest cannot decode (`Net`).
+            UniErr::Json(x) => TdError::Network(format!("Stripe API request failed: {}", x)),
+            UniErr::App(x) => {
+                let e = &x.error;
+                let msg = format!("Stripe API error: {}", e.message.clone().unwrap_or(format!("{:?}", e.type_x)));
+
+                // Stripe uses `code=rate_limit` on 429 responses (and `type=rate_limit_error` in the Open API spec).
+                if e.code.as_deref() == Some("rate_limit") {
+                    return TdError::RateLimit(msg);
+                }
+
+                match e.type_x {
+                    UniStrTypeA3B873::AuthenticationError => TdError::StripeAuth(msg),
+                    UniStrTypeA3B873::RateLimitError => TdError::RateLimit(msg),
+                    UniStrTypeA3B873::ApiConnectionError => TdError::Network(msg),
+                    _ => TdError::Internal(msg)
+                }
+            }
+        }
+    }
+}
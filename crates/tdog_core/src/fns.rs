@@ -25,8 +25,14 @@ use unicon_proc_macro::{*};
 use uuid::Uuid;
 
 use crate::*;
+use crate::error::TdError;
 use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::check;
+use crate::providers::stripe::event_archive;
+use crate::providers::stripe::replay::replay;
+use crate::providers::stripe::snapshot;
 use crate::providers::stripe::watch::{once, poll};
+use crate::providers::stripe::write_ctx::{with_write_ctx, WriteCtx};
 use crate::util::*;
 use stripe_client::http::http::{StripeClient, Config};
 
@@ -47,33 +53,37 @@ use stripe_client::http::http::{StripeClient, Config};
 
 // See `Repository` in gql schema, start there? contains a lot of fields.
 impl Download {
-    pub async fn watch(&self) {
-        let mut uc = UniCon::new(&UniConCreate {
-            engine: self.to.clone()
-        });
+    pub async fn watch(&self) -> Result<(), TdError> {
+        let mut uc = self.get_uc()?;
         let mut sc = self.get_stripe_client();
-        sc.get_account_set_cache().await;
+        sc.get_account_set_cache().await?;
         let poll_freq_ms: u64 = self.options.poll_freq_ms.unwrap_or(400).into();
 
-        let watch = match &self.from {
+        match &self.from {
             FromAPI::Stripe(s) => {
-                poll(&sc, &mut uc, poll_freq_ms, &self).await;
+                let ctx = WriteCtx::new(&self.options)?;
+                sc.raw = ctx.get_raw_index();
+                with_write_ctx(ctx, poll(&sc, &mut uc, poll_freq_ms, self)).await
             }
-        };
+        }
     }
-    pub async fn download_all(&self) {
-        let mut uc = UniCon::new(&UniConCreate {
-            engine: self.to.clone()
-        });
+    pub async fn download_all(&self) -> Result<(), TdError> {
+        let mut uc = self.get_uc()?;
         let mut sc = self.get_stripe_client();
-        sc.get_account_set_cache().await;
+        sc.get_account_set_cache().await?;
 
 
         match &self.from {
             FromAPI::Stripe(s) => {
-                once(&sc, &mut uc, &self).await;
+                let ctx = WriteCtx::new(&self.options)?;
+                sc.raw = ctx.get_raw_index();
+                with_write_ctx(ctx, once(&sc, &mut uc, self)).await
             }
-        };
+        }
+    }
+
+    fn get_uc(&self) -> Result<UniCon, TdError> {
+        get_uc(&self.to)
     }
 
     pub fn get_stripe_client(&self) -> StripeClient {
@@ -96,6 +106,89 @@ impl Download {
 }
 
 
+impl Replay {
+    pub async fn replay(&self) -> Result<(), TdError> {
+        let mut uc = get_uc(&self.to)?;
+
+        // Without a key the client is never used (see `replay::replay`).
+        let mut sc = get_stripe_client_opt(&self.from, &self.to, &self.options).await?;
+
+        let ctx = WriteCtx::new(&self.options)?;
+        sc.raw = ctx.get_raw_index();
+        with_write_ctx(ctx, replay(&sc, &mut uc, self)).await
+    }
+}
+
+impl Check {
+    pub async fn check(&self) -> Result<(), TdError> {
+        let mut uc = get_uc(&self.db)?;
+
+        // Without a key the client is never used (see `check::check`).
+        let mut sc = get_stripe_client_opt(&self.from, &self.db, &self.options).await?;
+
+        let ctx = WriteCtx::new(&self.options)?;
+        sc.raw = ctx.get_raw_index();
+        with_write_ctx(ctx, check::check(&sc, &mut uc, self)).await.map(|_| ())
+    }
+}
+
+// `stripe_account` is only set when `from` is set.
+async fn get_stripe_client_opt(from: &Option<FromAPI>, to: &Engine, options: &Options) -> Result<StripeClient, TdError> {
+    Ok(match from {
+        Some(from) => {
+            let dl = Download {
+                from: from.clone(),
+                to: to.clone(),
+                options: options.clone(),
+            };
+            let mut sc = dl.get_stripe_client();
+            sc.get_account_set_cache().await?;
+            sc
+        }
+        None => StripeClient::new(Config {
+            secret_key: "".into(),
+            is_test: true,
+            base: "https://api.stripe.com".into(),
+            headers: None,
+            proxy: None,
+            timeout_ms: None,
+            retry: false,
+            log_requests: false,
+        })
+    })
+}
+
+impl EventsExport {
+    pub fn export(&self) -> Result<(), TdError> {
+        let mut uc = get_uc(&self.db)?;
+        event_archive::export(&mut uc, self).map(|_| ())
+    }
+}
+
+impl EventsImport {
+    pub fn import(&self) -> Result<(), TdError> {
+        let mut uc = get_uc(&self.to)?;
+        event_archive::import(&mut uc, self).map(|_| ())
+    }
+}
+
+impl Snapshot {
+    pub fn snapshot(&self) -> Result<(), TdError> {
+        let mut src = get_uc(&self.db)?;
+        let mut dst = get_uc(&self.to)?;
+        snapshot::snapshot(&mut src, &mut dst, self).map(|_| ())
+    }
+}
+
+fn get_uc(to: &Engine) -> Result<UniCon, TdError> {
+    let create = UniConCreate {
+        engine: to.clone()
+    };
+
+    UniCon::new(&create).map_err(|e| TdError::Db(e.to_string()))
+}
+
+
 pub(crate) fn get_temp_file(f: String) -> std::io::Result<String> {
     let mut dir = env::temp_dir();
     dir.push("td-data");
@@ -0,0 +1,117 @@
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+use crate::{Hook, HookCmd, HookHttp, Hooks};
+use crate::util::get_job_label;
+
+
+// Post-run hooks (`options.hooks`): tell other processes that a run completed or the job failed.
+// - Run after the tx has committed; a failed hook is logged and ignored (it never rolls back or stops the job).
+// - Awaited in the job's task, so the next poll starts after the hook returns or times out.
+// - The summary JSON is sent on stdin (cmd) or as the POST body (http).
+//      - cmd: also set as env vars `TDOG_HOOK`, `TDOG_JOB`, `TDOG_RUN_ID` and `TDOG_SUMMARY`.
+//      - http: `X-Tdog-Hook` header.
+
+
+pub async fn on_download_complete<T: Serialize>(hooks: &Hooks, summary: &T) {
+    run_opt("on_download_complete", &hooks.on_download_complete, summary).await
+}
+
+pub async fn on_apply_events<T: Serialize>(hooks: &Hooks, summary: &T) {
+    run_opt("on_apply_events", &hooks.on_apply_events, summary).await
+}
+
+pub async fn on_error<T: Serialize>(hooks: &Hooks, summary: &T) {
+    run_opt("on_error", &hooks.on_error, summary).await
+}
+
+async fn run_opt<T: Serialize>(name: &str, hook: &Option<Hook>, summary: &T) {
+    if let Some(x) = hook {
+        let summary = serde_json::to_value(summary).unwrap();
+        if let Err(e) = run(name, x, &summary).await {
+            warn!("Hook `{}` failed (ignored, the job continues): {}", name, e);
+        }
+    }
+}
+
+pub async fn run(name: &str, hook: &Hook, summary: &Value) -> Result<(), String> {
+    let ms = match hook {
+        Hook::Cmd(x) => x.timeout_ms,
+        Hook::Http(x) => x.timeout_ms
+    };
+
+    let res = match hook {
+        Hook::Cmd(x) => timeout(Duration::from_millis(ms), run_cmd(name, x, summary)).await,
+        Hook::Http(x) => timeout(Duration::from_millis(ms), run_http(name, x, summary)).await,
+    };
+
+    match res {
+        Ok(x) => x,
+        Err(_) => Err(format!("Timed out after {}ms.", ms))
+    }
+}
+
+fn get_shell(cmd: &str) -> Command {
+    if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C").arg(cmd);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(cmd);
+        c
+    }
+}
+
+// The process is killed when the timeout drops this future.
+async fn run_cmd(name: &str, x: &HookCmd, summary: &Value) -> Result<(), String> {
+    let json = summary.to_string();
+    let run_id = summary.get("run_id").map(|x| x.to_string()).unwrap_or_default();
+
+    let mut child = get_shell(&x.cmd)
+        .env("TDOG_HOOK", name)
+        .env("TDOG_JOB", get_job_label().unwrap_or_default())
+        .env("TDOG_RUN_ID", run_id)
+        .env("TDOG_SUMMARY", &json)
+        .stdin(Stdio::piped())
+        // Stdout may be CDC output (`options.cdc`).
+        .stdout(std::io::stderr())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Could not start `{}`: {}", &x.cmd, e))?;
+
+    {
+        // Ignore write errors; the command may not read stdin.
+        let mut stdin = child.stdin.take().unwrap();
+        let _ = stdin.write_all(json.as_bytes()).await;
+    }
+
+    let status = child.await.map_err(|e| format!("Could not run `{}`: {}", &x.cmd, e))?;
+    if !status.success() {
+        return Err(format!("`{}` exited with {}.", &x.cmd, status));
+    }
+
+    Ok(())
+}
+
+async fn run_http(name: &str, x: &HookHttp, summary: &Value) -> Result<(), String> {
+    let res = reqwest::Client::new()
+        .post(&x.url)
+        .header("X-Tdog-Hook", name)
+        .json(summary)
+        .send()
+        .await
+        .map_err(|e| format!("POST {} failed: {}", &x.url, e))?;
+
+    if !res.status().is_success() {
+        return Err(format!("POST {} responded with HTTP {}.", &x.url, res.status()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use hyper::service::{make_service_fn, service_fn};
+use serde_json::{json, Map, Value};
+use tokio::time::timeout;
+use unicon::uc::{Engine, UniCon, UniConCreate};
+
+use crate::{Cmd, Cmds, HttpServer};
+use crate::error::TdError;
+use crate::metrics;
+
+
+// Optional HTTP listener for monitoring (`http_server` in the config), shared by all jobs in the process.
+// - `GET /metrics`: Prometheus metrics (see `metrics.rs`).
+// - `GET /healthz`: 200 when every job's DB responds to `SELECT 1` (liveness).
+// - `GET /readyz`: 200 when every job has finished its first download and its last `apply_events` poll is within `ready_max_staleness_ms` (readiness).
+// - Runs on its own Tokio task; it only reads process state and never touches a job's DB connection (probes use their own connection).
+
+// A DB that does not respond in this time is reported as unhealthy.
+const DB_PING_TIMEOUT_MS: u64 = 5_000;
+
+struct State {
+    opts: HttpServer,
+    jobs: Vec<Job>,
+}
+
+struct Job {
+    // `tdog_job` label.
+    label: String,
+    db: Engine,
+
+    // Kept between probes; `None` until the first probe, or after a failed one (the next probe reconnects).
+    probe_uc: Arc<Mutex<Option<UniCon>>>,
+}
+
+fn get_jobs(cmds: &Cmds) -> Vec<Job> {
+    let get_job = |label: String, x: &Cmd| Job {
+        label,
+        db: x.get_db().clone(),
+        probe_uc: Arc::new(Mutex::new(None)),
+    };
+
+    // The label is only set on the job's task when there are many jobs (see `Cmds::run`).
+    match cmds {
+        Cmds::One(x) => vec![get_job("".into(), x)],
+        Cmds::Many(x) => x.iter().enumerate().map(|(i, cmd)| get_job(cmd.get_label(i), cmd)).collect()
+    }
+}
+
+// Uses a separate connection as the job's connection is owned by its task.
+async fn ping_db(job: &Job) -> Result<(), String> {
+    let (db, probe_uc) = (job.db.clone(), job.probe_uc.clone());
+
+    let ping = tokio::task::spawn_blocking(move || {
+        // A probe that timed out may still be blocked on the DB; do not queue more threads behind it.
+        let mut slot = probe_uc.try_lock().map_err(|_| "The previous probe has not returned yet.".to_string())?;
+
+        if slot.is_none() {
+            *slot = Some(UniCon::new(&UniConCreate { engine: db }).map_err(|e| e.to_string())?);
+        }
+
+        let res = slot.as_mut().unwrap().ping();
+        if res.is_err() {
+            *slot = None;
+        }
+        res
+    });
+
+    match timeout(Duration::from_millis(DB_PING_TIMEOUT_MS), ping).await {
+        Ok(Ok(x)) => x,
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("No response after {}ms.", DB_PING_TIMEOUT_MS))
+    }
+}
+
+fn get_job_key(label: &str) -> String {
+    if label.is_empty() { "job".into() } else { label.to_string() }
+}
+
+async fn get_healthz(s: &State) -> (bool, Value) {
+    let mut all_ok = true;
+    let mut jobs = Map::new();
+
+    for job in &s.jobs {
+        let res = ping_db(job).await;
+        all_ok = all_ok && res.is_ok();
+        jobs.insert(get_job_key(&job.label), json!({
+            "ok": res.is_ok(),
+            "db_error": res.err(),
+        }));
+    }
+
+    (all_ok, json!({"ok": all_ok, "jobs": jobs}))
+}
+
+// The heartbeat is written after every successful `apply_events` poll, which only runs once the first download has been committed.
+fn get_readyz(s: &State) -> (bool, Value) {
+    let now = Utc::now();
+    let max_secs = s.opts.ready_max_staleness_ms as f64 / 1000.0;
+    let mut all_ok = true;
+    let mut jobs = Map::new();
+
+    for Job { label, .. } in &s.jobs {
+        let secs = metrics::get_last_heartbeat(label).map(|x| now.signed_duration_since(x).num_milliseconds() as f64 / 1000.0);
+        let ok = matches!(secs, Some(x) if x <= max_secs);
+        all_ok = all_ok && ok;
+        jobs.insert(get_job_key(label), json!({
+            "ok": ok,
+            "seconds_since_heartbeat": secs,
+        }));
+    }
+
+    (all_ok, json!({"ok": all_ok, "jobs": jobs}))
+}
+
+fn json_res(ok: bool, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(if ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE })
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+async fn handle(s: Arc<State>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let res = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/metrics") => {
+            Response::builder()
+                .header("Content-Type", "text/plain; version=0.0.4")
+                .body(Body::from(metrics::render()))
+                .unwrap()
+        }
+        (&Method::GET, "/healthz") => {
+            let (ok, body) = get_healthz(&s).await;
+            json_res(ok, body)
+        }
+        (&Method::GET, "/readyz") => {
+            let (ok, body) = get_readyz(&s);
+            json_res(ok, body)
+        }
+        _ => {
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::from("Not found."))
+                .unwrap()
+        }
+    };
+
+    Ok(res)
+}
+
+// Returns Err when the address is invalid or cannot be bound (E.g. the port is in use).
+pub fn start(x: &HttpServer, cmds: &Cmds) -> Result<SocketAddr, TdError> {
+    let addr: SocketAddr = x.addr.parse().map_err(|e| TdError::Config(format!("Invalid `http_server.addr` `{}`: {}", &x.addr, e)))?;
+
+    let state = Arc::new(State {
+        opts: x.clone(),
+        jobs: get_jobs(cmds),
+    });
+
+    let make_svc = make_service_fn(move |_| {
+        let state = state.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), req)))
+        }
+    });
+
+    let server = Server::try_bind(&addr)
+        .map_err(|e| TdError::Config(format!("Could not listen on `http_server.addr` `{}`: {}", &x.addr, e)))?
+        .serve(make_svc);
+
+    let local = server.local_addr();
+    info!("HTTP server listening on http://{} (/metrics, /healthz, /readyz).", local);
+
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            error!("HTTP server stopped: {}", e);
+        }
+    });
+
+    Ok(local)
+}
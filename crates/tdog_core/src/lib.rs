@@ -6,8 +6,15 @@ extern crate log;
 use serde::{Deserialize, Serialize};
 //use unicon::uc::Engine;
 
+use std::collections::BTreeMap;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+use futures::FutureExt;
+use futures::future::join_all;
+use tokio::time::delay_for;
 use unicon::uc::{*};
-use crate::util::{get_temp_file, get_unique_id};
+use crate::util::{get_job_label, get_temp_file, get_unique_id, JOB_LABEL};
+use crate::error::TdError;
 
 #[allow(unused)]
 mod fns;
@@ -18,6 +25,16 @@ pub mod util;
 #[allow(unused)]
 pub mod providers;
 
+pub mod shutdown;
+
+pub mod error;
+
+pub mod hooks;
+
+pub mod metrics;
+
+pub mod http_server;
+
 
 #[cfg(test)]
 mod tests;
@@ -33,26 +50,178 @@ mod tests;
 #[serde(tag = "fn", content = "args")]
 pub enum Cmd {
     #[serde(rename = "download")]
-    Download(Download)
+    Download(Download),
+
+    #[serde(rename = "replay")]
+    Replay(Replay),
+
+    #[serde(rename = "events_export")]
+    EventsExport(EventsExport),
+
+    #[serde(rename = "events_import")]
+    EventsImport(EventsImport),
+
+    #[serde(rename = "snapshot")]
+    Snapshot(Snapshot),
+
+    #[serde(rename = "check")]
+    Check(Check),
+}
+
+
+// One or many commands from a single config.
+// - Many: Ops teams may run one process per (account, database) pair; this allows a single config/process/systemd unit to run them all.
+// - Each job gets its own Stripe client, DB connection and request queue; nothing is shared except the Tokio runtime and the log output.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[serde(untagged)]
+pub enum Cmds {
+    One(Box<Cmd>),
+    Many(Vec<Cmd>),
+}
+
+impl Cmds {
+    pub fn get_all(&self) -> Vec<&Cmd> {
+        match self {
+            Cmds::One(x) => vec![x.as_ref()],
+            Cmds::Many(x) => x.iter().collect()
+        }
+    }
+
+    pub fn get_all_mut(&mut self) -> Vec<&mut Cmd> {
+        match self {
+            Cmds::One(x) => vec![x.as_mut()],
+            Cmds::Many(x) => x.iter_mut().collect()
+        }
+    }
+
+    // Returns the first job (in config order) that failed and was not (or could no longer be) restarted.
+    // - The label is None when there is a single job.
+    // - Jobs run concurrently on the current task (not `tokio::spawn`) as the SQLite connection is not `Send`.
+    // - An error or panic in one job does not stop the others.
+    pub async fn run(&self) -> Result<(), (Option<String>, TdError)> {
+        match self {
+            Cmds::One(x) => x.run_with_restart(false).await.map_err(|e| (None, e)),
+            Cmds::Many(x) => {
+                let jobs = x.iter().enumerate().map(|(i, cmd)| {
+                    let label = cmd.get_label(i);
+                    JOB_LABEL.scope(Some(label.clone()), cmd.run_with_restart(true)).map(|res| res.map_err(|e| (Some(label), e)))
+                });
+
+                match join_all(jobs).await.into_iter().find_map(|res| res.err()) {
+                    None => Ok(()),
+                    Some(x) => Err(x)
+                }
+            }
+        }
+    }
 }
 
 
 impl Cmd {
-    pub async fn run(&self) {
+    // Used to identify a job in logs when many jobs run in the same process.
+    pub fn get_label(&self, i: usize) -> String {
+        self.get_options().label.clone().unwrap_or(format!("job_{}", i + 1))
+    }
+
+    pub fn get_options(&self) -> &Options {
         match self {
-            Cmd::Download(dl) => {
-                if dl.options.watch {
-                    dl.watch().await;
-
-                    // if let Some(f) = after_run {
-                    //     f(None, 123);
-                    // }
-                } else {
-                    dl.download_all().await;
+            Cmd::Download(dl) => &dl.options,
+            Cmd::Replay(rp) => &rp.options,
+            Cmd::EventsExport(x) => &x.options,
+            Cmd::EventsImport(x) => &x.options,
+            Cmd::Snapshot(x) => &x.options,
+            Cmd::Check(x) => &x.options
+        }
+    }
+
+    // The DB the job reads or writes.
+    pub fn get_db(&self) -> &Engine {
+        match self {
+            Cmd::Download(dl) => &dl.to,
+            Cmd::Replay(rp) => &rp.to,
+            Cmd::EventsExport(x) => &x.db,
+            Cmd::EventsImport(x) => &x.to,
+            Cmd::Snapshot(x) => &x.to,
+            Cmd::Check(x) => &x.db
+        }
+    }
+
+    // - `isolate`: Many jobs run in this process, so a failed job does not stop the others.
+    //      - When there is a single job and no restart option, the error is returned to exit the process ("fail and reboot" - the process scheduler restarts the process).
+    async fn run_with_restart(&self, isolate: bool) -> Result<(), TdError> {
+        let (restart, hooks) = (self.get_options().restart.clone(), &self.get_options().hooks);
+
+        let mut restarts = 0;
+        let mut backoff_ms = restart.as_ref().map(|x| x.backoff_ms).unwrap_or(0);
+        let others = if isolate { " Other jobs will continue to run." } else { "" };
+
+        loop {
+            let e = match self.run().await {
+                Ok(()) => return Ok(()),
+                Err(e) => e
+            };
+
+            error!("Job failed: {}", e);
+            hooks::on_error(hooks, &e.to_json(get_job_label().as_deref())).await;
+
+            if shutdown::is_shutdown() {
+                error!("Job failed during shutdown. Not restarting.");
+                return Err(e);
+            }
+
+            let r = match &restart {
+                None => {
+                    if isolate {
+                        error!("Job stopped.{}", others);
+                    }
+                    return Err(e);
+                }
+                Some(x) => x
+            };
+
+            if !e.is_retryable() {
+                error!("Not restarting the job as a `{}` error will not resolve without changing the config, Stripe key or DB.{}", e.get_kind(), others);
+                return Err(e);
+            }
+
+            if let Some(max) = r.max_restarts {
+                if restarts >= max {
+                    error!("Job has been restarted the maximum number of times ({}).{}", max, others);
+                    return Err(e);
                 }
             }
+
+            restarts += 1;
+            error!("Restarting job in {}ms (restart {}).", backoff_ms, restarts);
+            delay_for(Duration::from_millis(backoff_ms)).await;
+            backoff_ms = (backoff_ms * 2).min(r.max_backoff_ms);
         }
     }
+
+    // Panics are caught and returned as `TdError::Internal` so that every failure has an exit code, and panics do not unwind into other jobs.
+    pub async fn run(&self) -> Result<(), TdError> {
+        shutdown::listen_for_signals();
+
+        let res = AssertUnwindSafe(async {
+            match self {
+                Cmd::Download(dl) => {
+                    if dl.options.watch {
+                        dl.watch().await
+                    } else {
+                        dl.download_all().await
+                    }
+                }
+                Cmd::Replay(rp) => rp.replay().await,
+                Cmd::EventsExport(x) => x.export(),
+                Cmd::EventsImport(x) => x.import(),
+                Cmd::Snapshot(x) => x.snapshot(),
+                Cmd::Check(x) => x.check().await
+            }
+        }).catch_unwind().await;
+
+        res.unwrap_or_else(|e| Err(TdError::from_panic(e)))
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -63,9 +232,95 @@ pub struct Download {
 
     #[serde(default)]
     pub options: Options,
+}
+
+// Apply Stripe events exported to local files (E.g. from the dashboard or a webhook log) to a DB that contains a download.
+// - `options.watch` and `options.apply_events_after_one_shot_dl` are ignored.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct Replay {
+    // A directory of `.json` files, or a `.jsonl` file (one event per line).
+    pub events: String,
+
+    pub to: Engine,
+
+    // Only needed when the DB was created with `options.created_gte` (parents created before the cutoff are downloaded from Stripe).
+    // - When set, the Stripe account must match the one that wrote the DB.
+    pub from: Option<FromAPI>,
+
+    // Events per `apply_events` tx (one `td_runs` row per batch).
+    #[serde(default = "default_replay_batch_size")]
+    pub batch_size: usize,
+
+    #[serde(default)]
+    pub options: Options,
+}
+
+fn default_replay_batch_size() -> usize { 500 }
+
+// Append `notification_events` rows to gzipped JSONL segments (one per UTC day) for a long term event archive.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct EventsExport {
+    pub db: Engine,
+
+    // Only contains the events of one Stripe account.
+    pub dir: String,
+
+    // After exporting, delete exported events created more than this many days ago from `db`.
+    pub retention_days: Option<u32>,
+
+    #[serde(default)]
+    pub options: Options,
+}
+
+// Insert the rows of every segment in `dir` into `notification_events` (E.g. a fresh DB for auditors).
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct EventsImport {
+    pub dir: String,
+
+    pub to: Engine,
+
+    #[serde(default)]
+    pub options: Options,
+}
+
+// Build a new DB with the state of `db` at `as_of`: the first download, plus the stored events created up to `as_of`.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub db: Engine,
+
+    // A date (`2021-01-31`, midnight UTC), an RFC 3339 date time (`2021-01-31T23:59:59Z`) or a duration before now (`30days`).
+    pub as_of: String,
+
+    // Must be empty.
+    pub to: Engine,
+
+    // An `events_export` directory, for events deleted from `db` by `retention_days`.
+    pub events_dir: Option<String>,
+
+    #[serde(default)]
+    pub options: Options,
+}
+
+// Audit the relations between rows (`schema/relations.rs`): report referenced owner ids that are missing, per relation.
+// - Fails with `TdError::Integrity` when relations that the Stripe API keeps consistent have missing owners.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub db: Engine,
 
-    // #[serde(skip)]
-    // pub run_complete_cb: Option<Box<dyn FnMut(&mut UniCon, i64)>>
+    // Only needed for `repair`; the Stripe account must match the one that wrote the DB.
+    pub from: Option<FromAPI>,
+
+    // Download the missing owners of relations the Stripe API keeps consistent, and write them in a new `td_runs` row.
+    #[serde(default = "default_bool_false")]
+    pub repair: bool,
+
+    #[serde(default)]
+    pub options: Options,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -175,11 +430,223 @@ pub struct Options {
     // - The DB may have issues with many small transactions, but be OK with one per minute.
     // - The user may not need "real time" polling.
     pub poll_freq_ms: Option<u32>,
+
+    // Identifies this job in logs when the config has many jobs.
+    // - Defaults to `job_n` (1-indexed position in the `cmd` array).
+    pub label: Option<String>,
+
+    // When set, restart this job after it fails instead of leaving it stopped (or exiting the process when it is the only job).
+    pub restart: Option<Restart>,
+
+    // Selective sync: only create, download and apply events for a subset of tables.
+    #[serde(default)]
+    pub objects: Objects,
+
+    // Only download objects created at or after this point (E.g. `2021-01-01`, `2021-01-01T00:00:00Z`, or a duration before now like `90days`, `1year`).
+    // - Resolved once when the DB is created and recorded in `td_metadata.created_gte`; later runs use the recorded cutoff.
+    // - Parents created before the cutoff are downloaded individually when a written row references them.
+    pub created_gte: Option<String>,
+
+    // Keep every version of every row in `<table>_history` tables (`valid_from`, `valid_to`, `run_id`, `event_id`).
+    // - Only applies when the DB is created; later runs must use the same value.
+    #[serde(default = "default_bool_false")]
+    pub history: bool,
+
+    // Add a `raw` JSON column to every Stripe object table with the full API object (or the event's `data.object`), including the fields the typed columns leave out.
+    // - Only applies when the DB is created; later runs must use the same value.
+    #[serde(default = "default_bool_false")]
+    pub store_raw_json: bool,
+
+    // Copy `metadata` keys into indexed `md_<key>` columns, by table (E.g. `{"customers": ["tenant_id"], "subscriptions": ["plan_tier"]}`).
+    // - Only applies when the DB is created; later runs must use the same value.
+    #[serde(default)]
+    pub metadata_columns: BTreeMap<String, Vec<String>>,
+
+    // Add the relations between tables as foreign keys so tools reading the schema can discover joins (`none`, `declared` or `enforced`).
+    // - Only applies when the DB is created; `enforced` is only supported on SQLite and Postgres.
+    #[serde(default)]
+    pub foreign_keys: ForeignKeys,
+
+    // Create analytics views over the tables (`v_mrr_by_subscription`, `v_active_customers`, ...; `v_mrr_daily` needs `options.history`).
+    // - Can be enabled for an existing DB; views are replaced when a newer tdog version changes their SQL.
+    #[serde(default = "default_bool_false")]
+    pub views: bool,
+
+    // Record each change to a subscription's MRR (new, expansion, contraction, churn) in the `mrr_movements` table, starting from the first download.
+    // - Can be enabled for an existing DB; it starts from the current rows.
+    #[serde(default = "default_bool_false")]
+    pub mrr_movements: bool,
+
+    // Signal consumers after each `apply_events` tx with the run id and the written tables/ids.
+    // - Postgres: `NOTIFY tdog`; other engines: a row in `td_changes`.
+    #[serde(default = "default_bool_false")]
+    pub notify: bool,
+
+    // Change data capture: after each `apply_events` tx commits, write one JSON line per `td_stripe_writes` row to this output.
+    pub cdc: Option<CdcTo>,
+
+    // Apply events in a tx that is always rolled back, and log the rows that would be inserted, updated or deleted per table (and skipped events).
+    // - Runs once (`watch` is ignored); a DB that needs a full download is not downloaded.
+    #[serde(default = "default_bool_false")]
+    pub dry_run: bool,
+
+    // Run a command or POST to a URL after each download, apply (when events were applied) or job failure.
+    #[serde(default)]
+    pub hooks: Hooks,
 }
 
 fn default_bool_false() -> bool { false }
 fn default_bool_true() -> bool { true }
 
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct Restart {
+    // None = restart forever.
+    pub max_restarts: Option<u32>,
+
+    // Doubled after every failure up to `max_backoff_ms`.
+    #[serde(default = "default_backoff_ms")]
+    pub backoff_ms: u64,
+
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+fn default_backoff_ms() -> u64 { 5_000 }
+fn default_max_backoff_ms() -> u64 { 300_000 }
+
+// Table names (E.g. `customers`, `subscriptions`).
+// - Tables that are written as part of an included table's tree are included automatically (E.g. `subscription_items` with `subscriptions`).
+// - `td_*` and `notification_events` are always included.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[derive(Default)]
+pub struct Objects {
+    // None = all tables.
+    pub include: Option<Vec<String>>,
+
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ForeignKeys {
+    #[default]
+    None,
+
+    // Every relation from an id column, as a constraint that is not checked (or a column comment when the engine has no unchecked constraints).
+    Declared,
+
+    // Relations that the Stripe API keeps consistent, checked at commit.
+    Enforced,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub enum CdcTo {
+    #[serde(rename = "file")]
+    File(CdcFile),
+
+    #[serde(rename = "stdout")]
+    Stdout(CdcStdout),
+
+    // Connects to a socket the consumer is listening on.
+    #[serde(rename = "unix_socket")]
+    UnixSocket(CdcUnixSocket),
+}
+
+// Append only; when `file` would exceed `max_bytes` it is renamed to `file.1` (`file.1` to `file.2` etc, keeping `max_files`).
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct CdcFile {
+    pub file: String,
+
+    #[serde(default = "default_cdc_max_bytes")]
+    pub max_bytes: u64,
+
+    #[serde(default = "default_cdc_max_files")]
+    pub max_files: u32,
+}
+
+fn default_cdc_max_bytes() -> u64 { 100 * 1024 * 1024 }
+fn default_cdc_max_files() -> u32 { 5 }
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[derive(Default)]
+pub struct CdcStdout {}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct CdcUnixSocket {
+    pub file: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[derive(Default)]
+pub struct Hooks {
+    // Summary: `{"run_id", "inserts": {"customer": 10}, "write_quota_used"}`.
+    pub on_download_complete: Option<Hook>,
+
+    // Summary: `ApplySummary`.
+    pub on_apply_events: Option<Hook>,
+
+    // Summary: the error JSON written to stderr on exit (`{"kind", "exit_code", "retryable", "job", "msg"}`).
+    // - Runs before the job is restarted (when `options.restart` is set).
+    pub on_error: Option<Hook>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub enum Hook {
+    // Run with `sh -c` (`cmd /C` on Windows).
+    #[serde(rename = "cmd")]
+    Cmd(HookCmd),
+
+    #[serde(rename = "http")]
+    Http(HookHttp),
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct HookCmd {
+    pub cmd: String,
+
+    #[serde(default = "default_hook_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+// POST the summary as JSON (E.g. to a local service: `http://127.0.0.1:8080/tdog`).
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct HookHttp {
+    pub url: String,
+
+    #[serde(default = "default_hook_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_hook_timeout_ms() -> u64 { 10_000 }
+
+// Monitoring HTTP listener (one per process, not per job).
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct HttpServer {
+    // E.g. `127.0.0.1:9184`, `0.0.0.0:9184`.
+    pub addr: String,
+
+    // `/readyz` fails when a job's last successful `apply_events` poll is older than this.
+    // - Should be larger than `options.poll_freq_ms` plus the time to apply a large batch of events.
+    #[serde(default = "default_ready_max_staleness_ms")]
+    pub ready_max_staleness_ms: u64,
+}
+
+fn default_ready_max_staleness_ms() -> u64 { 60_000 }
+
 #[derive(Serialize, Deserialize)]
 #[derive(Debug, Clone)]
 #[derive(Default)]
@@ -0,0 +1,202 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use stripe_client::http::http::ReqLog;
+
+use crate::util::get_job_label;
+
+
+// Process metrics for the `/metrics` endpoint (Prometheus text format), labelled by job (`tdog_job`, empty when there is a single job).
+// - Always recorded (cheap counters); only served when `http_server` is set.
+// - Request metrics are read from `Stats.req_log` when it is drained (every 30s during the download, after each poll when watching).
+
+const REQ_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+const TX_BUCKETS: &[f64] = &[0.005, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0, 30.0];
+
+lazy_static! {
+    static ref METRICS: Mutex<BTreeMap<String, JobMetrics>> = Mutex::new(BTreeMap::new());
+}
+
+struct Histogram {
+    buckets: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Histogram {
+        Histogram {
+            buckets,
+            counts: vec![0; buckets.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, v: f64) {
+        for (i, le) in self.buckets.iter().enumerate() {
+            if v <= *le {
+                self.counts[i] += 1;
+            }
+        }
+        self.sum += v;
+        self.count += 1;
+    }
+}
+
+struct JobMetrics {
+    // Key = HTTP status code, or `net_error`.
+    requests: BTreeMap<String, u64>,
+    req_seconds: Histogram,
+    responses_429: u64,
+    reqs_retrying_429: u32,
+    queue_len: u64,
+    events_applied: BTreeMap<String, u64>,
+    tx_lock_seconds: Histogram,
+    last_heartbeat: Option<DateTime<Utc>>,
+}
+
+impl Default for JobMetrics {
+    fn default() -> Self {
+        JobMetrics {
+            requests: BTreeMap::new(),
+            req_seconds: Histogram::new(REQ_BUCKETS),
+            responses_429: 0,
+            reqs_retrying_429: 0,
+            queue_len: 0,
+            events_applied: BTreeMap::new(),
+            tx_lock_seconds: Histogram::new(TX_BUCKETS),
+            last_heartbeat: None,
+        }
+    }
+}
+
+fn with_job<R>(f: impl FnOnce(&mut JobMetrics) -> R) -> R {
+    let mut all = METRICS.lock().unwrap();
+    f(all.entry(get_job_label().unwrap_or_default()).or_default())
+}
+
+pub fn observe_reqs(req_log: &[ReqLog], reqs_retrying_429: u32) {
+    with_job(|m| {
+        for x in req_log {
+            let code = match x.code {
+                None => "net_error".to_string(),
+                Some(c) => c.to_string()
+            };
+            *m.requests.entry(code).or_insert(0) += 1;
+
+            if x.code == Some(429) {
+                m.responses_429 += 1;
+            }
+
+            m.req_seconds.observe(x.duration_ms as f64 / 1000.0);
+        }
+        m.reqs_retrying_429 = reqs_retrying_429;
+    });
+}
+
+pub fn set_queue_len(queue_len: u64) {
+    with_job(|m| m.queue_len = queue_len);
+}
+
+pub fn add_events_applied(event_type_count: &HashMap<String, u32>) {
+    with_job(|m| {
+        for (k, v) in event_type_count {
+            *m.events_applied.entry(k.clone()).or_insert(0) += *v as u64;
+        }
+    });
+}
+
+pub fn observe_tx_lock(d: Duration) {
+    with_job(|m| m.tx_lock_seconds.observe(d.as_secs_f64()));
+}
+
+// Called after `td_metadata.heartbeat_ts` has been written.
+pub fn set_heartbeat_now() {
+    with_job(|m| m.last_heartbeat = Some(Utc::now()));
+}
+
+// `job` = the `tdog_job` label (empty when there is a single job).
+pub fn get_last_heartbeat(job: &str) -> Option<DateTime<Utc>> {
+    METRICS.lock().unwrap().get(job).and_then(|m| m.last_heartbeat)
+}
+
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn write_header(s: &mut String, name: &str, t: &str, help: &str) {
+    writeln!(s, "# HELP {} {}", name, help).unwrap();
+    writeln!(s, "# TYPE {} {}", name, t).unwrap();
+}
+
+fn write_histogram(s: &mut String, name: &str, job: &str, h: &Histogram) {
+    for (le, count) in h.buckets.iter().zip(&h.counts) {
+        writeln!(s, "{}_bucket{{tdog_job=\"{}\",le=\"{}\"}} {}", name, job, le, count).unwrap();
+    }
+    writeln!(s, "{}_bucket{{tdog_job=\"{}\",le=\"+Inf\"}} {}", name, job, h.count).unwrap();
+    writeln!(s, "{}_sum{{tdog_job=\"{}\"}} {}", name, job, h.sum).unwrap();
+    writeln!(s, "{}_count{{tdog_job=\"{}\"}} {}", name, job, h.count).unwrap();
+}
+
+// Prometheus text exposition format (version 0.0.4).
+pub fn render() -> String {
+    let all = METRICS.lock().unwrap();
+    let jobs: Vec<(String, &JobMetrics)> = all.iter().map(|(k, v)| (escape(k), v)).collect();
+    let now = Utc::now();
+    let mut s = String::new();
+
+    write_header(&mut s, "tdog_stripe_requests_total", "counter", "Stripe API responses by HTTP status code (`net_error` when there was no response).");
+    for (job, m) in &jobs {
+        for (code, n) in &m.requests {
+            writeln!(s, "tdog_stripe_requests_total{{tdog_job=\"{}\",code=\"{}\"}} {}", job, code, n).unwrap();
+        }
+    }
+
+    write_header(&mut s, "tdog_stripe_request_duration_seconds", "histogram", "Stripe API request latency.");
+    for (job, m) in &jobs {
+        write_histogram(&mut s, "tdog_stripe_request_duration_seconds", job, &m.req_seconds);
+    }
+
+    write_header(&mut s, "tdog_stripe_429_responses_total", "counter", "Stripe API HTTP 429 (rate limited) responses.");
+    for (job, m) in &jobs {
+        writeln!(s, "tdog_stripe_429_responses_total{{tdog_job=\"{}\"}} {}", job, m.responses_429).unwrap();
+    }
+
+    write_header(&mut s, "tdog_stripe_429_requests_retrying", "gauge", "Requests retrying after one or more HTTP 429 responses.");
+    for (job, m) in &jobs {
+        writeln!(s, "tdog_stripe_429_requests_retrying{{tdog_job=\"{}\"}} {}", job, m.reqs_retrying_429).unwrap();
+    }
+
+    write_header(&mut s, "tdog_queue_length", "gauge", "Download requests waiting in the queue.");
+    for (job, m) in &jobs {
+        writeln!(s, "tdog_queue_length{{tdog_job=\"{}\"}} {}", job, m.queue_len).unwrap();
+    }
+
+    write_header(&mut s, "tdog_events_applied_total", "counter", "Stripe events applied, by event type.");
+    for (job, m) in &jobs {
+        for (t, n) in &m.events_applied {
+            writeln!(s, "tdog_events_applied_total{{tdog_job=\"{}\",type=\"{}\"}} {}", job, escape(t), n).unwrap();
+        }
+    }
+
+    write_header(&mut s, "tdog_apply_events_tx_lock_seconds", "histogram", "How long each apply_events DB transaction was held open.");
+    for (job, m) in &jobs {
+        write_histogram(&mut s, "tdog_apply_events_tx_lock_seconds", job, &m.tx_lock_seconds);
+    }
+
+    write_header(&mut s, "tdog_seconds_since_heartbeat", "gauge", "Seconds since `td_metadata.heartbeat_ts` was last written (after each successful apply_events poll).");
+    for (job, m) in &jobs {
+        if let Some(x) = m.last_heartbeat {
+            let secs = now.signed_duration_since(x).num_milliseconds() as f64 / 1000.0;
+            writeln!(s, "tdog_seconds_since_heartbeat{{tdog_job=\"{}\"}} {}", job, secs).unwrap();
+        }
+    }
+
+    s
+}
@@ -59,6 +59,17 @@ use crate::providers::stripe::schema::types::sku::Sku;
 use crate::providers::stripe::schema::types::tax_id::TaxId;
 use crate::providers::traits::{ExistsTx, GetInsertTs};
 use crate::Stripe;
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::dl::dl_missing_owners;
+use crate::error::TdError;
+use crate::providers::stripe::write_ctx;
+use crate::providers::stripe::raw_json;
+use crate::providers::stripe::cdc;
+use crate::providers::stripe::notify;
+use crate::providers::stripe::mrr_movements;
+use crate::providers::stripe::schema::util::ToDT;
+use crate::providers::stripe::dry_run;
+use crate::metrics;
 
 use super::schema_meta::{*};
 use stripe_client::types::types::UniStrObject6D0693::Event;
@@ -138,6 +149,10 @@ enum Skip {
     #[serde(rename = "skip.not_data_write")]
     NotDataWrite,
 
+    /// The event's object is written to a table that is excluded via `options.objects`.
+    #[serde(rename = "skip.object_excluded")]
+    ObjectExcluded,
+
     // When: first_dl, first_apply
     // - If the event_ts is before the row insert_ts, and that insert_ts was the download time (indicating the current version timestamp).
     // #[serde(rename = "skip.old_data")]
@@ -147,7 +162,7 @@ enum Skip {
 
 /// Make polling once per second very cheap when there are no new events.
 /// - E.g. do not download a page of 100 events using `starting_after` when all the contained events have already been applied.
-async fn has_events_after(c: &StripeClient, last_event: &String) -> bool {
+async fn has_events_after(c: &StripeClient, last_event: &String) -> Result<bool, TdError> {
     let p = Some(GetEvents {
         type_x: None,
         created: None,
@@ -160,17 +175,20 @@ async fn has_events_after(c: &StripeClient, last_event: &String) -> bool {
     });
 
 
-    // Assumption: This `unwrap` will exit the process if:
+    // Assumption: A Stripe error that is not auth/rate limit/network occurs when:
     // - The user has deleted all data for the Stripe account (they should drop the DB and start fresh).
     // - Two TD processes with different Stripe API keys pointing to the same DB.
     // - Running the CLI against a DB that is > 30 days old, so has missing events.
-    let res = c.v1_events_get(&p).await.unwrap();
-    res.data.len() > 0
+    let res = c.v1_events_get(&p).await.map_err(|e| match TdError::from(e) {
+        TdError::Internal(msg) => TdError::SchemaConflict(format!("Could not read events after the last applied event {}, the DB may belong to another Stripe account or be older than 30 days. {}", last_event, msg)),
+        x => x
+    })?;
+    Ok(!res.data.is_empty())
 }
 
 /// @todo/low Detect if (db, account) have been mixed up (a db for one account used with another account).
 /// - This is an operator error, but it should still be protected against.
-async fn get_all_unapplied_events(c: &StripeClient, uc: &mut UniCon, created_gte: Option<i64>) -> Vec<API::NotificationEvent> {
+async fn get_all_unapplied_events(c: &StripeClient, uc: &mut UniCon, created_gte: Option<i64>) -> Result<Vec<API::NotificationEvent>, TdError> {
     let mut o = vec![];
 
     let mut last_event = None;
@@ -179,8 +197,8 @@ async fn get_all_unapplied_events(c: &StripeClient, uc: &mut UniCon, created_gte
         last_event = Some(x.event_id.clone());
         debug!("Last event that was applied to db: {}.", &x.event_id);
 
-        if !has_events_after(&c, &x.event_id).await {
-            return o;
+        if !has_events_after(c, &x.event_id).await? {
+            return Ok(o);
         }
     }
 
@@ -219,7 +237,7 @@ async fn get_all_unapplied_events(c: &StripeClient, uc: &mut UniCon, created_gte
     let mut st = c.v1_events_get_st(&p.unwrap());
     pin_mut!(st);
     'walk: while let Some(val) = st.next().await {
-        let events = val.unwrap().data;
+        let events = val?.data;
 
         trace!("Downloaded a page of {} events.", events.len());
         for e in events {
@@ -247,7 +265,7 @@ async fn get_all_unapplied_events(c: &StripeClient, uc: &mut UniCon, created_gte
     // Ensure sorted by created ASC.
     // Docs = `fast in cases where the slice is nearly sorted`
     // o.sort_by_key(|k| k.created); // Do not sort. Keep Stripe response order in case two events have the same `created` value, but one event must always become before another (E.g. child updates before parent).
-    o
+    Ok(o)
 }
 
 
@@ -264,7 +282,7 @@ fn log_skip_not_data_write(utx: &mut UniTx, run_id: i64, event_id: String) {
 }
 
 
-pub fn apply_events_body(utx: &mut UniTx<'_>, run_id: i64, events: &Vec<API::NotificationEvent>) {
+pub fn apply_events_body(utx: &mut UniTx<'_>, run_id: i64, events: &Vec<API::NotificationEvent>, objects: &ObjectSelection) {
     if events.len() > 1 {
         // order created asc
         assert!(events.first().unwrap().created <= events.last().unwrap().created);
@@ -273,18 +291,39 @@ pub fn apply_events_body(utx: &mut UniTx<'_>, run_id: i64, events: &Vec<API::Not
     insert_all(utx, run_id, &events);
 
     for e in events {
-        if !Db::event_is_table_write(&e) {
-            log_skip_not_data_write(utx, run_id, e.id.clone());
-            continue;
+        match Db::get_event_table_name(&e) {
+            None => {
+                log_skip_not_data_write(utx, run_id, e.id.clone());
+                continue;
+            }
+            Some(t) => {
+                if !objects.is_included(t) {
+                    (Action::Skip(Skip::ObjectExcluded)).log(utx, run_id, e.id.clone());
+                    continue;
+                }
+            }
         }
 
-        let action = write_one_event(utx, run_id, &e);
+        write_ctx::set_event(Some((e.id.clone(), e.created.to_dt3())));
+        raw_json::set_event_scope(&e.id);
+        let action = write_one_event(utx, run_id, &e, objects);
         action.log(utx, run_id, e.id.clone());
+
+        if write_ctx::is_mrr_movements_enabled() {
+            if let Action::Write(write_ids) = &action {
+                for sub_id in mrr_movements::tx_get_written_sub_ids(utx, write_ids) {
+                    mrr_movements::tx_write(utx, run_id, &e.id, ToDT::to_dt(e.created), &sub_id);
+                }
+            }
+        }
     }
+
+    write_ctx::set_event(None);
+    write_ctx::set_raw_scope(None);
 }
 
 
-fn write_one_event(utx: &mut UniTx, run_id: i64, e: &API::NotificationEvent) -> Action {
+fn write_one_event(utx: &mut UniTx, run_id: i64, e: &API::NotificationEvent, objects: &ObjectSelection) -> Action {
     use UniNotificationEventDataObject as Obj;
 
     /// Match represents current set of implemented `UpsertTree` (over time more will be implemented).
@@ -312,7 +351,10 @@ fn write_one_event(utx: &mut UniTx, run_id: i64, e: &API::NotificationEvent) ->
                         match (&x.customer, &x.subscription, &x.invoice, &x.invoice_item) {
                             (Some(_), None, None, None) => {
                                 // Customer owns discount - no `customer.updated` triggered on changes, manually update `discount` field.
-                                write_ids.push(Customer::update_discount_id(utx, run_id, e.type_x.as_str(), &x));
+                                // - Discounts can be included via another parent when customers are excluded.
+                                if objects.is_included(Customer::get_table_name_static()) {
+                                    write_ids.push(Customer::update_discount_id(utx, run_id, e.type_x.as_str(), &x));
+                                }
                             }
                             _ => {
                                 // Discount owned by non-customer - these have their own update events which updates the `discount`.
@@ -543,7 +585,7 @@ pub fn get_incorrect_versions(e: &Vec<API::NotificationEvent>) -> HashMap<String
 // - They use /events for other processes.
 // Fix: Eventually add webhook functionality which would enable pining that particular webhooks version (but would require incoming connections, 100% uptime, per account config, no ability to batch apply events in a single transaction in order).
 // Fix: Allow flag to ignore Stripe version and potentially have incorrect query results.
-fn assert_correct_version(e: &Vec<API::NotificationEvent>) {
+pub fn assert_correct_version(e: &Vec<API::NotificationEvent>) -> Result<(), TdError> {
     let incorrect_versions = get_incorrect_versions(&e);
     if incorrect_versions.len() > 0 {
         error!("Events NOT applied. Events found that do not match this CLI's Stripe version ({}): {}", StripeClient::get_api_version(), incorrect_versions.to_json());
@@ -552,13 +594,24 @@ fn assert_correct_version(e: &Vec<API::NotificationEvent>) {
         error!("You can version-pin existing webhook endpoints at https://dashboard.stripe.com/webhooks.");
         error!("You can version-pin existing Stripe API HTTP clients by using the Stripe-Version: xxxx-xx-xx HTTP header.");
         error!("Events retrieved from `/v1/events` have a version that matches the Stripe account version at the time of event creation.");
-        assert_eq!(incorrect_versions.len(), 0);
+        return Err(TdError::VersionMismatch(format!("{} events do not match this CLI's Stripe version ({}).", incorrect_versions.len(), StripeClient::get_api_version())));
     }
+
+    Ok(())
+}
+
+// Only the time the tx was open; not the CDC output or the owner downloads after the commit.
+fn observe_tx_lock(start: Instant) {
+    let duration = start.elapsed();
+    debug!("DB TX lock held for {:?}", duration);
+    metrics::observe_tx_lock(duration);
 }
 
 /// @todo/next
 /// - `customer.tax_id.created` == `skip.object_type_not_written_to_table`?
-pub async fn apply_events(c: &StripeClient, uc: &mut UniCon, events: Option<Vec<API::NotificationEvent>>) {
+///
+/// Returns the summary when events were applied and committed (for `options.hooks.on_apply_events`).
+pub async fn apply_events(c: &StripeClient, uc: &mut UniCon, events: Option<Vec<API::NotificationEvent>>, objects: &ObjectSelection) -> Result<Option<ApplySummary>, TdError> {
     info!("Checking for new events.");
 
     // If last run was a download, limit events to 2 min's prior (in case user upgrades their Stripe version and re-downloads, and old-version events cannot be deleted - no need to wait 30 days for the events to delete, just wait 2 minutes).
@@ -575,16 +628,21 @@ pub async fn apply_events(c: &StripeClient, uc: &mut UniCon, events: Option<Vec<
 
     // Allow passing in events for testing.
     let e = match events {
-        None => get_all_unapplied_events(&c, uc, since).await,
+        None => get_all_unapplied_events(c, uc, since).await?,
         Some(e) => e
     };
 
+    // When set, rows written from events can reference owners created before the cutoff that were never downloaded.
+    let created_gte = TdMetadata::get_last(uc, "id").and_then(|x| x.get_created_gte());
+
     // Note: at this stage events have been strongly typed OR Serde will stop the process (so Serde may of been able to parse another versions JSON format as the structure changes only slightly version to version).
-    assert_correct_version(&e);
+    assert_correct_version(&e)?;
+
+    let mut applied = None;
 
     // Only get tx if there are events to apply (Postgres creates a new auto-increment ID for every rolled back tx).
     if e.len() > 0 {
-        let mut utx_o = uc.tx_open().unwrap();
+        let mut utx_o = uc.tx_open().map_err(|e| TdError::Db(format!("Could not open a DB transaction: {:?}", e)))?;
         let start = Instant::now();
         let utx = &mut utx_o;
 
@@ -592,7 +650,7 @@ pub async fn apply_events(c: &StripeClient, uc: &mut UniCon, events: Option<Vec<
         let last_run_tx = TdRun::get_last_run_tx(utx).expect("Cannot apply events without a prior full download.");
         if last_run.run_id.unwrap() != last_run_tx.run_id.unwrap() {
             warn!("Ignoring events downloaded. Another process has inserted the same events. start_run_id={}, end_run_id={}", last_run.run_id.unwrap(), last_run_tx.run_id.unwrap());
-            return;
+            return Ok(None);
         }
 
         let mut run = TdRun {
@@ -606,26 +664,52 @@ pub async fn apply_events(c: &StripeClient, uc: &mut UniCon, events: Option<Vec<
         run.tx_insert_set_pk(utx);
         let run_id = run.run_id.unwrap();
 
-        apply_events_body(utx, run_id, &e);
+        cdc::tx_start();
+        apply_events_body(utx, run_id, &e, objects);
 
         run.end_ts = Some(now_3());
         run.tx_update_pk(utx);
 
-        if e.len() > 0 {
-            let write_quota_used = TdStripeWrite::get_write_count_excluding_deletes(&mut utx_o, run_id);
-            utx_o.tx_close().unwrap();
-            let summary = ApplySummary::from_event_list(run_id, &e);
-            info!("Applied {} events: {}", &e.len(), summary.to_json());
-        } else {
-            // Remove this run_id as it has no events; reduce disk usage.
-            utx_o.tx_rollback();
+        if write_ctx::is_dry_run() {
+            let summary = dry_run::tx_get_summary(utx, run_id, e.len());
+            utx_o.tx_rollback().map_err(|e| TdError::Db(format!("Could not roll back the dry run transaction: {:?}", e)))?;
+            observe_tx_lock(start);
+            dry_run::log_summary(&summary);
+            return Ok(None);
         }
 
-        let duration = start.elapsed();
-        debug!("DB TX lock held for {:?}", duration);
+        if write_ctx::is_notify_enabled() {
+            notify::tx_notify(utx, run_id);
+        }
+
+        let write_quota_used = TdStripeWrite::get_write_count_excluding_deletes(&mut utx_o, run_id);
+        utx_o.tx_close().map_err(|e| TdError::from_commit("apply_events transaction", e))?;
+        observe_tx_lock(start);
+        cdc::tx_committed(uc).await?;
+
+        // Owners created before the cutoff are downloaded after the commit, so the tx is not held open for the HTTP requests.
+        if created_gte.is_some() {
+            cdc::tx_start();
+            dl_missing_owners(c, uc, run_id, objects).await?;
+            cdc::tx_committed(uc).await?;
+        }
+
+        let summary = ApplySummary::from_event_list(run_id, &e);
+        info!("Applied {} events: {}", &e.len(), summary.to_json());
+        metrics::add_events_applied(&summary.event_type_count);
+        applied = Some(summary);
+    } else if !write_ctx::is_dry_run() {
+        // Committed writes the output missed (E.g. after a restart, or when it could not be written to).
+        cdc::catch_up(uc).await?;
+    }
+
+    if write_ctx::is_dry_run() {
+        info!("Dry run: no new events to apply.");
+        return Ok(None);
     }
 
     TdMetadata::set_heartbeat_now(uc);
+    metrics::set_heartbeat_now();
 
 
     #[cfg(test)]
@@ -633,12 +717,15 @@ pub async fn apply_events(c: &StripeClient, uc: &mut UniCon, events: Option<Vec<
             // @todo/low ensure this is compiled out in release build.
             // Note: This is ok during testing as a read tx can be emulated.
             //      - Stripe API writes are not occurring during the download.
-            let missing = Db::get_missing_owner_all(&uc);
+            let gone = if created_gte.is_some() { TdMissingOwner::get_keys(uc) } else { HashSet::new() };
+            let missing = Db::get_missing_owner_all(uc, objects, &gone);
             if missing.len() > 0 {
                 dbg!(&missing);
             }
             assert_eq!(missing.len(), 0, "Foreign key constraints violated (note: not native SQL FK constraint).");
         }
+
+    Ok(applied)
 }
 
 
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+use serde_json::{json, Value};
+use unicon::traits::{*};
+use unicon::uc::UniCon;
+
+use crate::{CdcFile, CdcTo};
+use crate::error::TdError;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema_meta::{TdCdc, TdStripeApplyEvent, TdStripeWrite};
+use crate::providers::stripe::write_ctx::{get_event_id, is_cdc_enabled, with_cdc};
+
+
+// Change data capture (`options.cdc`): downstream services react to writes after they have been committed, in the same order.
+// - One JSON line per `td_stripe_writes` row: `offset` (= `write_id`), `run_id`, `table`, `obj_type`, `obj_id`, `write_type`, `event_id`, `row` (null for deletes).
+//      - `offset` always increases; a consumer stores the last offset it processed and skips lines <= it after a restart.
+// - Only writes from `apply_events` are output (the first download is a snapshot; consumers read it from the DB).
+// - Lines are collected during the tx, and written after it commits (nothing is written for a rolled back tx).
+// - The last offset written to the output is stored in `td_cdc`.
+//      - Committed writes after it that are not in RAM (the process stopped before writing them, or the output could not be written to) are read from `td_stripe_writes` after the next commit or poll.
+//      - Lines read back from the DB have the row's current state (not its state at the time of the write).
+//      - A line is only complete when it ends with `\n`: a partial line is removed from a file, and the socket is closed after one.
+
+
+// Lines read from `td_stripe_writes` per output write when catching up.
+const CATCH_UP_BATCH: usize = 1_000;
+
+pub struct Cdc {
+    to: CdcTo,
+
+    // None while a write is running on a blocking thread.
+    out: Option<Out>,
+
+    // `td_cdc.output`
+    key: String,
+
+    // None until read from `td_cdc`.
+    state: Option<TdCdc>,
+
+    // (offset, line) of the open `apply_events` tx (None when no tx is open).
+    tx_lines: Option<Vec<(i64, String)>>,
+}
+
+enum Out {
+    File(FileOut),
+    Stdout,
+
+    #[cfg(unix)]
+    UnixSocket(String, Option<std::os::unix::net::UnixStream>),
+}
+
+struct FileOut {
+    opts: CdcFile,
+    f: File,
+    bytes: u64,
+}
+
+impl FileOut {
+    fn open(opts: &CdcFile) -> io::Result<FileOut> {
+        let f = OpenOptions::new().create(true).append(true).open(&opts.file)?;
+        let bytes = f.metadata()?.len();
+
+        Ok(FileOut {
+            opts: opts.clone(),
+            f,
+            bytes,
+        })
+    }
+
+    // `file.n` is removed, `file.n-1` -> `file.n`, ..., `file` -> `file.1`.
+    fn rotate(&mut self) -> io::Result<()> {
+        let file = &self.opts.file;
+        let n = self.opts.max_files;
+
+        let rotated = |i: u32| format!("{}.{}", file, i);
+
+        if n == 0 {
+            std::fs::remove_file(file)?;
+        } else {
+            if Path::new(&rotated(n)).exists() {
+                std::fs::remove_file(rotated(n))?;
+            }
+
+            for i in (1..n).rev() {
+                if Path::new(&rotated(i)).exists() {
+                    std::fs::rename(rotated(i), rotated(i + 1))?;
+                }
+            }
+
+            std::fs::rename(file, rotated(1))?;
+        }
+
+        *self = FileOut::open(&self.opts)?;
+        Ok(())
+    }
+
+    fn write(&mut self, s: &str) -> io::Result<()> {
+        let len = s.len() as u64;
+        if self.bytes > 0 && self.bytes + len > self.opts.max_bytes {
+            self.rotate()?;
+        }
+
+        if let Err(e) = self.f.write_all(s.as_bytes()).and_then(|_| self.f.flush()) {
+            // Remove a partially written line.
+            let _ = self.f.set_len(self.bytes);
+            return Err(e);
+        }
+        self.bytes += len;
+        Ok(())
+    }
+}
+
+impl Out {
+    fn write(&mut self, s: &str) -> io::Result<()> {
+        match self {
+            Out::File(x) => x.write(s),
+            Out::Stdout => {
+                let mut x = io::stdout();
+                let mut lock = x.lock();
+                lock.write_all(s.as_bytes())?;
+                lock.flush()
+            }
+
+            #[cfg(unix)]
+            Out::UnixSocket(file, con) => {
+                if con.is_none() {
+                    *con = Some(std::os::unix::net::UnixStream::connect(file.as_str())?);
+                }
+
+                let res = con.as_mut().unwrap().write_all(s.as_bytes());
+                if res.is_err() {
+                    // Reconnect on the next write; the consumer discards a partial line when the connection closes.
+                    *con = None;
+                }
+                res
+            }
+        }
+    }
+
+    fn open(to: &CdcTo) -> Result<Out, TdError> {
+        let out = match to {
+            CdcTo::File(x) => {
+                let f = FileOut::open(x).map_err(|e| TdError::Config(format!("Could not open `options.cdc.file.file` {}: {}", &x.file, e)))?;
+                Out::File(f)
+            }
+            CdcTo::Stdout(_) => Out::Stdout,
+
+            #[cfg(unix)]
+            CdcTo::UnixSocket(x) => Out::UnixSocket(x.file.clone(), None),
+
+            #[cfg(not(unix))]
+            CdcTo::UnixSocket(_) => return Err(TdError::Config("`options.cdc.unix_socket` is only supported on Unix.".into())),
+        };
+
+        Ok(out)
+    }
+}
+
+impl Cdc {
+    pub fn new(to: &CdcTo) -> Result<Cdc, TdError> {
+        Ok(Cdc {
+            to: to.clone(),
+            out: Some(Out::open(to)?),
+            key: get_key(to),
+            state: None,
+            tx_lines: None,
+        })
+    }
+
+    // The output is reopened when a previous write was cancelled (E.g. by a shutdown signal) while it was on a blocking thread.
+    fn take_out(&mut self) -> Result<Out, TdError> {
+        match self.out.take() {
+            Some(x) => Ok(x),
+            None => Out::open(&self.to)
+        }
+    }
+}
+
+fn get_key(to: &CdcTo) -> String {
+    match to {
+        CdcTo::File(x) => format!("file:{}", &x.file),
+        CdcTo::Stdout(_) => "stdout".into(),
+        CdcTo::UnixSocket(x) => format!("unix_socket:{}", &x.file),
+    }
+}
+
+// Writes one line at a time; returns the number of lines written.
+fn write_lines(out: &mut Out, lines: &[(i64, String)]) -> (usize, Option<io::Error>) {
+    for (i, (_, line)) in lines.iter().enumerate() {
+        if let Err(e) = out.write(&format!("{}\n", line)) {
+            return (i, Some(e));
+        }
+    }
+    (lines.len(), None)
+}
+
+
+pub fn get_line(w: &TdStripeWrite, event_id: Option<String>, row: Option<Value>) -> String {
+    json!({
+        "offset": w.write_id,
+        "run_id": w.run_id,
+        "table": w.table_name,
+        "obj_type": w.obj_type,
+        "obj_id": w.obj_id,
+        "write_type": w.write_type,
+        "event_id": event_id,
+        "row": row,
+    }).to_string()
+}
+
+// Start collecting lines for a new `apply_events` tx.
+pub fn tx_start() {
+    with_cdc(|c| c.tx_lines = Some(vec![]));
+}
+
+// Called after the write has been logged (`write_id` is set).
+// - `get_row` is only called when lines are being collected (the row is serialized via its serde impl).
+pub fn push(w: &TdStripeWrite, get_row: impl FnOnce() -> Option<Value>) {
+    if !is_cdc_enabled() {
+        return;
+    }
+
+    let event_id = get_event_id();
+    with_cdc(|c| {
+        if let Some(lines) = &mut c.tx_lines {
+            lines.push((w.write_id.unwrap(), get_line(w, event_id, get_row())));
+        }
+    });
+}
+
+// Called after the `apply_events` tx has committed.
+pub async fn tx_committed(uc: &mut UniCon) -> Result<(), TdError> {
+    match with_cdc(|c| c.tx_lines.take().unwrap_or_default()) {
+        None => Ok(()),
+        Some(lines) => write(uc, lines).await
+    }
+}
+
+// Writes committed lines that have not been output yet (called when a poll has no events to apply).
+pub async fn catch_up(uc: &mut UniCon) -> Result<(), TdError> {
+    if !is_cdc_enabled() {
+        return Ok(());
+    }
+
+    write(uc, vec![]).await
+}
+
+async fn write(uc: &mut UniCon, lines: Vec<(i64, String)>) -> Result<(), TdError> {
+    let (out, key, state) = with_cdc(|c| (c.take_out(), c.key.clone(), c.state.take())).unwrap();
+    let mut out = out?;
+
+    let mut state = match state {
+        Some(x) => x,
+        None => get_state(uc, &key, lines.first().map(|x| x.0))?
+    };
+
+    let res = write_after(uc, &mut out, &mut state, lines).await;
+
+    with_cdc(|c| {
+        c.out = Some(out);
+        c.state = Some(state);
+    });
+
+    res
+}
+
+// Catches up from `td_stripe_writes`, then writes `lines`.
+// - Stops at the first output error; the remaining lines are read back from the DB on the next call.
+async fn write_after(uc: &mut UniCon, out: &mut Out, state: &mut TdCdc, lines: Vec<(i64, String)>) -> Result<(), TdError> {
+    let first = lines.first().map(|x| x.0);
+
+    loop {
+        let batch = get_lines_after(uc, state.last_offset, first);
+        if batch.is_empty() {
+            break;
+        }
+
+        if !write_blocking(uc, out, state, batch).await? {
+            return Ok(());
+        }
+    }
+
+    let lines: Vec<(i64, String)> = lines.into_iter().filter(|x| x.0 > state.last_offset).collect();
+    if !lines.is_empty() {
+        write_blocking(uc, out, state, lines).await?;
+    }
+
+    Ok(())
+}
+
+// Returns false when not all lines were written.
+async fn write_blocking(uc: &mut UniCon, out: &mut Out, state: &mut TdCdc, lines: Vec<(i64, String)>) -> Result<bool, TdError> {
+    // Files, pipes and sockets can block; do not block the Tokio executor the other jobs run on.
+    let o = std::mem::replace(out, Out::Stdout);
+    let (o, lines, (n, err)) = tokio::task::spawn_blocking(move || {
+        let mut o = o;
+        let res = write_lines(&mut o, &lines);
+        (o, lines, res)
+    }).await.map_err(|e| TdError::Internal(format!("CDC write task failed: {}", e)))?;
+    *out = o;
+
+    if n > 0 {
+        state.last_offset = lines[n - 1].0;
+        save_state(uc, state)?;
+    }
+
+    if let Some(e) = err {
+        warn!("Could not write {} CDC lines to `options.cdc`, retrying after the next commit or poll: {}", lines.len() - n, e);
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+// When the output has not been written to before, it starts at the current tx (or after the last write when there are no lines).
+fn get_state(uc: &mut UniCon, key: &str, first: Option<i64>) -> Result<TdCdc, TdError> {
+    let mut utx = uc.tx_open().map_err(|e| TdError::Db(e.to_string()))?;
+
+    let x = match TdCdc::get_where_tx(&mut utx, ("output", &key.to_string())).pop() {
+        Some(x) => x,
+        None => {
+            let last_offset = match first {
+                Some(x) => x - 1,
+                None => TdStripeWrite::tx_get_last(&mut utx, "write_id").and_then(|x| x.write_id).unwrap_or(0)
+            };
+
+            let mut x = TdCdc {
+                cdc_id: None,
+                output: key.to_string(),
+                last_offset,
+                insert_ts: None,
+                update_ts: None,
+            };
+            x.tx_insert_set_pk(&mut utx);
+            x
+        }
+    };
+
+    utx.tx_close().map_err(|e| TdError::Db(e.to_string()))?;
+    Ok(x)
+}
+
+fn save_state(uc: &mut UniCon, state: &TdCdc) -> Result<(), TdError> {
+    let mut utx = uc.tx_open().map_err(|e| TdError::Db(e.to_string()))?;
+    state.tx_update_pk(&mut utx);
+    utx.tx_close().map_err(|e| TdError::Db(e.to_string()))
+}
+
+// Committed `apply_events` writes after `offset` (and before `before`), oldest first.
+fn get_lines_after(uc: &mut UniCon, offset: i64, before: Option<i64>) -> Vec<(i64, String)> {
+    let before = before.map(|x| format!("AND w.write_id < {}", x)).unwrap_or_default();
+    let sql = format!(
+        "SELECT w.* FROM td_stripe_writes w JOIN td_runs r ON (w.run_id = r.run_id) WHERE r.type = 'apply_events' AND w.write_id > {} {} ORDER BY w.write_id ASC LIMIT {}",
+        offset, before, CATCH_UP_BATCH
+    );
+    let writes = uc.get_vec_from_sql::<TdStripeWrite>(&sql);
+    if writes.is_empty() {
+        return vec![];
+    }
+
+    let event_ids = get_event_ids(uc, writes.first().unwrap().run_id, writes.last().unwrap().run_id);
+
+    writes.iter().map(|w| {
+        let id = w.write_id.unwrap();
+        let row = if w.write_type == "d" { None } else { Db::get_row_json(uc, &w.table_name, &w.obj_id) };
+        (id, get_line(w, event_ids.get(&id).cloned(), row))
+    }).collect()
+}
+
+// `write_id` -> `event_id`
+fn get_event_ids(uc: &mut UniCon, from_run_id: i64, to_run_id: i64) -> HashMap<i64, String> {
+    let sql = format!("SELECT * FROM td_stripe_apply_events WHERE run_id >= {} AND run_id <= {}", from_run_id, to_run_id);
+    let mut m = HashMap::new();
+
+    for x in uc.get_vec_from_sql::<TdStripeApplyEvent>(&sql) {
+        if let Some(Value::Array(ids)) = &x.write_ids {
+            for id in ids.iter().filter_map(|id| id.as_i64()) {
+                m.insert(id, x.event_id.clone());
+            }
+        }
+    }
+    m
+}
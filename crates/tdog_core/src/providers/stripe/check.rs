@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+use std::convert::{TryFrom, TryInto};
+
+use serde::Serialize;
+use stripe_client::http::http::StripeClient;
+use unicon::traits::{*};
+use unicon::uc::{*};
+use unicon::utx::UniTx;
+
+use crate::Check;
+use crate::error::TdError;
+use crate::fns::now_3;
+use crate::providers::stripe::dl::dl_owners;
+use crate::providers::stripe::foreign_keys;
+use crate::providers::stripe::history::get_history_tables;
+use crate::providers::stripe::metadata_cols::MetadataCols;
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::raw_json;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::relations::EdgeGrp;
+use crate::providers::stripe::schema_meta::{TdMetadata, TdRun};
+use crate::providers::stripe::watch::assert_no_history_tables;
+
+
+// `check`: audit the relations between rows of a live DB (`schema/relations.rs`), on all engines.
+// - Missing owners are reported per relation, split into relations the Stripe API keeps consistent (`enforced_by_api`) and the rest.
+//      - Not enforced: expected (E.g. an invoice of a deleted customer).
+//      - Enforced: a tdog bug, or a DB created with `options.created_gte` (owners created before the cutoff that could not be downloaded).
+// - `repair`: download the missing owners of enforced, non-polymorphic relations with a `td_runs` row (`type = repair`); each write is logged in `td_stripe_writes`.
+//      - Owners are downloaded before each write tx, so locks are not held during HTTP requests.
+
+
+// Owner ids listed per relation in the log.
+const EXAMPLES: usize = 5;
+
+#[derive(Serialize)]
+#[derive(Debug, Clone)]
+pub struct EdgeCheck {
+    // E.g. `invoices.customer -> customers`.
+    pub edge: String,
+    pub enforced_by_api: bool,
+
+    // (copy_id, owner_id)
+    pub missing: Vec<(String, String)>,
+}
+
+#[derive(Serialize)]
+#[derive(Debug, Clone)]
+pub struct CheckReport {
+    pub edges: Vec<EdgeCheck>,
+
+    // Owners written by `repair`.
+    pub repaired: usize,
+}
+
+impl CheckReport {
+    pub fn get_missing_enforced(&self) -> usize {
+        self.edges.iter().filter(|x| x.enforced_by_api).map(|x| x.missing.len()).sum()
+    }
+}
+
+
+fn tx_get_edges(utx: &mut UniTx, objects: &ObjectSelection) -> Vec<(EdgeGrp, Vec<(String, String)>)> {
+    Db::get_missing_owners(utx, objects)
+}
+
+fn get_report(uc: &mut UniCon, objects: &ObjectSelection) -> Result<Vec<EdgeCheck>, TdError> {
+    let mut utx = uc.tx_open().map_err(|e| TdError::Db(e.to_string()))?;
+    let x = tx_get_edges(&mut utx, objects);
+    utx.tx_rollback().map_err(|e| TdError::Db(e.to_string()))?;
+
+    Ok(x.into_iter().map(|(g, missing)| EdgeCheck {
+        edge: g.get_name(),
+        enforced_by_api: g.is_enforced_by_api(),
+        missing,
+    }).collect())
+}
+
+fn log_report(edges: &[EdgeCheck]) {
+    for enforced in [true, false] {
+        let all: Vec<&EdgeCheck> = edges.iter().filter(|x| x.enforced_by_api == enforced).collect();
+        let with_missing: Vec<&&EdgeCheck> = all.iter().filter(|x| !x.missing.is_empty()).collect();
+        let kind = if enforced { "Enforced" } else { "Not enforced" };
+
+        info!("{} relations: {} of {} have missing owners.", kind, with_missing.len(), all.len());
+        for x in with_missing {
+            let examples: Vec<String> = x.missing.iter().take(EXAMPLES).map(|(copy_id, owner_id)| format!("{} -> {}", copy_id, owner_id)).collect();
+            let msg = format!("{}: {} missing (E.g. {})", &x.edge, x.missing.len(), examples.join(", "));
+            if enforced {
+                warn!("{}", msg);
+            } else {
+                info!("{}", msg);
+            }
+        }
+    }
+}
+
+// (owner_tbl, owner_id) of enforced, non-polymorphic relations (the owner table of a polymorphic id is unknown).
+fn tx_get_repairable(utx: &mut UniTx, objects: &ObjectSelection) -> Vec<(String, String)> {
+    let mut o = vec![];
+    let mut seen = HashSet::new();
+
+    for (g, missing) in tx_get_edges(utx, objects) {
+        if let EdgeGrp::Single(e) = g {
+            if !e.enforced_by_api {
+                continue;
+            }
+
+            for (_, owner_id) in missing {
+                let k = (e.owner_tbl.clone(), owner_id);
+                if seen.insert(k.clone()) {
+                    o.push(k);
+                }
+            }
+        }
+    }
+
+    o
+}
+
+// Repeats until no new owners are found, as downloaded owners can reference other missing owners.
+// - Each round reads the missing owners, downloads them with no tx open, then writes them in one short tx (`dl_owners`).
+async fn repair(sc: &StripeClient, uc: &mut UniCon, objects: &ObjectSelection) -> Result<usize, TdError> {
+    let mut run = TdRun {
+        run_id: None,
+        r#type: "repair".into(),
+        start_ts: now_3().into(),
+        end_ts: None,
+    };
+    run.insert_set_pk(uc);
+    let run_id = run.run_id.unwrap();
+
+    let mut tried = HashSet::new();
+    let mut total = 0;
+    loop {
+        let mut utx = uc.tx_open().map_err(|e| TdError::Db(e.to_string()))?;
+        let missing: Vec<(String, String)> = tx_get_repairable(&mut utx, objects).into_iter().filter(|x| !tried.contains(x)).collect();
+        utx.tx_rollback().map_err(|e| TdError::Db(e.to_string()))?;
+
+        if missing.is_empty() {
+            break;
+        }
+
+        tried.extend(missing.iter().cloned());
+        total += dl_owners(sc, uc, run_id, missing).await?;
+    }
+
+    run.end_ts = Some(now_3());
+    run.update(uc, "run_id");
+
+    info!("Repair run {} downloaded {} of {} missing owners.", run_id, total, tried.len());
+    Ok(total)
+}
+
+pub async fn check(sc: &StripeClient, uc: &mut UniCon, ck: &Check) -> Result<CheckReport, TdError> {
+    let objects: ObjectSelection = (&ck.options.objects).try_into()?;
+    let history_tables = if ck.options.history { get_history_tables(&objects) } else { vec![] };
+
+    if !uc.schema_exists_with::<Db>(&objects.get_excluded(), &history_tables).map_err(TdError::SchemaConflict)? {
+        return Err(TdError::SchemaConflict("The DB has no tables. `check` needs a DB that contains a download.".into()));
+    }
+
+    let md = match sc.stripe_account.as_ref() {
+        Some(sa) => TdMetadata::check_cli_and_stripe_versions_match(uc, sa)?,
+        None => TdMetadata::check_versions_match(uc)?
+    };
+
+    let mut edges = get_report(uc, &objects)?;
+    log_report(&edges);
+
+    let mut repaired = 0;
+    let missing = edges.iter().any(|x| x.enforced_by_api && !x.missing.is_empty());
+    if ck.repair && missing {
+        if sc.stripe_account.is_none() {
+            return Err(TdError::Config("`repair` downloads missing owners from Stripe: set `from`.".into()));
+        }
+
+        // Repaired rows are written with the same options as the rest of the DB.
+        if !ck.options.history {
+            assert_no_history_tables(uc, &objects)?;
+        }
+        raw_json::assert_raw_json_matches(uc, &objects, ck.options.store_raw_json)?;
+        MetadataCols::try_from(&ck.options.metadata_columns)?.assert_cols_match(uc, &objects)?;
+        foreign_keys::set_sqlite_enforcement(ck.options.foreign_keys, uc);
+
+        repaired = repair(sc, uc, &objects).await?;
+        edges = get_report(uc, &objects)?;
+        log_report(&edges);
+    }
+
+    let report = CheckReport {
+        edges,
+        repaired,
+    };
+
+    let n = report.get_missing_enforced();
+    if n > 0 {
+        if md.get_created_gte().is_some() {
+            warn!("The DB was created with `options.created_gte`: owners created before the cutoff may not be downloadable (E.g. deleted customers).");
+        }
+
+        let hint = if ck.repair { "" } else { " Run with `repair` to download them from Stripe." };
+        return Err(TdError::Integrity(format!("{} rows reference missing owners of relations the Stripe API keeps consistent.{}", n, hint)));
+    }
+
+    Ok(report)
+}
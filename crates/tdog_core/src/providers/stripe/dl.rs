@@ -1,8 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 
 use chrono::{DateTime, Utc};
-use futures::future::join_all;
+use futures::future::{join_all, try_join_all};
 use futures::future::TryFutureExt;
 use futures::join;
 use futures::stream::{self, Stream, StreamExt, TryStream, TryStreamExt, FuturesOrdered};
@@ -10,8 +10,8 @@ use futures::try_join;
 use futures_util::pin_mut;
 use log::{info, trace, warn};
 use stripe_client::http::http::{Config, StripeClient, UniErr};
-use stripe_client::types::req_params::{GetBalanceHistory, GetCharges, GetCheckoutSessions, GetCountrySpecs, GetCoupons, GetCreditNotes, GetCustomers, GetCustomersCustomerSources, GetDisputes, GetEvents, GetInvoiceitems, GetInvoices, GetOrderReturns, GetOrders, GetPaymentIntents, GetPaymentMethods, GetPrices, GetProducts, GetPromotionCodes, GetRefunds, GetSetupIntents, GetSkus, GetSubscriptionItems, GetSubscriptions, GetSubscriptionSchedules, GetTaxRates, UniStrStatus3EB683, UniStrTypeBAE85E};
-use stripe_client::types::responses::{ApmsSourcesSourceListF0771E, UniPolymorphic646C3F};
+use stripe_client::types::req_params::{GetBalanceHistory, GetCharges, GetCheckoutSessions, GetCountrySpecs, GetCoupons, GetCreditNotes, GetCustomers, GetCustomersCustomerSources, GetDisputes, GetEvents, GetInvoiceitems, GetInvoices, GetOrderReturns, GetOrders, GetPaymentIntents, GetPaymentMethods, GetPrices, GetProducts, GetPromotionCodes, GetRefunds, GetSetupIntents, GetSkus, GetSubscriptionItems, GetSubscriptions, GetSubscriptionSchedules, GetTaxRates, UniStrStatus3EB683, UniStrTypeBAE85E, UniCreated, RangeQuerySpecs};
+use stripe_client::types::responses::{ApmsSourcesSourceListF0771E, UniPolymorphic646C3F, UniGetCustomersCustomerRes};
 use stripe_client::types::types::{
     Address as APIAddress,
     Customer as APICustomer,
@@ -60,7 +60,9 @@ use crate::providers::stripe::schema::types::refund::Refund;
 use crate::providers::stripe::schema::types::session::Session;
 use crate::providers::stripe::schema::types::setup_intent::SetupIntent;
 use crate::providers::stripe::schema::types::sku::Sku;
+use crate::providers::stripe::objects::ObjectSelection;
 use crate::Stripe;
+use crate::error::TdError;
 
 use super::schema_meta::{*};
 use crate::providers::stripe::rate_limit::{RateLimit};
@@ -69,7 +71,7 @@ use std::sync::Arc;
 use std::future::Future;
 use std::pin::Pin;
 
-pub async fn dl_events(c: &StripeClient, mut uc: &mut UniCon) {
+pub async fn dl_events(c: &StripeClient, mut uc: &mut UniCon) -> Result<(), TdError> {
     let p = Some(GetEvents {
         type_x: None,
         created: None,
@@ -85,11 +87,161 @@ pub async fn dl_events(c: &StripeClient, mut uc: &mut UniCon) {
     let mut st = c.v1_events_get_st(&p.unwrap());
     pin_mut!(st);
     while let Some(val) = st.next().await {
-        for i in val.unwrap().data {
+        for i in val?.data {
             let mut i2: NotificationEvent = (&i).into();
             i2.insert_set_pk(&mut uc);
         }
     }
+
+    Ok(())
+}
+
+
+// `options.created_gte` as a list `created` filter (None = list all).
+// - Note: `credit_notes` and `skus` lists do not support a `created` filter so are always downloaded in full.
+pub fn get_created_filter(created_gte: Option<i64>) -> Option<UniCreated> {
+    created_gte.map(|gte| UniCreated::RangeQuerySpecs(RangeQuerySpecs {
+        gt: None,
+        gte: Some(gte),
+        lt: None,
+        lte: None,
+    }))
+}
+
+
+/// When `options.created_gte` is set, rows written in `run_id` can reference owners created before the cutoff (E.g. a new subscription for an old customer).
+/// - Download each missing owner individually so joins still work.
+/// - Repeat until no new owners are found, as downloaded owners can reference older owners (E.g. subscription -> customer).
+/// - Best effort: owners that cannot be downloaded (a network error, or a type with no retrieve endpoint) are logged and left missing; the relation checks report them.
+///
+/// Called after the run's tx has committed: the HTTP requests are made outside of a DB tx, and each round of owners is written in one short tx with the same `run_id`.
+/// - Only the first download has many missing owners; after that it is usually zero or a few per `apply_events` run.
+pub async fn dl_missing_owners(c: &StripeClient, uc: &mut UniCon, run_id: i64, objects: &ObjectSelection) -> Result<(), TdError> {
+    let mut tried = HashSet::new();
+    let mut total = 0;
+
+    loop {
+        let mut utx = uc.tx_open().map_err(|e| TdError::Db(e.to_string()))?;
+        let gone = TdMissingOwner::tx_get_keys(&mut utx);
+        let missing: Vec<(String, String)> = Db::get_missing_owner_ids_for_run(&mut utx, run_id, objects).into_iter().filter(|x| !tried.contains(x) && !gone.contains(x)).collect();
+        utx.tx_rollback().map_err(|e| TdError::Db(e.to_string()))?;
+
+        if missing.is_empty() {
+            break;
+        }
+
+        tried.extend(missing.iter().cloned());
+        total += dl_owners(c, uc, run_id, missing).await?;
+    }
+
+    if total > 0 {
+        info!("Downloaded {} objects created before `options.created_gte` that are referenced by newer objects.", total);
+    }
+
+    Ok(())
+}
+
+/// Downloads `(owner_tbl, id)` owners, then writes them in one tx; returns the number written.
+/// - Owners Stripe returns as deleted or missing are recorded in `td_missing_owners`.
+pub async fn dl_owners(c: &StripeClient, uc: &mut UniCon, run_id: i64, owners: Vec<(String, String)>) -> Result<usize, TdError> {
+    let mut fetched = vec![];
+    for (owner_tbl, id) in owners {
+        let x = get_one_owner(c, &owner_tbl, &id).await;
+        fetched.push((owner_tbl, id, x));
+    }
+
+    let mut utx = uc.tx_open().map_err(|e| TdError::Db(e.to_string()))?;
+    let mut written = 0;
+    for (owner_tbl, id, x) in fetched {
+        match x {
+            Owner::Found(write) => {
+                write(&mut utx, run_id);
+                written += 1;
+            }
+            Owner::Gone => {
+                TdMissingOwner {
+                    missing_owner_id: None,
+                    run_id,
+                    table_name: owner_tbl,
+                    obj_id: id,
+                    insert_ts: None,
+                }.tx_insert_set_pk(&mut utx);
+            }
+            Owner::NotFetched => {}
+        }
+    }
+    utx.tx_close().map_err(|e| TdError::from_commit("downloaded owners", e))?;
+
+    Ok(written)
+}
+
+// Upserts the owner tree (as the owner may of been written by an earlier event in the same run).
+pub type WriteOwner = Box<dyn FnOnce(&mut UniTx, i64)>;
+
+// An owner downloaded individually, written after all the HTTP requests.
+pub enum Owner {
+    Found(WriteOwner),
+
+    // Stripe returned the owner as deleted, or `resource_missing`.
+    Gone,
+
+    // Not downloaded: a network or API error, or a type that is written via its parent.
+    NotFetched,
+}
+
+pub async fn get_one_owner(c: &StripeClient, owner_tbl: &str, id: &str) -> Owner {
+    let i = id.to_string();
+
+    match owner_tbl {
+        t if t == Charge::get_table_name_static() => to_owner::<Charge>(t, id, c.v1_charges_x_get(i, &None).await),
+        t if t == Coupon::get_table_name_static() => to_owner::<Coupon>(t, id, c.v1_coupons_x_get(i, &None).await),
+        t if t == CreditNote::get_table_name_static() => to_owner::<CreditNote>(t, id, c.v1_credit_notes_x_get(i, &None).await),
+        t if t == Customer::get_table_name_static() => {
+            let res = c.v1_customers_x_get(i, &None).await.map(|x| match x {
+                UniGetCustomersCustomerRes::Customer(x) => Some(*x),
+                UniGetCustomersCustomerRes::DeletedCustomer(_) => None,
+            });
+
+            match res {
+                Ok(None) => {
+                    debug!("Not writing deleted customer {}.", id);
+                    Owner::Gone
+                }
+                Ok(Some(x)) => to_owner::<Customer>(t, id, Ok(x)),
+                Err(e) => to_owner::<Customer>(t, id, Err(e)),
+            }
+        }
+        t if t == Invoice::get_table_name_static() => to_owner::<Invoice>(t, id, c.v1_invoices_x_get(i, &None).await),
+        t if t == Order::get_table_name_static() => to_owner::<Order>(t, id, c.v1_orders_x_get(i, &None).await),
+        t if t == PaymentIntent::get_table_name_static() => to_owner::<PaymentIntent>(t, id, c.v1_payment_intents_x_get(i, &None).await),
+        t if t == PaymentMethod::get_table_name_static() => to_owner::<PaymentMethod>(t, id, c.v1_payment_methods_x_get(i, &None).await),
+        t if t == Price::get_table_name_static() => to_owner::<Price>(t, id, c.v1_prices_x_get(i, &None).await),
+        t if t == Product::get_table_name_static() => to_owner::<Product>(t, id, c.v1_products_x_get(i, &None).await),
+        t if t == PromotionCode::get_table_name_static() => to_owner::<PromotionCode>(t, id, c.v1_promotion_codes_x_get(i, &None).await),
+        t if t == Refund::get_table_name_static() => to_owner::<Refund>(t, id, c.v1_refunds_x_get(i, &None).await),
+        t if t == SetupIntent::get_table_name_static() => to_owner::<SetupIntent>(t, id, c.v1_setup_intents_x_get(i, &None).await),
+        t if t == Subscription::get_table_name_static() => to_owner::<Subscription>(t, id, c.v1_subscriptions_x_get(i, &None).await),
+        t if t == SubscriptionItem::get_table_name_static() => to_owner::<SubscriptionItem>(t, id, c.v1_subscription_items_x_get(i, &None).await),
+
+        // Children of other objects (E.g. discounts, invoice lines) or polymorphic sources; these are written via their parent.
+        _ => Owner::NotFetched
+    }
+}
+
+fn to_owner<B: WriteTree + 'static>(owner_tbl: &str, id: &str, res: Result<B::APIType, UniErr>) -> Owner where B::APIType: 'static {
+    match res {
+        Ok(x) => Owner::Found(Box::new(move |utx, run_id| {
+            B::upsert_tree(utx, run_id, &x);
+        })),
+        Err(UniErr::App(e)) if e.error.code.as_deref() == Some("resource_missing") => {
+            debug!("Not writing missing {}.id={}.", owner_tbl, id);
+            Owner::Gone
+        }
+        Err(e) => {
+            warn!("Could not download {}.id={}. Rows referencing it will not join. {:?}", owner_tbl, id, e);
+            Owner::NotFetched
+        }
+    }
 }
 
 
@@ -141,9 +293,9 @@ pub async fn dl_events(c: &StripeClient, mut uc: &mut UniCon) {
 // }
 
 
-pub async fn dl_customers(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_customers(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let p = Some(GetCustomers {
-        created: None,
+        created: get_created_filter(created_gte),
         email: None,
         ending_before: None,
         expand: Some(vec![
@@ -167,7 +319,7 @@ pub async fn dl_customers(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt:
 
 
     while let Some(val) = st.next().q_high(q_mt_a).await {
-        let res = val.unwrap();
+        let res = val?;
         let utx = &mut (utx_mt.lock().await);
 
         for c2 in res.data {
@@ -187,7 +339,7 @@ pub async fn dl_customers(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt:
 
                     // Note: `stream` fn not generated as this returns a polymorphic type that does not currently have a `get_id` trait for the ending_before param.
                     // - Ignore for now as its unlikely a single customer has >100 sources, and sources is being replaced by the intents API.
-                    let x: ApmsSourcesSourceListF0771E = (c.v1_customers_x_sources_get(c2.id.clone(), &p.into()).await).unwrap();
+                    let x: ApmsSourcesSourceListF0771E = (c.v1_customers_x_sources_get(c2.id.clone(), &p.into()).await)?;
                     assert!(!x.has_more, "Customer.id={} has more than 100 sources, pagination not currently supported.", &c2.id);
 
                     for x2 in &x.data {
@@ -222,14 +374,16 @@ pub async fn dl_customers(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt:
         for c2 in batch {
             all_payment_methods.push(dl_one_customer_payment_methods(&c, &q_mt_a, &utx_mt, run_id, c2.clone()));
         }
-        join_all(all_payment_methods).await;
+        try_join_all(all_payment_methods).await?;
     }
+
+    Ok(())
 }
 
 
-pub async fn dl_coupons(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_coupons(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let p = Some(GetCoupons {
-        created: None,
+        created: get_created_filter(created_gte),
         ending_before: None,
         expand: None,
         limit: Some(100),
@@ -240,7 +394,7 @@ pub async fn dl_coupons(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &M
     let mut st = c.v1_coupons_get_st(&p.unwrap());
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
-        let res = val.unwrap();
+        let res = val?;
         let utx = &mut (utx_mt.lock().await);
         for c in res.data {
             // Note: When a coupon is "deleted", valid=false.
@@ -248,11 +402,13 @@ pub async fn dl_coupons(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &M
             Coupon::insert_tree(utx, run_id, &c);
         }
     }
+
+    Ok(())
 }
 
-pub async fn dl_promotion_codes(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_promotion_codes(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let p = Some(GetPromotionCodes {
-        created: None,
+        created: get_created_filter(created_gte),
         active: None,
         code: None,
         coupon: None,
@@ -267,19 +423,21 @@ pub async fn dl_promotion_codes(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, ut
     let mut st = c.v1_promotion_codes_get_st(&p.unwrap());
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
-        let res = val.unwrap();
+        let res = val?;
         let utx = &mut (utx_mt.lock().await);
         for c in res.data {
             // Note: includes p.active = false.
             PromotionCode::insert_tree(utx, run_id, &c);
         }
     }
+
+    Ok(())
 }
 
 // Note: there is a difference between `invoiceitems` and `invoice_lines`, *they are different types but have 90% similar data*.
 // - https://stripe.com/docs/api/invoices/invoice_lines (attached to invoice, has tax, discounts etc)
 // - https://stripe.com/docs/api/invoiceitems/list (attach to upcoming invoice)
-pub async fn dl_invoice_lines(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, invoice_id: &str) {
+pub async fn dl_invoice_lines(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, invoice_id: &str) -> Result<(), TdError> {
     let p = GetCountrySpecs {
         ending_before: None,
         expand: None,
@@ -296,7 +454,7 @@ pub async fn dl_invoice_lines(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_
     let mut st = c.v1_invoices_x_lines_get_st(invoice_id.to_string(), &p);
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
-        let res = val.unwrap();
+        let res = val?;
         let utx = &mut (utx_mt.lock().await);
         for x in res.data {
             // Upsert needed as parent invoice contains first 10 lines (and will insert them).
@@ -309,11 +467,13 @@ pub async fn dl_invoice_lines(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_
             InvoiceLineItemWithParentId::upsert_tree(utx, run_id, &x2);
         }
     }
+
+    Ok(())
 }
 
-pub async fn dl_invoices(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_invoices(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let p = Some(GetInvoices {
-        created: None,
+        created: get_created_filter(created_gte),
         due_date: None,
         collection_method: None,
         customer: None,
@@ -333,7 +493,7 @@ pub async fn dl_invoices(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &
     pin_mut!(st);
     let mut lines_has_more = vec![];
     while let Some(val) = st.next().q_high(q_mt_a).await {
-        let res = val.unwrap();
+        let res = val?;
         let utx = &mut (utx_mt.lock().await);
         for x in res.data {
             Invoice::insert_tree(utx, run_id, &x);
@@ -358,7 +518,7 @@ pub async fn dl_invoices(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &
     }
 
     for x in lines_has_more {
-        dl_invoice_lines(&c, q_mt_a, utx_mt, run_id, x.as_str()).await;
+        dl_invoice_lines(c, q_mt_a, utx_mt, run_id, x.as_str()).await?;
     }
 
 
@@ -373,12 +533,14 @@ pub async fn dl_invoices(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &
     // @todo/low Create a Stripe issue, ask them to commit to a non-lossy event stream (missing out data and requiring a direct download).
     // - This prevents converting a stream to complete database (because when has_more=true, there is missing data).
     // - And prevents creating testing without having to use a stateful Stripe server.
+
+    Ok(())
 }
 
 
-pub async fn dl_invoiceitems(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_invoiceitems(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let p = Some(GetInvoiceitems {
-        created: None,
+        created: get_created_filter(created_gte),
         customer: None,
         ending_before: None,
         expand: Some(vec![
@@ -394,16 +556,18 @@ pub async fn dl_invoiceitems(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_m
     let mut st = c.v1_invoiceitems_get_st(&p.unwrap());
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
-        let res = val.unwrap();
+        let res = val?;
         let utx = &mut (utx_mt.lock().await);
         for x in res.data {
             Invoiceitem::insert_tree(utx, run_id, &x);
         }
     }
+
+    Ok(())
 }
 
 
-pub async fn dl_credit_notes(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_credit_notes(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) -> Result<(), TdError> {
     let p = GetCreditNotes {
         customer: None,
         ending_before: None,
@@ -417,7 +581,7 @@ pub async fn dl_credit_notes(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_m
     let mut st = c.v1_credit_notes_get_st(&p);
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
-        let res = val.unwrap();
+        let res = val?;
         for x in res.data {
             {
                 let utx = &mut (utx_mt.lock().await);
@@ -426,13 +590,15 @@ pub async fn dl_credit_notes(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_m
 
 
             if x.lines.has_more {
-                dl_credit_note_line_items(&c, q_mt_a, utx_mt, run_id, x.id.as_str()).await;
+                dl_credit_note_line_items(c, q_mt_a, utx_mt, run_id, x.id.as_str()).await?;
             }
         }
     }
+
+    Ok(())
 }
 
-pub async fn dl_credit_note_line_items(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, credit_note_id: &str) {
+pub async fn dl_credit_note_line_items(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, credit_note_id: &str) -> Result<(), TdError> {
     let p = GetCountrySpecs {
         ending_before: None,
         expand: None,
@@ -444,7 +610,7 @@ pub async fn dl_credit_note_line_items(c: &StripeClient, q_mt_a: &Arc<Mutex<Queu
     let mut st = c.v1_credit_notes_x_lines_get_st(credit_note_id.to_string(), &p.into());
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
-        let res = val.unwrap();
+        let res = val?;
         let utx = &mut (utx_mt.lock().await);
         for x in res.data {
             // Upsert needed as parent credit_note contains first 10 lines (and will insert them).
@@ -456,10 +622,12 @@ pub async fn dl_credit_note_line_items(c: &StripeClient, q_mt_a: &Arc<Mutex<Queu
             CreditNoteLineItemWithParentId::upsert_tree(utx, run_id, &x2);
         }
     }
+
+    Ok(())
 }
 
 
-pub async fn dl_one_customer_payment_methods(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, cid: String) {
+pub async fn dl_one_customer_payment_methods(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, cid: String) -> Result<(), TdError> {
     // Download PaymentMethods
     // PaymentMethods can be attached to a customer, and then used in many (Setup|Payment)Intent
     // - Issue: They can only be seen by 1 request per (custId, type).
@@ -499,7 +667,7 @@ pub async fn dl_one_customer_payment_methods(c: &StripeClient, q_mt_a: &Arc<Mute
                 starting_after: None,
             };
 
-            let list = c.v1_payment_methods_get(&p).q_low(&q_mt_a).await.unwrap();
+            let list = c.v1_payment_methods_get(&p).q_low(q_mt_a).await?;
 
             // @todo/low use stream in case of a customer having > 100 methods.
             assert_eq!(list.has_more, false);
@@ -509,19 +677,23 @@ pub async fn dl_one_customer_payment_methods(c: &StripeClient, q_mt_a: &Arc<Mute
                 let mut utx = &mut (utx_mt.lock().await);
                 PaymentMethod::upsert_tree(utx, run_id, &pm);
             }
+
+            Ok::<(), TdError>(())
         };
 
         all.push(one);
     }
 
-    futures::future::join_all(all).await;
+    try_join_all(all).await?;
+
+    Ok(())
 }
 
 
-pub async fn dl_prices(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_prices(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let mut p_price = GetPrices {
         type_x: None,
-        created: None,
+        created: get_created_filter(created_gte),
         active: None,
         currency: None,
         ending_before: None,
@@ -539,7 +711,7 @@ pub async fn dl_prices(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mu
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
         let utx = &mut (utx_mt.lock().await);
-        for x in val.unwrap().data {
+        for x in val?.data {
             Price::insert_tree(utx, run_id, &x);
         }
     }
@@ -551,16 +723,18 @@ pub async fn dl_prices(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mu
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
         let utx = &mut (utx_mt.lock().await);
-        for x in val.unwrap().data {
+        for x in val?.data {
             Price::insert_tree(utx, run_id, &x);
         }
     }
+
+    Ok(())
 }
 
-pub async fn dl_subscriptions(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_subscriptions(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let subs = async {
         let p_subs = GetSubscriptions {
-            created: None,
+            created: get_created_filter(created_gte),
             current_period_end: None,
             current_period_start: None,
             collection_method: None,
@@ -583,7 +757,7 @@ pub async fn dl_subscriptions(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_
         let mut st = c.v1_subscriptions_get_st(&p_subs);
         pin_mut!(st);
         while let Some(val) = st.next().q_high(q_mt_a).await {
-            let res = val.unwrap();
+            let res = val?;
             let utx = &mut (utx_mt.lock().await);
             for x in res.data {
                 Subscription::insert_tree(utx, run_id, &x);
@@ -591,13 +765,15 @@ pub async fn dl_subscriptions(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_
                 // - Sub items limited to 20, are always included in both dl and events.
             }
         }
+
+        Ok::<(), TdError>(())
     };
 
     let schedules = async {
         let p_sched = GetSubscriptionSchedules {
             canceled_at: None,
             completed_at: None,
-            created: None,
+            created: get_created_filter(created_gte),
             released_at: None,
             customer: None,
             ending_before: None,
@@ -610,23 +786,27 @@ pub async fn dl_subscriptions(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_
         let mut st = c.v1_subscription_schedules_get_st(&p_sched);
         pin_mut!(st);
         while let Some(val) = st.next().q_high(q_mt_a).await {
-            let res = val.unwrap();
+            let res = val?;
             let utx = &mut (utx_mt.lock().await);
             for x in res.data {
                 SubscriptionSchedule::insert_tree(utx, run_id, &x);
             }
         }
+
+        Ok::<(), TdError>(())
     };
 
-    join!(
+    try_join!(
         subs,
         schedules
-    );
+    )?;
+
+    Ok(())
 }
 
-pub async fn dl_tax_rates(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_tax_rates(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let mut p = GetTaxRates {
-        created: None,
+        created: get_created_filter(created_gte),
         active: None,
         ending_before: None,
         expand: None,
@@ -639,7 +819,7 @@ pub async fn dl_tax_rates(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt:
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
         let utx = &mut (utx_mt.lock().await);
-        for x in val.unwrap().data {
+        for x in val?.data {
             TaxRate::insert_tree(utx, run_id, &x);
         }
     }
@@ -651,12 +831,14 @@ pub async fn dl_tax_rates(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt:
     // while let Some(val) = st.next().await {
     //     insert_tax_rates(&uc, val.unwrap().data);
     // }
+
+    Ok(())
 }
 
 
-pub async fn dl_products(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_products(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let mut p = GetProducts {
-        created: None,
+        created: get_created_filter(created_gte),
         active: None,
         ending_before: None,
         expand: None,
@@ -671,16 +853,18 @@ pub async fn dl_products(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
         let utx = &mut (utx_mt.lock().await);
-        for x in val.unwrap().data {
+        for x in val?.data {
             Product::insert_tree(utx, run_id, &x);
         }
     }
+
+    Ok(())
 }
 
 
-pub async fn dl_payment_intents(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_payment_intents(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let mut p = GetPaymentIntents {
-        created: None,
+        created: get_created_filter(created_gte),
         customer: None,
         ending_before: None,
         expand: Some(vec![
@@ -694,16 +878,18 @@ pub async fn dl_payment_intents(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, ut
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
         let utx = &mut (utx_mt.lock().await);
-        for x in val.unwrap().data {
+        for x in val?.data {
             PaymentIntent::insert_tree(utx, run_id, &x);
         }
     }
+
+    Ok(())
 }
 
 
-pub async fn dl_setup_intents(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_setup_intents(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let mut p = GetSetupIntents {
-        created: None,
+        created: get_created_filter(created_gte),
         customer: None,
         ending_before: None,
         expand: Some(vec![
@@ -718,16 +904,18 @@ pub async fn dl_setup_intents(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
         let utx = &mut (utx_mt.lock().await);
-        for x in val.unwrap().data {
+        for x in val?.data {
             SetupIntent::insert_tree(utx, run_id, &x);
         }
     }
+
+    Ok(())
 }
 
 
-pub async fn dl_charges(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_charges(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let mut p = GetCharges {
-        created: None,
+        created: get_created_filter(created_gte),
         customer: None,
         ending_before: None,
         expand: None,
@@ -741,15 +929,17 @@ pub async fn dl_charges(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &M
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
         let utx = &mut (utx_mt.lock().await);
-        for x in val.unwrap().data {
+        for x in val?.data {
             Charge::insert_tree(utx, run_id, &x);
         }
     }
+
+    Ok(())
 }
 
-pub async fn dl_refunds(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_refunds(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let mut p = GetRefunds {
-        created: None,
+        created: get_created_filter(created_gte),
         charge: None,
         ending_before: None,
         expand: None,
@@ -762,16 +952,18 @@ pub async fn dl_refunds(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &M
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
         let utx = &mut (utx_mt.lock().await);
-        for x in val.unwrap().data {
+        for x in val?.data {
             // Note: Refunds exist as children on (OrderReturn, TransferReversal, CreditNote), so can be inserted already from any of these depending on download order. (This can be an insert_tree if refunds are downloaded first).
             Refund::upsert_tree(utx, run_id, &x);
         }
     }
+
+    Ok(())
 }
 
-pub async fn dl_disputes(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_disputes(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let mut p = GetDisputes {
-        created: None,
+        created: get_created_filter(created_gte),
         charge: None,
         ending_before: None,
         expand: None,
@@ -784,10 +976,12 @@ pub async fn dl_disputes(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
         let utx = &mut (utx_mt.lock().await);
-        for x in val.unwrap().data {
+        for x in val?.data {
             Dispute::insert_tree(utx, run_id, &x);
         }
     }
+
+    Ok(())
 }
 
 /// These are not kept up to date with apply_events as there are no events for balance transactions (only other types that may contain balance transactions).
@@ -797,11 +991,11 @@ pub async fn dl_disputes(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &
 /// Note: Balances are different from BalanceTransactions.
 /// - BalanceTransactions do not have events so cannot be kept up to date.
 ///     - The `balance.available` is just a "current balance" event?
-pub async fn dl_balance_transactions(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_balance_transactions(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let mut p = GetBalanceHistory {
         type_x: None,
         available_on: None,
-        created: None,
+        created: get_created_filter(created_gte),
         currency: None,
         ending_before: None,
         expand: None,
@@ -815,14 +1009,16 @@ pub async fn dl_balance_transactions(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
         let utx = &mut (utx_mt.lock().await);
-        for x in val.unwrap().data {
+        for x in val?.data {
             BalanceTransaction::insert_tree(utx, run_id, &x);
         }
     }
+
+    Ok(())
 }
 
 
-pub async fn dl_sessions(c: &StripeClient, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_sessions(c: &StripeClient, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) -> Result<(), TdError> {
     let mut p = GetCheckoutSessions {
         ending_before: None,
         expand: None, // line_items can be expanded, but its always missing from events.
@@ -836,7 +1032,7 @@ pub async fn dl_sessions(c: &StripeClient, utx_mt: &Mutex<UniTx<'_>>, run_id: i6
     pin_mut!(st);
     while let Some(val) = st.next().await {
         let utx = &mut (utx_mt.lock().await);
-        for x in val.unwrap().data {
+        for x in val?.data {
 
             // @todo/low Potentially filter both download and events for payment_status=paid, as these can be kept up to date with events (unpaid cannot as there is no create event).
 
@@ -853,12 +1049,14 @@ pub async fn dl_sessions(c: &StripeClient, utx_mt: &Mutex<UniTx<'_>>, run_id: i6
             // dl_at_end_of_apply_events
         }
     }
+
+    Ok(())
 }
 
 
-pub async fn dl_orders(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_orders(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, created_gte: Option<i64>) -> Result<(), TdError> {
     let p = GetOrders {
-        created: None,
+        created: get_created_filter(created_gte),
         customer: None,
         ending_before: None,
         // `returns` expanded in both dl and events by default.
@@ -876,11 +1074,13 @@ pub async fn dl_orders(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mu
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
         let utx = &mut (utx_mt.lock().await);
-        let res = val.unwrap();
+        let res = val?;
         for x in res.data {
             Order::insert_tree(utx, run_id, &x);
         }
     }
+
+    Ok(())
 }
 
 // Note: always expanded on `order`, `order` events are complete (order_return is missing update event with prevents refund from being updated)
@@ -905,7 +1105,7 @@ pub async fn dl_orders(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mu
 //     }
 // }
 
-pub async fn dl_skus(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) {
+pub async fn dl_skus(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mutex<UniTx<'_>>, run_id: i64) -> Result<(), TdError> {
     let p = GetSkus {
         active: None,
         attributes: None,
@@ -922,10 +1122,12 @@ pub async fn dl_skus(c: &StripeClient, q_mt_a: &Arc<Mutex<Queue>>, utx_mt: &Mute
     let mut st = c.v1_skus_get_st(&p);
     pin_mut!(st);
     while let Some(val) = st.next().q_high(q_mt_a).await {
-        let res = val.unwrap();
+        let res = val?;
         let utx = &mut (utx_mt.lock().await);
         for x in res.data {
             Sku::insert_tree(utx, run_id, &x);
         }
     }
+
+    Ok(())
 }
\ No newline at end of file
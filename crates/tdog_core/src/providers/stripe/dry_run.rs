@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use unicon::traits::{*};
+use unicon::utx::UniTx;
+
+use crate::providers::stripe::schema_meta::{TdStripeApplyEvent, TdStripeWrite};
+use crate::providers::stripe::write_ctx::with_dry_run;
+
+
+// Dry run (`options.dry_run`): show the impact of applying events (E.g. before pointing a new CLI version at a production DB).
+// - Events are fetched and written with the same code as a normal run, but the tx is always rolled back.
+// - `td_stripe_writes` and `td_stripe_apply_events` rows for the run are read before the rollback to summarize the writes and skipped events.
+// - Updated rows are read before and after the update to get the changed columns.
+// - When a download is needed, it is written in a tx that is rolled back in the same way (the list pages are requested from Stripe).
+// - No other writes are made (E.g. `td_metadata.heartbeat_ts`, CDC, hooks, notifications, migrations).
+
+
+// Not changes to the Stripe data.
+const IGNORE_COLS: &[&str] = &["insert_ts", "update_ts"];
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Default)]
+pub struct TableWrites {
+    pub inserts: i64,
+    pub updates: i64,
+    pub deletes: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColChange {
+    pub from: Value,
+    pub to: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct RowUpdate {
+    pub table: String,
+    pub id: String,
+    pub cols: BTreeMap<String, ColChange>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct DryRunSummary {
+    pub events: usize,
+    pub tables: BTreeMap<String, TableWrites>,
+
+    // Key = `td_stripe_apply_events.action` (E.g. `skip.not_data_write`).
+    pub skips: BTreeMap<String, i64>,
+    pub updates: Vec<RowUpdate>,
+}
+
+pub fn get_col_changes(before: &Value, after: &Value) -> BTreeMap<String, ColChange> {
+    let mut cols = BTreeMap::new();
+
+    if let (Value::Object(a), Value::Object(b)) = (before, after) {
+        for (k, to) in b {
+            if IGNORE_COLS.contains(&k.as_str()) {
+                continue;
+            }
+
+            let from = a.get(k).cloned().unwrap_or(Value::Null);
+            if &from != to {
+                cols.insert(k.clone(), ColChange {
+                    from,
+                    to: to.clone(),
+                });
+            }
+        }
+    }
+
+    cols
+}
+
+// Called from `LogWrite` with the row read before and after an update (only when `is_dry_run`).
+pub fn push_update<T: Serialize>(table: &str, id: &str, before: &T, after: &T) {
+    let cols = get_col_changes(&serde_json::to_value(before).unwrap(), &serde_json::to_value(after).unwrap());
+
+    // Re-applying an event can update a row to the same values.
+    if cols.is_empty() {
+        return;
+    }
+
+    with_dry_run(|updates| updates.push(RowUpdate {
+        table: table.to_string(),
+        id: id.to_string(),
+        cols,
+    }));
+}
+
+// Read before the tx is rolled back.
+pub fn tx_get_summary(utx: &mut UniTx, run_id: i64, events: usize) -> DryRunSummary {
+    let mut tables: BTreeMap<String, TableWrites> = BTreeMap::new();
+    for w in TdStripeWrite::get_where_tx(utx, ("run_id", &run_id)) {
+        let t = tables.entry(w.table_name).or_default();
+        match w.write_type.as_str() {
+            "c" => t.inserts += 1,
+            "u" => t.updates += 1,
+            "d" => t.deletes += 1,
+            _ => unreachable!()
+        }
+    }
+
+    let mut skips = BTreeMap::new();
+    for a in TdStripeApplyEvent::get_where_tx(utx, ("run_id", &run_id)) {
+        if a.action != "write" {
+            *skips.entry(a.action).or_insert(0) += 1;
+        }
+    }
+
+    DryRunSummary {
+        events,
+        tables,
+        skips,
+        updates: with_dry_run(std::mem::take).unwrap_or_default(),
+    }
+}
+
+pub fn log_summary(x: &DryRunSummary) {
+    info!("Dry run: {} events would be applied (the transaction was rolled back, nothing was written).", x.events);
+
+    for (table, w) in &x.tables {
+        info!("Dry run: {}: {} inserts, {} updates, {} deletes.", table, w.inserts, w.updates, w.deletes);
+    }
+
+    for (action, n) in &x.skips {
+        info!("Dry run: {} events would be skipped ({}).", n, action);
+    }
+
+    for u in &x.updates {
+        info!("Dry run: update {} {}: {}", u.table, u.id, serde_json::to_string(&u.cols).unwrap());
+    }
+
+    info!("Dry run summary: {}", serde_json::to_string(x).unwrap());
+}
+
+pub fn log_download_summary(x: &DryRunSummary) {
+    info!("Dry run: a download would write these rows (the transaction was rolled back, nothing was written).");
+
+    for (table, w) in &x.tables {
+        info!("Dry run: {}: {} inserts, {} updates, {} deletes.", table, w.inserts, w.updates, w.deletes);
+    }
+
+    info!("Dry run summary: {}", serde_json::to_string(x).unwrap());
+}
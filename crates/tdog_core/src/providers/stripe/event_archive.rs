@@ -0,0 +1,276 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use chrono::{Duration, Utc};
+use flate2::Compression;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use mysql::Params;
+use mysql::prelude::Queryable;
+use serde::{Deserialize, Serialize};
+use unicon::dt::DT;
+use unicon::traits::{*};
+use unicon::uc::{*};
+use unicon::utx::UniTx;
+
+use crate::{EventsExport, EventsImport};
+use crate::error::TdError;
+use crate::providers::stripe::migrations::tx_open_existing;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::notification_event::NotificationEvent;
+use crate::providers::stripe::schema_meta::TdMetadata;
+
+
+// Long term archive of `notification_events` (Stripe only keeps 30 days of events).
+// - `events_export`: appends new rows to gzipped JSONL segments, one per UTC day of `created` (`events-2021-01-31.jsonl.gz`).
+//      - Each export appends a new gzip member, so segments are never rewritten.
+//      - `manifest.json` records the last exported row; an interrupted export may repeat rows, which `events_import` skips.
+//      - `retention_days`: after exporting, delete exported rows older than this from the live DB.
+// - `events_import`: inserts the rows of every segment into `notification_events` (creating only that table when the DB is empty).
+// - Lines are `notification_events` rows (not Stripe events) so they can be imported without parsing each event version.
+
+
+const MANIFEST: &str = "manifest.json";
+
+// Rows read from the DB per query.
+const PAGE_SIZE: i64 = 1_000;
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    // A directory only contains one account's events.
+    pub stripe_account_id: String,
+    pub stripe_is_test: bool,
+
+    // `notification_events.event_id` (the DB row id, not the Stripe id) of the last exported row.
+    pub last_event_id: i64,
+}
+
+pub fn get_segment_file(dir: &str, created: &DT) -> String {
+    format!("{}/events-{}.jsonl.gz", dir, created.dt.format("%Y-%m-%d"))
+}
+
+fn read_manifest(dir: &str) -> Result<Option<Manifest>, TdError> {
+    let file = format!("{}/{}", dir, MANIFEST);
+    if !Path::new(&file).exists() {
+        return Ok(None);
+    }
+
+    let s = fs::read_to_string(&file).map_err(|e| TdError::Config(format!("Could not read `{}`: {}", file, e)))?;
+    serde_json::from_str(&s).map(Some).map_err(|e| TdError::Config(format!("Invalid `{}`: {}", file, e)))
+}
+
+fn check_account(dir: &str, m: &Manifest, md: &TdMetadata) -> Result<(), TdError> {
+    if m.stripe_account_id != md.stripe_account_id || m.stripe_is_test != md.stripe_is_test {
+        return Err(TdError::SchemaConflict(format!("`{}` contains events from Stripe account {} (is_test={}), the DB is for {} (is_test={}).", dir, m.stripe_account_id, m.stripe_is_test, md.stripe_account_id, md.stripe_is_test)));
+    }
+    Ok(())
+}
+
+// Renamed into place so a crash never leaves a partial manifest.
+fn write_manifest(dir: &str, x: &Manifest) -> Result<(), TdError> {
+    let file = format!("{}/{}", dir, MANIFEST);
+    let tmp = format!("{}.tmp", file);
+    fs::write(&tmp, serde_json::to_string_pretty(x).unwrap()).map_err(|e| TdError::Config(format!("Could not write `{}`: {}", tmp, e)))?;
+    fs::rename(&tmp, &file).map_err(|e| TdError::Config(format!("Could not write `{}`: {}", file, e)))
+}
+
+fn append_segment(file: &str, rows: &[NotificationEvent]) -> Result<(), TdError> {
+    let err = |e: std::io::Error| TdError::Config(format!("Could not write `{}`: {}", file, e));
+    let f = OpenOptions::new().create(true).append(true).open(file).map_err(err)?;
+
+    let mut gz = GzEncoder::new(f, Compression::default());
+    for x in rows {
+        let mut row = x.clone();
+        row.event_id = None;
+        row.insert_ts = None;
+        writeln!(gz, "{}", serde_json::to_string(&row).unwrap()).map_err(err)?;
+    }
+    gz.finish().map_err(err)?.sync_all().map_err(err)
+}
+
+// Returns the number of exported rows.
+pub fn export(uc: &mut UniCon, x: &EventsExport) -> Result<usize, TdError> {
+    fs::create_dir_all(&x.dir).map_err(|e| TdError::Config(format!("Could not create `{}`: {}", &x.dir, e)))?;
+
+    let md: TdMetadata = TdMetadata::get_last(uc, "id").ok_or_else(|| TdError::SchemaConflict("The DB has no `td_metadata` row; export from a DB written by `tdog`.".into()))?;
+    let mut manifest = match read_manifest(&x.dir)? {
+        Some(m) => {
+            check_account(&x.dir, &m, &md)?;
+            m
+        }
+        None => Manifest {
+            stripe_account_id: md.stripe_account_id.clone(),
+            stripe_is_test: md.stripe_is_test,
+            last_event_id: 0,
+        }
+    };
+
+    let table = NotificationEvent::get_table_name_static();
+    let mut total = 0;
+    loop {
+        let sql = format!("SELECT * FROM {} WHERE event_id > {} ORDER BY event_id ASC LIMIT {}", table, manifest.last_event_id, PAGE_SIZE);
+        let rows: Vec<NotificationEvent> = uc.get_vec_from_sql(&sql);
+        if rows.is_empty() {
+            break;
+        }
+
+        let mut segments: BTreeMap<String, Vec<NotificationEvent>> = BTreeMap::new();
+        for r in &rows {
+            segments.entry(get_segment_file(&x.dir, &r.created)).or_default().push(r.clone());
+        }
+        for (file, rows) in &segments {
+            append_segment(file, rows)?;
+        }
+
+        total += rows.len();
+        manifest.last_event_id = rows.last().unwrap().event_id.unwrap();
+        write_manifest(&x.dir, &manifest)?;
+    }
+
+    info!("Exported {} events to `{}`.", total, &x.dir);
+
+    if let Some(days) = x.retention_days {
+        prune(uc, days, manifest.last_event_id)?;
+    }
+
+    Ok(total)
+}
+
+// Only rows that have been exported are deleted.
+// - The last exported row is kept: MySQL before 8.0 resets `AUTO_INCREMENT` to the max id on restart, so emptying the table would re-use exported ids and hide new rows from the next export.
+fn prune(uc: &mut UniCon, retention_days: u32, last_event_id: i64) -> Result<(), TdError> {
+    let cutoff: DT = (Utc::now() - Duration::days(retention_days as i64)).into();
+    let table = NotificationEvent::get_table_name_static();
+    let sql_std = format!("DELETE FROM {} WHERE created < ? AND event_id < ?", table);
+    let sql_pg = format!("DELETE FROM {} WHERE created < $1 AND event_id < $2", table);
+    let err = |e: String| TdError::Db(format!("Could not delete exported events: {}", e));
+
+    let mut utx = uc.tx_open().map_err(|e| TdError::Db(format!("Could not open a DB transaction: {:?}", e)))?;
+    match &mut utx {
+        UniTx::Rusqlite(tx) => {
+            tx.execute(&sql_std, rusqlite::params![&cutoff, last_event_id]).map_err(|e| err(e.to_string()))?;
+        }
+        UniTx::MySQL(tx) => {
+            tx.exec_drop(&sql_std, Params::Positional(vec![cutoff.clone().into(), last_event_id.into()])).map_err(|e| err(e.to_string()))?;
+        }
+        UniTx::Postgres(tx) => {
+            tx.execute(sql_pg.as_str(), &[&cutoff, &last_event_id]).map_err(|e| err(e.to_string()))?;
+        }
+        UniTx::PlaceholderLibA(_) => unreachable!()
+    }
+    utx.tx_close().map_err(|e| TdError::from_commit("prune transaction", e))?;
+
+    info!("Deleted exported events created before {} from the DB (`retention_days` = {}).", cutoff.dt.format("%Y-%m-%d %H:%M:%S"), retention_days);
+    Ok(())
+}
+
+
+fn get_segment_files(dir: &str) -> Result<Vec<String>, TdError> {
+    let entries = fs::read_dir(dir).map_err(|e| TdError::Config(format!("Could not read directory `{}`: {}", dir, e)))?;
+    let mut files: Vec<String> = entries
+        .filter_map(|x| x.ok())
+        .map(|x| x.file_name().to_string_lossy().to_string())
+        .filter(|x| x.starts_with("events-") && x.ends_with(".jsonl.gz"))
+        .map(|x| format!("{}/{}", dir, x))
+        .collect();
+
+    // File names sort by date.
+    files.sort();
+    Ok(files)
+}
+
+// An empty DB only gets `notification_events`, so it cannot later be used as a `download` target by mistake.
+fn ensure_events_table(uc: &mut UniCon) -> Result<(), TdError> {
+    if let Ok(true) = uc.schema_exists_with::<Db>(&vec![], &vec![]) {
+        return Ok(());
+    }
+
+    let table = NotificationEvent::get_table_name_static();
+    let others: Vec<String> = Db::get_target_schema_sqlite().tables.into_iter().map(|x| x.name).filter(|x| x != table).collect();
+    let (created, _) = uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&others, &vec![]).map_err(TdError::SchemaConflict)?;
+    if created {
+        info!("Created table `{}`.", table);
+    }
+
+    Ok(())
+}
+
+// Rows of one segment, in export order.
+pub fn read_segment(file: &str) -> Result<Vec<NotificationEvent>, TdError> {
+    let err = |e: std::io::Error| TdError::Config(format!("Could not read `{}`: {}", file, e));
+    let f = File::open(file).map_err(err)?;
+
+    let mut rows = vec![];
+    for (i, line) in BufReader::new(MultiGzDecoder::new(f)).lines().enumerate() {
+        let line = line.map_err(err)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let row: NotificationEvent = serde_json::from_str(&line).map_err(|e| TdError::Config(format!("Invalid row on line {} of `{}`: {}", i + 1, file, e)))?;
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+// The segments of `dir` in date order, each read when the iterator reaches it (an archive can span years, so it is never loaded at once).
+// - `stripe_account_id` must match the manifest (when there is one).
+pub fn read_segments(dir: &str, stripe_account_id: &str) -> Result<impl Iterator<Item=Result<Vec<NotificationEvent>, TdError>>, TdError> {
+    if let Some(m) = read_manifest(dir)? {
+        if m.stripe_account_id != stripe_account_id {
+            return Err(TdError::SchemaConflict(format!("`{}` contains events from Stripe account {}, the DB is for {}.", dir, m.stripe_account_id, stripe_account_id)));
+        }
+    }
+
+    Ok(get_segment_files(dir)?.into_iter().map(|file| read_segment(&file)))
+}
+
+// Returns the number of inserted rows (rows with an existing Stripe id are skipped).
+// - A DB written by `tdog` (with `td_metadata`) must be for the account in the manifest (when there is one).
+pub fn import(uc: &mut UniCon, x: &EventsImport) -> Result<usize, TdError> {
+    let files = get_segment_files(&x.dir)?;
+    if files.is_empty() {
+        return Err(TdError::Config(format!("No `events-*.jsonl.gz` files in `{}`.", &x.dir)));
+    }
+
+    if let Some(m) = read_manifest(&x.dir)? {
+        if let Some(mut utx) = tx_open_existing(uc)? {
+            let md: Option<TdMetadata> = TdMetadata::tx_get_last(&mut utx, "id");
+            utx.tx_rollback().map_err(|e| TdError::Db(e.to_string()))?;
+            if let Some(md) = md {
+                check_account(&x.dir, &m, &md)?;
+            }
+        }
+    }
+
+    ensure_events_table(uc)?;
+
+    let mut total = 0;
+    for file in &files {
+        let rows = read_segment(file)?;
+
+        let mut utx = uc.tx_open().map_err(|e| TdError::Db(format!("Could not open a DB transaction: {:?}", e)))?;
+        let (mut inserted, mut skipped) = (0, 0);
+        for mut row in rows {
+            if !NotificationEvent::get_where_tx(&mut utx, ("id", &row.id)).is_empty() {
+                skipped += 1;
+                continue;
+            }
+
+            row.event_id = None;
+            row.tx_insert_set_pk(&mut utx);
+            inserted += 1;
+        }
+        utx.tx_close().map_err(|e| TdError::from_commit("import transaction", e))?;
+
+        debug!("Imported `{}`: {} inserted, {} skipped.", file, inserted, skipped);
+        total += inserted;
+    }
+
+    info!("Imported {} events from {} files in `{}`.", total, files.len(), &x.dir);
+    Ok(total)
+}
@@ -0,0 +1,93 @@
+use unicon::table::{ColRef, Table};
+use unicon::traits::{*};
+use unicon::uc::UniCon;
+
+use crate::error::TdError;
+use crate::ForeignKeys;
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::Db;
+
+
+// Foreign keys (`options.foreign_keys`): the relations in `schema/relations.rs` as constraints, so BI tools can discover joins.
+// - `declared`: every relation from an id column to a single table.
+//      - SQLite: `FOREIGN KEY` clauses, with `PRAGMA foreign_keys = OFF` on tdog's connection.
+//      - Postgres, MySQL: column comments (`References customers(id)`); Postgres `NOT VALID` constraints still check new rows, and MySQL has no unchecked foreign keys.
+// - `enforced`: only the relations the Stripe API keeps consistent, as `DEFERRABLE INITIALLY DEFERRED` constraints (SQLite, Postgres).
+//      - A download and each batch of applied events are written in one tx, so rows are checked at commit regardless of the order their lists are downloaded in.
+//      - Not with `options.created_gte`: owners created before the cutoff may not be downloadable (E.g. deleted customers).
+// - Polymorphic relations (E.g. `customers.default_source`), JSON arrays of ids and owners without a unique `id` are not included.
+// - The constraints are added to copies of the `Insert` tables when the DB is created (like `options.history`).
+
+
+pub fn assert_supported(mode: ForeignKeys, uc: &UniCon, created_gte: Option<&String>) -> Result<(), TdError> {
+    if mode != ForeignKeys::Enforced {
+        return Ok(());
+    }
+
+    if let UniCon::MySQL(_) = uc {
+        return Err(TdError::Config("`options.foreign_keys=enforced` is not supported on MySQL (it has no deferred constraints). Use `declared`.".into()));
+    }
+
+    if created_gte.is_some() {
+        return Err(TdError::Config("`options.foreign_keys=enforced` cannot be used with `options.created_gte`: owners created before the cutoff may be missing. Use `declared`.".into()));
+    }
+
+    Ok(())
+}
+
+// When the DB is created: sets `references` on the copy columns of the tables in `extra` (E.g. from `store_raw_json`), or of copies of the `Insert` tables that replace them.
+pub fn add_tables(mode: ForeignKeys, objects: &ObjectSelection, excluded: &mut Vec<String>, extra: &mut Vec<Table>) {
+    if mode == ForeignKeys::None {
+        return;
+    }
+
+    let all = Db::get_tables();
+    let mut changed = vec![];
+
+    for (copy_tbl, copy_col, owner_tbl, enforced_by_api) in Db::get_fk_edges(objects) {
+        let enforced = mode == ForeignKeys::Enforced;
+        if enforced && !enforced_by_api {
+            continue;
+        }
+
+        // SQLite and Postgres require the referenced column to be unique (E.g. `payment_methods.id` is not).
+        let owner = all.iter().find(|x| x.name == owner_tbl).unwrap();
+        if !owner.cols.iter().any(|c| c.name == "id" && c.unique) {
+            continue;
+        }
+
+        let i = match extra.iter().position(|x| x.name == copy_tbl) {
+            Some(i) => i,
+            None => {
+                let t = all.iter().find(|x| x.name == copy_tbl).unwrap();
+                excluded.push(copy_tbl.clone());
+                extra.push((*t).clone());
+                extra.len() - 1
+            }
+        };
+
+        let t = &mut extra[i];
+        let c = t.cols.iter_mut().find(|c| c.name == copy_col).unwrap();
+        c.references = Some(ColRef {
+            table: owner_tbl,
+            col: "id".into(),
+            enforced,
+        });
+
+        if !changed.contains(&i) {
+            changed.push(i);
+        }
+    }
+
+    for i in changed {
+        extra[i].create_static_strings();
+    }
+}
+
+// SQLite checks foreign keys per connection (`unicon` enables them).
+pub fn set_sqlite_enforcement(mode: ForeignKeys, uc: &mut UniCon) {
+    if let UniCon::Rusqlite(x) = uc {
+        let on = if mode == ForeignKeys::Enforced { "ON" } else { "OFF" };
+        x.c.pragma_update(None, "foreign_keys", &on).unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use mysql::Params;
+use mysql::prelude::Queryable;
+use unicon::dt3::DT3;
+use unicon::table::{*};
+use unicon::traits::{*};
+use unicon::utx::UniTx;
+
+use crate::providers::stripe::objects::{get_always_included, ObjectSelection};
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema_meta::{TdRun, TdStripeWrite};
+use crate::providers::stripe::write_ctx::{get_event_created, get_event_id, get_run_start, is_history_enabled, set_run_start};
+
+
+// History tables (`options.history`): keep every version of every row (type-2 slowly changing dimension).
+// - Each `Db` table (except `td_*` and `notification_events`) gets a `<table>_history` copy with the columns:
+//      - `valid_from`, `valid_to` (null = current version), `run_id` (`td_run`), `event_id` (null = written by a download).
+//      - `valid_from`/`valid_to` are when the row changed in Stripe (the event's `created`), or the run's `start_ts` for rows written without an event (downloads), not the time of the write.
+//          - Replaying old events or catching up after downtime keeps the Stripe timeline.
+// - Versions are written in the same tx as the row write, via the `LogWrite` functions (every row write is logged to `td_stripe_writes`).
+//      - Insert/update: close the current version, copy the new row state with `INSERT ... SELECT`.
+//      - Delete: close the current version.
+// - Only applies when set at DB create time (the history tables are part of the target schema).
+
+
+pub fn get_history_table_name(table_name: &str) -> String {
+    format!("{}_history", table_name)
+}
+
+fn has_history(table_name: &str) -> bool {
+    !get_always_included().contains(&table_name)
+}
+
+// History tables for the tables that will be created.
+pub fn get_history_tables(objects: &ObjectSelection) -> Vec<Table> {
+    Db::get_tables().into_iter()
+        .filter(|t| has_history(&t.name) && objects.is_included(&t.name))
+        .map(get_history_table)
+        .collect()
+}
+
+// Same columns as the source table, but without the primary key/unique constraints (there are many versions of each row).
+fn get_history_table(t: &Table) -> Table {
+    let name = get_history_table_name(&t.name);
+
+    let mut history_id = Col::new("history_id", RustType::I64, false);
+    history_id.primary = true;
+
+    let mut cols = vec![history_id];
+
+    for c in t.cols_not_skipped() {
+        let mut c2 = c.clone();
+
+        // Keep the same SQL type (MySQL/Postgres use VARCHAR(255) for unique or indexed strings).
+        c2.is_used_in_index = c.unique || c.is_used_in_index || c.name == "id";
+        c2.primary = false;
+        c2.unique = false;
+        c2.insert_ts = false;
+        c2.update_ts = false;
+        cols.push(c2);
+    }
+
+    cols.push(Col::new("valid_from", RustType::DT3, false));
+    cols.push(Col::new("valid_to", RustType::DT3, true));
+    cols.push(Col::new("run_id", RustType::I64, false));
+    cols.push(Col::new("event_id", RustType::String, true));
+
+    let index_name = format!("{}_id_valid_to", &name);
+    let mut x = Table {
+        struct_name: format!("{}History", &t.struct_name),
+        name: name.clone(),
+        cols,
+        indexes: vec![Index {
+            sql: format!("CREATE INDEX {} ON {} (id, valid_to)", &index_name, &name),
+            name: index_name,
+            fields_used: vec!["id".into(), "valid_to".into()],
+        }],
+        foreign_keys: vec![],
+        static_sql_strings: None,
+    };
+
+    x.create_static_strings();
+    x
+}
+
+
+struct HistorySQL {
+    close_std: String,
+    close_pg: String,
+    insert_std: String,
+    insert_pg: String,
+}
+
+impl From<&Table> for HistorySQL {
+    fn from(t: &Table) -> Self {
+        let h = get_history_table_name(&t.name);
+        let cols = t.cols_not_skipped().iter().map(|c| c.name.clone()).collect::<Vec<String>>().join(", ");
+
+        // Note: Postgres does not infer param types in a `SELECT` list.
+        HistorySQL {
+            close_std: format!("UPDATE {} SET valid_to = ? WHERE id = ? AND valid_to IS NULL", &h),
+            close_pg: format!("UPDATE {} SET valid_to = $1 WHERE id = $2 AND valid_to IS NULL", &h),
+            insert_std: format!("INSERT INTO {} ({}, valid_from, run_id, event_id) SELECT {}, ?, ?, ? FROM {} WHERE id = ?", &h, &cols, &cols, &t.name),
+            insert_pg: format!("INSERT INTO {} ({}, valid_from, run_id, event_id) SELECT {}, $1::timestamp, $2::bigint, $3::varchar FROM {} WHERE id = $4", &h, &cols, &cols, &t.name),
+        }
+    }
+}
+
+lazy_static! {
+    static ref HISTORY_SQL: HashMap<&'static str, HistorySQL> = {
+        Db::get_tables().into_iter()
+            .filter(|t| has_history(&t.name))
+            .map(|t| (t.name.as_str(), t.into()))
+            .collect()
+    };
+}
+
+
+// Close the current version of the written row, and insert the new version (unless deleted).
+// - Called after the row write, before it is logged to `td_stripe_writes`.
+pub fn tx_write_version(utx: &mut UniTx, w: &TdStripeWrite) {
+    if !is_history_enabled() {
+        return;
+    }
+
+    let sql = match HISTORY_SQL.get(w.table_name.as_str()) {
+        None => return,
+        Some(x) => x
+    };
+
+    let now = get_valid_from(utx, w.run_id);
+    let event_id = get_event_id();
+    let id = &w.obj_id;
+    let insert = w.write_type != "d";
+
+    match utx {
+        UniTx::Rusqlite(tx) => {
+            tx.prepare_cached(&sql.close_std).unwrap().execute(rusqlite::params![&now, id]).unwrap();
+
+            if insert {
+                let changes = tx.prepare_cached(&sql.insert_std).unwrap().execute(rusqlite::params![&now, &w.run_id, &event_id, id]).unwrap();
+                assert_eq!(changes, 1);
+            }
+        }
+        UniTx::MySQL(tx) => {
+            tx.exec_drop(&sql.close_std, Params::Positional(vec![now.clone().into(), id.into()])).unwrap();
+
+            if insert {
+                tx.exec_drop(&sql.insert_std, Params::Positional(vec![now.into(), w.run_id.into(), event_id.into(), id.into()])).unwrap();
+                assert_eq!(tx.affected_rows(), 1);
+            }
+        }
+        UniTx::Postgres(tx) => {
+            tx.execute(sql.close_pg.as_str(), &[&now, id]).unwrap();
+
+            if insert {
+                let changes = tx.execute(sql.insert_pg.as_str(), &[&now, &w.run_id, &event_id, id]).unwrap();
+                assert_eq!(changes, 1);
+            }
+        }
+        UniTx::PlaceholderLibA(_) => {}
+    }
+}
+
+
+fn get_valid_from(utx: &mut UniTx, run_id: i64) -> DT3 {
+    if let Some(x) = get_event_created() {
+        return x;
+    }
+
+    if let Some(x) = get_run_start(run_id) {
+        return x;
+    }
+
+    let start_ts = TdRun::get_where_tx(utx, ("run_id", &run_id)).pop().and_then(|x| x.start_ts).unwrap_or_else(|| Utc::now().into());
+    set_run_start(run_id, start_ts.clone());
+    start_ts
+}
@@ -0,0 +1,281 @@
+use std::collections::{BTreeMap, HashSet};
+use std::convert::TryFrom;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+use unicon::params::ExtraVal;
+use unicon::table::{*};
+use unicon::traits::{*};
+use unicon::uc::UniCon;
+
+use crate::Options;
+use crate::error::TdError;
+use crate::providers::stripe::objects::{get_always_included, ObjectSelection};
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::{*};
+use crate::providers::stripe::schema::types::balance_transaction::BalanceTransaction;
+use crate::providers::stripe::schema::types::bank_account::BankAccount;
+use crate::providers::stripe::schema::types::card::Card;
+use crate::providers::stripe::schema::types::coupon::Coupon;
+use crate::providers::stripe::schema::types::credit_note::CreditNote;
+use crate::providers::stripe::schema::types::credit_note_line_item::CreditNoteLineItem;
+use crate::providers::stripe::schema::types::discount::Discount;
+use crate::providers::stripe::schema::types::dispute::Dispute;
+use crate::providers::stripe::schema::types::invoice::Invoice;
+use crate::providers::stripe::schema::types::invoice_line_item::InvoiceLineItem;
+use crate::providers::stripe::schema::types::invoiceitem::Invoiceitem;
+use crate::providers::stripe::schema::types::order::Order;
+use crate::providers::stripe::schema::types::order_return::OrderReturn;
+use crate::providers::stripe::schema::types::plan::Plan;
+use crate::providers::stripe::schema::types::promotion::PromotionCode;
+use crate::providers::stripe::schema::types::refund::Refund;
+use crate::providers::stripe::schema::types::session::Session;
+use crate::providers::stripe::schema::types::setup_intent::SetupIntent;
+use crate::providers::stripe::schema::types::sku::Sku;
+use crate::providers::stripe::schema::types::tax_id::TaxId;
+
+
+// Metadata columns (`options.metadata_columns`): copy configured `metadata` keys into indexed `md_<key>` columns (E.g. `{"customers": ["tenant_id"]}` adds `customers.md_tenant_id`).
+// - Queries can filter/join on them without engine specific JSON functions.
+// - A key can set the column type: `seats:integer`, `rate:float`, `trial:boolean`; the default is `string`.
+//      - Stripe metadata values are always strings. They are parsed to the column type; values that do not parse are written as null.
+//      - Strings are truncated to `MAX_LEN` chars so they fit in an indexable column on every engine (MySQL `VARCHAR(255)`).
+// - The columns are added to copies of the `Insert` tables when the DB is created, and written in the same insert/update as the row via the `LogWrite` functions.
+//      - Row structs have `#[extra_cols_prefix("md_")]`, so reading rows ignores these columns.
+// - Only applies when set at DB create time (like `options.history`); history tables do not copy them.
+
+
+pub const MD_PREFIX: &str = "md_";
+const MAX_LEN: usize = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MdType {
+    String,
+    Integer,
+    Float,
+    Boolean,
+}
+
+// A key from the config (`key` or `key:type`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct MdKey {
+    pub key: String,
+    pub t: MdType,
+}
+
+// (table_name, metadata keys), validated.
+#[derive(Debug, Clone, Default)]
+pub struct MetadataCols {
+    tables: BTreeMap<String, Vec<MdKey>>,
+}
+
+pub fn get_col_name(key: &str) -> String {
+    format!("{}{}", MD_PREFIX, key.to_lowercase())
+}
+
+impl MdKey {
+    fn get_rust_type(&self) -> RustType {
+        match self.t {
+            MdType::String => RustType::String,
+            MdType::Integer => RustType::I64,
+            MdType::Float => RustType::F64,
+            MdType::Boolean => RustType::Bool,
+        }
+    }
+
+    fn get_val(&self, md: Option<&Value>) -> ExtraVal {
+        let x = md.and_then(|x| x.get(&self.key)).and_then(|x| x.as_str());
+
+        match self.t {
+            MdType::String => ExtraVal::String(x.map(|x| x.chars().take(MAX_LEN).collect())),
+            MdType::Integer => ExtraVal::I64(x.and_then(|x| x.trim().parse().ok())),
+            MdType::Float => ExtraVal::F64(x.and_then(|x| x.trim().parse().ok())),
+            MdType::Boolean => ExtraVal::Bool(x.and_then(|x| x.trim().parse().ok())),
+        }
+    }
+}
+
+impl TryFrom<&str> for MdKey {
+    type Error = String;
+
+    fn try_from(x: &str) -> Result<Self, Self::Error> {
+        let (key, t) = match x.split_once(':') {
+            None => (x, MdType::String),
+            Some((key, t)) => (key, match t {
+                "string" => MdType::String,
+                "integer" => MdType::Integer,
+                "float" => MdType::Float,
+                "boolean" => MdType::Boolean,
+                _ => return Err(format!("Unknown type `{}`, use one of: string, integer, float, boolean.", t))
+            })
+        };
+
+        Ok(MdKey {
+            key: key.to_string(),
+            t,
+        })
+    }
+}
+
+fn has_metadata(t: &Table) -> bool {
+    !get_always_included().contains(&t.name.as_str()) && t.cols.iter().any(|c| c.name == "metadata")
+}
+
+impl TryFrom<&BTreeMap<String, Vec<String>>> for MetadataCols {
+    type Error = TdError;
+
+    fn try_from(x: &BTreeMap<String, Vec<String>>) -> Result<Self, Self::Error> {
+        lazy_static! {
+            // Used as part of a column name; Stripe allows keys up to 40 chars.
+            static ref KEY: Regex = Regex::new(r"^[A-Za-z0-9_]{1,40}$").unwrap();
+        }
+
+        let valid: Vec<&str> = Db::get_tables().into_iter().filter(|t| has_metadata(t)).map(|t| t.name.as_str()).collect();
+
+        for (table, keys) in x {
+            if !valid.contains(&table.as_str()) {
+                return Err(TdError::Config(format!("Unknown table name `{}` in `options.metadata_columns`. Use a table with a `metadata` column: {:?}", table, &valid)));
+            }
+
+            let mut cols = HashSet::new();
+            for k in keys {
+                let k = MdKey::try_from(k.as_str()).map_err(|e| TdError::Config(format!("Invalid metadata key `{}` for `{}` in `options.metadata_columns`. {}", k, table, e)))?;
+
+                if !KEY.is_match(&k.key) {
+                    return Err(TdError::Config(format!("Invalid metadata key `{}` for `{}` in `options.metadata_columns`. Keys must be 1-40 letters, digits or underscores.", &k.key, table)));
+                }
+
+                if !cols.insert(get_col_name(&k.key)) {
+                    return Err(TdError::Config(format!("Duplicate metadata key `{}` for `{}` in `options.metadata_columns` (column names are lowercase).", &k.key, table)));
+                }
+            }
+        }
+
+        Ok(MetadataCols {
+            tables: x.iter()
+                .filter(|(_, keys)| !keys.is_empty())
+                .map(|(t, keys)| (t.clone(), keys.iter().map(|k| MdKey::try_from(k.as_str()).unwrap()).collect()))
+                .collect()
+        })
+    }
+}
+
+impl MetadataCols {
+    pub fn get_keys(&self, table_name: &str) -> Option<&Vec<MdKey>> {
+        self.tables.get(table_name)
+    }
+
+    // The values of a row's `md_` columns, in the order they were added to its table (`add_tables`).
+    pub fn get_vals(&self, table_name: &str, md: Option<&Value>) -> Vec<ExtraVal> {
+        self.get_keys(table_name).into_iter().flatten().map(|k| k.get_val(md)).collect()
+    }
+
+    pub fn assert_tables_included(&self, objects: &ObjectSelection) -> Result<(), TdError> {
+        if let Some(t) = self.tables.keys().find(|t| !objects.is_included(t)) {
+            return Err(TdError::Config(format!("Table `{}` in `options.metadata_columns` is excluded by `options.objects`.", t)));
+        }
+
+        Ok(())
+    }
+
+    // When the DB is created: adds the `md_` columns to the tables in `extra` (E.g. from `store_raw_json`), or to copies of the `Insert` tables that replace them.
+    pub fn add_tables(&self, excluded: &mut Vec<String>, extra: &mut Vec<Table>) {
+        for (table_name, keys) in &self.tables {
+            let i = match extra.iter().position(|x| &x.name == table_name) {
+                Some(i) => i,
+                None => {
+                    let t = Db::get_tables().into_iter().find(|x| &x.name == table_name).unwrap();
+                    excluded.push(table_name.clone());
+                    extra.push(t.clone());
+                    extra.len() - 1
+                }
+            };
+
+            let t = &mut extra[i];
+            for k in keys {
+                let name = get_col_name(&k.key);
+                let mut c = Col::new(&name, k.get_rust_type(), true);
+                c.is_used_in_index = true;
+                t.cols.push(c);
+
+                let index_name = format!("{}_{}", &t.name, &name);
+                t.indexes.push(Index {
+                    sql: format!("CREATE INDEX {} ON {} ({})", &index_name, &t.name, &name),
+                    name: index_name,
+                    fields_used: vec![name],
+                });
+            }
+
+            t.create_static_strings();
+        }
+    }
+
+    // The `md_` columns of an existing DB must match the config: a removed key would go stale, and an added key has no column.
+    pub fn assert_cols_match(&self, uc: &mut UniCon, objects: &ObjectSelection) -> Result<(), TdError> {
+        let mut utx = uc.tx_open().map_err(|e| TdError::Db(e.to_string()))?;
+
+        let mut mismatch = vec![];
+        for t in Db::get_tables().into_iter().filter(|t| has_metadata(t) && objects.is_included(&t.name)) {
+            let mut existing: Vec<String> = utx.get_columns(&t.name).map_err(TdError::Db)?.into_iter().filter(|x| x.starts_with(MD_PREFIX)).collect();
+            let mut target: Vec<String> = self.get_keys(&t.name).into_iter().flatten().map(|x| get_col_name(&x.key)).collect();
+            existing.sort();
+            target.sort();
+
+            if existing != target {
+                mismatch.push(format!("{} (DB: {:?}, config: {:?})", &t.name, existing, target));
+            }
+        }
+        utx.tx_close();
+
+        if !mismatch.is_empty() {
+            return Err(TdError::SchemaConflict(format!("`options.metadata_columns` does not match the columns created with the DB, set it to the same value. {}", mismatch.join(", "))));
+        }
+
+        Ok(())
+    }
+}
+
+// Migration (`Step::AddOptionCols`): adds the `md_` columns to DBs created before `options.metadata_columns` existed, when it is set.
+// - The config is checked when the write context is created, before the DB is opened.
+pub fn get_migration_tables(opts: &Options, objects: &ObjectSelection) -> Vec<Table> {
+    let mut extra = vec![];
+    MetadataCols::try_from(&opts.metadata_columns).expect("`metadata_columns` should be checked before the DB is opened.").add_tables(&mut vec![], &mut extra);
+    extra.into_iter().filter(|x| objects.is_included(&x.name)).collect()
+}
+
+
+
+// The `metadata` column of each row struct, to set its `md_` columns without serializing the row.
+pub trait GetMetadata {
+    fn get_metadata(&self) -> Option<&Value>;
+}
+
+macro_rules! impl_get_metadata {
+    ($($t:ty),*) => {
+        $(
+            impl GetMetadata for $t {
+                fn get_metadata(&self) -> Option<&Value> {
+                    self.metadata.as_ref()
+                }
+            }
+        )*
+    };
+}
+
+impl_get_metadata!(Card, Charge, Coupon, CreditNote, Customer, Dispute, Invoice, InvoiceLineItem, Invoiceitem, Order, PaymentIntent, PaymentMethod, Plan, Price, Product, PromotionCode, Refund, Session, SetupIntent, Sku, Source, Subscription, SubscriptionItem, SubscriptionSchedule, TaxRate);
+
+macro_rules! impl_get_metadata_none {
+    ($($t:ty),*) => {
+        $(
+            impl GetMetadata for $t {
+                fn get_metadata(&self) -> Option<&Value> {
+                    None
+                }
+            }
+        )*
+    };
+}
+
+// No `metadata` column.
+impl_get_metadata_none!(BalanceTransaction, BankAccount, CreditNoteLineItem, Discount, NotificationEvent, OrderReturn, TaxId);
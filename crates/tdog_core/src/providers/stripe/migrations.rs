@@ -0,0 +1,364 @@
+use serde::{Deserialize, Serialize};
+use stripe_client::http::http::StripeClient;
+use unicon::{*};
+use unicon::dt::{*};
+use unicon::dt3::{*};
+use unicon::engines::mysql::{*};
+use unicon::engines::postgres::{*};
+use unicon::engines::placeholder::{*};
+use unicon::engines::sqlite::{*};
+use unicon::table::{*};
+use unicon::traits::{*};
+use unicon::uc::{*};
+use unicon::utx::{*};
+use unicon_proc_macro::{*};
+
+use crate::Options;
+use crate::error::TdError;
+use crate::fns::now_3;
+use crate::providers::stripe::{metadata_cols, mrr_movements, notify, raw_json};
+use crate::providers::stripe::history::{get_history_table_name, get_history_tables};
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema_meta::{get_cli_version, get_semver_ints, TdMetadata};
+
+
+// Schema migrations: bring a DB written by an older CLI version up to the schema of this version, instead of requiring it to be dropped.
+// - `get_migrations` is the registry, keyed by the CLI version that introduced each change.
+// - On start up (before the target schema is compared to the DB), migrations newer than `td_metadata.cli_version` are applied in one tx, then `cli_version` is set to this version.
+//      - Each applied migration is recorded in `td_migrations`, so it is never applied twice.
+//      - The DB must have the same Stripe API version, and be at least `MIN_VERSION` (the first version with a registry).
+// - Steps use the `Db` tables of this version (E.g. `AddCol` reads the column type from the struct), and are also applied to `<table>_history` tables when they exist.
+// - Steps that target excluded tables (`options.objects`) are skipped.
+// - MySQL commits each DDL statement implicitly: a failed migration may be partly applied, so steps skip tables and columns that already exist.
+// - The table is not part of the target schema, so DBs created before it existed are not a schema conflict.
+
+
+// DBs written by older versions cannot be migrated (they must be dropped and downloaded again).
+const MIN_VERSION: &str = "0.7.0";
+
+#[derive(Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq)]
+#[derive(Insert)]
+pub struct TdMigration {
+    #[primary_key]
+    pub migration_id: Option<i64>,
+
+    // CLI version that introduced the migration.
+    pub version: String,
+    pub name: String,
+
+    // `td_metadata.cli_version` before the migration.
+    pub from_cli_version: String,
+    pub applied_ts: Option<DT3>,
+}
+
+
+pub enum Step {
+    // A `Db` table and its indexes.
+    AddTable(&'static str),
+
+    // A column of a `Db` table; `default` is an SQL literal for existing rows (needed for `NOT NULL` columns).
+    AddCol { table: &'static str, col: &'static str, default: Option<&'static str> },
+
+    DropCol { table: &'static str, col: &'static str },
+
+    // The generated columns for the `#[flatten]` paths of a `Db` JSON column.
+    AddFlatCols { table: &'static str, col: &'static str },
+
+    // An index of a `Db` table, by name.
+    AddIndex { table: &'static str, index: &'static str },
+
+    // Writes to existing rows (E.g. to fill a new column); runs after the preceding steps in the same tx.
+    Backfill(fn(&mut UniTx)),
+
+    // Columns (and their indexes) of tables built from `options` at runtime (E.g. `raw`, `md_*`), that are not in the `Db` table.
+    // - Only tables and columns of the options at the time of the migration; the DB must be created with them to enable them later.
+    AddOptionCols(fn(&Options, &ObjectSelection) -> Vec<Table>),
+
+    // Tables that are only created when an option is set (E.g. `td_changes` for `notify`); enabling the option later creates them on start up.
+    Optional(fn(&mut UniTx, &Options)),
+}
+
+pub struct Migration {
+    pub version: &'static str,
+    pub name: &'static str,
+    pub steps: Vec<Step>,
+}
+
+
+// Ordered by version. Add an entry with the CLI version that changes the schema of existing tables; new DBs are created with the schema of this version.
+pub fn get_migrations() -> Vec<Migration> {
+    vec![
+        Migration {
+            version: "0.8.0",
+            name: "td_missing_owners",
+            steps: vec![Step::AddTable("td_missing_owners")],
+        },
+        Migration {
+            version: "0.8.0",
+            name: "td_cdc",
+            steps: vec![Step::AddTable("td_cdc")],
+        },
+        // Requires SQLite 3.31+, MySQL 5.7+ or Postgres 12+.
+        Migration {
+            version: "0.8.0",
+            name: "flattened_cols",
+            steps: vec![
+                Step::AddFlatCols { table: "customers", col: "address" },
+                Step::AddFlatCols { table: "customers", col: "shipping" },
+                Step::AddFlatCols { table: "customers", col: "invoice_settings" },
+                Step::AddFlatCols { table: "charges", col: "billing_details" },
+                Step::AddFlatCols { table: "charges", col: "shipping" },
+                Step::AddFlatCols { table: "invoices", col: "customer_address" },
+                Step::AddFlatCols { table: "invoices", col: "customer_shipping" },
+                Step::AddFlatCols { table: "payment_intents", col: "shipping" },
+                Step::AddFlatCols { table: "payment_methods", col: "billing_details" },
+                Step::AddFlatCols { table: "sources", col: "owner" },
+            ],
+        },
+        Migration {
+            version: "0.8.0",
+            name: "raw_json",
+            steps: vec![Step::AddOptionCols(raw_json::get_migration_tables)],
+        },
+        Migration {
+            version: "0.8.0",
+            name: "metadata_cols",
+            steps: vec![Step::AddOptionCols(metadata_cols::get_migration_tables)],
+        },
+        Migration {
+            version: "0.8.0",
+            name: "td_changes",
+            steps: vec![Step::Optional(notify::tx_migrate)],
+        },
+        Migration {
+            version: "0.8.0",
+            name: "mrr_movements",
+            steps: vec![Step::Optional(mrr_movements::tx_migrate)],
+        },
+    ]
+}
+
+
+fn get_db_table(name: &str) -> &'static Table {
+    Db::get_tables().into_iter().find(|x| x.name == name).unwrap_or_else(|| panic!("Migration table `{}` is not a `Db` table.", name))
+}
+
+fn get_strings(utx: &UniTx, t: &Table) -> StaticSQLStrings {
+    let all = t.static_sql_strings.as_ref().unwrap();
+    match utx {
+        UniTx::Rusqlite(_) => &all.sqlite,
+        UniTx::MySQL(_) => &all.mysql,
+        UniTx::Postgres(_) => &all.postgres,
+        UniTx::PlaceholderLibA(_) => unreachable!()
+    }.clone()
+}
+
+// Returns true when the table was created.
+fn tx_create_table(utx: &mut UniTx, existing: &[String], t: &Table) -> bool {
+    if existing.contains(&t.name) {
+        return false;
+    }
+
+    let x = get_strings(utx, t);
+    utx.exec_one(&x.create.create);
+    for i in &x.indexes {
+        utx.exec_one(&i.create);
+    }
+
+    true
+}
+
+fn tx_create_table_if_not_exists(utx: &mut UniTx) {
+    let existing = utx.get_tables();
+    tx_create_table(utx, &existing, <TdMigration as TableStatic>::get_table());
+}
+
+fn tx_apply_step(utx: &mut UniTx, objects: &ObjectSelection, opts: &Options, step: &Step) -> Result<(), TdError> {
+    let existing = utx.get_tables();
+
+    // (table, history table) when they exist in the DB.
+    let get_targets = |table: &str| -> Vec<String> {
+        vec![table.to_string(), get_history_table_name(table)].into_iter().filter(|x| existing.contains(x)).collect()
+    };
+
+    match step {
+        Step::AddTable(name) => {
+            if !objects.is_included(name) {
+                return Ok(());
+            }
+            tx_create_table(utx, &existing, get_db_table(name));
+
+            // The DB was created with `options.history`.
+            if existing.iter().any(|x| x.ends_with("_history")) {
+                if let Some(h) = get_history_tables(objects).into_iter().find(|x| x.name == get_history_table_name(name)) {
+                    tx_create_table(utx, &existing, &h);
+                }
+            }
+        }
+        Step::AddCol { table, col, default } => {
+            let history_tables = get_history_tables(objects);
+            for t in get_targets(table) {
+                if utx.get_columns(&t).map_err(TdError::Db)?.iter().any(|x| x == col) {
+                    continue;
+                }
+
+                let def = match history_tables.iter().find(|x| x.name == t) {
+                    Some(h) => h,
+                    None => get_db_table(table)
+                };
+                let c = def.cols.iter().find(|x| &x.name == col).unwrap_or_else(|| panic!("Migration column `{}.{}` is not a `Db` column.", &def.name, col));
+                utx.add_col(&t, c, *default);
+            }
+        }
+        Step::AddFlatCols { table, col } => {
+            let c = get_db_table(table).cols.iter().find(|x| &x.name == col && !x.flatten.is_empty()).unwrap_or_else(|| panic!("Migration column `{}.{}` has no flattened columns.", table, col));
+            for t in get_targets(table) {
+                utx.add_flat_cols(&t, c).map_err(TdError::Db)?;
+            }
+        }
+        Step::DropCol { table, col } => {
+            for t in get_targets(table) {
+                if utx.get_columns(&t).map_err(TdError::Db)?.iter().any(|x| x == col) {
+                    utx.drop_col(&t, col);
+                }
+            }
+        }
+        Step::AddIndex { table, index } => {
+            if !existing.contains(&table.to_string()) {
+                return Ok(());
+            }
+
+            let x = get_strings(utx, get_db_table(table));
+            let i = x.indexes.iter().find(|x| &x.name == index).unwrap_or_else(|| panic!("Migration index `{}` is not an index of `{}`.", index, table));
+
+            // E.g. a MySQL run that committed the DDL without recording the migration.
+            if utx.get_index_names(table).map_err(TdError::Db)?.contains(&i.name) {
+                return Ok(());
+            }
+            utx.exec_one(&i.create);
+        }
+        Step::Backfill(f) => f(utx),
+        Step::AddOptionCols(f) => {
+            for t in f(opts, objects).iter().filter(|x| existing.contains(&x.name)) {
+                let base: Vec<String> = get_db_table(&t.name).cols_not_skipped().iter().map(|c| c.name.clone()).collect();
+                let cols = utx.get_columns(&t.name).map_err(TdError::Db)?;
+                let added: Vec<&Col> = t.cols.iter().filter(|c| !c.skip && !base.contains(&c.name) && !cols.contains(&c.name)).collect();
+                for c in &added {
+                    utx.add_col(&t.name, c, None);
+                }
+
+                let x = get_strings(utx, t);
+                for i in t.indexes.iter().filter(|i| i.fields_used.iter().any(|f| added.iter().any(|c| &c.name == f))) {
+                    let create = x.indexes.iter().find(|x| x.name == i.name).unwrap();
+                    utx.exec_one(&create.create);
+                }
+            }
+        }
+        Step::Optional(f) => f(utx, opts),
+    }
+
+    Ok(())
+}
+
+
+// A tx on the DB's schema, or None when the DB has not been created yet (no `td_metadata`).
+pub fn tx_open_existing(uc: &mut UniCon) -> Result<Option<UniTx<'_>>, TdError> {
+    let schema = uc.get_user_defined_schema().map(|x| x.name);
+    let mut utx = uc.tx_open().map_err(|e| TdError::Db(e.to_string()))?;
+
+    if let Some(x) = &schema {
+        if !utx.get_schemas().contains(x) {
+            utx.tx_rollback().map_err(|e| TdError::Db(e.to_string()))?;
+            return Ok(None);
+        }
+        utx.set_active_schema(x);
+    }
+
+    if !utx.get_tables().contains(&TdMetadata::get_table_name_static().to_string()) {
+        utx.tx_rollback().map_err(|e| TdError::Db(e.to_string()))?;
+        return Ok(None);
+    }
+
+    Ok(Some(utx))
+}
+
+pub fn migrate(uc: &mut UniCon, objects: &ObjectSelection, opts: &Options) -> Result<Vec<String>, TdError> {
+    migrate_with(uc, objects, opts, &get_migrations(), get_cli_version())
+}
+
+// Returns the names of the applied migrations; nothing is written when the DB does not exist or cannot be migrated (`TdMetadata::check_versions_match` logs why).
+// - `Step::Optional` steps of migrations the DB already has are run again, as their option may have been set since.
+// - `cli` is the version of this CLI.
+// - `cli_version` is only set when there are migrations between the two versions (E.g. not for a patch version without schema changes).
+//      - Also when they were all applied already: on MySQL a failed run can commit them without setting `cli_version`.
+pub fn migrate_with(uc: &mut UniCon, objects: &ObjectSelection, opts: &Options, migrations: &[Migration], cli: &str) -> Result<Vec<String>, TdError> {
+    let mut utx = match tx_open_existing(uc)? {
+        Some(x) => x,
+        None => return Ok(vec![])
+    };
+
+    let mut md: TdMetadata = TdMetadata::tx_get_last(&mut utx, "id").expect("Metadata should be created with the DB schema.");
+    let from = get_semver_ints(&md.cli_version);
+    let to = get_semver_ints(cli);
+    if from > to || from < get_semver_ints(MIN_VERSION) || md.stripe_version != StripeClient::get_api_version() {
+        utx.tx_rollback().map_err(|e| TdError::Db(e.to_string()))?;
+        return Ok(vec![]);
+    }
+
+    for m in migrations.iter().filter(|m| get_semver_ints(m.version) <= from) {
+        for step in m.steps.iter().filter(|x| matches!(x, Step::Optional(_))) {
+            tx_apply_step(&mut utx, objects, opts, step)?;
+        }
+    }
+
+    let pending: Vec<&Migration> = migrations.iter().filter(|m| {
+        let v = get_semver_ints(m.version);
+        v > from && v <= to
+    }).collect();
+
+    if pending.is_empty() {
+        utx.tx_close().map_err(|e| TdError::from_commit("migration transaction", e))?;
+        return Ok(vec![]);
+    }
+
+    tx_create_table_if_not_exists(&mut utx);
+    let applied: Vec<String> = TdMigration::tx_get_all(&mut utx).into_iter().map(|x| x.name).collect();
+
+    let mut names = vec![];
+    for m in pending {
+        if applied.iter().any(|x| x == m.name) {
+            continue;
+        }
+
+        info!("Migrating the DB to CLI version {}: {}.", m.version, m.name);
+        for step in &m.steps {
+            tx_apply_step(&mut utx, objects, opts, step)?;
+        }
+
+        TdMigration {
+            migration_id: None,
+            version: m.version.into(),
+            name: m.name.into(),
+            from_cli_version: md.cli_version.clone(),
+            applied_ts: now_3().into(),
+        }.tx_insert_set_pk(&mut utx);
+        names.push(m.name.to_string());
+    }
+
+    info!("Migrated the DB from CLI version {} to {} ({} migrations).", &md.cli_version, cli, names.len());
+    md.cli_version = cli.into();
+    md.tx_update_pk(&mut utx);
+
+    utx.tx_close().map_err(|e| TdError::from_commit("migration transaction", e))?;
+    Ok(names)
+}
+
+// New DBs have the schema of this version; the table records migrations applied after this.
+pub fn create_table_if_not_exists(uc: &mut UniCon) -> Result<(), TdError> {
+    let mut utx = uc.tx_open().map_err(|e| TdError::Db(e.to_string()))?;
+    tx_create_table_if_not_exists(&mut utx);
+    utx.tx_close().map_err(|e| TdError::Db(e.to_string()))?;
+    Ok(())
+}
@@ -6,4 +6,21 @@ pub mod schema_meta;
 pub mod apply_events;
 pub mod queue;
 pub mod rate_limit;
+pub mod objects;
+pub mod history;
+pub mod write_ctx;
+pub mod cdc;
+pub mod notify;
+pub mod dry_run;
+pub mod replay;
+pub mod event_archive;
+pub mod snapshot;
 
+pub mod raw_json;
+pub mod metadata_cols;
+pub mod views;
+pub mod mrr_movements;
+pub mod foreign_keys;
+pub mod schema_graph;
+pub mod check;
+pub mod migrations;
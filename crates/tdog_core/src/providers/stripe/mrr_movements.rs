@@ -0,0 +1,302 @@
+use std::collections::BTreeSet;
+
+use mysql::prelude::Queryable;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use unicon::{*};
+use unicon::dt::{*};
+use unicon::dt3::{*};
+use unicon::engines::mysql::{*};
+use unicon::engines::postgres::{*};
+use unicon::engines::placeholder::{*};
+use unicon::engines::sqlite::{*};
+use unicon::table::{*};
+use unicon::traits::{*};
+use unicon::uc::{*};
+use unicon::utx::{*};
+use unicon_proc_macro::{*};
+
+use crate::Options;
+use crate::error::TdError;
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::types::{Price, Subscription, SubscriptionItem};
+use crate::providers::stripe::schema::util::ToDT;
+use crate::providers::stripe::schema_meta::{TdRun, TdStripeWrite};
+
+
+// MRR movements (`options.mrr_movements`): a ledger of changes to each subscription's MRR, written in the same tx as the event that caused them.
+// - A subscription's MRR is the sum of its items' `unit_amount * quantity * (periods per year / 12) / interval_count` while it is `active` or `past_due`.
+//      - Only licensed, per unit prices are included (the same definition as `v_mrr_by_subscription.mrr_gross`); coupons are not applied.
+// - The first download writes a `starting` movement for each subscription with MRR.
+// - After each event that writes a subscription or one of its items (E.g. a quantity or price change), the subscription's MRR is compared to the `mrr` of its last movement; a difference writes a `new`, `expansion`, `contraction` or `churn` movement.
+// - The table is not part of the target schema, so the ledger can be enabled for an existing DB (it is seeded from the current rows).
+
+
+#[derive(Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq)]
+#[derive(Insert)]
+#[index("CREATE INDEX mrr_movements_subscription ON self (subscription)")]
+#[index("CREATE INDEX mrr_movements_ts ON self (ts)")]
+pub struct MrrMovement {
+    #[primary_key]
+    pub movement_id: Option<i64>,
+
+    pub subscription: String,
+    pub customer: String,
+    pub currency: Option<String>,
+
+    // `starting`, `new`, `expansion`, `contraction` or `churn`.
+    pub category: String,
+
+    // Monthly amounts in the currency's minor unit, rounded to 2 decimals.
+    pub delta: f64,
+    pub mrr: f64,
+
+    // The event's `created` (download start time for `starting` movements).
+    pub ts: DT,
+    pub run_id: i64,
+    pub event_id: Option<String>,
+}
+
+
+pub fn assert_tables_included(objects: &ObjectSelection) -> Result<(), TdError> {
+    let tables = [
+        Subscription::get_table_name_static(),
+        SubscriptionItem::get_table_name_static(),
+        Price::get_table_name_static(),
+    ];
+
+    if let Some(t) = tables.iter().find(|t| !objects.is_included(t)) {
+        return Err(TdError::Config(format!("`options.mrr_movements` requires the `{}` table, which is excluded by `options.objects`.", t)));
+    }
+
+    Ok(())
+}
+
+// Returns true when the table was created.
+fn tx_create_table_if_not_exists(utx: &mut UniTx) -> bool {
+    let t = <MrrMovement as TableStatic>::get_table();
+    if utx.get_tables().contains(&t.name) {
+        return false;
+    }
+
+    let all = t.static_sql_strings.as_ref().unwrap();
+    let x = match utx {
+        UniTx::Rusqlite(_) => &all.sqlite,
+        UniTx::MySQL(_) => &all.mysql,
+        UniTx::Postgres(_) => &all.postgres,
+        UniTx::PlaceholderLibA(_) => unreachable!()
+    }.clone();
+
+    utx.exec_one(&x.create.create);
+    for i in &x.indexes {
+        utx.exec_one(&i.create);
+    }
+
+    true
+}
+
+// When the ledger is enabled for a DB that already has a download, it starts from the current rows.
+fn tx_create_table_and_seed(utx: &mut UniTx) {
+    if tx_create_table_if_not_exists(utx) {
+        if let Some(run) = TdRun::get_last_run_tx(utx) {
+            tx_seed(utx, run.run_id.unwrap(), now_dt());
+        }
+    }
+}
+
+pub fn create_table_if_not_exists(uc: &mut UniCon) -> Result<(), TdError> {
+    let mut utx = uc.tx_open().map_err(|e| TdError::Db(e.to_string()))?;
+    tx_create_table_and_seed(&mut utx);
+    utx.tx_close().map_err(|e| TdError::Db(e.to_string()))?;
+
+    Ok(())
+}
+
+// Migration (`Step::Optional`): DBs created before `mrr_movements` existed.
+pub fn tx_migrate(utx: &mut UniTx, opts: &Options) {
+    if opts.mrr_movements {
+        tx_create_table_and_seed(utx);
+    }
+}
+
+fn now_dt() -> DT {
+    ToDT::to_dt(chrono::Utc::now().timestamp())
+}
+
+
+// Monthly multiplier of a licensed recurring price.
+fn get_per_month(recurring: &Value) -> Option<f64> {
+    if recurring.get("usage_type")?.as_str()? != "licensed" {
+        return None;
+    }
+
+    let periods_per_year = match recurring.get("interval")?.as_str()? {
+        "day" => 365.0,
+        "week" => 52.0,
+        "month" => 12.0,
+        "year" => 1.0,
+        _ => return None
+    };
+
+    Some(periods_per_year / 12.0 / recurring.get("interval_count")?.as_f64()?)
+}
+
+fn round2(x: f64) -> f64 {
+    (x * 100.0).round() / 100.0
+}
+
+struct SubMrr {
+    customer: String,
+    currency: Option<String>,
+    mrr: f64,
+}
+
+// None when the subscription row does not exist.
+fn tx_get_sub_mrr(utx: &mut UniTx, sub_id: &str) -> Option<SubMrr> {
+    let s = Subscription::get_where_tx(utx, ("id", &sub_id)).pop()?;
+    let is_counted = s.status == "active" || s.status == "past_due";
+
+    let mut currency = None;
+    let mut mrr = 0.0;
+    for si in SubscriptionItem::get_where_tx(utx, ("subscription", &sub_id)) {
+        let p = match Price::get_where_tx(utx, ("id", &si.price)).pop() {
+            Some(x) => x,
+            None => continue
+        };
+
+        let per_month = p.recurring.as_ref().and_then(get_per_month);
+        if let (Some(unit_amount), Some(per_month)) = (p.unit_amount, per_month) {
+            if is_counted {
+                mrr += unit_amount as f64 * si.quantity.unwrap_or(1) as f64 * per_month;
+            }
+            currency = Some(p.currency.clone());
+        }
+    }
+
+    Some(SubMrr {
+        customer: s.customer,
+        currency,
+        mrr: round2(mrr),
+    })
+}
+
+fn tx_get_last_movement(utx: &mut UniTx, sub_id: &str) -> Option<MrrMovement> {
+    let q = format!("SELECT * FROM {} WHERE subscription = ? ORDER BY movement_id DESC LIMIT 1", MrrMovement::get_table_name_static());
+
+    match utx {
+        UniTx::Rusqlite(tx) => {
+            let mut stmt = tx.prepare_cached(&q).unwrap();
+            let mut rows = stmt.query(rusqlite::params![sub_id]).unwrap();
+            rows.next().unwrap().map(|r| <MrrMovement as SQLiteFuncRusqliteStatic>::row_to_ins(r))
+        }
+        UniTx::MySQL(tx) => {
+            tx.exec_first(&q, (sub_id,)).unwrap().map(|mut r| <MrrMovement as MySQLFuncXStatic>::row_to_ins(&mut r))
+        }
+        UniTx::Postgres(tx) => {
+            tx.query(q.replace("?", "$1").as_str(), &[&sub_id]).unwrap().first().map(<MrrMovement as PostgresFuncXStatic>::row_to_ins)
+        }
+        UniTx::PlaceholderLibA(_) => unreachable!()
+    }
+}
+
+fn tx_get_sub_ids(utx: &mut UniTx) -> Vec<String> {
+    let q = format!("SELECT id FROM {} ORDER BY id", Subscription::get_table_name_static());
+
+    match utx {
+        UniTx::Rusqlite(tx) => {
+            let mut stmt = tx.prepare(&q).unwrap();
+            let rows = stmt.query_map([], |r| r.get(0)).unwrap();
+            rows.map(|x| x.unwrap()).collect()
+        }
+        UniTx::MySQL(tx) => tx.query(q).unwrap(),
+        UniTx::Postgres(tx) => tx.query(q.as_str(), &[]).unwrap().iter().map(|x| x.get(0)).collect(),
+        UniTx::PlaceholderLibA(_) => unreachable!()
+    }
+}
+
+
+// Writes a `starting` movement for each subscription with MRR (in the download tx).
+pub fn tx_seed(utx: &mut UniTx, run_id: i64, ts: DT) {
+    for id in tx_get_sub_ids(utx) {
+        if let Some(x) = tx_get_sub_mrr(utx, &id) {
+            if x.mrr > 0.0 {
+                MrrMovement {
+                    movement_id: None,
+                    subscription: id,
+                    customer: x.customer,
+                    currency: x.currency,
+                    category: "starting".into(),
+                    delta: x.mrr,
+                    mrr: x.mrr,
+                    ts: ts.clone(),
+                    run_id,
+                    event_id: None,
+                }.tx_insert_set_pk(utx);
+            }
+        }
+    }
+}
+
+// Subscriptions whose MRR an event's writes can change: written subscriptions, and the parents of written items.
+// - A deleted item's row no longer exists; items are only removed by a subscription update, which also writes the subscription.
+pub fn tx_get_written_sub_ids(utx: &mut UniTx, write_ids: &[i64]) -> Vec<String> {
+    let mut ids = BTreeSet::new();
+    for write_id in write_ids {
+        let w = match TdStripeWrite::get_where_tx(utx, ("write_id", write_id)).pop() {
+            Some(x) => x,
+            None => continue
+        };
+
+        if w.table_name == Subscription::get_table_name_static() {
+            ids.insert(w.obj_id);
+        } else if w.table_name == SubscriptionItem::get_table_name_static() {
+            if let Some(si) = SubscriptionItem::get_where_tx(utx, ("id", &w.obj_id)).pop() {
+                ids.insert(si.subscription);
+            }
+        }
+    }
+
+    ids.into_iter().collect()
+}
+
+// After an event's writes: records the change in the subscription's MRR since its last movement (if any).
+pub fn tx_write(utx: &mut UniTx, run_id: i64, event_id: &str, ts: DT, sub_id: &str) {
+    let last = tx_get_last_movement(utx, sub_id);
+    let prev = last.as_ref().map(|x| x.mrr).unwrap_or(0.0);
+
+    let (customer, currency, mrr) = match (tx_get_sub_mrr(utx, sub_id), &last) {
+        (Some(x), _) => (x.customer, x.currency.or_else(|| last.as_ref().and_then(|l| l.currency.clone())), x.mrr),
+        // Deleted subscription row.
+        (None, Some(l)) => (l.customer.clone(), l.currency.clone(), 0.0),
+        (None, None) => return
+    };
+
+    let delta = round2(mrr - prev);
+    if delta == 0.0 {
+        return;
+    }
+
+    let category = if prev == 0.0 {
+        "new"
+    } else if mrr == 0.0 {
+        "churn"
+    } else if delta > 0.0 {
+        "expansion"
+    } else {
+        "contraction"
+    };
+
+    MrrMovement {
+        movement_id: None,
+        subscription: sub_id.to_string(),
+        customer,
+        currency,
+        category: category.into(),
+        delta,
+        mrr,
+        ts,
+        run_id,
+        event_id: Some(event_id.to_string()),
+    }.tx_insert_set_pk(utx);
+}
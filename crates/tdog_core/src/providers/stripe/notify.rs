@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+use unicon::table::Table;
+use unicon::traits::{*};
+use unicon::uc::UniCon;
+use unicon::utx::{Notify, UniTx};
+
+use crate::Options;
+use crate::providers::stripe::schema_meta::TdStripeWrite;
+
+
+// Signal consumers (E.g. cache invalidation) after each `apply_events` tx (`options.notify`).
+// - Sent inside the tx, so consumers only see it after the writes are committed.
+// - Payload: `{"run_id": 1, "tables": {"customers": ["cus_123"]}}`
+//      - When the payload would exceed the Postgres limit, tables map to null; read the ids from `td_stripe_writes WHERE run_id = x`.
+pub fn get_notify() -> Notify {
+    Notify {
+        channel: "tdog".into(),
+        changes_table: "td_changes".into(),
+        keep_rows: 10_000,
+    }
+}
+
+// The changes table for engines without notifications; part of the target schema so it is created with the other `td_*` tables and checked by the schema diff.
+pub fn get_tables(uc: &UniCon) -> Vec<Table> {
+    match uc {
+        UniCon::Postgres(_) => vec![],
+        _ => vec![get_notify().get_changes_table()]
+    }
+}
+
+// Migration (`Step::Optional`): DBs created before `notify` existed, or without `options.notify`.
+// - Run before the target schema is compared to the DB.
+pub fn tx_migrate(utx: &mut UniTx, opts: &Options) {
+    if opts.notify {
+        get_notify().create_changes_table_if_not_exists(utx);
+    }
+}
+
+// Postgres payload limit is 8000 bytes.
+const MAX_PAYLOAD_BYTES: usize = 7_999;
+
+pub fn get_payload(run_id: i64, writes: &Vec<TdStripeWrite>) -> String {
+    let mut tables: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for w in writes {
+        let ids = tables.entry(w.table_name.as_str()).or_default();
+        if !ids.contains(&w.obj_id.as_str()) {
+            ids.push(w.obj_id.as_str());
+        }
+    }
+
+    let x = json!({
+        "run_id": run_id,
+        "tables": tables,
+    }).to_string();
+
+    if x.len() <= MAX_PAYLOAD_BYTES {
+        return x;
+    }
+
+    let tables: BTreeMap<&str, Value> = tables.keys().map(|k| (*k, Value::Null)).collect();
+    json!({
+        "run_id": run_id,
+        "tables": tables,
+    }).to_string()
+}
+
+// Nothing is sent when the run has no writes.
+pub fn tx_notify(utx: &mut UniTx, run_id: i64) {
+    let writes = TdStripeWrite::get_where_tx(utx, ("run_id", &run_id));
+    if writes.is_empty() {
+        return;
+    }
+
+    let payload = get_payload(run_id, &writes);
+    utx.notify(&get_notify(), &payload);
+}
@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+use unicon::traits::{*};
+
+use crate::Objects;
+use crate::error::TdError;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::{*};
+use crate::providers::stripe::schema::types::bank_account::BankAccount;
+use crate::providers::stripe::schema::types::card::Card;
+use crate::providers::stripe::schema::types::credit_note::CreditNote;
+use crate::providers::stripe::schema::types::credit_note_line_item::CreditNoteLineItem;
+use crate::providers::stripe::schema::types::discount::Discount;
+use crate::providers::stripe::schema::types::invoice::Invoice;
+use crate::providers::stripe::schema::types::invoice_line_item::InvoiceLineItem;
+use crate::providers::stripe::schema::types::invoiceitem::Invoiceitem;
+use crate::providers::stripe::schema::types::order::Order;
+use crate::providers::stripe::schema::types::order_return::OrderReturn;
+use crate::providers::stripe::schema::types::refund::Refund;
+use crate::providers::stripe::schema::types::setup_intent::SetupIntent;
+use crate::providers::stripe::schema::types::tax_id::TaxId;
+use crate::providers::stripe::schema_meta::{*};
+
+
+// Tables that are always created and written.
+// - `td_*` is the write log/run metadata, `notification_events` is the raw event log (events are stored even if the object type they contain is excluded).
+pub fn get_always_included() -> Vec<&'static str> {
+    vec![
+        TdStripeApplyEvent::get_table_name_static(),
+        TdStripeWrite::get_table_name_static(),
+        TdRun::get_table_name_static(),
+        TdMetadata::get_table_name_static(),
+        TdMissingOwner::get_table_name_static(),
+        TdCdc::get_table_name_static(),
+        NotificationEvent::get_table_name_static(),
+    ]
+}
+
+// (parent, children) - child rows are written as part of the parent's `WriteTree` (or its download fn).
+// - A child cannot be excluded when its parent is included, as the parent write would fail on a missing table.
+// - @see `WriteTree` impls in `schema/types` and `dl.rs`.
+fn get_tree_children() -> Vec<(&'static str, Vec<&'static str>)> {
+    vec![
+        (Customer::get_table_name_static(), vec![
+            Discount::get_table_name_static(),
+            TaxId::get_table_name_static(),
+            Source::get_table_name_static(),
+            Card::get_table_name_static(),
+            BankAccount::get_table_name_static(),
+            PaymentMethod::get_table_name_static(),
+        ]),
+        (Charge::get_table_name_static(), vec![
+            Refund::get_table_name_static(),
+        ]),
+        (CreditNote::get_table_name_static(), vec![
+            CreditNoteLineItem::get_table_name_static(),
+        ]),
+        (Invoice::get_table_name_static(), vec![
+            Discount::get_table_name_static(),
+            InvoiceLineItem::get_table_name_static(),
+            PaymentMethod::get_table_name_static(),
+        ]),
+        (Invoiceitem::get_table_name_static(), vec![
+            Discount::get_table_name_static(),
+            Price::get_table_name_static(),
+        ]),
+        (Order::get_table_name_static(), vec![
+            OrderReturn::get_table_name_static(),
+        ]),
+        (PaymentIntent::get_table_name_static(), vec![
+            PaymentMethod::get_table_name_static(),
+        ]),
+        (SetupIntent::get_table_name_static(), vec![
+            PaymentMethod::get_table_name_static(),
+        ]),
+        // Schedules are not part of the subscription tree, but are downloaded by `dl_subscriptions`.
+        (Subscription::get_table_name_static(), vec![
+            Discount::get_table_name_static(),
+            PaymentMethod::get_table_name_static(),
+            SubscriptionItem::get_table_name_static(),
+            SubscriptionSchedule::get_table_name_static(),
+        ]),
+        (SubscriptionItem::get_table_name_static(), vec![
+            Price::get_table_name_static(),
+        ]),
+    ]
+}
+
+
+/// The resolved `options.objects` config: which Stripe object tables are created, downloaded and written to from events.
+/// - Default = all tables included.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectSelection {
+    excluded: HashSet<String>,
+}
+
+impl ObjectSelection {
+    pub fn is_included(&self, table_name: &str) -> bool {
+        !self.excluded.contains(table_name)
+    }
+
+    pub fn get_excluded(&self) -> Vec<String> {
+        let mut x: Vec<String> = self.excluded.iter().cloned().collect();
+        x.sort();
+        x
+    }
+}
+
+// Note: Config validation at start up (before any writes).
+impl TryFrom<&Objects> for ObjectSelection {
+    type Error = TdError;
+
+    fn try_from(x: &Objects) -> Result<Self, Self::Error> {
+        let always = get_always_included();
+        let all: Vec<&str> = Db::get_table_names().into_iter().filter(|t| !always.contains(t)).collect();
+
+        for t in x.include.iter().flatten().chain(x.exclude.iter()) {
+            if !all.contains(&t.as_str()) {
+                error!("Unknown table name `{}` in `options.objects`. Use one of: {:?}", t, &all);
+                return Err(TdError::Config(format!("Unknown table name `{}` in `options.objects`.", t)));
+            }
+        }
+
+        let mut included: HashSet<String> = match &x.include {
+            None => all.iter().map(|t| t.to_string()).collect(),
+            Some(inc) => inc.iter().cloned().collect()
+        };
+        for t in &x.exclude {
+            included.remove(t);
+        }
+
+        // Include child tables of included parents until no more are added (children can have their own children).
+        let tree = get_tree_children();
+        let mut implicit = vec![];
+        loop {
+            let mut added = false;
+
+            for (parent, children) in &tree {
+                if !included.contains(*parent) {
+                    continue;
+                }
+
+                for child in children {
+                    if included.contains(*child) {
+                        continue;
+                    }
+
+                    if x.exclude.iter().any(|t| t == child) {
+                        error!("`{}` cannot be excluded as it is written as part of `{}`. Exclude `{}` too, or remove `{}` from `options.objects.exclude`.", child, parent, parent, child);
+                        return Err(TdError::Config(format!("`{}` cannot be excluded as it is written as part of `{}`.", child, parent)));
+                    }
+
+                    included.insert(child.to_string());
+                    implicit.push(*child);
+                    added = true;
+                }
+            }
+
+            if !added {
+                break;
+            }
+        }
+
+        if !implicit.is_empty() {
+            info!("Including tables that are written as part of other included tables: {:?}", &implicit);
+        }
+
+        let excluded: HashSet<String> = all.iter().filter(|t| !included.contains(**t)).map(|t| t.to_string()).collect();
+
+        Ok(ObjectSelection {
+            excluded
+        })
+    }
+}
@@ -11,6 +11,7 @@ use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::oneshot::{Sender, Receiver};
 use core::cmp;
+use crate::util::with_job_label;
 
 
 // Queues items, starts them in priority order in a rate limited way.
@@ -164,7 +165,7 @@ impl Queue {
     pub fn run_scheduler(q_mt_a: Arc<Mutex<Queue>>) -> AbortHandle {
         let (a_h, a_r) = AbortHandle::new_pair();
 
-        tokio::task::spawn(Abortable::new(async move {
+        tokio::task::spawn(with_job_label(Abortable::new(async move {
             loop {
                 // Do not hold lock over long `.await` points (will prevent items being queued).
                 let (heap_len, remaining_second_capacity, ms_remaining, paused) = {
@@ -226,7 +227,7 @@ impl Queue {
             }
 
             ()
-        }, a_r));
+        }, a_r)));
 
         a_h
     }
@@ -0,0 +1,177 @@
+use serde_json::Value;
+use stripe_client::types::types::GetId;
+use unicon::table::{*};
+use unicon::traits::{*};
+use unicon::uc::UniCon;
+
+use crate::Options;
+use crate::error::TdError;
+use crate::providers::stripe::objects::{get_always_included, ObjectSelection};
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::{*};
+use crate::providers::stripe::schema::types::GetObjType;
+use crate::providers::stripe::schema::types::balance_transaction::BalanceTransaction;
+use crate::providers::stripe::schema::types::bank_account::BankAccount;
+use crate::providers::stripe::schema::types::card::Card;
+use crate::providers::stripe::schema::types::coupon::Coupon;
+use crate::providers::stripe::schema::types::credit_note::CreditNote;
+use crate::providers::stripe::schema::types::credit_note_line_item::CreditNoteLineItem;
+use crate::providers::stripe::schema::types::discount::Discount;
+use crate::providers::stripe::schema::types::dispute::Dispute;
+use crate::providers::stripe::schema::types::invoice::Invoice;
+use crate::providers::stripe::schema::types::invoice_line_item::InvoiceLineItem;
+use crate::providers::stripe::schema::types::invoiceitem::Invoiceitem;
+use crate::providers::stripe::schema::types::order::Order;
+use crate::providers::stripe::schema::types::order_return::OrderReturn;
+use crate::providers::stripe::schema::types::plan::Plan;
+use crate::providers::stripe::schema::types::promotion::PromotionCode;
+use crate::providers::stripe::schema::types::refund::Refund;
+use crate::providers::stripe::schema::types::session::Session;
+use crate::providers::stripe::schema::types::setup_intent::SetupIntent;
+use crate::providers::stripe::schema::types::sku::Sku;
+use crate::providers::stripe::schema::types::tax_id::TaxId;
+use crate::providers::stripe::write_ctx;
+use crate::providers::stripe::write_ctx::is_raw_json_enabled;
+
+
+// Raw JSON (`options.store_raw_json`): a `raw` column with the full Stripe object on every Stripe object table (every `Db` table except `td_*` and `notification_events`).
+// - The row structs only keep some fields (E.g. `Customer` drops `tax_ids`, `sources` and `subscriptions`); `raw` can be queried with the SQL engine's JSON functions without waiting for a CLI release.
+// - Each row struct has an `#[optional_col]` `raw` field, so the column is not in the static schema or the insert/update SQL of the `Insert` derive (but is read by `SELECT *`).
+//      - When the DB is created, these tables are created from copies of the `Insert` tables with `raw` not skipped (and moved to the end, after the struct's columns).
+// - `raw` is the JSON of the API response, not the typed object: fields the client does not model are kept.
+//      - The `StripeClient` keeps the JSON of each top level object it returns (`RawIndex`: list items, retrieved objects and events).
+//      - `raw` is set in each row's `From` impl; it is written in the same insert/update as the row via the `LogWrite` functions.
+// - Only applies when set at DB create time (like `options.history`); history tables do not copy `raw`.
+
+
+pub const RAW_COL: &str = "raw";
+
+fn has_raw(table_name: &str) -> bool {
+    !get_always_included().contains(&table_name)
+}
+
+// Used in each row's `From` impl (`R` is the row struct); None when not enabled.
+// - A top level object becomes the scope its nested objects are read from (E.g. a subscription's items), as `WriteTree` writes parents before their children.
+pub fn get_raw<R: GetObjType>(x: &impl GetId) -> Option<Value> {
+    if !is_raw_json_enabled() {
+        return None;
+    }
+
+    let (object, id) = (R::get_obj_type_static(), x.get_id());
+    if let Some(raw) = write_ctx::take_raw(object, &id) {
+        write_ctx::set_raw_scope(Some(raw.clone()));
+        return Some(raw);
+    }
+
+    write_ctx::find_in_raw_scope(object, &id)
+}
+
+// Set before writing each event's objects: they are read from its `data.object`.
+pub fn set_event_scope(event_id: &str) {
+    if !is_raw_json_enabled() {
+        return;
+    }
+
+    let x = write_ctx::take_raw("event", event_id).and_then(|mut x| x.pointer_mut("/data/object").map(Value::take));
+    write_ctx::set_raw_scope(x);
+}
+
+// Depth first; `x` or the first nested object with the type and id.
+// - The type is needed as some objects share ids (E.g. a subscription item's `plan` and `price`).
+pub fn find_by_id<'a>(x: &'a Value, object: &str, id: &str) -> Option<&'a Value> {
+    match x {
+        Value::Object(o) => {
+            if o.get("id").and_then(|x| x.as_str()) == Some(id) && o.get("object").and_then(|x| x.as_str()) == Some(object) {
+                return Some(x);
+            }
+            o.values().find_map(|x| find_by_id(x, object, id))
+        }
+        Value::Array(a) => a.iter().find_map(|x| find_by_id(x, object, id)),
+        _ => None
+    }
+}
+
+// Tables with a `raw` column, created instead of the `Insert` tables of the same name.
+pub fn get_raw_tables(objects: &ObjectSelection) -> Vec<Table> {
+    Db::get_tables().into_iter()
+        .filter(|t| has_raw(&t.name) && objects.is_included(&t.name))
+        .map(get_raw_table)
+        .collect()
+}
+
+pub fn get_raw_table_names(objects: &ObjectSelection) -> Vec<String> {
+    get_raw_tables(objects).into_iter().map(|x| x.name).collect()
+}
+
+// When the DB is created: replaces the `Insert` tables with the `raw` tables.
+pub fn add_raw_tables(objects: &ObjectSelection, excluded: &mut Vec<String>, extra: &mut Vec<Table>) {
+    let raw_tables = get_raw_tables(objects);
+    excluded.extend(raw_tables.iter().map(|x| x.name.clone()));
+    extra.extend(raw_tables);
+}
+
+// `raw` is moved after the struct's columns, so it can be written with `Insert::tx_insert_with`.
+fn get_raw_table(t: &Table) -> Table {
+    let mut x = t.clone();
+    let i = x.cols.iter().position(|c| c.name == RAW_COL).unwrap_or_else(|| panic!("`{}` has no `#[optional_col] {}` field.", &t.struct_name, RAW_COL));
+    let mut c = x.cols.remove(i);
+    c.skip = false;
+    x.cols.push(c);
+
+    x.create_static_strings();
+    x
+}
+
+// Migration (`Step::AddOptionCols`): adds `raw` to DBs created before `options.store_raw_json` existed, when it is set.
+pub fn get_migration_tables(opts: &Options, objects: &ObjectSelection) -> Vec<Table> {
+    if opts.store_raw_json { get_raw_tables(objects) } else { vec![] }
+}
+
+pub fn has_raw_col(t: &Table) -> bool {
+    t.cols.iter().any(|c| c.name == RAW_COL && !c.skip)
+}
+
+// The DB must have been created with the same `options.store_raw_json`: otherwise writes would fail (no column), or `raw` would silently go stale.
+pub fn assert_raw_json_matches(uc: &mut UniCon, objects: &ObjectSelection, store_raw_json: bool) -> Result<(), TdError> {
+    let mut utx = uc.tx_open().map_err(|e| TdError::Db(e.to_string()))?;
+    let mut mismatch = vec![];
+    for t in get_raw_table_names(objects) {
+        if utx.get_columns(&t).map_err(TdError::Db)?.contains(&RAW_COL.to_string()) != store_raw_json {
+            mismatch.push(t);
+        }
+    }
+    utx.tx_close();
+
+    if !mismatch.is_empty() {
+        let created = if store_raw_json { "without" } else { "with" };
+        return Err(TdError::SchemaConflict(format!("The DB was created {} `options.store_raw_json`, set it to the same value. Tables: {:?}", created, mismatch)));
+    }
+
+    Ok(())
+}
+
+
+pub trait GetRaw {
+    fn get_raw(&self) -> Option<&Value>;
+}
+
+macro_rules! impl_get_raw {
+    ($($t:ty),*) => {
+        $(
+            impl GetRaw for $t {
+                fn get_raw(&self) -> Option<&Value> {
+                    self.raw.as_ref()
+                }
+            }
+        )*
+    };
+}
+
+impl_get_raw!(BalanceTransaction, BankAccount, Card, Charge, Coupon, CreditNote, CreditNoteLineItem, Customer, Discount, Dispute, Invoice, InvoiceLineItem, Invoiceitem, Order, OrderReturn, PaymentIntent, PaymentMethod, Plan, Price, Product, PromotionCode, Refund, Session, SetupIntent, Sku, Source, Subscription, SubscriptionItem, SubscriptionSchedule, TaxId, TaxRate);
+
+// `notification_events` already stores the full event in `data`.
+impl GetRaw for NotificationEvent {
+    fn get_raw(&self) -> Option<&Value> {
+        None
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashSet;
+use std::convert::{TryFrom, TryInto};
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+use stripe_client::http::http::StripeClient;
+use stripe_client::types::types as API;
+use unicon::traits::{*};
+use unicon::uc::{*};
+
+use crate::{hooks, shutdown, Replay};
+use crate::error::TdError;
+use crate::providers::stripe::apply_events::{apply_events, assert_correct_version};
+use crate::providers::stripe::foreign_keys;
+use crate::providers::stripe::history::get_history_tables;
+use crate::providers::stripe::metadata_cols::MetadataCols;
+use crate::providers::stripe::migrations;
+use crate::providers::stripe::raw_json;
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::util::ToISODate;
+use crate::providers::stripe::schema_meta::{TdMetadata, TdRun, TdStripeApplyEvent};
+use crate::providers::stripe::watch::assert_no_history_tables;
+use crate::providers::stripe::write_ctx;
+
+
+// `replay`: apply Stripe events read from local files instead of `/v1/events` (E.g. to rebuild state from a backup in a disaster recovery drill).
+// - The DB must contain a download (E.g. restored from a backup); events cannot re-create objects that were not written by an event in the files.
+// - Events are applied in batches with the same code as polling: one `td_runs` row and tx per batch, one `td_stripe_apply_events` row per event.
+// - Events already in `td_stripe_apply_events` are skipped so a replay can be re-run after a failure.
+// - Not limited to the 30 days of events kept by Stripe, but gaps in the files are not detected.
+
+
+// Each `.json` file is an event, an array of events, or a list response (`{"object": "list", "data": [...]}`).
+fn get_events_from_json(file: &str, s: &str) -> Result<Vec<Value>, TdError> {
+    let v: Value = serde_json::from_str(s).map_err(|e| TdError::Config(format!("Invalid JSON in `{}`: {}", file, e)))?;
+
+    Ok(match v {
+        Value::Array(x) => x,
+        Value::Object(mut x) if x.get("object") == Some(&Value::String("list".into())) => {
+            match x.remove("data") {
+                Some(Value::Array(data)) => data,
+                _ => return Err(TdError::Config(format!("Expected an array at `data` in `{}`.", file)))
+            }
+        }
+        x => vec![x]
+    })
+}
+
+// - A directory: all `.json` files, in file name order.
+// - A `.jsonl` file: one event per line.
+// - Any other file: read as a `.json` file.
+pub fn read_events(path: &str) -> Result<Vec<API::NotificationEvent>, TdError> {
+    let read = |file: &str| fs::read_to_string(file).map_err(|e| TdError::Config(format!("Could not read `{}`: {}", file, e)));
+    let mut values = vec![];
+
+    if Path::new(path).is_dir() {
+        let dir = fs::read_dir(path).map_err(|e| TdError::Config(format!("Could not read directory `{}`: {}", path, e)))?;
+        let mut files: Vec<String> = dir
+            .filter_map(|x| x.ok())
+            .map(|x| x.path())
+            .filter(|x| x.extension().map(|ext| ext == "json").unwrap_or(false))
+            .map(|x| x.to_string_lossy().to_string())
+            .collect();
+        files.sort();
+
+        for f in files {
+            values.extend(get_events_from_json(&f, &read(&f)?)?);
+        }
+    } else if path.ends_with(".jsonl") {
+        for (i, line) in read(path)?.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let v: Value = serde_json::from_str(line).map_err(|e| TdError::Config(format!("Invalid JSON on line {} of `{}`: {}", i + 1, path, e)))?;
+            values.push(v);
+        }
+    } else {
+        values = get_events_from_json(path, &read(path)?)?;
+    }
+
+    values.into_iter().map(|v| {
+        // `store_raw_json`: the rows written for the event keep the JSON from the file.
+        write_ctx::add_raw(&v);
+        let id = v.get("id").and_then(|x| x.as_str()).unwrap_or("").to_string();
+        serde_json::from_value(v).map_err(|e| TdError::Config(format!("Not a Stripe event{} in `{}`: {}", if id.is_empty() { "".into() } else { format!(" (`{}`)", id) }, path, e)))
+    }).collect()
+}
+
+// Events must be in the order they were created: oldest first, or newest first (the order of `/v1/events` and the dashboard).
+// - `created` has a resolution of one second; events in the same second keep their order from the files.
+// - Duplicate events (E.g. retried webhooks) are removed.
+pub fn get_ordered(mut events: Vec<API::NotificationEvent>) -> Result<Vec<API::NotificationEvent>, TdError> {
+    let mut seen = HashSet::new();
+    let len = events.len();
+    events.retain(|x| seen.insert(x.id.clone()));
+    if events.len() != len {
+        warn!("Ignoring {} duplicate events (same event id).", len - events.len());
+    }
+
+    if let (Some(first), Some(last)) = (events.first(), events.last()) {
+        if first.created > last.created {
+            events.reverse();
+        }
+    }
+
+    if let Some(w) = events.windows(2).find(|w| w[0].created > w[1].created) {
+        return Err(TdError::Config(format!(
+            "Events are not ordered by `created`: `{}` ({}) is followed by `{}` ({}). Events must be oldest first or newest first.",
+            w[0].id, w[0].created.to_iso(), w[1].id, w[1].created.to_iso()
+        )));
+    }
+
+    Ok(events)
+}
+
+// Live and test data cannot be mixed in one DB.
+fn assert_livemode(events: &[API::NotificationEvent], md: &TdMetadata) -> Result<(), TdError> {
+    if let Some(x) = events.iter().find(|x| x.livemode == md.stripe_is_test) {
+        return Err(TdError::SchemaConflict(format!("Event `{}` has livemode={}, but the DB has Stripe is_test={}.", x.id, x.livemode, md.stripe_is_test)));
+    }
+
+    Ok(())
+}
+
+// `sc` is only used to download parents created before `created_gte` (it has no key when `rp.from` is None).
+pub async fn replay(sc: &StripeClient, uc: &mut UniCon, rp: &Replay) -> Result<(), TdError> {
+    let objects: ObjectSelection = (&rp.options.objects).try_into()?;
+    let history_tables = if rp.options.history { get_history_tables(&objects) } else { vec![] };
+
+    migrations::migrate(uc, &objects, &rp.options)?;
+    if !uc.schema_exists_with::<Db>(&objects.get_excluded(), &history_tables).map_err(TdError::SchemaConflict)? {
+        return Err(TdError::SchemaConflict("The DB has no tables. `replay` needs a DB that contains a download (E.g. restored from a backup).".into()));
+    }
+
+    if !rp.options.history {
+        assert_no_history_tables(uc, &objects)?;
+    }
+    raw_json::assert_raw_json_matches(uc, &objects, rp.options.store_raw_json)?;
+    MetadataCols::try_from(&rp.options.metadata_columns)?.assert_cols_match(uc, &objects)?;
+    foreign_keys::set_sqlite_enforcement(rp.options.foreign_keys, uc);
+
+    let md = match sc.stripe_account.as_ref() {
+        Some(sa) => TdMetadata::check_cli_and_stripe_versions_match(uc, sa)?,
+        None => TdMetadata::check_versions_match(uc)?
+    };
+
+    if TdRun::get_last_run(uc).is_none() {
+        return Err(TdError::SchemaConflict("The DB has no completed download. `replay` needs a DB that contains a download (E.g. restored from a backup).".into()));
+    }
+
+    if md.get_created_gte().is_some() && sc.stripe_account.is_none() {
+        return Err(TdError::Config("The DB was created with `options.created_gte`: set `from` so that parents created before the cutoff can be downloaded.".into()));
+    }
+
+    let all = get_ordered(read_events(&rp.events)?)?;
+    info!("Read {} events from `{}`.", all.len(), &rp.events);
+
+    // Check every event before applying the first batch.
+    assert_correct_version(&all)?;
+    assert_livemode(&all, &md)?;
+
+    let mut events = vec![];
+    for x in all {
+        if TdStripeApplyEvent::get_where(uc, ("event_id", &x.id)).is_empty() {
+            events.push(x);
+        }
+    }
+
+    if let Some(x) = events.first() {
+        info!("{} events have not been applied; replaying from `{}` ({}).", events.len(), &x.id, x.created.to_iso());
+    } else {
+        info!("All events have already been applied.");
+        return Ok(());
+    }
+
+    let batches = events.len().div_ceil(rp.batch_size);
+    for (i, batch) in events.chunks(rp.batch_size).enumerate() {
+        // Batches are committed independently; stopping between them leaves the DB consistent.
+        if shutdown::is_shutdown() {
+            return Err(TdError::Interrupted(format!("Stopped after {} of {} batches.", i, batches)));
+        }
+
+        info!("Replaying batch {} of {} ({} events).", i + 1, batches, batch.len());
+        if let Some(summary) = apply_events(sc, uc, Some(batch.to_vec()), &objects).await? {
+            hooks::on_apply_events(&rp.options.hooks, &summary).await;
+        }
+    }
+
+    info!("Replay complete.");
+    Ok(())
+}
@@ -69,6 +69,8 @@ pub enum Db {
     TdStripeWrite(TdStripeWrite),
     TdRun(TdRun),
     TdMetadata(TdMetadata),
+    TdMissingOwner(TdMissingOwner),
+    TdCdc(TdCdc),
 
     // Address(Address),
     BalanceTransaction(BalanceTransaction),
@@ -115,49 +117,81 @@ pub enum Db {
 impl Db {
     /// All types that can be written to their own SQL tables (matches `Db`).
     pub fn event_is_table_write(e: &API::NotificationEvent) -> bool {
-        use API::UniNotificationEventDataObject::*;
-
-        match &(*e.data.object) {
-            Charge(_) |
-            Coupon(_) |
-            CreditNote(_) |
-            Customer(_) |
-            Discount(_) |
-            Dispute(_) |
-            UniPolymorphic70BAFA(_) | // card|bank via `customer.source.x` event.
-            InvoiceItem(_) |
-            Order(_) |
-            OrderReturn(_) |
-            PaymentIntent(_) |
-            PaymentMethod(_) |
-            Price(_) |
+        Db::get_event_table_name(e).is_some()
+    }
+
+    /// The table the event's object is written to, or None if it is not written to a table.
+    /// - Used to skip events for tables excluded via `options.objects`.
+    pub fn get_event_table_name(e: &API::NotificationEvent) -> Option<&'static str> {
+        use API::UniNotificationEventDataObject as Obj;
+
+        let t = match &(*e.data.object) {
+            Obj::Charge(_) => Charge::get_table_name_static(),
+            Obj::Coupon(_) => Coupon::get_table_name_static(),
+            Obj::CreditNote(_) => CreditNote::get_table_name_static(),
+            Obj::Customer(_) => Customer::get_table_name_static(),
+            Obj::Discount(_) => Discount::get_table_name_static(),
+            Obj::Dispute(_) => Dispute::get_table_name_static(),
+            // card|bank via `customer.source.x` event.
+            Obj::UniPolymorphic70BAFA(x) => match x {
+                APIRes::UniPolymorphic70BAFA::BankAccount(_) => BankAccount::get_table_name_static(),
+                APIRes::UniPolymorphic70BAFA::Card(_) => Card::get_table_name_static(),
+            },
+            Obj::InvoiceItem(_) => Invoiceitem::get_table_name_static(),
+            Obj::Order(_) => Order::get_table_name_static(),
+            Obj::OrderReturn(_) => OrderReturn::get_table_name_static(),
+            Obj::PaymentIntent(_) => PaymentIntent::get_table_name_static(),
+            Obj::PaymentMethod(_) => PaymentMethod::get_table_name_static(),
+            Obj::Price(_) => Price::get_table_name_static(),
             // Plan(_) Note: plans are just aliases for prices.
-            Product(_) |
-            PromotionCode(_) |
-            Refund(_) |
-            SetupIntent(_) |
+            Obj::Product(_) => Product::get_table_name_static(),
+            Obj::PromotionCode(_) => PromotionCode::get_table_name_static(),
+            Obj::Refund(_) => Refund::get_table_name_static(),
+            Obj::SetupIntent(_) => SetupIntent::get_table_name_static(),
             // Session(_) | See comment on test.
-            Sku(_) |
+            Obj::Sku(_) => Sku::get_table_name_static(),
 
             // Note: `customer.source.created` can contain (source, card, bank, ...).
-            Source(_) |
-            Subscription(_) |
+            Obj::Source(_) => Source::get_table_name_static(),
+            Obj::Subscription(_) => Subscription::get_table_name_static(),
             // SubscriptionItem(_) | (!has_dl_list && !has_direct_event) - these are inserted/upserted with Subscription which always includes 100% of the sub items.
-            SubscriptionSchedule(_) |
-            TaxId(_) |
-            TaxRate(_)
-            => true,
-            Invoice(i) => {
+            Obj::SubscriptionSchedule(_) => SubscriptionSchedule::get_table_name_static(),
+            Obj::TaxId(_) => TaxId::get_table_name_static(),
+            Obj::TaxRate(_) => TaxRate::get_table_name_static(),
+            Obj::Invoice(i) => {
                 // Ignore these as they have no `id` - it is the only exception to the rule `every type has an id` which the write log is based on.
                 // Docs: `invoice.upcoming` event, Occurs X number of days before a subscription is scheduled to create an invoice that is automatically charged—where X is determined by your subscriptions settings. Note: The received Invoice object will not have an invoice ID.
                 if e.type_x == "invoice.upcoming" {
                     assert_eq!((*i).id, None);
-                    return false;
+                    return None;
                 }
-                true
+                Invoice::get_table_name_static()
+            }
+            _ => return None
+        };
+
+        Some(t)
+    }
+
+    /// The current row of a data table, serialized via its serde impl (None when it does not exist).
+    pub fn get_row_json(uc: &mut UniCon, table_name: &str, id: &str) -> Option<Value> {
+        macro_rules! get_row {
+            ($($t:ty),*) => {
+                $(
+                    if table_name == <$t>::get_table_name_static() {
+                        return <$t>::get_where(uc, ("id", &id.to_string())).pop().and_then(|x| serde_json::to_value(x).ok());
+                    }
+                )*
             }
-            _ => false
         }
+
+        get_row!(
+            BalanceTransaction, BankAccount, Card, Charge, Coupon, CreditNote, CreditNoteLineItem, Customer, Discount, Dispute,
+            Invoice, Invoiceitem, InvoiceLineItem, Order, OrderReturn, PaymentMethod, PaymentIntent, Price, Product, PromotionCode,
+            Refund, SetupIntent, Sku, Source, Subscription, SubscriptionItem, SubscriptionSchedule, TaxId, TaxRate
+        );
+
+        None
     }
 }
 
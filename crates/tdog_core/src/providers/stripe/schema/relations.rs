@@ -1,7 +1,9 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 // This file defines the foreign keys between Rust structs (that represent 1 level deep rows; no nested types).
 // @see `./relations-readme.md`
+use mysql::Params;
+use mysql::prelude::Queryable;
 use serde::{Deserialize, Serialize};
 use unicon::{*};
 use unicon::dt::{*};
@@ -16,7 +18,10 @@ use unicon::uc::{*};
 use unicon::utx::{*};
 use unicon_proc_macro::{*};
 
+use crate::providers::stripe::objects::ObjectSelection;
 use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema_meta::TdMissingOwner;
+use crate::providers::stripe::schema::util::ToJSONString;
 use crate::providers::stripe::schema::types::{Charge, Customer, PaymentIntent, PaymentMethod, Price, Product, Source, Subscription, SubscriptionItem, SubscriptionSchedule, TaxRate};
 use crate::providers::stripe::schema::types::balance_transaction::BalanceTransaction;
 use crate::providers::stripe::schema::types::bank_account::BankAccount;
@@ -56,20 +61,20 @@ pub enum RelType {
 #[derive(Clone)]
 pub struct Edge {
     // `parent` in SQL FK terminology.
-    owner_tbl: String,
+    pub owner_tbl: String,
 
     // `child` in SQL FK terminology.
-    copy_tbl: String,
-    copy_col: String,
+    pub copy_tbl: String,
+    pub copy_col: String,
 
-    rel_type: RelType,
+    pub rel_type: RelType,
 
     // Is this relation enforced by the API (and as a result can be enforced by our copy of the DB).
     // - E.g. A Stripe customer can be deleted, but invoices and subs still reference the deleted customer ID.
     // - Collecting this data allows:
     //      - Tests that can check the relations at specific points in time when they should exist.
     //      - CLI that outputs all missing relations to inform query writing.
-    enforced_by_api: bool,
+    pub enforced_by_api: bool,
 }
 
 #[derive(Debug)]
@@ -187,7 +192,7 @@ impl Edge {
 /// - Which relations are never consistent (payment method not attached to customer at apply events time).
 ///
 /// @todo/low Use notes/exceptions on this list to choose which URL/objects to download at event processing time to keep sets complete and queries correct/symmetric at download/event apply times.
-fn get_edges() -> Vec<EdgeGrp> {
+pub fn get_edges() -> Vec<EdgeGrp> {
     let mut o: Vec<EdgeGrp> = vec![];
 
 
@@ -493,8 +498,55 @@ fn get_edges() -> Vec<EdgeGrp> {
 }
 
 
+impl EdgeGrp {
+    pub fn get_edges(&self) -> Vec<&Edge> {
+        match self {
+            EdgeGrp::Single(e) => vec![e],
+            EdgeGrp::Or(x) => x.iter().collect()
+        }
+    }
+
+    // E.g. `invoices.customer -> customers`, `customers.default_source -> sources|cards|bank_accounts`.
+    pub fn get_name(&self) -> String {
+        let x = self.get_edges();
+        let owners: Vec<&str> = x.iter().map(|e| e.owner_tbl.as_str()).collect();
+        let json_array = if x[0].rel_type == RelType::JSONArray { " (JSON array)" } else { "" };
+
+        format!("{}.{} -> {}{}", &x[0].copy_tbl, &x[0].copy_col, owners.join("|"), json_array)
+    }
+
+    pub fn is_enforced_by_api(&self) -> bool {
+        self.get_edges()[0].enforced_by_api
+    }
+
+    // Both sides of every edge exist as tables.
+    pub fn is_included(&self, objects: &ObjectSelection) -> bool {
+        self.get_edges().iter().all(|e| objects.is_included(&e.owner_tbl) && objects.is_included(&e.copy_tbl))
+    }
+}
+
 impl Db {
-    pub fn get_missing_owner_all(uc: &UniCon) -> Vec<(EdgeGrp, Vec<(String, String)>)> {
+    /// Warn when an included table references an excluded table.
+    /// - E.g. `invoices.customer` when `customers` is excluded: joins from invoices to customers are not possible.
+    pub fn warn_excluded_owners(objects: &ObjectSelection) {
+        let mut o = vec![];
+
+        for g in get_edges() {
+            for e in g.get_edges() {
+                if objects.is_included(&e.copy_tbl) && !objects.is_included(&e.owner_tbl) {
+                    o.push(format!("{}.{} -> {}", &e.copy_tbl, &e.copy_col, &e.owner_tbl));
+                }
+            }
+        }
+
+        if !o.is_empty() {
+            warn!("Included tables reference tables excluded via `options.objects` (these owner rows will not exist): {}", o.to_json());
+        }
+    }
+
+    /// Edges with an excluded table are ignored (their owners are missing by design).
+    /// - `gone`: `td_missing_owners`, owners Stripe returned as deleted or missing when they were downloaded individually (`options.created_gte`); these are allowed to be missing.
+    pub fn get_missing_owner_all(uc: &UniCon, objects: &ObjectSelection, gone: &HashSet<(String, String)>) -> Vec<(EdgeGrp, Vec<(String, String)>)> {
         let mut o = vec![];
 
 
@@ -507,13 +559,18 @@ impl Db {
 
 
         for g in get_edges() {
+            if !g.is_included(objects) {
+                continue;
+            }
+
             match g {
                 EdgeGrp::Single(ref e) => {
                     if !e.enforced_by_api {
                         continue;
                     }
 
-                    let missing = get_missing(&e);
+                    let mut missing = get_missing(e);
+                    missing.retain(|(_, owner_id)| !gone.contains(&(e.owner_tbl.clone(), owner_id.clone())));
                     if missing.len() > 0 {
                         o.push((g.clone(), missing));
                     }
@@ -539,7 +596,9 @@ impl Db {
                         assert!(count <= &total_or);
                         // 0 edges exist; at least one should (OR).
                         count == &total_or
-                    }).map(|(copy_id, count)| copy_id).collect::<Vec<(String, String)>>();
+                    }).map(|(copy_id, count)| copy_id).filter(|(_, owner_id)| {
+                        !x2.iter().any(|e| gone.contains(&(e.owner_tbl.clone(), owner_id.clone())))
+                    }).collect::<Vec<(String, String)>>();
 
                     if missing.len() > 0 {
                         o.push((g.clone(), missing));
@@ -550,6 +609,159 @@ impl Db {
 
         o
     }
+
+    /// Single `Normal` edges with both tables included: `(copy_tbl, copy_col, owner_tbl, enforced_by_api)`.
+    /// - Used for SQL foreign keys (`options.foreign_keys`); polymorphic (`Or`) owners and JSON arrays of ids cannot be expressed as one.
+    pub fn get_fk_edges(objects: &ObjectSelection) -> Vec<(String, String, String, bool)> {
+        get_edges().into_iter().filter(|g| g.is_included(objects)).filter_map(|g| match g {
+            EdgeGrp::Single(e) if e.rel_type == RelType::Normal => Some((e.copy_tbl, e.copy_col, e.owner_tbl, e.enforced_by_api)),
+            _ => None
+        }).collect()
+    }
+
+    /// `(copy_id, owner_id)` pairs with a missing owner for every included edge group (enforced or not), on all engines (`tdog check`).
+    /// - `Or`: the owner id is missing from every owner table.
+    /// - Owners in `td_missing_owners` are allowed to be missing (see `get_missing_owner_all`).
+    pub fn get_missing_owners(utx: &mut UniTx, objects: &ObjectSelection) -> Vec<(EdgeGrp, Vec<(String, String)>)> {
+        let mut o = vec![];
+        let gone = TdMissingOwner::tx_get_keys(utx);
+
+        for g in get_edges() {
+            if !g.is_included(objects) {
+                continue;
+            }
+
+            let edges = g.get_edges();
+            let mut missing = tx_get_missing_owner(utx, edges[0]);
+            for e in &edges[1..] {
+                let in_other = tx_get_missing_owner(utx, e);
+                missing.retain(|x| in_other.contains(x));
+            }
+            missing.retain(|(_, owner_id)| !edges.iter().any(|e| gone.contains(&(e.owner_tbl.clone(), owner_id.clone()))));
+
+            o.push((g.clone(), missing));
+        }
+
+        o
+    }
+
+    /// Owner ids referenced by rows written in `run_id` that do not exist: `(owner_tbl, owner_id)`.
+    /// - Used with `options.created_gte` to find parents created before the cutoff (these need to be downloaded individually).
+    /// - Only single `Normal` edges: polymorphic (`Or`) owners and JSON arrays of ids are not resolved.
+    pub fn get_missing_owner_ids_for_run(utx: &mut UniTx, run_id: i64, objects: &ObjectSelection) -> Vec<(String, String)> {
+        let mut o = vec![];
+        let mut seen = HashSet::new();
+
+        for g in get_edges() {
+            if !g.is_included(objects) {
+                continue;
+            }
+
+            if let EdgeGrp::Single(e) = g {
+                if e.rel_type != RelType::Normal {
+                    continue;
+                }
+
+                for id in get_missing_owner_for_run(utx, run_id, &e.owner_tbl, &e.copy_tbl, &e.copy_col) {
+                    let k = (e.owner_tbl.clone(), id);
+                    if seen.insert(k.clone()) {
+                        o.push(k);
+                    }
+                }
+            }
+        }
+
+        o
+    }
+}
+
+
+// Same as `get_missing_owner`, but for all engines and only for `copy` rows written in `run_id`.
+fn get_missing_owner_for_run(utx: &mut UniTx, run_id: i64, owner_tbl: &str, copy_tbl: &str, copy_field_name: &str) -> Vec<String> {
+    // language=sql
+    let std_sql = format!(r###"
+        select distinct copy.{col} from {copy} copy
+            join td_stripe_writes w on(w.obj_id=copy.id and w.table_name='{copy}' and w.run_id={run_id})
+            left join {owner} owner on(copy.{col}=owner.id)
+        where owner.id is null and copy.{col} is not null
+    "###, col = copy_field_name, copy = copy_tbl, owner = owner_tbl, run_id = run_id);
+
+    let mut o = vec![];
+
+    match utx {
+        UniTx::Rusqlite(tx) => {
+            let mut stmt = tx.prepare_cached(&std_sql).unwrap();
+            let mut rows = stmt.query([]).unwrap();
+            while let Some(row) = rows.next().unwrap() {
+                o.push(row.get(0).unwrap());
+            }
+        }
+        UniTx::MySQL(tx) => {
+            o = tx.exec(&std_sql, Params::Empty).unwrap();
+        }
+        UniTx::Postgres(tx) => {
+            for row in tx.query(std_sql.as_str(), &[]).unwrap() {
+                o.push(row.get(0));
+            }
+        }
+        UniTx::PlaceholderLibA(_) => {}
+    }
+
+    o
+}
+
+
+// Same as `get_missing_owner` and `get_missing_owner_json_array`, but for all engines.
+fn tx_get_missing_owner(utx: &mut UniTx, e: &Edge) -> Vec<(String, String)> {
+    let (copy, col, owner) = (&e.copy_tbl, &e.copy_col, &e.owner_tbl);
+
+    // language=sql
+    let q = match (&e.rel_type, &utx) {
+        (RelType::Normal, _) => format!(r###"
+            select copy.id, copy.{col} from {copy} copy left join {owner} owner on(copy.{col}=owner.id) where owner.id is null and copy.{col} is not null
+        "###, col = col, copy = copy, owner = owner),
+        (RelType::JSONArray, UniTx::Rusqlite(_)) => format!(r###"
+            select copy.id, je.atom from {copy} copy, json_each(copy.{col}) je
+                left join {owner} owner on(je.atom=owner.id)
+            where owner.id is null and je.atom is not null
+        "###, col = col, copy = copy, owner = owner),
+        (RelType::JSONArray, UniTx::MySQL(_)) => format!(r###"
+            select copy.id, je.atom from {copy} copy
+                cross join JSON_TABLE(copy.{col}, '$[*]' COLUMNS (atom VARCHAR(255) PATH '$')) je
+                left join {owner} owner on(je.atom=owner.id)
+            where owner.id is null and je.atom is not null
+        "###, col = col, copy = copy, owner = owner),
+        // Some JSON array columns are `TEXT`; a JSON `null` is not an array.
+        (RelType::JSONArray, _) => format!(r###"
+            select copy.id, je.atom from {copy} copy
+                cross join lateral jsonb_array_elements_text(case when jsonb_typeof(copy.{col}::jsonb) = 'array' then copy.{col}::jsonb else '[]'::jsonb end) je(atom)
+                left join {owner} owner on(je.atom=owner.id)
+            where owner.id is null
+        "###, col = col, copy = copy, owner = owner),
+    };
+
+    let mut o = vec![];
+
+    match utx {
+        UniTx::Rusqlite(tx) => {
+            let mut stmt = tx.prepare_cached(&q).unwrap();
+            let mut rows = stmt.query([]).unwrap();
+            while let Some(row) = rows.next().unwrap() {
+                o.push((row.get(0).unwrap(), row.get(1).unwrap()));
+            }
+        }
+        UniTx::MySQL(tx) => {
+            o = tx.exec(&q, Params::Empty).unwrap();
+        }
+        UniTx::Postgres(tx) => {
+            for row in tx.query(q.as_str(), &[]).unwrap() {
+                o.push((row.get(0), row.get(1)));
+            }
+        }
+        UniTx::PlaceholderLibA(_) => {}
+    }
+
+    o
 }
 
 
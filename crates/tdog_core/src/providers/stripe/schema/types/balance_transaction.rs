@@ -31,6 +31,7 @@ use crate::providers::stripe::schema::{ArrayPKs, f, f_opt, GetIdFromEnumOrNone,
 use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::UpsertFirstLevel;
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -64,6 +65,10 @@ pub struct BalanceTransaction {
 
     #[insert_ts]
     pub insert_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 
@@ -97,6 +102,7 @@ impl From<&API::BalanceTransaction> for BalanceTransaction {
             created: i.created.to_dt(),
 
             insert_ts: None,
+            raw: get_raw::<Self>(i),
         }
     }
 }
@@ -30,6 +30,7 @@ use crate::providers::stripe::schema::{ArrayPKs, f, f_opt, GetIdAny, json_key, j
 use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::UpsertFirstLevel;
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -64,6 +65,10 @@ pub struct BankAccount {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 impl GetObjType for BankAccount {
@@ -80,6 +85,7 @@ impl GetId for BankAccount {
 
 impl From<&API::BankAccount> for BankAccount {
     fn from(x: &API::BankAccount) -> Self {
+        let raw = get_raw::<Self>(x);
         let x = x.clone();
 
         BankAccount {
@@ -101,6 +107,7 @@ impl From<&API::BankAccount> for BankAccount {
             status: x.status,
             insert_ts: None,
             update_ts: None,
+            raw,
         }
     }
 }
@@ -30,6 +30,7 @@ use crate::providers::stripe::schema::{ArrayPKs, f, f_opt, GetIdAny, json_key, j
 use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::UpsertFirstLevel;
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -37,6 +38,7 @@ use crate::providers::traits::UpsertFirstLevel;
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 pub struct Card {
     #[primary_key]
     pub card_id: Option<i64>,
@@ -79,6 +81,10 @@ pub struct Card {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 impl GetObjType for Card {
@@ -95,6 +101,7 @@ impl GetId for Card {
 
 impl From<&API::Card> for Card {
     fn from(x: &API::Card) -> Self {
+        let raw = get_raw::<Self>(x);
         let x = x.clone();
 
         Card {
@@ -132,6 +139,7 @@ impl From<&API::Card> for Card {
             metadata: x.metadata.json_or_none(),
             insert_ts: None,
             update_ts: None,
+            raw,
         }
     }
 }
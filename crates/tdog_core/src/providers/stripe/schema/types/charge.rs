@@ -31,6 +31,7 @@ use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema::types::refund::Refund;
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::{ExistsTx, UpsertFirstLevel};
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -38,6 +39,7 @@ use crate::providers::traits::{ExistsTx, UpsertFirstLevel};
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 pub struct Charge {
     #[primary_key]
     pub charge_id: Option<i64>,
@@ -66,6 +68,7 @@ pub struct Charge {
     pub amount_refunded: i64,
     pub application_fee_amount: Option<i64>,
 
+    #[flatten("name", "email", "phone", "address.city", "address.country", "address.line1", "address.line2", "address.postal_code", "address.state")]
     pub billing_details: Value,
     pub calculated_statement_descriptor: Option<String>,
     pub captured: bool,
@@ -97,6 +100,7 @@ pub struct Charge {
     // pub refunds_2: Vec<Refund>,
 
 
+    #[flatten("name", "phone", "address.city", "address.country", "address.line1", "address.line2", "address.postal_code", "address.state")]
     pub shipping: Option<Value>,
     pub statement_descriptor: Option<String>,
     pub statement_descriptor_suffix: Option<String>,
@@ -116,6 +120,10 @@ pub struct Charge {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 
@@ -180,6 +188,7 @@ impl From<&API::Charge> for Charge {
             metadata: i.metadata.json_or_none(),
             insert_ts: None,
             update_ts: None,
+            raw: get_raw::<Self>(i),
         }
     }
 }
@@ -30,6 +30,7 @@ use crate::providers::stripe::schema::{ArrayPKs, f, f_opt, GetIdFromEnumOrNone,
 use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::UpsertFirstLevel;
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -37,6 +38,7 @@ use crate::providers::traits::UpsertFirstLevel;
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 pub struct Coupon {
     #[primary_key]
     pub coupon_id: Option<i64>,
@@ -64,6 +66,10 @@ pub struct Coupon {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 
@@ -93,6 +99,7 @@ impl From<&API::Coupon> for Coupon {
             metadata: x.metadata.json_or_none(),
             insert_ts: None,
             update_ts: None,
+            raw: get_raw::<Self>(x),
         }
     }
 }
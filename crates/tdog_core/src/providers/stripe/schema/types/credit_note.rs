@@ -33,6 +33,7 @@ use crate::providers::stripe::schema::types::credit_note_line_item::{CreditNoteL
 use crate::providers::stripe::schema::types::discount::Discount;
 use crate::providers::stripe::schema_meta::{DeleteStaticLogWrite, GetInferredDeletes, LogWrite, TdStripeWrite};
 use crate::providers::traits::{ExistsTx, ExistsTxSelf, UpsertFirstLevel};
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -40,6 +41,7 @@ use crate::providers::traits::{ExistsTx, ExistsTxSelf, UpsertFirstLevel};
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 pub struct CreditNote {
     #[primary_key]
     pub credit_note_id: Option<i64>,
@@ -81,6 +83,10 @@ pub struct CreditNote {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 impl GetObjType for CreditNote {
@@ -135,6 +141,7 @@ impl From<&API::CreditNote> for CreditNote {
 
             insert_ts: None,
             update_ts: None,
+            raw: get_raw::<Self>(x),
 
         }
     }
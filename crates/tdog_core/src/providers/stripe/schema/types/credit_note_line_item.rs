@@ -32,6 +32,7 @@ use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema::types::discount::Discount;
 use crate::providers::stripe::schema_meta::{LogWrite, TdStripeWrite};
 use crate::providers::traits::{ExistsTx, ExistsTxSelf, UpsertFirstLevel};
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -68,6 +69,10 @@ pub struct CreditNoteLineItem {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 impl GetObjType for CreditNoteLineItem {
@@ -90,6 +95,7 @@ pub struct CreditNoteLineItemWithParentId<'a> {
 
 impl From<&CreditNoteLineItemWithParentId<'_>> for CreditNoteLineItem {
     fn from(x: &CreditNoteLineItemWithParentId) -> Self {
+        let raw = get_raw::<Self>(x.data);
         let x2 = x.data.clone();
 
         CreditNoteLineItem {
@@ -110,6 +116,7 @@ impl From<&CreditNoteLineItemWithParentId<'_>> for CreditNoteLineItem {
             livemode: x2.livemode,
             insert_ts: None,
             update_ts: None,
+            raw,
         }
     }
 }
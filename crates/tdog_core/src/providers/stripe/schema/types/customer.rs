@@ -35,8 +35,11 @@ use crate::providers::stripe::schema::types::bank_account::BankAccount;
 use crate::providers::stripe::schema::types::card::Card;
 use crate::providers::stripe::schema::types::discount::Discount;
 use crate::providers::stripe::schema::types::tax_id::TaxId;
+use crate::providers::stripe::history;
+use crate::providers::stripe::cdc;
 use crate::providers::stripe::schema_meta::{DeleteStaticLogWrite, GetInferredDeletes, LogWrite, TdStripeWrite};
 use crate::providers::traits::{ExistsTx, ExistsTxSelf, UpsertFirstLevel};
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -44,6 +47,7 @@ use crate::providers::traits::{ExistsTx, ExistsTxSelf, UpsertFirstLevel};
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 pub struct Customer {
     #[primary_key]
     pub customer_id: Option<i64>,
@@ -55,7 +59,9 @@ pub struct Customer {
     pub email: Option<String>,
     pub default_source: Option<String>,
 
+    #[flatten("city", "country", "line1", "line2", "postal_code", "state")]
     pub address: Option<Value>,
+    #[flatten("name", "phone", "address.city", "address.country", "address.line1", "address.line2", "address.postal_code", "address.state")]
     pub shipping: Option<Value>,
 
     // https://stripe.com/docs/api/customer_balance_transactions?lang=node
@@ -74,6 +80,7 @@ pub struct Customer {
 
     pub invoice_prefix: Option<String>,
 
+    #[flatten("default_payment_method")]
     pub invoice_settings: Option<Value>,
 
     pub next_invoice_sequence: Option<i64>,
@@ -103,6 +110,10 @@ pub struct Customer {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 
@@ -168,7 +179,10 @@ impl Customer {
             insert_ts: None,
         };
 
-        write.tx_insert_set_pk(utx)
+        history::tx_write_version(utx, &write);
+        let x = write.tx_insert_set_pk(utx);
+        cdc::push(&write, || Self::get_where_tx(utx, ("id", &write.obj_id)).pop().and_then(|x| serde_json::to_value(x).ok()));
+        x
     }
 }
 
@@ -214,6 +228,7 @@ impl From<&API::Customer> for Customer {
 
             insert_ts: None,
             update_ts: None,
+            raw: get_raw::<Self>(x),
 
         }
     }
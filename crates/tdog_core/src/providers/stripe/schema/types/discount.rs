@@ -4,6 +4,7 @@ use std::collections::HashMap;
 use std::hash::BuildHasherDefault;
 
 use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
 use stripe_client::types::responses::UniPolymorphic646C3F;
 use stripe_client::types::types::{GetId, UniCharge, UniCustomerC00F6E, UniDefaultSource, UniPaymentIntent, UniPaymentMethod, UniPromotionCode};
 use stripe_client::types::types as API;
@@ -29,6 +30,7 @@ use crate::providers::stripe::schema::{ArrayPKs, f, f_opt, GetIdFromEnumOrNone,
 use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::UpsertFirstLevel;
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -63,6 +65,10 @@ pub struct Discount {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 
@@ -102,6 +108,7 @@ impl From<&API::Discount> for Discount {
             subscription: x.subscription.clone(),
             insert_ts: None,
             update_ts: None,
+            raw: get_raw::<Self>(x),
         }
     }
 }
@@ -30,6 +30,7 @@ use crate::providers::stripe::schema::{ArrayPKs, f, f_opt, GetIdFromEnumOrNone,
 use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::UpsertFirstLevel;
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -37,6 +38,7 @@ use crate::providers::traits::UpsertFirstLevel;
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 pub struct Dispute {
     #[primary_key]
     pub dispute_id: Option<i64>,
@@ -72,6 +74,10 @@ pub struct Dispute {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 
@@ -115,6 +121,7 @@ impl From<&API::Dispute> for Dispute {
             metadata: i.metadata.json_or_none(),
             insert_ts: None,
             update_ts: None,
+            raw: get_raw::<Self>(i),
         }
     }
 }
@@ -32,6 +32,7 @@ use crate::providers::stripe::schema::types::discount::Discount;
 use crate::providers::stripe::schema::types::invoice_line_item::{InvoiceLineItem, InvoiceLineItemWithParentId};
 use crate::providers::stripe::schema_meta::{DeleteStaticLogWrite, GetInferredDeletes, LogWrite};
 use crate::providers::traits::UpsertFirstLevel;
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -39,6 +40,7 @@ use crate::providers::traits::UpsertFirstLevel;
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 pub struct Invoice {
     #[primary_key]
     pub invoice_id: Option<i64>,
@@ -94,10 +96,12 @@ pub struct Invoice {
     pub collection_method: Option<String>,
     pub currency: String,
     pub custom_fields: Option<Value>,
+    #[flatten("city", "country", "line1", "line2", "postal_code", "state")]
     pub customer_address: Option<Value>,
     pub customer_email: Option<String>,
     pub customer_name: Option<String>,
     pub customer_phone: Option<String>,
+    #[flatten("name", "phone", "address.city", "address.country", "address.line1", "address.line2", "address.postal_code", "address.state")]
     pub customer_shipping: Option<Value>,
     pub customer_tax_exempt: Option<String>,
     pub customer_tax_ids: Option<Value>,
@@ -149,6 +153,10 @@ pub struct Invoice {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 
@@ -273,6 +281,7 @@ impl From<&API::Invoice> for Invoice {
             metadata: x.metadata.json_or_none(),
             insert_ts: None,
             update_ts: None,
+            raw: get_raw::<Self>(x),
         }
     }
 }
@@ -31,6 +31,7 @@ use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema::types::discount::Discount;
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::{ExistsTxSelf, UpsertFirstLevel};
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -38,6 +39,7 @@ use crate::providers::traits::{ExistsTxSelf, UpsertFirstLevel};
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 #[index("CREATE INDEX invoice ON self (invoice)")]
 #[index("CREATE INDEX subscription ON self (subscription)")]
 #[index("CREATE INDEX invoice_item ON self (invoice_item)")]
@@ -80,6 +82,10 @@ pub struct InvoiceLineItem {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 /// Note: `InvoiceItem` == `invoiceitem`
@@ -142,6 +148,7 @@ impl From<&InvoiceLineItemWithParentId<'_>> for InvoiceLineItem {
             metadata: x.metadata.json_or_none(),
             insert_ts: None,
             update_ts: None,
+            raw: get_raw::<Self>(x),
         }
     }
 }
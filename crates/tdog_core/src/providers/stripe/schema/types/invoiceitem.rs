@@ -31,6 +31,7 @@ use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema::types::discount::Discount;
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::{ExistsTxSelf, UpsertFirstLevel};
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -38,6 +39,7 @@ use crate::providers::traits::{ExistsTxSelf, UpsertFirstLevel};
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 pub struct Invoiceitem {
     #[primary_key]
     pub invoiceitem_id: Option<i64>,
@@ -89,6 +91,10 @@ pub struct Invoiceitem {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 impl GetId for Invoiceitem {
@@ -146,6 +152,7 @@ impl From<&API::InvoiceItem> for Invoiceitem {
             metadata: x.metadata.json_or_none(),
             insert_ts: None,
             update_ts: None,
+            raw: get_raw::<Self>(x),
         }
     }
 }
@@ -31,6 +31,7 @@ use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema::types::order_return::OrderReturn;
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::UpsertFirstLevel;
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -38,6 +39,7 @@ use crate::providers::traits::UpsertFirstLevel;
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 pub struct Order {
     #[primary_key]
     pub order_id: Option<i64>,
@@ -72,6 +74,10 @@ pub struct Order {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 
@@ -89,6 +95,7 @@ impl GetId for Order {
 
 impl From<&API::Order> for Order {
     fn from(x: &API::Order) -> Self {
+        let raw = get_raw::<Self>(x);
         let x = x.clone();
 
         if let Some(x2) = &x.returns {
@@ -128,6 +135,7 @@ impl From<&API::Order> for Order {
             metadata: x.metadata.json_or_none(),
             insert_ts: None,
             update_ts: None,
+            raw,
         }
     }
 }
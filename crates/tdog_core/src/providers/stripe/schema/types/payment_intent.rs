@@ -30,6 +30,7 @@ use crate::providers::stripe::schema::{ArrayPKs, f, f_opt, GetIdAny, GetIdFromEn
 use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::UpsertFirstLevel;
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -37,6 +38,7 @@ use crate::providers::traits::UpsertFirstLevel;
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 pub struct PaymentIntent {
     #[primary_key]
     pub payment_intent_id: Option<i64>,
@@ -85,6 +87,7 @@ pub struct PaymentIntent {
     pub setup_future_usage: Option<String>,
 
 
+    #[flatten("name", "phone", "address.city", "address.country", "address.line1", "address.line2", "address.postal_code", "address.state")]
     pub shipping: Option<Value>,
 
     pub statement_descriptor: Option<String>,
@@ -108,6 +111,10 @@ pub struct PaymentIntent {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 impl GetId for PaymentIntent {
@@ -159,6 +166,7 @@ impl From<&API::PaymentIntent> for PaymentIntent {
             metadata: x.metadata.json_or_none(),
             insert_ts: None,
             update_ts: None,
+            raw: get_raw::<Self>(x),
         }
     }
 }
@@ -30,6 +30,7 @@ use crate::providers::stripe::schema::{ArrayPKs, f, f_opt, json_key, json_string
 use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::UpsertFirstLevel;
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -37,6 +38,7 @@ use crate::providers::traits::UpsertFirstLevel;
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 pub struct PaymentMethod {
     #[primary_key]
     pub payment_method_id: Option<i64>,
@@ -53,6 +55,7 @@ pub struct PaymentMethod {
 
     pub bancontact: Option<Value>,
 
+    #[flatten("name", "email", "phone", "address.city", "address.country", "address.line1", "address.line2", "address.postal_code", "address.state")]
     pub billing_details: Value,
 
     pub card: Option<Value>,
@@ -90,6 +93,10 @@ pub struct PaymentMethod {
     #[update_ts]
     pub update_ts: Option<DT3>,
 
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
+
 }
 
 impl GetId for PaymentMethod {
@@ -133,6 +140,7 @@ impl From<&API::PaymentMethod> for PaymentMethod {
             metadata: i.metadata.json_or_none(),
             insert_ts: None,
             update_ts: None,
+            raw: get_raw::<Self>(i),
         }
     }
 }
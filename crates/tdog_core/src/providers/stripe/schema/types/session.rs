@@ -32,6 +32,7 @@ use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema::types::discount::Discount;
 use crate::providers::stripe::schema_meta::{LogWrite, TdStripeWrite};
 use crate::providers::traits::{ExistsTx, ExistsTxSelf, UpsertFirstLevel};
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -39,6 +40,7 @@ use crate::providers::traits::{ExistsTx, ExistsTxSelf, UpsertFirstLevel};
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 pub struct Session {
     #[primary_key]
     pub session_id: Option<i64>,
@@ -95,6 +97,10 @@ pub struct Session {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 
@@ -149,6 +155,7 @@ impl From<&API::Session> for Session {
             metadata: x2.metadata.json_or_none(),
             insert_ts: None,
             update_ts: None,
+            raw: get_raw::<Self>(x),
         }
     }
 }
@@ -30,6 +30,7 @@ use crate::providers::stripe::schema::{ArrayPKs, f, f_opt, GetIdAny, GetIdFromEn
 use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::UpsertFirstLevel;
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -37,6 +38,7 @@ use crate::providers::traits::UpsertFirstLevel;
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 pub struct Sku {
     #[primary_key]
     pub sku_id: Option<i64>,
@@ -62,6 +64,10 @@ pub struct Sku {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 
@@ -79,6 +85,7 @@ impl GetId for Sku {
 
 impl From<&API::Sku> for Sku {
     fn from(x: &API::Sku) -> Self {
+        let raw = get_raw::<Self>(x);
         let x = x.clone();
 
         Sku {
@@ -98,6 +105,7 @@ impl From<&API::Sku> for Sku {
             metadata: x.metadata.json_or_none(),
             insert_ts: None,
             update_ts: None,
+            raw,
         }
     }
 }
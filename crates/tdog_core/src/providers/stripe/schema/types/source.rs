@@ -29,6 +29,7 @@ use unicon_proc_macro::{*};
 use crate::providers::stripe::schema::{ArrayPKs, f, f_opt, json_key, json_string_or_none, ToDT, ToJSONKey, ToJSONKeyOrNone, ToVal, ToValOrNone, unix_to_iso, WriteTree, x};
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::UpsertFirstLevel;
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -36,6 +37,7 @@ use crate::providers::traits::UpsertFirstLevel;
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 pub struct Source {
     #[primary_key]
     pub source_id: Option<i64>,
@@ -63,6 +65,7 @@ pub struct Source {
     pub ideal: Option<Value>,
     pub klarna: Option<Value>,
     pub multibanco: Option<Value>,
+    #[flatten("name", "email", "phone", "address.city", "address.country", "address.line1", "address.line2", "address.postal_code", "address.state")]
     pub owner: Option<Value>,
     pub p24: Option<Value>,
     pub receiver: Option<Value>,
@@ -85,6 +88,10 @@ pub struct Source {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 
@@ -96,6 +103,7 @@ impl GetId for Source {
 
 impl From<&API::Source> for Source {
     fn from(x: &API::Source) -> Self {
+        let raw = get_raw::<Self>(x);
         let x = x.clone();
 
         Source {
@@ -136,7 +144,8 @@ impl From<&API::Source> for Source {
             livemode: x.livemode,
             metadata: x.metadata.json_or_none(),
             insert_ts: None,
-            update_ts: None
+            update_ts: None,
+            raw
         }
     }
 }
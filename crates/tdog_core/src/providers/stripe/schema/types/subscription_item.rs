@@ -30,6 +30,7 @@ use crate::providers::stripe::schema::{ArrayPKs, f, f_opt, json_key, json_string
 use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::UpsertFirstLevel;
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -37,6 +38,7 @@ use crate::providers::traits::UpsertFirstLevel;
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 #[derive(Insert)]
+#[extra_cols_prefix("md_")]
 pub struct SubscriptionItem {
     #[primary_key]
     pub subscription_item_id: Option<i64>,
@@ -65,6 +67,10 @@ pub struct SubscriptionItem {
 
     #[update_ts]
     pub update_ts: Option<DT3>,
+
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
 }
 
 impl GetId for SubscriptionItem {
@@ -89,7 +95,8 @@ impl From<&API::SubscriptionItem> for SubscriptionItem {
             created: s.created.to_dt(),
             metadata: s.metadata.json_or_none(),
             insert_ts: None,
-            update_ts: None
+            update_ts: None,
+            raw: get_raw::<Self>(s)
         }
     }
 }
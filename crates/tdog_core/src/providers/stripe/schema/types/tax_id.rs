@@ -30,6 +30,7 @@ use crate::providers::stripe::schema::{ArrayPKs, f, f_opt, GetIdAny, json_key, j
 use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema_meta::LogWrite;
 use crate::providers::traits::UpsertFirstLevel;
+use crate::providers::stripe::raw_json::get_raw;
 
 //use unicon_proc_macro::{Db, Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
@@ -59,6 +60,10 @@ pub struct TaxId {
     #[update_ts]
     pub update_ts: Option<DT3>,
 
+    #[optional_col]
+    #[serde(skip)]
+    pub raw: Option<Value>,
+
 }
 
 impl GetObjType for TaxId {
@@ -89,6 +94,7 @@ impl From<&API::TaxId> for TaxId {
             livemode: x.livemode,
             insert_ts: None,
             update_ts: None,
+            raw: get_raw::<Self>(x),
         }
     }
 }
@@ -0,0 +1,221 @@
+use std::str::FromStr;
+
+use serde_json::{json, Value};
+use unicon::table::{RustType, Table};
+use unicon::traits::{*};
+
+use crate::error::TdError;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::relations::{Edge, EdgeGrp, get_edges, RelType};
+
+
+// Schema graph (`tdog schema graph`, `tdog schema dbt`): the `Db` tables and the relations in `schema/relations.rs`, for documentation and BI tooling.
+// - `Or` groups are polymorphic: the copy column holds an id of one of several owner tables (E.g. `customers.default_source`).
+// - `JSONArray` edges are a JSON array of owner ids in the copy column (E.g. `subscription_items.tax_rates`).
+// - `td_*` tables are not included.
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphFormat {
+    Mermaid,
+    Dot,
+    Json,
+}
+
+impl FromStr for GraphFormat {
+    type Err = TdError;
+
+    fn from_str(x: &str) -> Result<GraphFormat, TdError> {
+        match x {
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            "dot" => Ok(GraphFormat::Dot),
+            "json" => Ok(GraphFormat::Json),
+            _ => Err(TdError::Config(format!("Unknown `--format` `{}`. Use `mermaid`, `dot` or `json`.", x)))
+        }
+    }
+}
+
+
+fn get_tables() -> Vec<&'static Table> {
+    Db::get_tables().into_iter().filter(|t| !t.name.starts_with("td_")).collect()
+}
+
+// (name, type, `PK`/`UK`) of every column in the DB, including generated (`flatten`) columns.
+fn get_cols(t: &Table) -> Vec<(String, &'static str, Option<&'static str>)> {
+    let mut o = vec![];
+    for c in t.cols_not_skipped() {
+        let key = if c.primary {
+            Some("PK")
+        } else if c.unique {
+            Some("UK")
+        } else {
+            None
+        };
+
+        o.push((c.name.clone(), get_type_name(&c.t), key));
+        for (name, _) in c.get_flat_cols() {
+            o.push((name, "string", None));
+        }
+    }
+    o
+}
+
+// Engine independent type names.
+fn get_type_name(t: &RustType) -> &'static str {
+    match t {
+        RustType::String => "string",
+        RustType::I8 | RustType::I16 | RustType::I32 | RustType::I64 | RustType::U8 | RustType::U16 | RustType::U32 | RustType::U64 => "integer",
+        RustType::F32 | RustType::F64 => "float",
+        RustType::Bool => "boolean",
+        RustType::Value => "json",
+        RustType::DT3 | RustType::DT => "timestamp",
+    }
+}
+
+// E.g. `customer`, `tax_rates (JSON array)`, `default_source (polymorphic)`.
+fn get_label(g: &EdgeGrp, e: &Edge) -> String {
+    let mut x = e.copy_col.clone();
+    if e.rel_type == RelType::JSONArray {
+        x.push_str(" (JSON array)");
+    }
+    if let EdgeGrp::Or(_) = g {
+        x.push_str(" (polymorphic)");
+    }
+    x
+}
+
+
+pub fn get_graph(format: GraphFormat) -> String {
+    match format {
+        GraphFormat::Mermaid => get_mermaid(),
+        GraphFormat::Dot => get_dot(),
+        GraphFormat::Json => serde_json::to_string_pretty(&get_json()).unwrap(),
+    }
+}
+
+// Cardinality: a copy row references zero or one owner (`||` when the API keeps the owner row existing, `enforced_by_api`), or many owners for JSON arrays.
+// - Dotted lines are JSON arrays and polymorphic ids, which cannot be joined with a single equality.
+fn get_mermaid() -> String {
+    let mut o = vec!["erDiagram".to_string()];
+
+    for t in get_tables() {
+        o.push(format!("    {} {{", &t.name));
+        for (name, ty, key) in get_cols(t) {
+            o.push(format!("        {} {}{}", ty, name, key.map(|x| format!(" {}", x)).unwrap_or_default()));
+        }
+        o.push("    }".to_string());
+    }
+
+    for g in get_edges() {
+        for e in g.get_edges() {
+            let is_direct = e.rel_type == RelType::Normal && matches!(g, EdgeGrp::Single(_));
+            let owner = match (&e.rel_type, is_direct && e.enforced_by_api) {
+                (RelType::JSONArray, _) => "o{",
+                (_, true) => "||",
+                (_, false) => "o|",
+            };
+            let line = if is_direct { "--" } else { ".." };
+            o.push(format!("    {} }}o{}{} {} : \"{}\"", &e.copy_tbl, line, owner, &e.owner_tbl, get_label(&g, e)));
+        }
+    }
+
+    o.join("\n") + "\n"
+}
+
+// Edges are from the copy column to the owner `id`; dashed when not enforced by the API.
+fn get_dot() -> String {
+    let mut o = vec![
+        "digraph stripe {".to_string(),
+        "    rankdir=LR;".to_string(),
+        "    node [shape=record, fontname=\"Helvetica\"];".to_string(),
+    ];
+
+    for t in get_tables() {
+        let cols: Vec<String> = get_cols(t).into_iter().map(|(name, ty, _)| format!("<{}> {}: {}\\l", &name, &name, ty)).collect();
+        o.push(format!("    {} [label=\"{{{}|{}}}\"];", &t.name, &t.name, cols.join("|")));
+    }
+
+    for g in get_edges() {
+        for e in g.get_edges() {
+            let style = if e.enforced_by_api { "solid" } else { "dashed" };
+            o.push(format!("    {}:{} -> {}:id [label=\"{}\", style={}];", &e.copy_tbl, &e.copy_col, &e.owner_tbl, get_label(&g, e), style));
+        }
+    }
+
+    o.push("}".to_string());
+    o.join("\n") + "\n"
+}
+
+fn get_json() -> Value {
+    let tables: Vec<Value> = get_tables().into_iter().map(|t| json!({
+        "name": &t.name,
+        "columns": get_cols(t).into_iter().map(|(name, ty, _)| json!({"name": name, "type": ty})).collect::<Vec<Value>>(),
+    })).collect();
+
+    let edges: Vec<Value> = get_edges().into_iter().map(|g| {
+        let e = g.get_edges()[0].clone();
+        json!({
+            "copy_table": e.copy_tbl,
+            "copy_column": e.copy_col,
+            "owner_tables": g.get_edges().iter().map(|x| x.owner_tbl.clone()).collect::<Vec<String>>(),
+            "owner_column": "id",
+            "json_array": e.rel_type == RelType::JSONArray,
+            "polymorphic": matches!(g, EdgeGrp::Or(_)),
+            "enforced_by_api": e.enforced_by_api,
+        })
+    }).collect();
+
+    json!({
+        "tables": tables,
+        "edges": edges,
+    })
+}
+
+
+// A dbt `sources.yml` for the tables (`schema` defaults to the source name `stripe`).
+// - `unique` tests on `id` columns.
+// - `relationships` tests for single id columns that the API keeps consistent (`enforced_by_api`); these do not hold for a DB created with `options.created_gte`.
+pub fn get_dbt_sources(schema: Option<&str>) -> String {
+    let edges: Vec<Edge> = get_edges().into_iter().filter_map(|g| match g {
+        EdgeGrp::Single(e) if e.rel_type == RelType::Normal && e.enforced_by_api => Some(e),
+        _ => None
+    }).collect();
+
+    let mut o = vec![
+        "version: 2".to_string(),
+        "".to_string(),
+        "sources:".to_string(),
+        "  - name: stripe".to_string(),
+    ];
+    if let Some(x) = schema {
+        o.push(format!("    schema: {}", x));
+    }
+    o.push("    tables:".to_string());
+
+    for t in get_tables() {
+        o.push(format!("      - name: {}", &t.name));
+        o.push("        columns:".to_string());
+
+        for (name, _, key) in get_cols(t) {
+            let is_id = name == "id" && key == Some("UK");
+            o.push(format!("          - name: {}", &name));
+
+            let refs: Vec<&Edge> = edges.iter().filter(|e| e.copy_tbl == t.name && e.copy_col == name).collect();
+            if !is_id && refs.is_empty() {
+                continue;
+            }
+
+            o.push("            tests:".to_string());
+            if is_id {
+                o.push("              - unique".to_string());
+            }
+            for e in refs {
+                o.push("              - relationships:".to_string());
+                o.push(format!("                  to: source('stripe', '{}')", &e.owner_tbl));
+                o.push("                  field: id".to_string());
+            }
+        }
+    }
+
+    o.join("\n") + "\n"
+}
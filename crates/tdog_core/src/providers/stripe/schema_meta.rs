@@ -1,6 +1,6 @@
 //use unicon_proc_macro::{Insert, Table, SQLiteString, SQLiteStringSchema, SQLiteFuncRusqlite};
 //use unicon_proc_macro::{PlaceholderString, PlaceholderFuncStd};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 //use unicon::dt3::DT3;
 use std::hash::BuildHasherDefault;
 
@@ -22,6 +22,7 @@ use unicon::engines::mysql::{*};
 use unicon::engines::postgres::{*};
 use unicon::engines::placeholder::{*};
 use unicon::engines::sqlite::{*};
+use unicon::params::ExtraVal;
 use unicon::table::{*};
 use unicon::traits::{*};
 use unicon::uc::{*};
@@ -30,6 +31,14 @@ use unicon_proc_macro::{*};
 
 use crate::fns::{get_utc_dt, get_utc_dt_from_3ms};
 use crate::providers::stripe::schema::types::GetObjType;
+use crate::providers::stripe::history;
+use crate::providers::stripe::cdc;
+use crate::providers::stripe::dry_run;
+use crate::providers::stripe::metadata_cols::{GetMetadata, MetadataCols};
+use crate::providers::stripe::raw_json;
+use crate::providers::stripe::raw_json::GetRaw;
+use crate::providers::stripe::write_ctx::{is_dry_run, with_extra_table};
+use crate::error::TdError;
 use stripe_client::http::http::{StripeClient, StripeAccount};
 
 
@@ -57,6 +66,10 @@ pub struct TdMetadata {
     // Timestamp of last_run, regardless if any events were processed.
     // - Allows distinguishing if no new events means (A. td process is not running OR B. no new events).
     pub heartbeat_ts: Option<DT3>,
+
+    // `options.created_gte` resolved at DB creation: objects created before this were not downloaded.
+    // - Rows may reference owners that are older than this (which are either missing or were downloaded individually).
+    pub created_gte: Option<DT>,
 }
 
 
@@ -94,19 +107,41 @@ impl TdMetadata {
         }
     }
 
-    pub fn check_cli_and_stripe_versions_match(uc: &mut UniCon, sa: &StripeAccount) -> TdMetadata {
+    pub fn check_cli_and_stripe_versions_match(uc: &mut UniCon, sa: &StripeAccount) -> Result<TdMetadata, TdError> {
+        let x = Self::check_versions_match(uc)?;
+
+        // Stripe account must match the one previously used.
+        // - One DB = one Stripe account = one dataset.
+        // - Note: One account ID can have both test and prod data sets.
+        // - One account can have many different secret keys.
+        if x.stripe_account_id != sa.id {
+            error!("Stripe account ID does not match the one previously used. DB has {}, trying to use {}.", x.stripe_account_id, sa.id);
+            return Err(TdError::SchemaConflict(format!("DB Stripe account {} does not match {}.", x.stripe_account_id, sa.id)));
+        }
+
+        if x.stripe_is_test != sa.is_test {
+            error!("Stripe is_test does not match; cannot mix live and test data. DB has {}, trying to use {}.", x.stripe_is_test, sa.is_test);
+            return Err(TdError::SchemaConflict(format!("DB Stripe is_test={} does not match is_test={}.", x.stripe_is_test, sa.is_test)));
+        }
+
+        Ok(x)
+    }
+
+    // CLI and Stripe API versions only (`replay` may not have a Stripe key to read the account).
+    pub fn check_versions_match(uc: &mut UniCon) -> Result<TdMetadata, TdError> {
         let x: TdMetadata = Self::get_last(uc, "id").expect("Metadata should be created with the DB schema.");
         assert_eq!(x.id.unwrap(), 1);
         let (cli, stripe) = get_versions();
 
+        // A download or `replay` migrates DBs written by an older CLI version with the same Stripe version (see `migrations.rs`).
         let to_use_newer_msg = || {
-            error!("To use the newer CLI version, backup and drop the existing database so it can be re-created (incrementally migrating the SQL DB schema is not currently supported).");
+            error!("To use this CLI version with a DB written by an older one, run a download or `replay` to migrate it. DBs with a different Stripe version, or written by a newer CLI version, cannot be migrated: backup and drop the existing database so it can be re-created.");
         };
 
         if stripe != x.stripe_version {
             error!("The Stripe version this CLI uses ({}) does not match the one that originally wrote the db ({}).", stripe, x.stripe_version);
             to_use_newer_msg();
-            panic!();
+            return Err(TdError::VersionMismatch(format!("CLI Stripe version {} does not match DB Stripe version {}.", stripe, x.stripe_version)));
         }
 
         let a = get_semver_ints(cli);
@@ -122,28 +157,13 @@ impl TdMetadata {
         if !ok {
             error!("This CLI version ({}) does not match the one that originally wrote the db ({}).", cli, x.cli_version);
             to_use_newer_msg();
-            panic!();
+            return Err(TdError::VersionMismatch(format!("CLI version {} does not match DB CLI version {}.", cli, x.cli_version)));
         }
 
-
-        // Stripe account must match the one previously used.
-        // - One DB = one Stripe account = one dataset.
-        // - Note: One account ID can have both test and prod data sets.
-        // - One account can have many different secret keys.
-        if x.stripe_account_id != sa.id {
-            error!("Stripe account ID does not match the one previously used. DB has {}, trying to use {}.", x.stripe_account_id, sa.id);
-            panic!();
-        }
-
-        if x.stripe_is_test != sa.is_test {
-            error!("Stripe is_test does not match; cannot mix live and test data. DB has {}, trying to use {}.", x.stripe_is_test, sa.is_test);
-            panic!();
-        }
-
-        x
+        Ok(x)
     }
 
-    pub fn insert_cli_and_stripe_versions(uc: &mut UniCon, sa: &StripeAccount) {
+    pub fn insert_cli_and_stripe_versions(uc: &mut UniCon, sa: &StripeAccount, created_gte: Option<i64>) {
         let mut utx = uc.tx_open().unwrap();
         let (cli, stripe) = get_versions();
 
@@ -155,12 +175,18 @@ impl TdMetadata {
             stripe_account: sa.account.clone().into(),
             stripe_is_test: sa.is_test,
             heartbeat_ts: None,
+            created_gte: created_gte.map(|x| Utc.timestamp(x, 0).into()),
         };
 
         assert_eq!(row_1.tx_insert_set_pk(&mut utx), 1, "Expecting a single meta data row, found more than one.");
         utx.tx_close();
     }
 
+    // None = the full account was downloaded.
+    pub fn get_created_gte(&self) -> Option<i64> {
+        self.created_gte.as_ref().map(|x| x.dt.timestamp())
+    }
+
     // Update the Stripe account JSON on start up (in case end users want to read those settings).
     pub fn update_stripe_account(&mut self, uc: &mut UniCon, sa: &StripeAccount) {
         assert_eq!(sa.id, self.stripe_account_id);
@@ -181,7 +207,7 @@ fn get_versions() -> (&'static str, &'static str) {
 // Gets the lib-app crate Cargo.toml version.
 // Cannot use `cli` crate as Cargo does not allow cycling dependencies (cli <-> lib_app)
 // const VERSION: &'static str = env!("CARGO_PKG_VERSION");
-const VERSION: &'static str = "0.7.0";
+const VERSION: &str = "0.8.0";
 
 pub fn get_cli_version() -> &'static str {
     VERSION
@@ -204,12 +230,12 @@ pub struct TdRun {
 impl TdRun {
     /// Returns `None` when there are no rows (freshly created db; first run).
     /// This is either 28 days since the last run (either the first `download` or last `apply_events`).
-    pub fn is_apply_events_possible(mut uc: &mut UniCon) -> Option<bool> {
-        let row: TdRun = Self::get_last(&mut uc, "end_ts")?;
+    pub fn is_apply_events_possible(uc: &mut UniCon) -> Option<bool> {
+        let row = Self::get_last_run(uc)?;
 
         let now = Utc::now().naive_utc();
         let end: NaiveDateTime = row.end_ts.unwrap().into();
-        let days = end.signed_duration_since(now).num_days();
+        let days = now.signed_duration_since(end).num_days();
 
         // Stripes API only returns events that are less than 30 days old.
         (days < 28).into()
@@ -217,13 +243,16 @@ impl TdRun {
 
     /// The first run is always a full download (as Stripe events are only stored for 30 days), subsequent runs are incremental `apply_events`.
     pub fn get_last_run_tx(utx: &mut UniTx) -> Option<Self> {
-        let row: TdRun = Self::tx_get_last(utx, "end_ts")?;
-        row.into()
+        utx.get_vec_from_sql(&Self::get_last_run_sql()).into_iter().next()
     }
 
     pub fn get_last_run(uc: &mut UniCon) -> Option<Self> {
-        let row: TdRun = Self::get_last(uc, "end_ts")?;
-        row.into()
+        uc.get_vec_from_sql(&Self::get_last_run_sql()).into_iter().next()
+    }
+
+    // `repair` runs (`check --repair`) download single objects, not events: they do not restart the 30 day event window or end the first download.
+    fn get_last_run_sql() -> String {
+        format!("SELECT * FROM {} WHERE type IN ('download', 'apply_events') ORDER BY end_ts DESC LIMIT 1", Self::get_table_name_static())
     }
 
     pub fn is_download(&self) -> bool {
@@ -373,6 +402,59 @@ pub struct TdStripeApplyEvent {
     pub insert_ts: Option<DT3>,
 }
 
+// Owners that were downloaded individually (`options.created_gte`, `tdog check --repair`), but Stripe returned as deleted or missing.
+// - These are the only owners of enforced relations that the relation checks allow to be missing.
+#[derive(Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq)]
+#[derive(Insert)]
+pub struct TdMissingOwner {
+    #[primary_key]
+    pub missing_owner_id: Option<i64>,
+
+    pub run_id: i64,
+
+    // Owner table and id, E.g. (`customers`, `cus_123`).
+    pub table_name: String,
+    pub obj_id: String,
+
+    #[insert_ts]
+    pub insert_ts: Option<DT3>,
+}
+
+impl TdMissingOwner {
+    // `(table_name, obj_id)`
+    pub fn tx_get_keys(utx: &mut UniTx) -> HashSet<(String, String)> {
+        Self::tx_get_all(utx).into_iter().map(|x| (x.table_name, x.obj_id)).collect()
+    }
+
+    pub fn get_keys(uc: &mut UniCon) -> HashSet<(String, String)> {
+        Self::get_all(uc).into_iter().map(|x| (x.table_name, x.obj_id)).collect()
+    }
+}
+
+
+// The last `offset` (`write_id`) written to each `options.cdc` output, so a restarted process continues after it (see `cdc.rs`).
+#[derive(Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq)]
+#[derive(Insert)]
+#[table_name_plural(false)]
+pub struct TdCdc {
+    #[primary_key]
+    pub cdc_id: Option<i64>,
+
+    // E.g. `file:/path/cdc.jsonl`, `stdout`.
+    #[unique]
+    pub output: String,
+
+    pub last_offset: i64,
+
+    #[insert_ts]
+    pub insert_ts: Option<DT3>,
+
+    #[update_ts]
+    pub update_ts: Option<DT3>,
+}
+
 #[derive(Serialize, Deserialize)]
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct ResActionsTaken {
@@ -444,12 +526,28 @@ lazy_static! {
     static ref HIGH_LATENCY_WARN: Mutex<bool> = Mutex::new(false);
 }
 
-pub trait LogWrite: Insert + TableStatic + GetObjType + GetId {
+// `store_raw_json` and `metadata_columns` add columns to the `Insert` tables; their values are written in the same statement as the row.
+fn get_extra_vals<T: GetRaw + GetMetadata>(row: &T, t: &Table, md: &MetadataCols) -> Vec<ExtraVal> {
+    let mut x = vec![];
+    if raw_json::has_raw_col(t) {
+        x.push(ExtraVal::Json(row.get_raw().cloned()));
+    }
+    x.extend(md.get_vals(&t.name, row.get_metadata()));
+    x
+}
+
+pub trait LogWrite: Insert + TableStatic + GetObjType + GetId + GetRaw + GetMetadata + Serialize + QueryByStatic + Sized {
     fn tx_insert_set_pk_log_write(&mut self, utx: &mut UniTx, run_id: i64) -> i64 {
         let now = Instant::now();
         // thread::sleep(Duration::from_millis(30));
 
-        self.tx_insert_set_pk(utx);
+        with_extra_table(Self::get_table_name_static(), |x| match x {
+            None => self.tx_insert_set_pk(utx),
+            Some((t, md)) => {
+                let extra = get_extra_vals(self, t, md);
+                self.tx_insert_set_pk_with(utx, t, &extra)
+            }
+        });
 
         let mut write = TdStripeWrite {
             write_id: None,
@@ -461,7 +559,9 @@ pub trait LogWrite: Insert + TableStatic + GetObjType + GetId {
             insert_ts: None,
         };
 
+        history::tx_write_version(utx, &write);
         let x = write.tx_insert_set_pk(utx);
+        cdc::push(&write, || serde_json::to_value(&self).ok());
 
         let elapsed = now.elapsed();
         let micros_per_insert = (elapsed.as_micros() / 2);
@@ -481,7 +581,18 @@ pub trait LogWrite: Insert + TableStatic + GetObjType + GetId {
         let now = Instant::now();
         // thread::sleep(Duration::from_millis(30));
 
-        assert_eq!(self.tx_update(utx, &w), 1);
+        let before = if is_dry_run() { Self::get_where_tx(utx, (w, &self.get_id())).pop() } else { None };
+
+        let changes = with_extra_table(Self::get_table_name_static(), |x| match x {
+            None => self.tx_update(utx, w),
+            Some((t, md)) => self.tx_update_with(utx, t, w, &get_extra_vals(self, t, md))
+        });
+        assert_eq!(changes, 1);
+
+        if let Some(before) = before {
+            let after = Self::get_where_tx(utx, (w, &self.get_id())).pop().unwrap();
+            dry_run::push_update(Self::get_table_name_static(), &self.get_id(), &before, &after);
+        }
 
         let mut write = TdStripeWrite {
             write_id: None,
@@ -493,7 +604,9 @@ pub trait LogWrite: Insert + TableStatic + GetObjType + GetId {
             insert_ts: None,
         };
 
+        history::tx_write_version(utx, &write);
         let x = write.tx_insert_set_pk(utx);
+        cdc::push(&write, || serde_json::to_value(&self).ok());
 
         debug!("tx_update_log_write, elapsed: {}μs, {}", now.elapsed().as_micros(), self.get_id());
         x
@@ -516,7 +629,9 @@ pub trait LogWrite: Insert + TableStatic + GetObjType + GetId {
             insert_ts: None,
         };
 
+        history::tx_write_version(utx, &write);
         let x = write.tx_insert_set_pk(utx);
+        cdc::push(&write, || None);
 
         debug!("tx_delete_log_write, elapsed: {}μs, {}", now.elapsed().as_micros(), self.get_id());
         x
@@ -568,7 +683,10 @@ pub trait DeleteStaticLogWrite: GetObjType + DeleteStatic {
             insert_ts: None,
         };
 
-        write.tx_insert_set_pk(utx)
+        history::tx_write_version(utx, &write);
+        let x = write.tx_insert_set_pk(utx);
+        cdc::push(&write, || None);
+        x
     }
 }
 
@@ -579,7 +697,7 @@ impl<T> DeleteStaticLogWrite for T where T: GetObjType + DeleteStatic {}
 
 /// Implement this for all "Rust struct rows".
 /// - Any SQL writes are logged when applied via `tx_insert_set_pk_log_write`.
-impl<T> LogWrite for T where T: Insert + TableStatic + GetObjType + GetId {}
+impl<T> LogWrite for T where T: Insert + TableStatic + GetObjType + GetId + GetRaw + GetMetadata + Serialize + QueryByStatic {}
 
 
 pub trait GetInferredDeletes: TableStatic {
@@ -0,0 +1,359 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryInto;
+
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use serde_json::{json, Value};
+use stripe_client::types::types as API;
+use stripe_client::types::types::GetId;
+use unicon::traits::{*};
+use unicon::uc::{*};
+use unicon::utx::UniTx;
+
+use crate::Snapshot;
+use crate::error::TdError;
+use crate::fns::now_3;
+use crate::providers::stripe::apply_events::apply_events_body;
+use crate::providers::stripe::event_archive;
+use crate::providers::stripe::history::{get_history_table_name, get_history_tables};
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::{*};
+use crate::providers::stripe::schema::types::balance_transaction::BalanceTransaction;
+use crate::providers::stripe::schema::types::bank_account::BankAccount;
+use crate::providers::stripe::schema::types::card::Card;
+use crate::providers::stripe::schema::types::coupon::Coupon;
+use crate::providers::stripe::schema::types::credit_note::CreditNote;
+use crate::providers::stripe::schema::types::credit_note_line_item::CreditNoteLineItem;
+use crate::providers::stripe::schema::types::discount::Discount;
+use crate::providers::stripe::schema::types::dispute::Dispute;
+use crate::providers::stripe::schema::types::invoice::Invoice;
+use crate::providers::stripe::schema::types::invoice_line_item::InvoiceLineItem;
+use crate::providers::stripe::schema::types::invoiceitem::Invoiceitem;
+use crate::providers::stripe::schema::types::order::Order;
+use crate::providers::stripe::schema::types::order_return::OrderReturn;
+use crate::providers::stripe::schema::types::promotion::PromotionCode;
+use crate::providers::stripe::schema::types::refund::Refund;
+use crate::providers::stripe::schema::types::setup_intent::SetupIntent;
+use crate::providers::stripe::schema::types::sku::Sku;
+use crate::providers::stripe::schema::types::tax_id::TaxId;
+use crate::providers::stripe::schema_meta::{LogWrite, TdMetadata, TdRun, TdStripeApplyEvent, TdStripeWrite};
+use crate::util::parse_created_gte;
+
+
+// `snapshot`: build a new DB with the state of the account at `as_of` (E.g. to reproduce month end numbers).
+// - The new DB starts with the rows of the first download, then the stored events created up to `as_of` are applied with the same code as polling (`apply_events_body`).
+// - Download state:
+//      - With history tables (`options.history`): the versions written by the download, so any `as_of` after the download works.
+//      - Without: the current rows that no event has written since. Rows that were downloaded and only written by events after `as_of` have lost their download state, which is an error.
+// - Every applied event must be stored: in `notification_events`, or in an `events_export` directory (`events_dir`) when `retention_days` deleted it from the DB.
+// - The Stripe API is not used, so DBs created with `options.created_gte` are not supported (owners are downloaded while applying events).
+
+
+// Events per `td_runs` row and tx in the new DB.
+const BATCH_SIZE: usize = 1_000;
+
+// Stored events read from the DB per query.
+const PAGE_SIZE: i64 = 1_000;
+
+// (table_name, obj_id)
+type RowKey = (String, String);
+
+enum Seed {
+    // `run_id` of the download.
+    History(i64),
+
+    // Rows written by events since the download.
+    Unchanged(HashSet<RowKey>),
+}
+
+pub fn get_as_of(x: &str) -> Result<NaiveDateTime, TdError> {
+    let ts = parse_created_gte(x, Utc::now()).ok_or_else(|| TdError::Config(format!("Invalid `as_of` `{}`: expected a date (`2021-01-31`), an RFC 3339 date time (`2021-01-31T23:59:59Z`) or a duration before now (`30days`).", x)))?;
+    Ok(Utc.timestamp(ts, 0).naive_utc())
+}
+
+// `notification_events` row => Stripe event.
+fn to_event(x: &NotificationEvent) -> Result<API::NotificationEvent, TdError> {
+    let v = json!({
+        "object": "event",
+        "id": x.id,
+        "type": x.r#type,
+        "account": x.account,
+        "api_version": x.api_version,
+        "data": {
+            "object": x.data_object,
+            "previous_attributes": x.data_previous_attributes
+        },
+        "pending_webhooks": x.pending_webhooks,
+        "request": {
+            "id": x.request_id,
+            "idempotency_key": x.request_idempotency_key
+        },
+        "created": x.created.dt.timestamp(),
+        "livemode": x.livemode
+    });
+
+    serde_json::from_value(v).map_err(|e| TdError::Internal(format!("Could not read stored event `{}`: {}", x.id, e)))
+}
+
+// Rows of `notification_events` in `created` order, a page at a time.
+struct DbEvents {
+    offset: i64,
+    page: VecDeque<NotificationEvent>,
+}
+
+impl DbEvents {
+    fn next(&mut self, uc: &mut UniCon) -> Option<NotificationEvent> {
+        if self.page.is_empty() {
+            let sql = format!("SELECT * FROM {} ORDER BY created ASC, event_id ASC LIMIT {} OFFSET {}", NotificationEvent::get_table_name_static(), PAGE_SIZE, self.offset);
+            self.page = uc.get_vec_from_sql::<NotificationEvent>(&sql).into();
+            self.offset += self.page.len() as i64;
+        }
+
+        self.page.pop_front()
+    }
+}
+
+// Calls `f` with each stored event (once per Stripe id) in `created` order, reading one archive segment or DB page at a time.
+// - Archived rows come first (they were exported in insert order), then the rows still in the DB; events in the same second keep that order.
+// - Segments are one UTC day each, so sorting each segment sorts the archive.
+fn for_each_stored_event(uc: &mut UniCon, x: &Snapshot, md: &TdMetadata, mut f: impl FnMut(NotificationEvent) -> Result<(), TdError>) -> Result<(), TdError> {
+    let mut seen = HashSet::new();
+    let mut db = DbEvents { offset: 0, page: VecDeque::new() };
+    let mut db_next = db.next(uc);
+
+    if let Some(dir) = &x.events_dir {
+        for rows in event_archive::read_segments(dir, &md.stripe_account_id)? {
+            let mut rows = rows?;
+            rows.sort_by_key(|r| r.created.dt);
+
+            for r in rows {
+                while db_next.as_ref().map(|d| d.created.dt < r.created.dt).unwrap_or(false) {
+                    let d = db_next.take().unwrap();
+                    if seen.insert(d.id.clone()) {
+                        f(d)?;
+                    }
+                    db_next = db.next(uc);
+                }
+
+                if seen.insert(r.id.clone()) {
+                    f(r)?;
+                }
+            }
+        }
+    }
+
+    while let Some(d) = db_next {
+        if seen.insert(d.id.clone()) {
+            f(d)?;
+        }
+        db_next = db.next(uc);
+    }
+
+    Ok(())
+}
+
+fn get_write_ids(x: &TdStripeApplyEvent) -> Vec<i64> {
+    match &x.write_ids {
+        Some(Value::Array(ids)) => ids.iter().filter_map(|id| id.as_i64()).collect(),
+        _ => vec![]
+    }
+}
+
+// Rows written by events since the download; errors when a downloaded row has only been written after `as_of`.
+fn get_unchanged_seed(uc: &mut UniCon, dl_run_id: i64, applied: &[TdStripeApplyEvent], created: &HashMap<String, NaiveDateTime>, as_of: &NaiveDateTime) -> Result<Seed, TdError> {
+    let key = |w: &TdStripeWrite| (w.table_name.clone(), w.obj_id.clone());
+
+    let downloaded: HashSet<RowKey> = uc.get_vec_from_sql::<TdStripeWrite>(&format!("SELECT * FROM td_stripe_writes WHERE run_id = {}", dl_run_id)).iter().map(key).collect();
+    let writes: HashMap<i64, RowKey> = uc.get_vec_from_sql::<TdStripeWrite>(&format!("SELECT * FROM td_stripe_writes WHERE run_id > {}", dl_run_id)).iter().map(|w| (w.write_id.unwrap(), key(w))).collect();
+
+    let (mut before, mut after) = (HashSet::new(), HashSet::new());
+    for x in applied {
+        let set = if &created[&x.event_id] <= as_of { &mut before } else { &mut after };
+        for id in get_write_ids(x) {
+            if let Some(k) = writes.get(&id) {
+                set.insert(k.clone());
+            }
+        }
+    }
+
+    let mut lost: Vec<&RowKey> = after.iter().filter(|k| downloaded.contains(*k) && !before.contains(*k)).collect();
+    if !lost.is_empty() {
+        lost.sort();
+        let eg: Vec<String> = lost.iter().take(5).map(|(t, id)| format!("{}.{}", t, id)).collect();
+        return Err(TdError::SchemaConflict(format!(
+            "{} downloaded rows were first changed by events after `as_of`, so their state at `as_of` is not stored (E.g. {}). Create the DB with `options.history=true` to snapshot any time after the download.",
+            lost.len(), eg.join(", ")
+        )));
+    }
+
+    Ok(Seed::Unchanged(before.into_iter().chain(after).collect()))
+}
+
+fn seed_table<T: LogWrite>(src: &mut UniCon, utx: &mut UniTx, run_id: i64, seed: &Seed) -> usize {
+    let t = <T as TableStatic>::get_table();
+
+    let rows: Vec<T> = match seed {
+        Seed::History(dl_run_id) => {
+            let cols = t.cols_not_skipped().iter().map(|c| c.name.clone()).collect::<Vec<String>>().join(", ");
+            let sql = format!("SELECT {} FROM {} WHERE run_id = {} ORDER BY history_id ASC", cols, get_history_table_name(&t.name), dl_run_id);
+
+            // A row listed twice during the download has many versions; keep the last.
+            let mut rows: Vec<T> = vec![];
+            let mut index: HashMap<String, usize> = HashMap::new();
+            for x in src.get_vec_from_sql::<T>(&sql) {
+                match index.get(&x.get_id()) {
+                    Some(i) => rows[*i] = x,
+                    None => {
+                        index.insert(x.get_id(), rows.len());
+                        rows.push(x);
+                    }
+                }
+            }
+            rows
+        }
+        Seed::Unchanged(written) => {
+            T::get_all(src).into_iter().filter(|x| !written.contains(&(t.name.clone(), x.get_id()))).collect()
+        }
+    };
+
+    let n = rows.len();
+    for mut x in rows {
+        x.tx_insert_set_pk_log_write(utx, run_id);
+    }
+    n
+}
+
+// Copies the download state of every included table; returns the number of rows.
+fn seed_all(src: &mut UniCon, utx: &mut UniTx, run_id: i64, seed: &Seed, objects: &ObjectSelection) -> usize {
+    let mut n = 0;
+
+    macro_rules! seed {
+        ($($t:ty),*) => {
+            $(
+                if objects.is_included(<$t>::get_table_name_static()) {
+                    n += seed_table::<$t>(src, utx, run_id, seed);
+                }
+            )*
+        }
+    }
+
+    seed!(
+        BalanceTransaction, BankAccount, Card, Charge, Coupon, CreditNote, CreditNoteLineItem, Customer, Discount, Dispute,
+        Invoice, Invoiceitem, InvoiceLineItem, Order, OrderReturn, PaymentMethod, PaymentIntent, Price, Product, PromotionCode,
+        Refund, SetupIntent, Sku, Source, Subscription, SubscriptionItem, SubscriptionSchedule, TaxId, TaxRate
+    );
+
+    n
+}
+
+fn has_history_tables(uc: &mut UniCon, objects: &ObjectSelection) -> Result<bool, TdError> {
+    let mut utx = uc.tx_open().map_err(|e| TdError::Db(e.to_string()))?;
+    let existing = utx.get_tables();
+    utx.tx_close();
+
+    Ok(get_history_tables(objects).iter().any(|x| existing.contains(&x.name)))
+}
+
+// Returns the number of applied events.
+pub fn snapshot(src: &mut UniCon, dst: &mut UniCon, x: &Snapshot) -> Result<usize, TdError> {
+    let as_of = get_as_of(&x.as_of)?;
+    let objects: ObjectSelection = (&x.options.objects).try_into()?;
+
+    let history = has_history_tables(src, &objects)?;
+    let history_tables = if history { get_history_tables(&objects) } else { vec![] };
+    if !src.schema_exists_with::<Db>(&objects.get_excluded(), &history_tables).map_err(TdError::SchemaConflict)? {
+        return Err(TdError::SchemaConflict("`db` has no tables.".into()));
+    }
+
+    let md = TdMetadata::check_versions_match(src)?;
+    if md.get_created_gte().is_some() {
+        return Err(TdError::Config("`db` was created with `options.created_gte`, which `snapshot` does not support.".into()));
+    }
+
+    let dl: TdRun = TdRun::get_all(src).into_iter().next().filter(|r| r.is_download() && r.end_ts.is_some())
+        .ok_or_else(|| TdError::SchemaConflict("`db` has no completed download.".into()))?;
+    let dl_end: NaiveDateTime = dl.end_ts.clone().unwrap().into();
+    if as_of < dl_end {
+        return Err(TdError::Config(format!("`as_of` ({}) is before the first download completed ({}); the earliest snapshot is at the end of the download.", as_of, dl_end)));
+    }
+
+    match dst.schema_exists_with::<Db>(&objects.get_excluded(), &vec![]) {
+        Ok(false) => {}
+        _ => return Err(TdError::SchemaConflict("`to` must be an empty DB.".into()))
+    }
+
+    // First pass: only the `created` of each event is kept.
+    let mut created: HashMap<String, NaiveDateTime> = HashMap::new();
+    for_each_stored_event(src, x, &md, |r| {
+        created.insert(r.id, r.created.dt);
+        Ok(())
+    })?;
+
+    let applied = TdStripeApplyEvent::get_all(src);
+    if let Some(e) = applied.iter().find(|e| !created.contains_key(&e.event_id)) {
+        return Err(TdError::Config(format!("Applied event `{}` is not in `notification_events`; set `events_dir` to the `events_export` directory.", e.event_id)));
+    }
+
+    let dl_run_id = dl.run_id.unwrap();
+    let seed = if history { Seed::History(dl_run_id) } else { get_unchanged_seed(src, dl_run_id, &applied, &created, &as_of)? };
+
+    dst.ensure_schema_and_tables_exist_and_writable_with::<Db>(&objects.get_excluded(), &vec![]).map_err(TdError::SchemaConflict)?;
+    let tx_err = |e| TdError::Db(format!("Could not write the snapshot: {:?}", e));
+
+    // Download.
+    let mut utx = dst.tx_open().map_err(tx_err)?;
+    let mut md2 = md.clone();
+    md2.id = None;
+    md2.tx_insert_set_pk(&mut utx);
+
+    let mut run = TdRun {
+        run_id: None,
+        ..dl
+    };
+    run.tx_insert_set_pk(&mut utx);
+    let rows = seed_all(src, &mut utx, run.run_id.unwrap(), &seed, &objects);
+    utx.tx_close().map_err(|e| TdError::from_commit("snapshot", e))?;
+    info!("Copied {} rows from the download{}.", rows, if history { " (history tables)" } else { "" });
+
+    // Events: second pass, applied a batch at a time.
+    let mut applied_n = 0;
+    let mut batch = vec![];
+    let mut write_batch = |dst: &mut UniCon, batch: &mut Vec<API::NotificationEvent>| -> Result<(), TdError> {
+        let mut utx = dst.tx_open().map_err(tx_err)?;
+        let mut run = TdRun {
+            run_id: None,
+            r#type: "apply_events".into(),
+            start_ts: now_3().into(),
+            end_ts: None,
+        };
+        run.tx_insert_set_pk(&mut utx);
+
+        apply_events_body(&mut utx, run.run_id.unwrap(), batch, &objects);
+
+        run.end_ts = Some(now_3());
+        run.tx_update_pk(&mut utx);
+        utx.tx_close().map_err(|e| TdError::from_commit("snapshot", e))?;
+
+        applied_n += batch.len();
+        batch.clear();
+        Ok(())
+    };
+
+    for_each_stored_event(src, x, &md, |r| {
+        if r.created.dt > as_of {
+            return Ok(());
+        }
+
+        batch.push(to_event(&r)?);
+        if batch.len() == BATCH_SIZE {
+            write_batch(dst, &mut batch)?;
+        }
+        Ok(())
+    })?;
+    if !batch.is_empty() {
+        write_batch(dst, &mut batch)?;
+    }
+
+    info!("Snapshot as of {} complete: applied {} of {} stored events.", as_of, applied_n, created.len());
+    Ok(applied_n)
+}
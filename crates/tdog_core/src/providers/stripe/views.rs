@@ -0,0 +1,227 @@
+use log::info;
+use unicon::table::View;
+use unicon::uc::UniCon;
+use unicon::utx::Views;
+
+use crate::error::TdError;
+use crate::providers::stripe::objects::ObjectSelection;
+
+
+// Analytics views (`options.views`): reporting queries over the Stripe tables, created alongside them.
+// - Written once per engine dialect (JSON functions and date series differ); the definitions are documented in the readme.
+// - Amounts are in the currency's minor unit (E.g. cents), like the `unit_amount`/`amount_*` columns.
+// - Item MRR: `unit_amount * quantity * (periods per year / 12) / interval_count` of licensed, per unit prices.
+//      - Metered (`usage_type = metered`) and tiered (`unit_amount` is null) prices are not included.
+// - A view is only created when all of its tables are included by `options.objects`.
+// - Increment a view's version when its SQL changes so existing DBs replace it.
+
+
+#[derive(Debug, Clone, Copy)]
+enum Dialect {
+    SQLite,
+    MySQL,
+    Postgres,
+}
+
+impl Dialect {
+    // A JSON string as text.
+    fn json_text(&self, col: &str, key: &str) -> String {
+        match self {
+            Dialect::SQLite => format!("json_extract({}, '$.{}')", col, key),
+            Dialect::MySQL => format!("JSON_UNQUOTE(JSON_EXTRACT({}, '$.{}'))", col, key),
+            Dialect::Postgres => format!("({}->>'{}')", col, key),
+        }
+    }
+
+    fn json_int(&self, col: &str, key: &str) -> String {
+        match self {
+            Dialect::SQLite => format!("json_extract({}, '$.{}')", col, key),
+            Dialect::MySQL => format!("CAST(JSON_EXTRACT({}, '$.{}') AS UNSIGNED)", col, key),
+            Dialect::Postgres => format!("({}->>'{}')::int", col, key),
+        }
+    }
+
+    // Postgres has no `ROUND(double precision, int)`.
+    fn round2(&self, x: &str) -> String {
+        match self {
+            Dialect::Postgres => format!("ROUND(({})::numeric, 2)", x),
+            _ => format!("ROUND({}, 2)", x),
+        }
+    }
+
+    // Monthly multiplier of a recurring price: periods per year / 12 / interval_count.
+    fn per_month(&self, p: &str) -> String {
+        let recurring = format!("{}.recurring", p);
+        format!(
+            "CASE {} WHEN 'day' THEN 365 WHEN 'week' THEN 52 WHEN 'month' THEN 12 WHEN 'year' THEN 1 END / (12.0 * {})",
+            self.json_text(&recurring, "interval"),
+            self.json_int(&recurring, "interval_count")
+        )
+    }
+
+    fn item_mrr(&self, p: &str, si: &str) -> String {
+        format!("{}.unit_amount * COALESCE({}.quantity, 1) * {}", p, si, self.per_month(p))
+    }
+
+    fn is_licensed(&self, p: &str) -> String {
+        format!("{}.unit_amount IS NOT NULL AND {} = 'licensed'", p, self.json_text(&format!("{}.recurring", p), "usage_type"))
+    }
+}
+
+// MRR of each active or past due subscription, with the subscription's coupon applied (unless it only applied once).
+// - `amount_off` is per billing period; it is converted to a monthly amount with the items' interval.
+fn mrr_by_subscription(d: Dialect) -> String {
+    let item = d.item_mrr("p", "si");
+    let net = format!(
+        "SUM({}) * (1 - COALESCE(c.percent_off, 0) / 100.0) - COALESCE(c.amount_off, 0) * MAX({})",
+        &item,
+        d.per_month("p")
+    );
+
+    format!(r#"SELECT x.subscription, x.customer, x.status, x.currency, {} AS mrr_gross, {} AS mrr
+FROM (
+    SELECT s.id AS subscription, s.customer, s.status, MAX(p.currency) AS currency, SUM({}) AS mrr_gross, {} AS mrr_net
+    FROM subscriptions s
+    JOIN subscription_items si ON si.subscription = s.id
+    JOIN prices p ON p.id = si.price
+    LEFT JOIN discounts dc ON dc.id = s.discount
+    LEFT JOIN coupons c ON c.id = dc.coupon AND c.duration <> 'once'
+    WHERE s.status IN ('active', 'past_due') AND {}
+    GROUP BY s.id, s.customer, s.status, c.percent_off, c.amount_off
+) x"#,
+        d.round2("x.mrr_gross"),
+        d.round2("CASE WHEN x.mrr_net < 0 THEN 0 ELSE x.mrr_net END"),
+        &item,
+        &net,
+        d.is_licensed("p")
+    )
+}
+
+// Gross MRR per currency at the end of each UTC day, from the versions in the history tables.
+// - Days start at the first `subscriptions_history` version; `valid_from` is the Stripe change time (the event's `created`, or the download start).
+// - Prices are read from the `prices` table (a price's amount and interval cannot be changed in Stripe).
+fn mrr_daily(d: Dialect) -> String {
+    let (days, next_day, day) = match d {
+        Dialect::SQLite => (
+            "(\n    WITH RECURSIVE days(day) AS (\n        SELECT date(MIN(valid_from)) FROM subscriptions_history\n        UNION ALL\n        SELECT date(day, '+1 day') FROM days WHERE day < date('now')\n    )\n    SELECT day FROM days\n) d".to_string(),
+            "date(d.day, '+1 day')",
+            "d.day",
+        ),
+        Dialect::MySQL => {
+            // A recursive CTE would be limited by `cte_max_recursion_depth` (1000 days by default); digits cross joined give 10,000 days.
+            let digits = "(SELECT 0 AS n UNION ALL SELECT 1 UNION ALL SELECT 2 UNION ALL SELECT 3 UNION ALL SELECT 4 UNION ALL SELECT 5 UNION ALL SELECT 6 UNION ALL SELECT 7 UNION ALL SELECT 8 UNION ALL SELECT 9)";
+            (
+                format!(
+                    "(\n    SELECT DATE(m.first_ts) + INTERVAL (a.n + 10 * b.n + 100 * c.n + 1000 * e.n) DAY AS day\n    FROM (SELECT MIN(valid_from) AS first_ts FROM subscriptions_history) m, {} a, {} b, {} c, {} e\n    WHERE DATE(m.first_ts) + INTERVAL (a.n + 10 * b.n + 100 * c.n + 1000 * e.n) DAY <= UTC_DATE()\n) d",
+                    digits, digits, digits, digits
+                ),
+                "d.day + INTERVAL 1 DAY",
+                "d.day",
+            )
+        }
+        Dialect::Postgres => (
+            "generate_series((SELECT date_trunc('day', MIN(valid_from)) FROM subscriptions_history), date_trunc('day', now() AT TIME ZONE 'UTC'), interval '1 day') AS d(day)".to_string(),
+            "d.day + interval '1 day'",
+            "d.day::date",
+        ),
+    };
+
+    let valid = |x: &str| format!("{}.valid_from < {} AND ({}.valid_to IS NULL OR {}.valid_to >= {})", x, next_day, x, x, next_day);
+
+    format!(r#"SELECT {} AS day, p.currency, {} AS mrr_gross, COUNT(DISTINCT s.id) AS subscriptions
+FROM {}
+JOIN subscriptions_history s ON {}
+JOIN subscription_items_history si ON si.subscription = s.id AND {}
+JOIN prices p ON p.id = si.price
+WHERE s.status IN ('active', 'past_due') AND {}
+GROUP BY d.day, p.currency"#,
+        day,
+        d.round2(&format!("SUM({})", d.item_mrr("p", "si"))),
+        days,
+        valid("s"),
+        valid("si"),
+        d.is_licensed("p")
+    )
+}
+
+// Customers with at least one active, trialing or past due subscription.
+fn active_customers(_: Dialect) -> String {
+    r#"SELECT c.id AS customer, c.email, c.name, COUNT(s.id) AS active_subscriptions, MIN(s.start_date) AS first_subscription_start
+FROM customers c
+JOIN subscriptions s ON s.customer = c.id
+WHERE s.status IN ('active', 'trialing', 'past_due')
+GROUP BY c.id, c.email, c.name"#.to_string()
+}
+
+// Invoices with a failed payment attempt that are still unpaid.
+fn failed_invoices(_: Dialect) -> String {
+    r#"SELECT i.id AS invoice, i.customer, i.subscription, i.currency, i.amount_due, i.amount_remaining, i.attempt_count, i.next_payment_attempt, i.status, i.created
+FROM invoices i
+WHERE i.attempted AND NOT i.paid AND i.status IN ('open', 'uncollectible')"#.to_string()
+}
+
+// Canceled subscriptions, with the gross MRR of their items at cancellation.
+fn churned_subscriptions(d: Dialect) -> String {
+    format!(r#"SELECT s.id AS subscription, s.customer, s.created, s.canceled_at, s.ended_at, s.cancel_at_period_end, MAX(p.currency) AS currency, {} AS mrr_gross
+FROM subscriptions s
+LEFT JOIN subscription_items si ON si.subscription = s.id
+LEFT JOIN prices p ON p.id = si.price AND {}
+WHERE s.status = 'canceled'
+GROUP BY s.id, s.customer, s.created, s.canceled_at, s.ended_at, s.cancel_at_period_end"#,
+        d.round2(&format!("SUM({})", d.item_mrr("p", "si"))),
+        d.is_licensed("p")
+    )
+}
+
+
+struct ViewDef {
+    name: &'static str,
+    version: i64,
+    tables: Vec<&'static str>,
+    sql: fn(Dialect) -> String,
+    needs_history: bool,
+}
+
+fn get_all() -> Vec<ViewDef> {
+    let x = |name, version, tables, sql, needs_history| ViewDef { name, version, tables, sql, needs_history };
+
+    vec![
+        x("v_mrr_by_subscription", 1, vec!["subscriptions", "subscription_items", "prices", "discounts", "coupons"], mrr_by_subscription, false),
+        x("v_mrr_daily", 1, vec!["subscriptions", "subscription_items", "prices"], mrr_daily, true),
+        x("v_active_customers", 1, vec!["customers", "subscriptions"], active_customers, false),
+        x("v_failed_invoices", 1, vec!["invoices"], failed_invoices, false),
+        x("v_churned_subscriptions", 1, vec!["subscriptions", "subscription_items", "prices"], churned_subscriptions, false),
+    ]
+}
+
+pub fn get_views(objects: &ObjectSelection, history: bool) -> Views {
+    let views = get_all().into_iter()
+        .filter(|x| (history || !x.needs_history) && x.tables.iter().all(|t| objects.is_included(t)))
+        .map(|x| View {
+            name: x.name.into(),
+            version: x.version,
+            sqlite: (x.sql)(Dialect::SQLite),
+            mysql: (x.sql)(Dialect::MySQL),
+            postgres: (x.sql)(Dialect::Postgres),
+        })
+        .collect();
+
+    Views {
+        versions_table: "td_views".into(),
+        views,
+    }
+}
+
+pub fn create_or_replace_views(uc: &mut UniCon, objects: &ObjectSelection, history: bool) -> Result<(), TdError> {
+    let views = get_views(objects, history);
+
+    let mut utx = uc.tx_open().map_err(|e| TdError::Db(e.to_string()))?;
+    let changed = views.create_or_replace(&mut utx).map_err(TdError::SchemaConflict)?;
+    utx.tx_close().map_err(|e| TdError::Db(e.to_string()))?;
+
+    if !changed.is_empty() {
+        info!("Created views: {:?}", changed);
+    }
+
+    Ok(())
+}
@@ -1,8 +1,9 @@
 use std::collections::HashMap;
+use std::future::Future;
 use std::time::Duration;
 use futures::FutureExt;
 use chrono::{DateTime, Utc};
-use futures::future::{join_all, LocalBoxFuture, AbortHandle, Abortable};
+use futures::future::{self, join_all, LocalBoxFuture, AbortHandle, Abortable};
 use futures::future::TryFutureExt;
 use futures::join;
 use futures::stream::{self, Stream, StreamExt, TryStream, TryStreamExt};
@@ -24,7 +25,7 @@ use stripe_client::types::types::{
     SubscriptionSchedule as APISubscriptionSchedule,
     TaxRate as APITaxRate,
 };
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, oneshot};
 use tokio::time;
 use tokio::time::delay_for;
 use unicon::{*};
@@ -47,13 +48,16 @@ use crate::providers::stripe::schema::{Db, WriteTree};
 use crate::providers::stripe::schema::types::{*};
 use crate::providers::stripe::schema::types::balance_transaction::BalanceTransaction;
 use crate::providers::stripe::schema::types::coupon::Coupon;
+use crate::providers::stripe::schema::types::credit_note::CreditNote;
 use crate::providers::stripe::schema::types::dispute::Dispute;
 use crate::providers::stripe::schema::types::invoice::Invoice;
 use crate::providers::stripe::schema::types::invoice_line_item::{InvoiceLineItem, InvoiceLineItemWithParentId};
 use crate::providers::stripe::schema::types::invoiceitem::Invoiceitem;
+use crate::providers::stripe::schema::types::order::Order;
 use crate::providers::stripe::schema::types::promotion::PromotionCode;
 use crate::providers::stripe::schema::types::refund::Refund;
 use crate::providers::stripe::schema::types::setup_intent::SetupIntent;
+use crate::providers::stripe::schema::types::sku::Sku;
 //use unicon::{UniCon, UniTx};
 //use unicon::{
 //     Insert,
@@ -64,14 +68,28 @@ use crate::{Stripe, Download};
 
 use super::schema_meta::{*};
 
-use crate::providers::stripe::schema::util::ToJSONString;
-use crate::util::is_debug_build;
+use crate::providers::stripe::schema::util::{ToJSONString, ToISODate};
+use crate::util::{is_debug_build, with_job_label, AbortOnDrop, parse_created_gte};
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::{hooks, metrics, shutdown};
+use crate::error::TdError;
+use crate::providers::stripe::foreign_keys;
+use crate::providers::stripe::history::get_history_tables;
+use crate::providers::stripe::metadata_cols::MetadataCols;
+use crate::providers::stripe::raw_json;
+use crate::providers::stripe::notify;
+use crate::providers::stripe::dry_run::{log_download_summary, tx_get_summary};
+use crate::providers::stripe::views::create_or_replace_views;
+use crate::providers::stripe::mrr_movements;
+use crate::providers::stripe::migrations;
+use crate::providers::stripe::write_ctx;
 use serde_json::{Value, Map};
 use crate::providers::stripe::queue::Queue;
 use std::sync::Arc;
 use std::ops::Deref;
 use tokio::task::JoinHandle;
 use std::env;
+use std::convert::{TryFrom, TryInto};
 
 impl From<&Stripe> for Config {
     fn from(x: &Stripe) -> Self {
@@ -132,19 +150,33 @@ impl From<&Stripe> for Config {
 // ins_all_dyn(&diff_types, &uc);
 
 
+// Sent to `options.hooks.on_download_complete`.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct DownloadSummary {
+    pub run_id: i64,
+
+    // Keyed by Stripe object type.
+    pub inserts: HashMap<String, i64>,
+    pub write_quota_used: i64,
+}
+
 /// Issue, possible: FK relations can be broken when the underlying data is being mutated.
 /// - E.g. Download timeline: (customers, pause, subscriptions).
 ///     - During `pause` new customers + subs can be created, causing a SQL insert issue as the new sub with no customer will be inserted.
 /// Fix, A: Option to remove FK constraints (enable only for low throughput accounts).
 /// Fix, B: Download each dep individually if it is missing.
 /// Fix, C: Use the incremental download logic.
-pub async fn download_all(c: &StripeClient, uc: &mut UniCon, dl: &Download) {
+///
+/// Returns Err when the download was stopped (E.g. a shutdown signal, a Stripe error); nothing is written.
+/// - `stop` resolves with the reason to stop (`shutdown::wait_for_shutdown`).
+pub async fn download_all(c: &StripeClient, uc: &mut UniCon, dl: &Download, objects: &ObjectSelection, created_gte: Option<i64>, stop: impl Future<Output = &'static str>) -> Result<(), TdError> {
     info!("Download all started.");
     info!("HTTP requests are logged at https://dashboard.stripe.com/test/logs.");
     info!("HTTP requests can also be observed by setting an HTTP proxy via the JSON config.");
 
     // let c = StripeClient::new(s.into());
-    let mut utx = uc.tx_open().unwrap();
+    let mut utx = uc.tx_open().map_err(|e| TdError::Db(format!("Could not open a DB transaction: {:?}", e)))?;
 
 
     let mut run = TdRun {
@@ -159,7 +191,6 @@ pub async fn download_all(c: &StripeClient, uc: &mut UniCon, dl: &Download) {
     let run_id = run.run_id.unwrap();
 
 
-    // @todo/low Handle errors.
     // @todo/low Does the order of these downloads matter for foreign keys?
     // - Is it possible to enforce FK constraints on commit (instead of inside the tx after each statement)?
     // - Should downloads happen from the deepest levels to the root of the tree? What about for a graph of deps (E.g. discounts)?
@@ -172,9 +203,21 @@ pub async fn download_all(c: &StripeClient, uc: &mut UniCon, dl: &Download) {
     let utx_mt = Mutex::new(utx);
 
 
-    dl_all(&c, &utx_mt, run_id, dl).await;
+    let res = dl_all(c, &utx_mt, run_id, dl, objects, created_gte, stop).await;
 
     let mut utx = utx_mt.into_inner();
+    write_ctx::clear_raw();
+
+    if let Err(e) = res {
+        utx.tx_rollback().unwrap();
+        error!("Download stopped before completing; its transaction was rolled back. The download will start again from the beginning on the next run.");
+        return Err(e);
+    }
+
+    if write_ctx::is_mrr_movements_enabled() {
+        let start: DateTime<Utc> = run.start_ts.clone().unwrap().into();
+        mrr_movements::tx_seed(&mut utx, run_id, start.into());
+    }
 
     run.end_ts = Some(now_3());
     run.tx_update_pk(&mut utx);
@@ -182,11 +225,24 @@ pub async fn download_all(c: &StripeClient, uc: &mut UniCon, dl: &Download) {
     let inserts = TdStripeWrite::get_insert_count_by_obj_type(&mut utx);
     let write_quota_used = TdStripeWrite::get_write_count_excluding_deletes(&mut utx, run_id);
 
-    utx.tx_close().unwrap();
+    utx.tx_close().map_err(|e| TdError::from_commit("download transaction", e))?;
+
+    if created_gte.is_some() {
+        dl_missing_owners(c, uc, run_id, objects).await?;
+    }
 
     info!("Download all completed.");
     info!("Inserted objects: {}",  inserts.to_json());
 
+    let summary = DownloadSummary {
+        run_id,
+        inserts,
+        write_quota_used,
+    };
+    hooks::on_download_complete(&dl.options.hooks, &summary).await;
+
+    Ok(())
+
 
     // Note: this check is done after starting from a `dl` SQLite file.
     // - Maybe be an CLI option to debug relations in the future.
@@ -203,11 +259,12 @@ pub async fn download_all(c: &StripeClient, uc: &mut UniCon, dl: &Download) {
 
 
 // When a Stripe HTTP 429 occurs, stop starting new requests until all of the current ones have resolved.
-pub fn on_429_pause_queue_until_resolved(q_mt_a: Arc<Mutex<Queue>>, c: &StripeClient, exit_on_429: bool) -> AbortHandle {
+// - `exit_on_429`: `on_exit` is sent instead, so that the download can be stopped and its tx rolled back.
+pub fn on_429_pause_queue_until_resolved(q_mt_a: Arc<Mutex<Queue>>, c: &StripeClient, exit_on_429: bool, on_exit: oneshot::Sender<()>) -> AbortHandle {
     let (a_h, a_r) = AbortHandle::new_pair();
     let stats = c.stats.clone();
 
-    let h = tokio::spawn(Abortable::new(async move {
+    let h = tokio::spawn(with_job_label(Abortable::new(async move {
         loop {
             {
                 let stats = stats.read().await;
@@ -215,10 +272,11 @@ pub fn on_429_pause_queue_until_resolved(q_mt_a: Arc<Mutex<Queue>>, c: &StripeCl
                 if stats.cur_429_reqs_retrying > 0 {
                     if exit_on_429 {
                         error!("The Stripe API responded with HTTP code 429 (too many requests).");
-                        error!("Exited the process to avoid continually rate locking the Stripe account (this prevents impacting other systems read/writing to the same Stripe account).");
+                        error!("Stopping the download to avoid continually rate locking the Stripe account (this prevents impacting other systems read/writing to the same Stripe account).");
                         // Note: panic! does not kill main process.
-                        std::process::exit(1);
                         // Implicit: Current DB tx fails and is rolled back.
+                        let _ = on_exit.send(());
+                        break;
                     }
 
                     if !q.paused {
@@ -239,7 +297,7 @@ pub fn on_429_pause_queue_until_resolved(q_mt_a: Arc<Mutex<Queue>>, c: &StripeCl
 
             delay_for(Duration::from_millis(1000)).await;
         }
-    }, a_r));
+    }, a_r)));
 
     a_h
 }
@@ -259,7 +317,7 @@ pub fn log_stats_every(q_mt_a: Arc<Mutex<Queue>>, c: &StripeClient) -> AbortHand
     let (a_h, a_r) = AbortHandle::new_pair();
     let stats = c.stats.clone();
 
-    let h = tokio::spawn(Abortable::new(async move {
+    let h = tokio::spawn(with_job_label(Abortable::new(async move {
         loop {
             let ms = 30_000;
             delay_for(Duration::from_millis(ms)).await;
@@ -293,6 +351,8 @@ pub fn log_stats_every(q_mt_a: Arc<Mutex<Queue>>, c: &StripeClient) -> AbortHand
                     *c += 1;
                 }
 
+                metrics::observe_reqs(&stats.req_log, stats.cur_429_reqs_retrying);
+                metrics::set_queue_len(queue_len);
                 stats.req_log = vec![];
 
                 ReqSummary {
@@ -309,7 +369,7 @@ pub fn log_stats_every(q_mt_a: Arc<Mutex<Queue>>, c: &StripeClient) -> AbortHand
 
             info!("HTTP request summary, last 30 seconds:  {}", x.to_json());
         }
-    }, a_r));
+    }, a_r)));
 
     a_h
 }
@@ -334,7 +394,8 @@ pub struct SummaryInterval {
 }
 
 
-pub async fn dl_all(c: &StripeClient, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, dl: &Download) {
+/// Returns Err on the first failed download, `exit_on_429`, or when `stop` resolves (a shutdown signal).
+pub async fn dl_all(c: &StripeClient, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, dl: &Download, objects: &ObjectSelection, created_gte: Option<i64>, stop: impl Future<Output = &'static str>) -> Result<(), TdError> {
     let from_stripe = dl.get_stripe_from();
     let exit_on_429 = from_stripe.exit_on_429;
     let max_requests_per_second = match from_stripe.max_requests_per_second {
@@ -356,13 +417,16 @@ pub async fn dl_all(c: &StripeClient, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, dl
     // dbg!(env::var("RUST_MIN_STACK"));
 
     let q_mt_a = Arc::new(Mutex::new(Queue::new(max_requests_per_second)));
+    let (exit_429_tx, exit_429_rx) = oneshot::channel();
 
-    let a_queue = Queue::run_scheduler(q_mt_a.clone());
-    let a_on_429_pause_queue = on_429_pause_queue_until_resolved(q_mt_a.clone(), c, exit_on_429);
-    let a_log_stats = log_stats_every(q_mt_a.clone(), c);
+    // Stop related Tokio background tasks that loop when this fn returns (including on shutdown) or panics.
+    let _a_bg = AbortOnDrop(vec![
+        Queue::run_scheduler(q_mt_a.clone()),
+        on_429_pause_queue_until_resolved(q_mt_a.clone(), c, exit_on_429, exit_429_tx),
+        log_stats_every(q_mt_a.clone(), c),
+    ]);
 
 
-    // @todo/low Handle errors.
     // @todo/low Does the order of these downloads matter for foreign keys?
     // - Is it possible to enforce FK constraints on commit (instead of inside the tx after each statement)?
     // - Should downloads happen from the deepest levels to the root of the tree? What about for a graph of deps (E.g. discounts)?
@@ -378,27 +442,28 @@ pub async fn dl_all(c: &StripeClient, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, dl
     //          - SQLite, using it via Mutex/async functions on the same thread.
 
 
+    // Skipped downloads are never polled (an async fn does nothing until it is polled).
     // A
-    let f1 = dl_customers(c, &q_mt_a, &utx_mt, run_id).boxed_local();
-    let f2 = dl_payment_intents(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
+    let f1 = if_included::<Customer>(objects, dl_customers(c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
+    let f2 = if_included::<PaymentIntent>(objects, dl_payment_intents(&c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
 
-    let f3 = dl_setup_intents(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
-    let f4 = dl_refunds(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
-    let f5 = dl_charges(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
-    let f6 = dl_subscriptions(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
+    let f3 = if_included::<SetupIntent>(objects, dl_setup_intents(&c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
+    let f4 = if_included::<Refund>(objects, dl_refunds(&c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
+    let f5 = if_included::<Charge>(objects, dl_charges(&c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
+    let f6 = if_included::<Subscription>(objects, dl_subscriptions(&c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
 
     // B
-    let f7 = dl_products(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
-    let f8 = dl_prices(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
-    let f9 = dl_skus(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
-    let f10 = dl_orders(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
+    let f7 = if_included::<Product>(objects, dl_products(&c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
+    let f8 = if_included::<Price>(objects, dl_prices(&c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
+    let f9 = if_included::<Sku>(objects, dl_skus(&c, &q_mt_a, &utx_mt, run_id).boxed_local());
+    let f10 = if_included::<Order>(objects, dl_orders(&c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
 
     // dl_order_returns(&c, &utx, run_id).await;
 
     // D
-    let f11 = dl_invoices(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
-    let f12 = dl_invoiceitems(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
-    let f13 = dl_credit_notes(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
+    let f11 = if_included::<Invoice>(objects, dl_invoices(&c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
+    let f12 = if_included::<Invoiceitem>(objects, dl_invoiceitems(&c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
+    let f13 = if_included::<CreditNote>(objects, dl_credit_notes(&c, &q_mt_a, &utx_mt, run_id).boxed_local());
 
     // Z
     // Issue: tax rate is a parent of (sub, inv, inv line item, inv items), but the list could be downloaded before, then a new tax rate created (which is missing), and then the sub/inv list with a link to the missing tax_rate.
@@ -406,47 +471,64 @@ pub async fn dl_all(c: &StripeClient, utx_mt: &Mutex<UniTx<'_>>, run_id: i64, dl
     // - Why do the events from `28 days ago to now` have to be applied? Would a 2 minute window make a difference? for webhook replacements?
 
     // Z
-    let f14 = dl_coupons(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
-    let f15 = dl_promotion_codes(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
-    let f16 = dl_tax_rates(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
-    let f17 = dl_disputes(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
-    let f18 = dl_balance_transactions(&c, &q_mt_a, &utx_mt, run_id).boxed_local();
+    let f14 = if_included::<Coupon>(objects, dl_coupons(&c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
+    let f15 = if_included::<PromotionCode>(objects, dl_promotion_codes(&c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
+    let f16 = if_included::<TaxRate>(objects, dl_tax_rates(&c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
+    let f17 = if_included::<Dispute>(objects, dl_disputes(&c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
+    let f18 = if_included::<BalanceTransaction>(objects, dl_balance_transactions(&c, &q_mt_a, &utx_mt, run_id, created_gte).boxed_local());
 
     // dl_sessions(&c, &utx, run_id)
 
-    join!(
-        // A
-        f1,
-        f2,
-        f3,
-        f4,
-        f5,
-        f6,
-
-        // B
-        f7,
-        f8,
-        f9,
-        f10,
-
-        // D
-        f11,
-        f12,
-        f13,
-
-        // Z
-        f14,
-        f15,
-        f16,
-        f17,
-        f18
-    );
-
-
-    // Stop related Tokio background tasks that loop.
-    a_on_429_pause_queue.abort();
-    a_queue.abort();
-    a_log_stats.abort();
+    // The first error drops (cancels) the other downloads.
+    let all = async {
+        try_join!(
+            // A
+            f1,
+            f2,
+            f3,
+            f4,
+            f5,
+            f6,
+
+            // B
+            f7,
+            f8,
+            f9,
+            f10,
+
+            // D
+            f11,
+            f12,
+            f13,
+
+            // Z
+            f14,
+            f15,
+            f16,
+            f17,
+            f18
+        )
+    };
+
+    // Dropping the download futures cancels their in-flight requests.
+    tokio::select! {
+        res = all => res.map(|_| ()),
+        Ok(_) = exit_429_rx => Err(TdError::RateLimit("The Stripe API responded with HTTP code 429 and `exit_on_429` is set.".into())),
+        sig = stop => {
+            warn!("Stopping download ({}).", sig);
+            Err(TdError::Interrupted(format!("Download stopped by {}.", sig)))
+        }
+    }
+}
+
+// Returns a no-op future when `T`'s table is excluded via `options.objects`.
+fn if_included<'a, T: TableStatic>(objects: &ObjectSelection, f: LocalBoxFuture<'a, Result<(), TdError>>) -> LocalBoxFuture<'a, Result<(), TdError>> {
+    if objects.is_included(T::get_table_name_static()) {
+        return f;
+    }
+
+    info!("Skipping download of `{}` (excluded via `options.objects`).", T::get_table_name_static());
+    future::ready(Ok(())).boxed_local()
 }
 
 // @todo/med Issue: A Stripe event stream can be a subset of all possible events. If the type of events subscribed to is changed for a given API key, new events will start to be published against an incomplete DB which would lead to incorrect queries (as some of the object types are missing due being created between (download, x, apply_partial_events)).
@@ -473,60 +555,181 @@ fn log_using_sa(sa: &StripeAccount) {
     info!("Using Stripe account {} ({})", &url, live);
 }
 
-pub async fn once(sc: &StripeClient, uc: &mut UniCon, dl: &Download) {
+pub async fn once(sc: &StripeClient, uc: &mut UniCon, dl: &Download) -> Result<(), TdError> {
     let tx = Some(1);
 
 
     let sa = sc.stripe_account.as_ref().unwrap();
+    let objects = get_objects_and_log(dl)?;
+
+    if dl.options.dry_run {
+        return dry_run(sc, uc, dl, &objects).await;
+    }
 
     // @todo/low Issue: Create a tx here to prevent multiple TD processes from interacting (when more than one TD process is run accidentally it should not corrupt the DB or affect dependant processes query results).
     // - Not too much of an issue as each fn starts its own tx.
     //      - Conflicts should cause the tx to fail (E.g. unique constraints for dl/apply_events, table/index already exists for create/drop tables).
-    create_schema_if_not_exists_and_log::<Db>(uc, &sa);
+    let created_gte = create_schema_if_not_exists_and_log::<Db>(uc, sa, &objects, dl)?;
     log_using_sa(&sa);
 
 
     if let Some(possible) = TdRun::is_apply_events_possible(uc) {
         if possible {
             info!("{}", DL_NOT_NEEDED_MSG);
-            apply_events(sc, uc, None).await;
-            return;
+            return apply_events_and_hook(sc, uc, &objects, dl).await;
         }
 
-        drop_all_and_recreate_tables(uc);
+        drop_all_and_recreate_tables(uc)?;
     }
     /// else {Fresh empty DB}.
 
 
-    download_all(sc, uc, dl).await;
+    download_all(sc, uc, dl, &objects, created_gte, shutdown::wait_for_shutdown()).await?;
 
     if dl.options.apply_events_after_one_shot_dl {
-        apply_events(sc, uc, None).await;
+        apply_events_and_hook(sc, uc, &objects, dl).await?;
     }
+
+    Ok(())
 }
 
-pub async fn poll(sc: &StripeClient, mut uc: &mut UniCon, poll_freq_ms: u64, dl: &Download) {
+pub async fn poll(sc: &StripeClient, mut uc: &mut UniCon, poll_freq_ms: u64, dl: &Download) -> Result<(), TdError> {
     let tx = Some(1);
 
     let sa = sc.stripe_account.as_ref().unwrap();
-    create_schema_if_not_exists_and_log::<Db>(uc, &sa);
+    let objects = get_objects_and_log(dl)?;
+
+    if dl.options.dry_run {
+        info!("Dry run: `watch` is ignored; events are applied once.");
+        return dry_run(sc, uc, dl, &objects).await;
+    }
+
+    let created_gte = create_schema_if_not_exists_and_log::<Db>(uc, sa, &objects, dl)?;
     log_using_sa(&sa);
 
     if let Some(possible) = TdRun::is_apply_events_possible(uc) {
         if possible {
             info!("{}", DL_NOT_NEEDED_MSG);
             drop(tx);
-            poll_apply_events(sc, uc, poll_freq_ms).await;
-            return;
+            return poll_apply_events(sc, uc, poll_freq_ms, &objects, dl, shutdown::wait_for_shutdown()).await;
         }
 
-        drop_all_and_recreate_tables(uc);
+        drop_all_and_recreate_tables(uc)?;
     }
     /// else {Fresh empty DB}.
 
-    download_all(sc, uc, dl).await;
+    download_all(sc, uc, dl, &objects, created_gte, shutdown::wait_for_shutdown()).await?;
     drop(tx);
-    poll_apply_events(sc, uc, poll_freq_ms).await;
+    poll_apply_events(sc, uc, poll_freq_ms, &objects, dl, shutdown::wait_for_shutdown()).await
+}
+
+// `log_stats_every` only runs during the download; clear the log after each poll so it does not grow for the life of the process.
+async fn drain_req_log(sc: &StripeClient) {
+    let mut stats = sc.stats.write().await;
+    metrics::observe_reqs(&stats.req_log, stats.cur_429_reqs_retrying);
+    stats.req_log.clear();
+}
+
+// The hook runs after the tx has committed, and only when events were applied.
+async fn apply_events_and_hook(sc: &StripeClient, uc: &mut UniCon, objects: &ObjectSelection, dl: &Download) -> Result<(), TdError> {
+    if let Some(summary) = apply_events(sc, uc, None, objects).await? {
+        hooks::on_apply_events(&dl.options.hooks, &summary).await;
+    }
+
+    Ok(())
+}
+
+// `options.dry_run`: apply events, or preview a download, in a tx that is rolled back (see `dry_run.rs`); nothing is migrated or committed.
+async fn dry_run(sc: &StripeClient, uc: &mut UniCon, dl: &Download, objects: &ObjectSelection) -> Result<(), TdError> {
+    let sa = sc.stripe_account.as_ref().unwrap();
+    let mut t = get_target_tables(uc, objects, dl)?;
+    foreign_keys::set_sqlite_enforcement(dl.options.foreign_keys, uc);
+    log_using_sa(sa);
+
+    if !uc.schema_exists_with::<Db>(&t.excluded, &t.extra).or_else(|_| {
+        // A run creates the changes table of an existing DB before comparing it (the `td_changes` migration, see `migrations::migrate`).
+        t.extra.retain(|x| x.name != notify::get_notify().changes_table);
+        uc.schema_exists_with::<Db>(&t.excluded, &t.extra)
+    }).map_err(TdError::SchemaConflict)? {
+        let created_gte = dl.options.created_gte.as_ref().map(|x| parse_created_gte(x, Utc::now()).unwrap());
+        return dry_run_download(sc, uc, dl, objects, Some(&t), created_gte).await;
+    }
+
+    // All of the tables exist, so this only sets the active schema.
+    let (_, target_schema) = uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&t.excluded, &t.extra).map_err(TdError::SchemaConflict)?;
+    info!("Tables already exist: {:?}", target_schema.tables.iter().map(|x| &x.name).collect::<Vec<&String>>());
+    let x = assert_existing_tables_match(uc, sa, objects, dl, &t)?;
+
+    match TdRun::is_apply_events_possible(uc) {
+        Some(true) => {
+            apply_events(sc, uc, None, objects).await?;
+        }
+        Some(false) => info!("Dry run: the last run was longer than 28 days ago. A run would stop and ask for the tables to be dropped."),
+        None => {
+            info!("Dry run: the DB has no completed download.");
+            dry_run_download(sc, uc, dl, objects, None, x.get_created_gte()).await?;
+        }
+    }
+
+    Ok(())
+}
+
+// The list pages are downloaded and written in a tx that is rolled back, and the writes are summarized.
+// - `create`: the DB has no tables; they are created in the same tx. MySQL commits `CREATE` statements implicitly, so only SQLite and Postgres are previewed.
+// - Not previewed: owners missing because of `created_gte`, MRR movement seeds and hooks.
+async fn dry_run_download(sc: &StripeClient, uc: &mut UniCon, dl: &Download, objects: &ObjectSelection, create: Option<&TargetTables>, created_gte: Option<i64>) -> Result<(), TdError> {
+    if create.is_some() {
+        if let UniCon::MySQL(_) = uc {
+            info!("Dry run: the DB has no tables. A run would create them and download all objects (downloads are not previewed for MySQL as it cannot roll back creating tables).");
+            return Ok(());
+        }
+    }
+
+    let target_schema = create.map(|t| uc.get_target_schema_with::<Db>(&t.excluded, &t.extra));
+    let mut utx = uc.tx_open().map_err(|e| TdError::Db(format!("Could not open a DB transaction: {:?}", e)))?;
+
+    if let Some(x) = &target_schema {
+        info!("Dry run: the DB has no tables. Creating them in the dry run transaction.");
+        let schema_exists = x.schema.as_ref().map(|s| utx.get_schemas().contains(&s.name)).unwrap_or(false);
+        utx.create_schema_and_tables(x, schema_exists);
+    }
+
+    let mut run = TdRun {
+        run_id: None,
+        r#type: "download".into(),
+        start_ts: now_3().into(),
+        end_ts: None,
+    };
+    run.tx_insert_set_pk(&mut utx);
+    let run_id = run.run_id.unwrap();
+
+    let utx_mt = Mutex::new(utx);
+    let res = dl_all(sc, &utx_mt, run_id, dl, objects, created_gte, shutdown::wait_for_shutdown()).await;
+    let mut utx = utx_mt.into_inner();
+    write_ctx::clear_raw();
+
+    if let Err(e) = res {
+        utx.tx_rollback().map_err(|e| TdError::Db(format!("Could not roll back the dry run transaction: {:?}", e)))?;
+        return Err(e);
+    }
+
+    let summary = tx_get_summary(&mut utx, run_id, 0);
+    utx.tx_rollback().map_err(|e| TdError::Db(format!("Could not roll back the dry run transaction: {:?}", e)))?;
+    log_download_summary(&summary);
+
+    Ok(())
+}
+
+fn get_objects_and_log(dl: &Download) -> Result<ObjectSelection, TdError> {
+    let objects: ObjectSelection = (&dl.options.objects).try_into()?;
+
+    let excluded = objects.get_excluded();
+    if !excluded.is_empty() {
+        info!("Excluded tables (not created, downloaded or written from events): {:?}", &excluded);
+        Db::warn_excluded_owners(&objects);
+    }
+
+    Ok(objects)
 }
 
 
@@ -534,36 +737,179 @@ pub async fn poll(sc: &StripeClient, mut uc: &mut UniCon, poll_freq_ms: u64, dl:
 /// - Or
 ///     - Logging not writing to stdout at the correct time.
 ///     - Blocking on waiting for tx. Timeout?
-async fn poll_apply_events(sc: &StripeClient, uc: &mut UniCon, poll_freq_ms: u64) {
+/// - `stop` resolves with the reason to stop (`shutdown::wait_for_shutdown`).
+pub async fn poll_apply_events(sc: &StripeClient, uc: &mut UniCon, poll_freq_ms: u64, objects: &ObjectSelection, dl: &Download, stop: impl Future<Output = &'static str>) -> Result<(), TdError> {
     let d = Duration::from_millis(poll_freq_ms);
     let mut interval_day = time::interval(d);
+    tokio::pin!(stop);
 
     info!("Polling for new events every {:?}.", d);
 
+    // On shutdown, the current `apply_events` tx is completed before the loop stops.
     loop {
-        let now = interval_day.tick().await;
-        apply_events(sc, uc, None).await;
-        // return;
+        // A signal received while events were being applied stops the loop before the next tick.
+        if stop.as_mut().now_or_never().is_some() {
+            break;
+        }
+
+        let stop = tokio::select! {
+            _ = &mut stop => true,
+            _ = interval_day.tick() => false,
+        };
+
+        if stop {
+            break;
+        }
+
+        apply_events_and_hook(sc, uc, objects, dl).await?;
+        drain_req_log(sc).await;
+
+        // `store_raw_json`: the JSON of responses that were not written (E.g. the events of excluded tables).
+        write_ctx::clear_raw();
     }
+
+    info!("Stopped polling for events.");
+    Ok(())
 }
 
 
-async fn drop_all_and_recreate_tables(uc: &mut UniCon) {
+fn drop_all_and_recreate_tables(uc: &mut UniCon) -> Result<(), TdError> {
     info!("It is not possible to incrementally apply events as the last run was longer than 28 days ago. Stripe only stores the last 30 days of events.");
 
     // @todo/low Store SQL drop statements to allow a newer version to apply the older versions drop table (in case of adding/removing tables).
     // - Not needed as CLI expects user to drop the old version when using a newer CLI.
 
     error!("Please drop the schema and try again. Tables were not automatically dropped as you may require historical records of Stripe events (the API is limited to the last 30 days).");
-    panic!();
+    Err(TdError::SchemaConflict("The DB is older than the 30 days of events kept by Stripe, so it cannot be brought up to date.".into()))
     // Db::drop_all(uc);
     // create_schema_if_not_exists_and_log::<Db>(uc);
 }
 
 
-// Creates schema (if the engine supports it) and tables if they do not exist.
-fn create_schema_if_not_exists_and_log<T: DbStatic>(uc: &mut UniCon, sa: &StripeAccount) {
-    let (created_all, target_schema) = uc.ensure_schema_and_tables_exist_and_writable::<T>();
+// When `options.history` is removed from the config of a DB created with it, the history tables would silently stop being updated.
+pub fn assert_no_history_tables(uc: &mut UniCon, objects: &ObjectSelection) -> Result<(), TdError> {
+    let mut utx = uc.tx_open().map_err(|e| TdError::Db(e.to_string()))?;
+    let existing = utx.get_tables();
+    utx.tx_close();
+
+    let found: Vec<String> = get_history_tables(objects).into_iter().map(|x| x.name).filter(|x| existing.contains(x)).collect();
+    if !found.is_empty() {
+        return Err(TdError::SchemaConflict(format!("The DB was created with `options.history=true`, set it to keep the history tables up to date. Found: {:?}", found)));
+    }
+
+    Ok(())
+}
+
+
+// Tables changed after they were created (E.g. by a user) are detected on start up, instead of when an insert fails during a download.
+// - Conflicts: missing columns, and columns that are (not) generated or are `NOT NULL` when tdog writes nulls.
+// - Warnings: extra columns, missing indexes, missing generated columns (DBs created before `flatten` columns existed) and a different type.
+//      - Types are read back in each engine's spelling (E.g. MySQL `int(11)`, Postgres `character varying`), so a mismatch is not always a change.
+pub fn assert_cols_compatible(diff: &SchemaDiff) -> Result<(), TdError> {
+    let mut conflicts = vec![];
+
+    for t in &diff.tables {
+        for msg in t.get_msgs() {
+            warn!("Schema difference: {}", msg);
+        }
+
+        for c in t.missing_cols.iter().filter(|c| !c.generated) {
+            conflicts.push(format!("{}.{} is missing", &t.name, &c.name));
+        }
+        for (a, b) in &t.changed_cols {
+            if a.generated != b.generated {
+                conflicts.push(format!("{}.{} is {}generated", &t.name, &a.name, if b.generated { "" } else { "not " }));
+            }
+            if a.nullable && !b.nullable {
+                conflicts.push(format!("{}.{} is `NOT NULL`", &t.name, &a.name));
+            }
+        }
+    }
+
+    if !conflicts.is_empty() {
+        return Err(TdError::SchemaConflict(format!("Tables were changed after they were created: {}.", conflicts.join(", "))));
+    }
+
+    Ok(())
+}
+
+
+// The tables a run writes to: `excluded` tables of `Db` are not created, and `extra` tables are built from `options` at runtime.
+pub struct TargetTables {
+    pub excluded: Vec<String>,
+    pub extra: Vec<Table>,
+    md: MetadataCols,
+}
+
+// Used by both normal and dry runs, so they compare the DB to the same tables.
+fn get_target_tables(uc: &UniCon, objects: &ObjectSelection, dl: &Download) -> Result<TargetTables, TdError> {
+    let mut excluded = objects.get_excluded();
+    let mut extra = if dl.options.history { get_history_tables(objects) } else { vec![] };
+    if dl.options.store_raw_json {
+        raw_json::add_raw_tables(objects, &mut excluded, &mut extra);
+    }
+    let md = MetadataCols::try_from(&dl.options.metadata_columns)?;
+    md.assert_tables_included(objects)?;
+    md.add_tables(&mut excluded, &mut extra);
+    foreign_keys::assert_supported(dl.options.foreign_keys, uc, dl.options.created_gte.as_ref())?;
+    foreign_keys::add_tables(dl.options.foreign_keys, objects, &mut excluded, &mut extra);
+
+    if dl.options.notify {
+        extra.extend(notify::get_tables(uc));
+    }
+
+    if dl.options.mrr_movements {
+        mrr_movements::assert_tables_included(objects)?;
+    }
+
+    Ok(TargetTables {
+        excluded,
+        extra,
+        md,
+    })
+}
+
+// Checks that an existing DB can be written to with `options`; nothing is written.
+// - Returns the `td_metadata` row (E.g. for the `created_gte` cutoff recorded when the DB was created).
+fn assert_existing_tables_match(uc: &mut UniCon, sa: &StripeAccount, objects: &ObjectSelection, dl: &Download, t: &TargetTables) -> Result<TdMetadata, TdError> {
+    assert_cols_compatible(&uc.get_schema_diff_with::<Db>(&t.excluded, &t.extra).map_err(TdError::Db)?)?;
+    if !dl.options.history {
+        assert_no_history_tables(uc, objects)?;
+    }
+    raw_json::assert_raw_json_matches(uc, objects, dl.options.store_raw_json)?;
+    t.md.assert_cols_match(uc, objects)?;
+
+    let x = TdMetadata::check_cli_and_stripe_versions_match(uc, sa)?;
+    match x.get_created_gte() {
+        Some(ts) => info!("Using `created_gte` {} recorded when the DB was created.", ts.to_iso()),
+        None => {
+            if dl.options.created_gte.is_some() {
+                warn!("Ignoring `options.created_gte`: it only applies when the DB is created, and this DB contains a full download.");
+            }
+        }
+    }
+
+    Ok(x)
+}
+
+// Creates schema (if the engine supports it) and tables if they do not exist; not used by dry runs.
+// - Returns the `created_gte` cutoff recorded in `td_metadata` (relative durations are resolved once, when the DB is created).
+fn create_schema_if_not_exists_and_log<T: DbStatic>(uc: &mut UniCon, sa: &StripeAccount, objects: &ObjectSelection, dl: &Download) -> Result<Option<i64>, TdError> {
+    let t = get_target_tables(uc, objects, dl)?;
+
+    // Before the target schema is compared to the DB (a migration may add tables).
+    migrations::migrate(uc, objects, &dl.options)?;
+
+    let (created_all, target_schema) = uc.ensure_schema_and_tables_exist_and_writable_with::<T>(&t.excluded, &t.extra).map_err(TdError::SchemaConflict)?;
+    foreign_keys::set_sqlite_enforcement(dl.options.foreign_keys, uc);
+
+    if dl.options.views {
+        create_or_replace_views(uc, objects, dl.options.history)?;
+    }
+
+    if dl.options.mrr_movements {
+        mrr_movements::create_table_if_not_exists(uc)?;
+    }
 
     if created_all {
         if let Some(x) = &target_schema.schema {
@@ -571,16 +917,23 @@ fn create_schema_if_not_exists_and_log<T: DbStatic>(uc: &mut UniCon, sa: &Stripe
         }
 
         info!("Created tables: {:?}", target_schema.tables.iter().map(|x| &x.name).collect::<Vec<&String>>());
-        TdMetadata::insert_cli_and_stripe_versions(uc, sa);
+
+        let created_gte = dl.options.created_gte.as_ref().map(|x| parse_created_gte(x, Utc::now()).unwrap());
+        if let Some(x) = created_gte {
+            info!("Only downloading objects created at or after {} (`options.created_gte`).", x.to_iso());
+        }
+
+        TdMetadata::insert_cli_and_stripe_versions(uc, sa, created_gte);
+        migrations::create_table_if_not_exists(uc)?;
+        Ok(created_gte)
     } else {
         if let Some(x) = &target_schema.schema {
             info!("Schema already exists: `{}`.", &x.name);
         }
 
         info!("Tables already exist: {:?}", target_schema.tables.iter().map(|x| &x.name).collect::<Vec<&String>>());
-        let mut x = TdMetadata::check_cli_and_stripe_versions_match(uc, sa);
+        let mut x = assert_existing_tables_match(uc, sa, objects, dl, &t)?;
         x.update_stripe_account(uc, sa);
+        Ok(x.get_created_gte())
     }
 }
-
-
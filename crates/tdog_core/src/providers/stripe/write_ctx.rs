@@ -0,0 +1,190 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::future::Future;
+
+use serde_json::Value;
+use stripe_client::http::raw::RawIndex;
+use unicon::dt3::DT3;
+use unicon::table::Table;
+
+use crate::Options;
+use crate::error::TdError;
+use crate::providers::stripe::cdc::Cdc;
+use crate::providers::stripe::dry_run::RowUpdate;
+use crate::providers::stripe::metadata_cols::MetadataCols;
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::raw_json;
+
+
+// Write options for the job the current Tokio task is running, and the event being applied.
+// - Task local as `LogWrite` is implemented for every row struct and is called from deep inside the `WriteTree` fns; passing this down each of them is not needed for opt-in modes.
+// - Used by `history`, `store_raw_json`, `metadata_columns`, `notify`, `cdc` and `dry_run`.
+pub struct WriteCtx {
+    history: bool,
+    raw_json: bool,
+    metadata_cols: MetadataCols,
+
+    // The `Insert` tables with the columns added by `store_raw_json` and `metadata_columns`, by name.
+    extra_tables: HashMap<String, Table>,
+
+    // `store_raw_json`: the JSON of API responses (shared with the `StripeClient`), and of the top level object being written.
+    raw_index: Option<RawIndex>,
+    raw_scope: RefCell<Option<Value>>,
+
+    notify: bool,
+    mrr_movements: bool,
+    cdc: Option<RefCell<Cdc>>,
+
+    // Column changes of updated rows (Some when `options.dry_run`).
+    dry_run: Option<RefCell<Vec<RowUpdate>>>,
+
+    // The event being applied (`id`, `created`).
+    event: RefCell<Option<(String, DT3)>>,
+
+    // (`run_id`, `start_ts`) of the last run rows were written for without an event.
+    run_start: RefCell<Option<(i64, DT3)>>,
+}
+
+impl WriteCtx {
+    pub fn new(opts: &Options) -> Result<WriteCtx, TdError> {
+        let metadata_cols = MetadataCols::try_from(&opts.metadata_columns)?;
+
+        // The same tables as created with the DB; rows of excluded tables are never written.
+        let mut extra = vec![];
+        if opts.store_raw_json {
+            raw_json::add_raw_tables(&ObjectSelection::default(), &mut vec![], &mut extra);
+        }
+        metadata_cols.add_tables(&mut vec![], &mut extra);
+
+        Ok(WriteCtx {
+            history: opts.history,
+            raw_json: opts.store_raw_json,
+            metadata_cols,
+            extra_tables: extra.into_iter().map(|t| (t.name.clone(), t)).collect(),
+            raw_index: if opts.store_raw_json { Some(RawIndex::default()) } else { None },
+            raw_scope: RefCell::new(None),
+            notify: opts.notify,
+            // Dry runs do not create the table.
+            mrr_movements: opts.mrr_movements && !opts.dry_run,
+            cdc: opts.cdc.as_ref().map(Cdc::new).transpose()?.map(RefCell::new),
+            dry_run: if opts.dry_run { Some(RefCell::new(vec![])) } else { None },
+            event: RefCell::new(None),
+            run_start: RefCell::new(None),
+        })
+    }
+
+    // Set as `StripeClient.raw` so the client keeps the JSON of its responses.
+    pub fn get_raw_index(&self) -> Option<RawIndex> {
+        self.raw_index.clone()
+    }
+}
+
+tokio::task_local! {
+    static WRITE_CTX: WriteCtx;
+}
+
+pub fn with_write_ctx<F: Future>(ctx: WriteCtx, f: F) -> impl Future<Output=F::Output> {
+    WRITE_CTX.scope(ctx, f)
+}
+
+pub fn is_history_enabled() -> bool {
+    WRITE_CTX.try_with(|x| x.history).unwrap_or(false)
+}
+
+pub fn is_raw_json_enabled() -> bool {
+    WRITE_CTX.try_with(|x| x.raw_json).unwrap_or(false)
+}
+
+// Runs `f` with the table that has the columns added by `store_raw_json` and `metadata_columns` (None when neither applies to it, or outside of a `WriteCtx`).
+pub fn with_extra_table<R>(table_name: &str, f: impl FnOnce(Option<(&Table, &MetadataCols)>) -> R) -> R {
+    let mut f = Some(f);
+    match WRITE_CTX.try_with(|x| (f.take().unwrap())(x.extra_tables.get(table_name).map(|t| (t, &x.metadata_cols)))) {
+        Ok(r) => r,
+        Err(_) => (f.take().unwrap())(None)
+    }
+}
+
+// Keeps the JSON of objects not read from the `StripeClient` (E.g. replayed events).
+pub fn add_raw(x: &Value) {
+    let _ = WRITE_CTX.try_with(|ctx| {
+        if let Some(i) = &ctx.raw_index {
+            i.add(x);
+        }
+    });
+}
+
+pub fn take_raw(object: &str, id: &str) -> Option<Value> {
+    WRITE_CTX.try_with(|x| x.raw_index.as_ref().and_then(|i| i.take(object, id))).ok().flatten()
+}
+
+// Drops the JSON of objects that were not written (E.g. events of excluded tables); called after each run.
+pub fn clear_raw() {
+    let _ = WRITE_CTX.try_with(|x| {
+        if let Some(i) = &x.raw_index {
+            i.clear();
+        }
+        *x.raw_scope.borrow_mut() = None;
+    });
+}
+
+pub fn set_raw_scope(raw: Option<Value>) {
+    let _ = WRITE_CTX.try_with(|x| {
+        *x.raw_scope.borrow_mut() = raw;
+    });
+}
+
+pub fn find_in_raw_scope(object: &str, id: &str) -> Option<Value> {
+    WRITE_CTX.try_with(|x| x.raw_scope.borrow().as_ref().and_then(|raw| raw_json::find_by_id(raw, object, id)).cloned()).ok().flatten()
+}
+
+pub fn is_notify_enabled() -> bool {
+    WRITE_CTX.try_with(|x| x.notify).unwrap_or(false)
+}
+
+pub fn is_mrr_movements_enabled() -> bool {
+    WRITE_CTX.try_with(|x| x.mrr_movements).unwrap_or(false)
+}
+
+pub fn is_cdc_enabled() -> bool {
+    WRITE_CTX.try_with(|x| x.cdc.is_some()).unwrap_or(false)
+}
+
+// Returns None when CDC is not enabled.
+pub fn with_cdc<R>(f: impl FnOnce(&mut Cdc) -> R) -> Option<R> {
+    WRITE_CTX.try_with(|x| x.cdc.as_ref().map(|c| f(&mut c.borrow_mut()))).ok().flatten()
+}
+
+pub fn is_dry_run() -> bool {
+    WRITE_CTX.try_with(|x| x.dry_run.is_some()).unwrap_or(false)
+}
+
+// Returns None when not a dry run.
+pub fn with_dry_run<R>(f: impl FnOnce(&mut Vec<RowUpdate>) -> R) -> Option<R> {
+    WRITE_CTX.try_with(|x| x.dry_run.as_ref().map(|c| f(&mut c.borrow_mut()))).ok().flatten()
+}
+
+// Set before writing each event's objects, None after (downloads have no event).
+pub fn set_event(event: Option<(String, DT3)>) {
+    let _ = WRITE_CTX.try_with(|x| {
+        *x.event.borrow_mut() = event;
+    });
+}
+
+pub fn get_event_id() -> Option<String> {
+    WRITE_CTX.try_with(|x| x.event.borrow().as_ref().map(|(id, _)| id.clone())).ok().flatten()
+}
+
+pub fn get_event_created() -> Option<DT3> {
+    WRITE_CTX.try_with(|x| x.event.borrow().as_ref().map(|(_, created)| created.clone())).ok().flatten()
+}
+
+pub fn get_run_start(run_id: i64) -> Option<DT3> {
+    WRITE_CTX.try_with(|x| x.run_start.borrow().as_ref().filter(|(id, _)| *id == run_id).map(|(_, ts)| ts.clone())).ok().flatten()
+}
+
+pub fn set_run_start(run_id: i64, start_ts: DT3) {
+    let _ = WRITE_CTX.try_with(|x| {
+        *x.run_start.borrow_mut() = Some((run_id, start_ts));
+    });
+}
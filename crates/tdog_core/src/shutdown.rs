@@ -0,0 +1,70 @@
+use std::sync::Once;
+
+use tokio::sync::watch;
+
+// Graceful shutdown on SIGINT/SIGTERM (E.g. `docker stop`, Kubernetes pod termination).
+// - Polling: the current `apply_events` tx is completed, then the poll loop stops.
+// - First download: the download is stopped and its tx is rolled back (it cannot be resumed); the job returns `TdError::Interrupted`.
+// - A second signal exits immediately.
+
+// `128 + SIGINT`, the shell convention for a process killed by Ctrl+C.
+const EXIT_CODE_SECOND_SIGNAL: i32 = 130;
+
+lazy_static! {
+    // Name of the signal received, None = running.
+    static ref SHUTDOWN: (watch::Sender<Option<&'static str>>, watch::Receiver<Option<&'static str>>) = watch::channel(None);
+}
+
+static LISTEN: Once = Once::new();
+
+
+// Install the signal handlers once per process (many jobs can call this).
+// - Note: Once installed, signals no longer terminate the process by default.
+pub fn listen_for_signals() {
+    LISTEN.call_once(|| {
+        tokio::spawn(async {
+            let sig = wait_for_signal().await;
+            warn!("Received {}. Shutting down gracefully (send it again to exit immediately).", sig);
+            SHUTDOWN.0.broadcast(Some(sig)).unwrap();
+
+            let sig = wait_for_signal().await;
+            error!("Received {} again. Exiting immediately; any open DB transactions will be rolled back.", sig);
+            std::process::exit(EXIT_CODE_SECOND_SIGNAL);
+        });
+    });
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> &'static str {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut term = signal(SignalKind::terminate()).unwrap();
+    tokio::select! {
+        _ = term.recv() => "SIGTERM",
+        _ = tokio::signal::ctrl_c() => "SIGINT",
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() -> &'static str {
+    tokio::signal::ctrl_c().await.unwrap();
+    "SIGINT"
+}
+
+
+pub fn is_shutdown() -> bool {
+    SHUTDOWN.1.borrow().is_some()
+}
+
+// Resolves with the signal name once a shutdown has been requested (immediately if it already has).
+pub async fn wait_for_shutdown() -> &'static str {
+    let mut rx = SHUTDOWN.1.clone();
+
+    loop {
+        match rx.recv().await {
+            Some(Some(sig)) => return sig,
+            Some(None) => continue,
+            None => futures::future::pending().await
+        }
+    }
+}
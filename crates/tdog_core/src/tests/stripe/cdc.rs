@@ -0,0 +1,284 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Map, Value};
+use stripe_client::http::http::{Config, StripeAccount, StripeClient};
+use unicon::traits::{*};
+use unicon::uc::UniCon;
+
+use crate::{CdcFile, CdcTo, CdcUnixSocket, Options};
+use crate::fns::now_3;
+use crate::providers::stripe::apply_events::apply_events;
+use crate::providers::stripe::cdc::{catch_up, tx_committed, tx_start};
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::Customer;
+use crate::providers::stripe::schema_meta::{LogWrite, TdMetadata, TdRun, TdStripeApplyEvent};
+use crate::providers::stripe::schema::util::ToDT;
+use crate::providers::stripe::write_ctx::{set_event, with_write_ctx, WriteCtx};
+use crate::tests::stripe::util::get_unicon;
+use crate::util::{get_temp_file, get_unique_id};
+
+
+fn read_lines(file: &str) -> Vec<Value> {
+    fs::read_to_string(file).unwrap().lines().map(|x| serde_json::from_str(x).unwrap()).collect()
+}
+
+#[tokio::main]
+#[test]
+async fn cdc_file_lines_after_commit() {
+    let db_file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let cdc_file = get_temp_file(get_unique_id() + ".jsonl").unwrap();
+    let mut uc = get_unicon(&db_file);
+    uc.ensure_schema_and_tables_exist_and_writable::<Db>();
+
+    let ctx = WriteCtx::new(&Options {
+        cdc: Some(CdcTo::File(CdcFile {
+            file: cdc_file.clone(),
+            max_bytes: 1024 * 1024,
+            max_files: 2,
+        })),
+        ..Default::default()
+    }).unwrap();
+
+    let write_ids = with_write_ctx(ctx, async {
+        let mut c = Customer {
+            id: "cus_1".into(),
+            name: Some("a".into()),
+            ..Default::default()
+        };
+
+        // Rolled back: no lines.
+        tx_start();
+        let mut utx = uc.tx_open().unwrap();
+        c.clone().tx_insert_set_pk_log_write(&mut utx, 1);
+        utx.tx_rollback().unwrap();
+
+        tx_start();
+        set_event(Some(("evt_1".into(), 1_600_000_000.to_dt3())));
+        let mut utx = uc.tx_open().unwrap();
+        let a = c.tx_insert_set_pk_log_write(&mut utx, 1);
+        c.name = Some("b".into());
+        let b = c.tx_update_log_write(&mut utx, 1, "id");
+        let d = c.tx_delete_log_write(&mut utx, 1, "id");
+        utx.tx_close().unwrap();
+        tx_committed(&mut uc).await.unwrap();
+
+        vec![a, b, d]
+    }).await;
+
+    let lines = read_lines(&cdc_file);
+    assert_eq!(lines.len(), 3);
+
+    let offsets: Vec<i64> = lines.iter().map(|x| x["offset"].as_i64().unwrap()).collect();
+    assert_eq!(offsets, write_ids);
+
+    let write_types: Vec<&str> = lines.iter().map(|x| x["write_type"].as_str().unwrap()).collect();
+    assert_eq!(write_types, vec!["c", "u", "d"]);
+
+    assert_eq!(lines[0]["table"], "customers");
+    assert_eq!(lines[0]["event_id"], "evt_1");
+    assert_eq!(lines[1]["row"]["name"], "b");
+    assert!(lines[2]["row"].is_null());
+}
+
+#[tokio::main]
+#[test]
+async fn cdc_file_rotates() {
+    let db_file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let cdc_file = get_temp_file(get_unique_id() + ".jsonl").unwrap();
+    let mut uc = get_unicon(&db_file);
+    uc.ensure_schema_and_tables_exist_and_writable::<Db>();
+
+    let ctx = WriteCtx::new(&Options {
+        cdc: Some(CdcTo::File(CdcFile {
+            file: cdc_file.clone(),
+            max_bytes: 100,
+            max_files: 1,
+        })),
+        ..Default::default()
+    }).unwrap();
+
+    with_write_ctx(ctx, async {
+        for i in 0..3 {
+            let mut c = Customer {
+                id: format!("cus_{}", i),
+                ..Default::default()
+            };
+
+            tx_start();
+            let mut utx = uc.tx_open().unwrap();
+            c.tx_insert_set_pk_log_write(&mut utx, 1);
+            utx.tx_close().unwrap();
+            tx_committed(&mut uc).await.unwrap();
+        }
+    }).await;
+
+    // One tx per file; the oldest is removed.
+    assert_eq!(read_lines(&cdc_file)[0]["obj_id"], "cus_2");
+    assert_eq!(read_lines(&format!("{}.1", &cdc_file))[0]["obj_id"], "cus_1");
+    assert!(!Path::new(&format!("{}.2", &cdc_file)).exists());
+}
+
+fn get_file_ctx(cdc_file: &str) -> WriteCtx {
+    WriteCtx::new(&Options {
+        cdc: Some(CdcTo::File(CdcFile {
+            file: cdc_file.to_string(),
+            max_bytes: 1024 * 1024,
+            max_files: 2,
+        })),
+        ..Default::default()
+    }).unwrap()
+}
+
+// Commits one customer insert in an `apply_events` run; returns the `write_id`.
+fn insert_customer(uc: &mut UniCon, id: &str) -> i64 {
+    let mut run = TdRun {
+        run_id: None,
+        r#type: "apply_events".into(),
+        start_ts: Some(now_3()),
+        end_ts: None,
+    };
+    run.insert_set_pk(uc);
+
+    let mut utx = uc.tx_open().unwrap();
+    let write_id = Customer { id: id.into(), name: Some("a".into()), ..Default::default() }.tx_insert_set_pk_log_write(&mut utx, run.run_id.unwrap());
+    TdStripeApplyEvent {
+        apply_id: None,
+        run_id: run.run_id.unwrap(),
+        event_id: format!("evt_{}", id),
+        action: "applied".into(),
+        write_ids: Some(json!([write_id])),
+        insert_ts: None,
+    }.tx_insert_set_pk(&mut utx);
+    utx.tx_close().unwrap();
+
+    write_id
+}
+
+// Committed writes that were not output before the process stopped are read from `td_stripe_writes` on the next start.
+#[tokio::main]
+#[test]
+async fn cdc_catch_up_after_restart() {
+    let db_file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let cdc_file = get_temp_file(get_unique_id() + ".jsonl").unwrap();
+    let mut uc = get_unicon(&db_file);
+    uc.ensure_schema_and_tables_exist_and_writable::<Db>();
+
+    let a = with_write_ctx(get_file_ctx(&cdc_file), async {
+        tx_start();
+        let a = insert_customer(&mut uc, "cus_1");
+        tx_committed(&mut uc).await.unwrap();
+
+        // Stopped after the commit, before the lines were written.
+        tx_start();
+        insert_customer(&mut uc, "cus_2");
+        a
+    }).await;
+    assert_eq!(read_lines(&cdc_file).len(), 1);
+
+    with_write_ctx(get_file_ctx(&cdc_file), async {
+        catch_up(&mut uc).await.unwrap();
+
+        // Nothing new: no duplicates.
+        catch_up(&mut uc).await.unwrap();
+    }).await;
+
+    let lines = read_lines(&cdc_file);
+    let offsets: Vec<i64> = lines.iter().map(|x| x["offset"].as_i64().unwrap()).collect();
+    assert_eq!(offsets, vec![a, a + 1]);
+    assert_eq!(lines[1]["obj_id"], "cus_2");
+    assert_eq!(lines[1]["event_id"], "evt_cus_2");
+    assert_eq!(lines[1]["row"]["name"], "a");
+}
+
+// A poll with no events writes the committed lines the output missed, without waiting for the next event.
+#[tokio::main]
+#[test]
+async fn cdc_catch_up_on_empty_poll() {
+    let db_file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let cdc_file = get_temp_file(get_unique_id() + ".jsonl").unwrap();
+    let mut uc = get_unicon(&db_file);
+    uc.ensure_schema_and_tables_exist_and_writable::<Db>();
+    TdMetadata::insert_cli_and_stripe_versions(&mut uc, &StripeAccount {
+        id: "acct_1".into(),
+        is_test: true,
+        account: Map::new(),
+    }, None);
+
+    TdRun {
+        run_id: None,
+        r#type: "download".into(),
+        start_ts: Some(now_3()),
+        end_ts: Some(now_3()),
+    }.insert_set_pk(&mut uc);
+
+    // The second insert was committed by a previous process that stopped before writing its line.
+    let a = with_write_ctx(get_file_ctx(&cdc_file), async {
+        tx_start();
+        let a = insert_customer(&mut uc, "cus_1");
+        tx_committed(&mut uc).await.unwrap();
+
+        tx_start();
+        insert_customer(&mut uc, "cus_2");
+        a
+    }).await;
+    assert_eq!(read_lines(&cdc_file).len(), 1);
+
+    let c = StripeClient::new(Config {
+        secret_key: "dummy test".to_string(),
+        is_test: true,
+        base: "dummy test".to_string(),
+        headers: None,
+        proxy: None,
+        timeout_ms: None,
+        retry: false,
+        log_requests: false,
+    });
+
+    with_write_ctx(get_file_ctx(&cdc_file), async {
+        assert!(apply_events(&c, &mut uc, Some(vec![]), &ObjectSelection::default()).await.unwrap().is_none());
+    }).await;
+
+    let offsets: Vec<i64> = read_lines(&cdc_file).iter().map(|x| x["offset"].as_i64().unwrap()).collect();
+    assert_eq!(offsets, vec![a, a + 1]);
+}
+
+// Lines are not kept in RAM when the output cannot be written to; they are read back from the DB once it can.
+#[cfg(unix)]
+#[tokio::main]
+#[test]
+async fn cdc_unix_socket_unavailable() {
+    use std::io::Read;
+    use std::os::unix::net::UnixListener;
+
+    let db_file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let socket = std::env::temp_dir().join(get_unique_id() + ".sock").to_str().unwrap().to_string();
+    let mut uc = get_unicon(&db_file);
+    uc.ensure_schema_and_tables_exist_and_writable::<Db>();
+
+    let ctx = WriteCtx::new(&Options {
+        cdc: Some(CdcTo::UnixSocket(CdcUnixSocket { file: socket.clone() })),
+        ..Default::default()
+    }).unwrap();
+
+    let listener = with_write_ctx(ctx, async {
+        // No consumer is listening.
+        tx_start();
+        insert_customer(&mut uc, "cus_1");
+        tx_committed(&mut uc).await.unwrap();
+
+        let listener = UnixListener::bind(&socket).unwrap();
+        catch_up(&mut uc).await.unwrap();
+        listener
+    }).await;
+
+    let (mut con, _) = listener.accept().unwrap();
+    con.set_read_timeout(Some(std::time::Duration::from_millis(100))).unwrap();
+    let mut s = String::new();
+    let _ = con.read_to_string(&mut s);
+
+    let lines: Vec<Value> = s.lines().map(|x| serde_json::from_str(x).unwrap()).collect();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["obj_id"], "cus_1");
+}
@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+use serde_json::Map;
+use stripe_client::http::http::{Config, StripeAccount, StripeClient};
+use unicon::traits::{*};
+use unicon::uc::{Engine, SQLiteCreate, UniCon};
+
+use crate::{Check, Options};
+use crate::error::TdError;
+use crate::providers::stripe::check::check;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::Price;
+use crate::providers::stripe::schema::util::ToDT;
+use crate::providers::stripe::schema_meta::{TdMetadata, TdMissingOwner, TdRun};
+use crate::providers::stripe::write_ctx::{with_write_ctx, WriteCtx};
+use crate::tests::stripe::util::{get_item, get_sub, get_unicon};
+use crate::util::{get_temp_file, get_unique_id};
+
+// `subscription_items.price` (enforced by the API) and `subscriptions.customer` (not enforced) reference missing rows.
+fn get_db(file: &str, price: &str) -> UniCon {
+    let mut uc = get_unicon(file);
+    uc.ensure_schema_and_tables_exist_and_writable::<Db>();
+    TdMetadata::insert_cli_and_stripe_versions(&mut uc, &StripeAccount {
+        id: "acct_1".into(),
+        is_test: true,
+        account: Map::new(),
+    }, None);
+
+    let mut utx = uc.tx_open().unwrap();
+    get_item("si_1", "sub_1", price, None).tx_insert_set_pk(&mut utx);
+    get_sub("sub_1", "cus_missing", "active", None).tx_insert_set_pk(&mut utx);
+    Price { id: "price_1".into(), currency: "usd".into(), ..Default::default() }.tx_insert_set_pk(&mut utx);
+    utx.tx_close().unwrap();
+
+    uc
+}
+
+fn get_check(file: &str, repair: bool) -> Check {
+    Check {
+        db: Engine::SQLite(SQLiteCreate { file: file.to_string() }),
+        from: None,
+        repair,
+        options: Options::default(),
+    }
+}
+
+// Without `from` the client is never used.
+fn get_client() -> StripeClient {
+    StripeClient::new(Config {
+        secret_key: "".into(),
+        is_test: true,
+        base: "".into(),
+        headers: None,
+        proxy: None,
+        timeout_ms: None,
+        retry: false,
+        log_requests: false,
+    })
+}
+
+#[tokio::main]
+#[test]
+async fn check_missing_owners() {
+    let sc = get_client();
+
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_db(&file, "price_1");
+    let ck = get_check(&file, false);
+    let report = with_write_ctx(WriteCtx::new(&ck.options).unwrap(), check(&sc, &mut uc, &ck)).await.unwrap();
+
+    let customer = report.edges.iter().find(|x| x.edge == "subscriptions.customer -> customers").unwrap();
+    assert!(!customer.enforced_by_api);
+    assert_eq!(customer.missing, vec![("sub_1".to_string(), "cus_missing".to_string())]);
+    assert_eq!(report.get_missing_enforced(), 0);
+
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_db(&file, "price_missing");
+    let ck = get_check(&file, false);
+    let res = with_write_ctx(WriteCtx::new(&ck.options).unwrap(), check(&sc, &mut uc, &ck)).await;
+    assert!(matches!(res, Err(TdError::Integrity(_))));
+
+    // Repairing downloads from Stripe.
+    let ck = get_check(&file, true);
+    let res = with_write_ctx(WriteCtx::new(&ck.options).unwrap(), check(&sc, &mut uc, &ck)).await;
+    assert!(matches!(res, Err(TdError::Config(_))));
+
+    // Owners Stripe returned as deleted or missing are allowed; the other enforced edges are still checked (also with `options.created_gte`).
+    TdMissingOwner { missing_owner_id: None, run_id: 1, table_name: "prices".into(), obj_id: "price_missing".into(), insert_ts: None }.insert_set_pk(&mut uc);
+    let ck = get_check(&file, false);
+    let report = with_write_ctx(WriteCtx::new(&ck.options).unwrap(), check(&sc, &mut uc, &ck)).await.unwrap();
+    assert_eq!(report.get_missing_enforced(), 0);
+    let gone = TdMissingOwner::get_keys(&mut uc);
+    assert_eq!(Db::get_missing_owner_all(&uc, &Default::default(), &gone).len(), 0);
+    assert_eq!(Db::get_missing_owner_all(&uc, &Default::default(), &HashSet::new()).len(), 1);
+}
+
+fn insert_run(uc: &mut UniCon, r#type: &str, end: i64) {
+    TdRun {
+        run_id: None,
+        r#type: r#type.into(),
+        start_ts: Some(end.to_dt3()),
+        end_ts: Some(end.to_dt3()),
+    }.insert_set_pk(uc);
+}
+
+// A repair does not restart the 28 day window of the events, or end the first download (the `since` cutoff of the first `apply_events`).
+#[test]
+fn check_repair_is_not_an_event_run() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_db(&file, "price_1");
+    let now = Utc::now().timestamp();
+
+    insert_run(&mut uc, "download", now - 60);
+    insert_run(&mut uc, "repair", now);
+    assert!(TdRun::get_last_run(&mut uc).unwrap().is_download());
+    assert_eq!(TdRun::is_apply_events_possible(&mut uc), Some(true));
+
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_db(&file, "price_1");
+    insert_run(&mut uc, "download", now - 40 * 86400);
+    insert_run(&mut uc, "apply_events", now - 29 * 86400);
+    insert_run(&mut uc, "repair", now);
+    assert_eq!(TdRun::get_last_run(&mut uc).unwrap().r#type, "apply_events");
+    assert_eq!(TdRun::is_apply_events_possible(&mut uc), Some(false));
+}
@@ -0,0 +1,34 @@
+use chrono::{TimeZone, Utc};
+use stripe_client::types::req_params::UniCreated;
+
+use crate::providers::stripe::dl::get_created_filter;
+use crate::util::parse_created_gte;
+
+
+#[test]
+fn created_gte_parse() {
+    let now = Utc.ymd(2021, 6, 1).and_hms(12, 0, 0);
+    let day = 24 * 60 * 60;
+
+    assert_eq!(parse_created_gte("2021-01-01", now), Some(Utc.ymd(2021, 1, 1).and_hms(0, 0, 0).timestamp()));
+    assert_eq!(parse_created_gte("2021-01-01T10:00:00Z", now), Some(Utc.ymd(2021, 1, 1).and_hms(10, 0, 0).timestamp()));
+    assert_eq!(parse_created_gte("2021-01-01T10:00:00+01:00", now), Some(Utc.ymd(2021, 1, 1).and_hms(9, 0, 0).timestamp()));
+    assert_eq!(parse_created_gte("90days", now), Some(now.timestamp() - (90 * day)));
+    assert_eq!(parse_created_gte("1week 2days", now), Some(now.timestamp() - (9 * day)));
+
+    assert_eq!(parse_created_gte("last year", now), None);
+    assert_eq!(parse_created_gte("2021-13-01", now), None);
+}
+
+#[test]
+fn created_gte_list_filter() {
+    assert!(get_created_filter(None).is_none());
+
+    match get_created_filter(Some(1609459200)) {
+        Some(UniCreated::RangeQuerySpecs(x)) => {
+            assert_eq!(x.gte, Some(1609459200));
+            assert!(x.gt.is_none() && x.lt.is_none() && x.lte.is_none());
+        }
+        _ => panic!("Expected a range filter.")
+    }
+}
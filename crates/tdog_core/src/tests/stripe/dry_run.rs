@@ -0,0 +1,79 @@
+use rusqlite::Connection;
+use serde_json::json;
+
+use crate::Options;
+use crate::providers::stripe::dry_run::{ColChange, get_col_changes, tx_get_summary};
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::Customer;
+use crate::providers::stripe::schema_meta::LogWrite;
+use crate::providers::stripe::write_ctx::{with_write_ctx, WriteCtx};
+use crate::tests::stripe::util::get_unicon;
+use crate::util::{get_temp_file, get_unique_id};
+
+
+// (id, name)
+fn get_customers(file: &str) -> Vec<(String, Option<String>)> {
+    let c = Connection::open(file).unwrap();
+    let mut stmt = c.prepare("SELECT id, name FROM customers ORDER BY id").unwrap();
+    let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?))).unwrap();
+    rows.map(|x| x.unwrap()).collect()
+}
+
+#[tokio::main]
+#[test]
+async fn dry_run_summary_and_rollback() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    assert!(!uc.schema_exists_with::<Db>(&vec![], &vec![]).unwrap());
+    uc.ensure_schema_and_tables_exist_and_writable::<Db>();
+    assert!(uc.schema_exists_with::<Db>(&vec![], &vec![]).unwrap());
+
+    let mut c = Customer {
+        id: "cus_1".into(),
+        name: Some("a".into()),
+        ..Default::default()
+    };
+
+    let mut utx = uc.tx_open().unwrap();
+    c.tx_insert_set_pk_log_write(&mut utx, 1);
+    utx.tx_close().unwrap();
+
+    let ctx = WriteCtx::new(&Options { dry_run: true, ..Default::default() }).unwrap();
+    let x = with_write_ctx(ctx, async {
+        let mut utx = uc.tx_open().unwrap();
+
+        c.name = Some("b".into());
+        c.tx_update_log_write(&mut utx, 2, "id");
+
+        let mut c2 = Customer {
+            id: "cus_2".into(),
+            ..Default::default()
+        };
+        c2.tx_insert_set_pk_log_write(&mut utx, 2);
+
+        let x = tx_get_summary(&mut utx, 2, 2);
+        utx.tx_rollback().unwrap();
+        x
+    }).await;
+
+    assert_eq!(x.events, 2);
+    let t = &x.tables["customers"];
+    assert_eq!((t.inserts, t.updates, t.deletes), (1, 1, 0));
+    assert_eq!(x.updates.len(), 1);
+    assert_eq!(x.updates[0].id, "cus_1");
+    assert_eq!(x.updates[0].cols.len(), 1);
+    assert_eq!(x.updates[0].cols["name"], ColChange { from: json!("a"), to: json!("b") });
+
+    assert_eq!(get_customers(&file), vec![("cus_1".into(), Some("a".into()))]);
+}
+
+#[test]
+fn dry_run_col_changes() {
+    let x = get_col_changes(
+        &json!({"id": "cus_1", "name": "a", "email": null, "update_ts": 1}),
+        &json!({"id": "cus_1", "name": "a", "email": "a@example.com", "update_ts": 2}),
+    );
+
+    assert_eq!(x.len(), 1);
+    assert_eq!(x["email"], ColChange { from: json!(null), to: json!("a@example.com") });
+}
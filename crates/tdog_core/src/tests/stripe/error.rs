@@ -0,0 +1,69 @@
+use stripe_client::http::http::UniErr;
+use stripe_client::types::types::Error;
+
+use crate::error::TdError;
+
+
+fn get_stripe_err(type_x: &str, code: Option<&str>) -> UniErr {
+    let json = serde_json::json!({
+        "error": {
+            "type": type_x,
+            "code": code,
+            "message": "msg",
+            "payment_intent": null,
+            "payment_method": null,
+            "setup_intent": null,
+        }
+    });
+
+    let e: Error = serde_json::from_value(json).unwrap();
+    e.into()
+}
+
+#[test]
+fn error_from_stripe() {
+    assert!(matches!(TdError::from(get_stripe_err("authentication_error", None)), TdError::StripeAuth(_)));
+    assert!(matches!(TdError::from(get_stripe_err("rate_limit_error", None)), TdError::RateLimit(_)));
+    assert!(matches!(TdError::from(get_stripe_err("invalid_request_error", Some("rate_limit"))), TdError::RateLimit(_)));
+    assert!(matches!(TdError::from(get_stripe_err("api_connection_error", None)), TdError::Network(_)));
+    assert!(matches!(TdError::from(get_stripe_err("invalid_request_error", Some("resource_missing"))), TdError::Internal(_)));
+}
+
+#[test]
+fn error_exit_codes_are_unique() {
+    let all = vec![
+        TdError::Config("".into()),
+        TdError::StripeAuth("".into()),
+        TdError::RateLimit("".into()),
+        TdError::Network("".into()),
+        TdError::VersionMismatch("".into()),
+        TdError::SchemaConflict("".into()),
+        TdError::Db("".into()),
+        TdError::Interrupted("".into()),
+        TdError::Internal("".into()),
+    ];
+
+    let mut codes: Vec<i32> = all.iter().map(|x| x.get_exit_code()).collect();
+    codes.sort();
+    codes.dedup();
+    assert_eq!(codes.len(), all.len());
+    assert!(!codes.contains(&0));
+}
+
+#[test]
+fn error_json_line() {
+    let e = TdError::StripeAuth("Invalid API Key".into());
+    let v: serde_json::Value = serde_json::from_str(&e.to_json_line(Some("acc_a"))).unwrap();
+
+    assert_eq!(v["error"]["kind"], "stripe_auth");
+    assert_eq!(v["error"]["exit_code"], 11);
+    assert_eq!(v["error"]["retryable"], false);
+    assert_eq!(v["error"]["job"], "acc_a");
+    assert_eq!(v["error"]["msg"], "Invalid API Key");
+}
+
+#[test]
+fn error_from_panic() {
+    let res = std::panic::catch_unwind(|| panic!("x={}", 1));
+    assert_eq!(TdError::from_panic(res.unwrap_err()).get_msg(), "x=1");
+}
@@ -0,0 +1,114 @@
+use std::fs;
+
+use rusqlite::Connection;
+use serde_json::Map;
+use stripe_client::http::http::StripeAccount;
+use unicon::traits::{*};
+use unicon::uc::{Engine, SQLiteCreate};
+
+use crate::{EventsExport, EventsImport, Options};
+use crate::error::TdError;
+use crate::providers::stripe::event_archive::{export, import, Manifest};
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::notification_event::NotificationEvent;
+use crate::providers::stripe::schema_meta::TdMetadata;
+use crate::tests::stripe::util::{get_events, get_unicon, read_file};
+use crate::util::{get_temp_file, get_unique_id};
+
+
+fn get_count(file: &str) -> i64 {
+    Connection::open(file).unwrap().query_row("SELECT COUNT(*) FROM notification_events", [], |r| r.get(0)).unwrap()
+}
+
+fn get_sqlite(file: &str) -> Engine {
+    Engine::SQLite(SQLiteCreate { file: file.to_string() })
+}
+
+#[test]
+fn event_archive_export_import() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    uc.ensure_schema_and_tables_exist_and_writable::<Db>();
+    TdMetadata::insert_cli_and_stripe_versions(&mut uc, &StripeAccount {
+        id: "acct_1".into(),
+        is_test: true,
+        account: Map::new(),
+    }, None);
+
+    let mut events = get_events("customer_1");
+
+    // Next day: a second segment.
+    events[3].created += 86_400;
+
+    let mut utx = uc.tx_open().unwrap();
+    for e in &events[0..3] {
+        NotificationEvent::from(e).tx_insert_set_pk(&mut utx);
+    }
+    utx.tx_close().unwrap();
+
+    let dir = get_temp_file(get_unique_id()).unwrap();
+    fs::remove_file(&dir).unwrap();
+    let mut x = EventsExport {
+        db: get_sqlite(&file),
+        dir: dir.clone(),
+        retention_days: None,
+        options: Options::default(),
+    };
+
+    assert_eq!(export(&mut uc, &x).unwrap(), 3);
+    assert_eq!(export(&mut uc, &x).unwrap(), 0);
+
+    // Appended to the existing segment and a new one.
+    let mut utx = uc.tx_open().unwrap();
+    NotificationEvent::from(&events[3]).tx_insert_set_pk(&mut utx);
+    let mut extra = events[0].clone();
+    extra.id = "evt_extra".into();
+    NotificationEvent::from(&extra).tx_insert_set_pk(&mut utx);
+    utx.tx_close().unwrap();
+    assert_eq!(export(&mut uc, &x).unwrap(), 2);
+
+    let mut files: Vec<String> = fs::read_dir(&dir).unwrap().map(|x| x.unwrap().file_name().to_string_lossy().to_string()).collect();
+    files.sort();
+    assert_eq!(files, vec!["events-2022-05-11.jsonl.gz", "events-2022-05-12.jsonl.gz", "manifest.json"]);
+
+    let m: Manifest = serde_json::from_str(&read_file(&format!("{}/manifest.json", &dir))).unwrap();
+    assert_eq!(m.last_event_id, 5);
+
+    // Into a fresh DB: only `notification_events` is created; importing again skips existing rows.
+    let file_2 = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc_2 = get_unicon(&file_2);
+    let y = EventsImport {
+        dir: dir.clone(),
+        to: get_sqlite(&file_2),
+        options: Options::default(),
+    };
+    assert_eq!(import(&mut uc_2, &y).unwrap(), 5);
+    assert_eq!(import(&mut uc_2, &y).unwrap(), 0);
+    assert_eq!(get_count(&file_2), 5);
+
+    let tables: i64 = Connection::open(&file_2).unwrap().query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name != 'sqlite_sequence'", [], |r| r.get(0)).unwrap();
+    assert_eq!(tables, 1);
+
+    let a: NotificationEvent = NotificationEvent::get_where(&mut uc, ("id", &events[1].id)).pop().unwrap();
+    let b: NotificationEvent = NotificationEvent::get_where(&mut uc_2, ("id", &events[1].id)).pop().unwrap();
+    assert_eq!(a.data_object, b.data_object);
+    assert_eq!(a.created, b.created);
+
+    // Retention: the last exported row is kept.
+    x.retention_days = Some(0);
+    assert_eq!(export(&mut uc, &x).unwrap(), 0);
+    assert_eq!(get_count(&file), 1);
+
+    // Another account cannot use the same directory.
+    let file_3 = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc_3 = get_unicon(&file_3);
+    uc_3.ensure_schema_and_tables_exist_and_writable::<Db>();
+    TdMetadata::insert_cli_and_stripe_versions(&mut uc_3, &StripeAccount {
+        id: "acct_2".into(),
+        is_test: true,
+        account: Map::new(),
+    }, None);
+    assert!(export(&mut uc_3, &x).is_err());
+    assert!(matches!(import(&mut uc_3, &EventsImport { to: get_sqlite(&file_3), ..y.clone() }), Err(TdError::SchemaConflict(_))));
+    assert_eq!(get_count(&file_3), 0);
+}
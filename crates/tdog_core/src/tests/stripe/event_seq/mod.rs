@@ -1,5 +1,5 @@
 use std::{sync::Mutex, time};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
 use std::path::{Path};
 
@@ -15,6 +15,7 @@ use unicon::uc::{*};
 
 use crate::providers::stripe::apply_events::apply_events;
 use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::objects::ObjectSelection;
 
 use crate::tests::stripe::util::{cp_to_temp_and_get_uc, init_log_output, path_from_cargo, read_file};
 use stripe_client::http::http::{StripeClient, Config};
@@ -150,7 +151,7 @@ fn assert_relations(db_file: &str) {
         c: Connection::open(&db_file).unwrap(),
     });
 
-    let missing = Db::get_missing_owner_all(&uc);
+    let missing = Db::get_missing_owner_all(&uc, &ObjectSelection::default(), &HashSet::new());
     if missing.len() > 0 {
         dbg!(&missing);
     }
@@ -206,7 +207,7 @@ impl Exec {
 
         let events = ag_g.event_indexes.iter().map(|ei| self.es.events[*ei as usize].clone()).collect();
 
-        apply_events(&stripe_dummy, &mut self.uc, Some(events)).await;
+        apply_events(&stripe_dummy, &mut self.uc, Some(events), &ObjectSelection::default()).await.unwrap();
         let copy = copy_db_file(&self.db_file, format!("{}-apply-{}", self.step, &path_part).as_str());
         // dbg!(&copy);
         info!("DB: Snapshot: db file copied **after** events were applied: {}", &copy);
@@ -485,7 +486,7 @@ impl Exec {
             engine: Engine::SQLite(SQLiteCreate {
                 file: self.db_file.clone()
             })
-        })
+        }).unwrap()
     }
 }
 
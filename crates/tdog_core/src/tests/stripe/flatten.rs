@@ -0,0 +1,62 @@
+use rusqlite::Connection;
+use serde_json::json;
+use unicon::traits::{*};
+
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::Customer;
+use crate::tests::stripe::util::get_unicon;
+use crate::util::{get_temp_file, get_unique_id};
+
+
+// (address_country, address_line2, shipping_name, shipping_address_postal_code, invoice_settings_default_payment_method)
+type Flat = (Option<String>, Option<String>, Option<String>, Option<String>, Option<String>);
+
+fn get_flat(file: &str) -> Vec<Flat> {
+    let c = Connection::open(file).unwrap();
+    let mut stmt = c.prepare("SELECT address_country, address_line2, shipping_name, shipping_address_postal_code, invoice_settings_default_payment_method FROM customers ORDER BY id").unwrap();
+    let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?))).unwrap();
+    rows.map(|x| x.unwrap()).collect()
+}
+
+#[test]
+fn flatten_generated_cols() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&ObjectSelection::default().get_excluded(), &vec![]).unwrap();
+
+    let a = Customer {
+        id: "cus_a".into(),
+        address: Some(json!({"country": "GB", "line2": null, "postal_code": "N1"})),
+        shipping: Some(json!({"name": "A", "address": {"postal_code": "E1"}})),
+        invoice_settings: Some(json!({"default_payment_method": "pm_1"})),
+        ..Default::default()
+    };
+    let b = Customer {
+        id: "cus_b".into(),
+        ..Default::default()
+    };
+    for x in [a, b] {
+        x.insert(&mut uc);
+    }
+
+    assert_eq!(get_flat(&file), vec![
+        (Some("GB".into()), None, Some("A".into()), Some("E1".into()), Some("pm_1".into())),
+        (None, None, None, None, None),
+    ]);
+
+    // Reading rows ignores the generated columns.
+    assert_eq!(Customer::get_all(&mut uc).len(), 2);
+}
+
+#[test]
+fn flatten_create_sql() {
+    let all = <Customer as TableStatic>::get_table().static_sql_strings.as_ref().unwrap();
+
+    assert!(all.sqlite.create.create.contains("shipping_address_country TEXT GENERATED ALWAYS AS (json_extract(shipping, '$.address.country')) VIRTUAL"));
+    assert!(all.mysql.create.create.contains("address_country TEXT GENERATED ALWAYS AS (CASE WHEN JSON_TYPE(JSON_EXTRACT(address, '$.country')) = 'NULL' THEN NULL ELSE JSON_UNQUOTE(JSON_EXTRACT(address, '$.country')) END) VIRTUAL"));
+    assert!(all.postgres.create.create.contains("shipping_address_country TEXT GENERATED ALWAYS AS (shipping->'address'->>'country') STORED"));
+
+    // Not written on insert.
+    assert!(!all.sqlite.insert.contains("address_country"));
+}
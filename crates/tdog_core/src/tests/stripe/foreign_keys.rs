@@ -0,0 +1,94 @@
+use rusqlite::Connection;
+use unicon::error::UniConError;
+use unicon::table::ObjType;
+use unicon::traits::{*};
+use unicon::uc::UniCon;
+
+use crate::ForeignKeys;
+use crate::error::TdError;
+use crate::providers::stripe::foreign_keys::{add_tables, assert_supported, set_sqlite_enforcement};
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::Price;
+use crate::tests::stripe::util::{get_item, get_sub, get_unicon};
+use crate::util::{get_temp_file, get_unique_id};
+
+fn create(mode: ForeignKeys) -> (String, UniCon) {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    let objects = ObjectSelection::default();
+
+    let mut excluded = objects.get_excluded();
+    let mut extra = vec![];
+    add_tables(mode, &objects, &mut excluded, &mut extra);
+    uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&excluded, &extra).unwrap();
+    set_sqlite_enforcement(mode, &mut uc);
+
+    (file, uc)
+}
+
+// (from, table) for each foreign key of a table.
+fn get_fks(file: &str, table: &str) -> Vec<(String, String)> {
+    let c = Connection::open(file).unwrap();
+    let mut stmt = c.prepare(&format!("PRAGMA foreign_key_list({})", table)).unwrap();
+    let rows = stmt.query_map([], |r| Ok((r.get("from")?, r.get("table")?))).unwrap();
+    let mut o: Vec<(String, String)> = rows.map(|x| x.unwrap()).collect();
+    o.sort();
+    o
+}
+
+// Rows are inserted in reverse order (items before their owners); they are checked at commit.
+fn write_sub(uc: &mut UniCon, price: &str) -> Result<(), UniConError> {
+    let mut utx = uc.tx_open().unwrap();
+    get_item("si_1", "sub_1", price, None).tx_insert_set_pk(&mut utx);
+    get_sub("sub_1", "cus_missing", "active", None).tx_insert_set_pk(&mut utx);
+    Price { id: "price_1".into(), currency: "usd".into(), ..Default::default() }.tx_insert_set_pk(&mut utx);
+    utx.tx_close()
+}
+
+#[test]
+fn foreign_keys_enforced_sqlite() {
+    let (file, mut uc) = create(ForeignKeys::Enforced);
+
+    // `subscriptions.customer` is not enforced by the API (deleted customers are still referenced).
+    assert_eq!(get_fks(&file, "subscription_items"), vec![("price".into(), "prices".into()), ("subscription".into(), "subscriptions".into())]);
+    assert!(!get_fks(&file, "subscriptions").iter().any(|(from, _)| from == "customer"));
+
+    let e = write_sub(&mut uc, "price_missing").unwrap_err();
+    assert!(matches!(e, UniConError::ForeignKey(_)));
+    assert!(matches!(TdError::from_commit("test transaction", e), TdError::Integrity(_)));
+    assert!(write_sub(&mut uc, "price_1").is_ok());
+}
+
+#[test]
+fn foreign_keys_declared_sqlite() {
+    let (file, mut uc) = create(ForeignKeys::Declared);
+
+    assert!(get_fks(&file, "subscriptions").contains(&("customer".into(), "customers".into())));
+    assert!(write_sub(&mut uc, "price_missing").is_ok());
+}
+
+#[test]
+fn foreign_keys_sql() {
+    let objects = ObjectSelection::default();
+    let get_item_table = |mode| {
+        let mut extra = vec![];
+        add_tables(mode, &objects, &mut vec![], &mut extra);
+        extra.into_iter().find(|x| x.name == "subscription_items").unwrap().static_sql_strings.unwrap()
+    };
+
+    let x = get_item_table(ForeignKeys::Enforced);
+    let pg: Vec<String> = x.postgres.indexes.into_iter().filter(|x| x.obj_type == ObjType::Constraint).map(|x| x.create).collect();
+    assert!(pg.contains(&"ALTER TABLE subscription_items ADD CONSTRAINT subscription_items_price_fkey FOREIGN KEY (price) REFERENCES prices (id) DEFERRABLE INITIALLY DEFERRED".to_string()));
+
+    let x = get_item_table(ForeignKeys::Declared);
+    assert!(x.postgres.indexes.iter().any(|x| x.create == "COMMENT ON COLUMN subscription_items.price IS 'References prices(id)'"));
+    assert!(x.mysql.create.create.contains("COMMENT 'References prices(id)'"));
+
+    // `none` adds no tables.
+    let mut extra = vec![];
+    add_tables(ForeignKeys::None, &objects, &mut vec![], &mut extra);
+    assert!(extra.is_empty());
+
+    assert!(assert_supported(ForeignKeys::Enforced, &get_unicon(&get_temp_file(get_unique_id() + ".sqlite").unwrap()), Some(&"90days".to_string())).is_err());
+}
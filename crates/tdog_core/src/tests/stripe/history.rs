@@ -0,0 +1,148 @@
+use rusqlite::Connection;
+use unicon::dt3::DT3;
+use unicon::traits::{*};
+
+use crate::providers::stripe::history::get_history_tables;
+use crate::Options;
+use crate::providers::stripe::write_ctx::{set_event, with_write_ctx, WriteCtx};
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::Customer;
+use crate::providers::stripe::schema::util::ToDT;
+use crate::providers::stripe::schema_meta::{LogWrite, TdRun};
+use crate::tests::stripe::util::get_unicon;
+use crate::util::{get_temp_file, get_unique_id};
+
+
+// (name, valid_to is null, run_id, event_id)
+fn get_versions(file: &str) -> Vec<(Option<String>, bool, i64, Option<String>)> {
+    let c = Connection::open(file).unwrap();
+    let mut stmt = c.prepare("SELECT name, valid_to IS NULL, run_id, event_id FROM customers_history WHERE id = 'cus_1' ORDER BY history_id").unwrap();
+    let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?))).unwrap();
+    rows.map(|x| x.unwrap()).collect()
+}
+
+#[tokio::main]
+#[test]
+async fn history_versions_cud() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    let objects = ObjectSelection::default();
+    let (created_all, _) = uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&vec![], &get_history_tables(&objects)).unwrap();
+    assert!(created_all);
+
+    let ctx = WriteCtx::new(&Options { history: true, ..Default::default() }).unwrap();
+    with_write_ctx(ctx, async {
+        let mut c = Customer {
+            id: "cus_1".into(),
+            name: Some("a".into()),
+            ..Default::default()
+        };
+
+        let mut utx = uc.tx_open().unwrap();
+        c.tx_insert_set_pk_log_write(&mut utx, 1);
+        utx.tx_close().unwrap();
+
+        set_event(Some(("evt_1".into(), 1_600_000_000.to_dt3())));
+        let mut utx = uc.tx_open().unwrap();
+        c.name = Some("b".into());
+        c.tx_update_log_write(&mut utx, 2, "id");
+        utx.tx_close().unwrap();
+
+        set_event(Some(("evt_2".into(), 1_600_000_100.to_dt3())));
+        let mut utx = uc.tx_open().unwrap();
+        c.tx_delete_log_write(&mut utx, 2, "id");
+        utx.tx_close().unwrap();
+    }).await;
+
+    assert_eq!(get_versions(&file), vec![
+        (Some("a".into()), false, 1, None),
+        (Some("b".into()), false, 2, Some("evt_1".into())),
+    ]);
+
+    // Not enabled: no versions are written.
+    let mut c = Customer {
+        id: "cus_1".into(),
+        ..Default::default()
+    };
+    let mut utx = uc.tx_open().unwrap();
+    c.tx_insert_set_pk_log_write(&mut utx, 3);
+    utx.tx_close().unwrap();
+
+    assert_eq!(get_versions(&file).len(), 2);
+}
+
+// (valid_from, valid_to)
+fn get_valid(file: &str) -> Vec<(DT3, Option<DT3>)> {
+    let c = Connection::open(file).unwrap();
+    let mut stmt = c.prepare("SELECT valid_from, valid_to FROM customers_history WHERE id = 'cus_1' ORDER BY history_id").unwrap();
+    let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?))).unwrap();
+    rows.map(|x| x.unwrap()).collect()
+}
+
+// Versions are valid from the event's `created` (not the time the event was applied), or the run's start for downloads.
+#[tokio::main]
+#[test]
+async fn history_valid_from_event_created() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    let objects = ObjectSelection::default();
+    uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&vec![], &get_history_tables(&objects)).unwrap();
+
+    let run_start = 1_500_000_000.to_dt3();
+    let mut run = TdRun {
+        run_id: None,
+        r#type: "download".into(),
+        start_ts: Some(run_start.clone()),
+        end_ts: None,
+    };
+    run.insert_set_pk(&mut uc);
+    let run_id = run.run_id.unwrap();
+
+    let (e1, e2) = (1_600_000_000.to_dt3(), 1_600_000_100.to_dt3());
+
+    let ctx = WriteCtx::new(&Options { history: true, ..Default::default() }).unwrap();
+    with_write_ctx(ctx, async {
+        let mut c = Customer {
+            id: "cus_1".into(),
+            name: Some("a".into()),
+            ..Default::default()
+        };
+
+        let mut utx = uc.tx_open().unwrap();
+        c.tx_insert_set_pk_log_write(&mut utx, run_id);
+        utx.tx_close().unwrap();
+
+        // Both events are applied in the same tx.
+        let mut utx = uc.tx_open().unwrap();
+        set_event(Some(("evt_1".into(), e1.clone())));
+        c.name = Some("b".into());
+        c.tx_update_log_write(&mut utx, run_id, "id");
+
+        set_event(Some(("evt_2".into(), e2.clone())));
+        c.name = Some("c".into());
+        c.tx_update_log_write(&mut utx, run_id, "id");
+        utx.tx_close().unwrap();
+        set_event(None);
+    }).await;
+
+    assert_eq!(get_valid(&file), vec![
+        (run_start, Some(e1.clone())),
+        (e1, Some(e2.clone())),
+        (e2, None),
+    ]);
+}
+
+#[test]
+fn history_tables_follow_objects() {
+    let all = get_history_tables(&ObjectSelection::default());
+    let names: Vec<&str> = all.iter().map(|x| x.name.as_str()).collect();
+
+    assert!(names.contains(&"customers_history"));
+    assert!(!names.contains(&"notification_events_history"));
+    assert!(!names.iter().any(|x| x.starts_with("td_")));
+
+    let c = all.iter().find(|x| x.name == "customers_history").unwrap();
+    assert_eq!(c.get_primary_key_col_name(), "history_id");
+    assert!(c.cols.iter().all(|x| !x.unique));
+}
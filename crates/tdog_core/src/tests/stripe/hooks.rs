@@ -0,0 +1,45 @@
+use std::fs;
+use std::time::Instant;
+
+use serde_json::json;
+
+use crate::{Hook, HookCmd};
+use crate::hooks::run;
+use crate::util::{get_temp_file, get_unique_id};
+
+
+fn get_cmd(cmd: String, timeout_ms: u64) -> Hook {
+    Hook::Cmd(HookCmd {
+        cmd,
+        timeout_ms,
+    })
+}
+
+#[cfg(unix)]
+#[tokio::main]
+#[test]
+async fn hook_cmd_stdin_and_env() {
+    let out = get_temp_file(get_unique_id() + ".txt").unwrap();
+    let summary = json!({"run_id": 2, "to": {"id": "evt_1"}});
+
+    let cmd = format!("cat > {0}; echo \" $TDOG_HOOK $TDOG_RUN_ID $TDOG_SUMMARY\" >> {0}", &out);
+    run("on_apply_events", &get_cmd(cmd, 5_000), &summary).await.unwrap();
+
+    let s = fs::read_to_string(&out).unwrap();
+    let summary = summary.to_string();
+    assert_eq!(s.trim_end(), format!("{} on_apply_events 2 {}", &summary, &summary));
+}
+
+#[cfg(unix)]
+#[tokio::main]
+#[test]
+async fn hook_cmd_errors() {
+    let summary = json!({"run_id": 2});
+
+    assert!(run("on_error", &get_cmd("exit 3".into(), 5_000), &summary).await.is_err());
+
+    let start = Instant::now();
+    let e = run("on_error", &get_cmd("sleep 10".into(), 100), &summary).await.unwrap_err();
+    assert!(e.contains("Timed out"));
+    assert!(start.elapsed().as_secs() < 5);
+}
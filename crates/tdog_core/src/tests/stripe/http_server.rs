@@ -0,0 +1,66 @@
+use serde_json::{json, Value};
+
+use crate::{Cmds, HttpServer};
+use crate::http_server;
+use crate::metrics::set_heartbeat_now;
+use crate::util::{get_temp_file, get_unique_id, JOB_LABEL};
+
+
+fn get_cmds(jobs: Vec<(&str, &str)>) -> Cmds {
+    let cmds: Vec<Value> = jobs.iter().map(|(label, file)| json!({
+        "fn": "download",
+        "args": {
+            "from": {"stripe": {"secret_key": "sk_test_x"}},
+            "to": {"sqlite": {"file": file}},
+            "options": {"label": label}
+        }
+    })).collect();
+
+    serde_json::from_value(Value::Array(cmds)).unwrap()
+}
+
+async fn get(url: String) -> (u16, Value) {
+    let res = reqwest::get(&url).await.unwrap();
+    (res.status().as_u16(), res.json().await.unwrap())
+}
+
+#[tokio::main]
+#[test]
+async fn http_server_healthz_readyz() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let bad_file = "/non_existent_dir/db.sqlite";
+
+    let a = get_unique_id();
+    let b = get_unique_id();
+    let cmds = get_cmds(vec![(&a, &file), (&b, bad_file)]);
+    let opts = HttpServer {
+        addr: "127.0.0.1:0".into(),
+        ready_max_staleness_ms: 60_000,
+    };
+    let addr = http_server::start(&opts, &cmds).unwrap();
+
+    let (code, body) = get(format!("http://{}/healthz", addr)).await;
+    assert_eq!(code, 503);
+    assert_eq!(body["jobs"][&a]["ok"], true);
+    assert_eq!(body["jobs"][&b]["ok"], false);
+    assert!(body["jobs"][&b]["db_error"].is_string());
+
+    // Not ready until each job has completed an `apply_events` poll.
+    let (code, body) = get(format!("http://{}/readyz", addr)).await;
+    assert_eq!(code, 503);
+    assert!(body["jobs"][&a]["seconds_since_heartbeat"].is_null());
+
+    JOB_LABEL.scope(Some(a.clone()), async { set_heartbeat_now() }).await;
+    let (code, body) = get(format!("http://{}/readyz", addr)).await;
+    assert_eq!(code, 503);
+    assert_eq!(body["jobs"][&a]["ok"], true);
+
+    JOB_LABEL.scope(Some(b.clone()), async { set_heartbeat_now() }).await;
+    let (code, _) = get(format!("http://{}/readyz", addr)).await;
+    assert_eq!(code, 200);
+
+    let cmds = get_cmds(vec![(&a, &file)]);
+    let addr = http_server::start(&opts, &cmds).unwrap();
+    let (code, _) = get(format!("http://{}/healthz", addr)).await;
+    assert_eq!(code, 200);
+}
@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use rusqlite::Connection;
+use serde_json::json;
+use unicon::params::ExtraVal;
+use unicon::traits::{*};
+
+use crate::Options;
+use crate::error::TdError;
+use crate::providers::stripe::metadata_cols::MetadataCols;
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::raw_json::add_raw_tables;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::Customer;
+use crate::providers::stripe::schema_meta::LogWrite;
+use crate::providers::stripe::write_ctx::{with_write_ctx, WriteCtx};
+use crate::tests::stripe::util::get_unicon;
+use crate::util::{get_temp_file, get_unique_id};
+
+
+fn get_config(x: Vec<(&str, Vec<&str>)>) -> BTreeMap<String, Vec<String>> {
+    x.into_iter().map(|(t, keys)| (t.to_string(), keys.into_iter().map(|k| k.to_string()).collect())).collect()
+}
+
+// (id, md_tenant_id, md_plantier, md_seats)
+type MdRow = (String, Option<String>, Option<String>, Option<i64>);
+
+fn get_md_cols(file: &str) -> Vec<MdRow> {
+    let c = Connection::open(file).unwrap();
+    let mut stmt = c.prepare("SELECT id, md_tenant_id, md_plantier, md_seats FROM customers ORDER BY id").unwrap();
+    let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?))).unwrap();
+    rows.map(|x| x.unwrap()).collect()
+}
+
+#[test]
+fn metadata_cols_config() {
+    assert!(MetadataCols::try_from(&get_config(vec![("customers", vec!["tenant_id", "seats:integer", "rate:float", "trial:boolean", "tier:string"])])).is_ok());
+
+    for x in [
+        get_config(vec![("td_runs", vec!["a"])]),
+        get_config(vec![("customer", vec!["a"])]),
+        // No `metadata` column.
+        get_config(vec![("discounts", vec!["a"])]),
+        get_config(vec![("customers", vec!["tenant-id"])]),
+        get_config(vec![("customers", vec!["a", "A"])]),
+        get_config(vec![("customers", vec!["a", "A:integer"])]),
+        get_config(vec![("customers", vec!["a:date"])]),
+        get_config(vec![("customers", vec![":integer"])]),
+    ] {
+        assert!(matches!(MetadataCols::try_from(&x), Err(TdError::Config(_))));
+    }
+}
+
+#[tokio::main]
+#[test]
+async fn metadata_cols_insert_and_update() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    let objects = ObjectSelection::default();
+    let opts = Options {
+        store_raw_json: true,
+        metadata_columns: get_config(vec![("customers", vec!["tenant_id", "planTier", "seats:integer"])]),
+        ..Default::default()
+    };
+
+    // Both options change the `customers` table.
+    let md = MetadataCols::try_from(&opts.metadata_columns).unwrap();
+    let mut excluded = objects.get_excluded();
+    let mut extra = vec![];
+    add_raw_tables(&objects, &mut excluded, &mut extra);
+    md.add_tables(&mut excluded, &mut extra);
+    uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&excluded, &extra).unwrap();
+
+    assert!(md.assert_cols_match(&mut uc, &objects).is_ok());
+    assert!(matches!(MetadataCols::default().assert_cols_match(&mut uc, &objects), Err(TdError::SchemaConflict(_))));
+
+    with_write_ctx(WriteCtx::new(&opts).unwrap(), async {
+        let mut c = Customer {
+            id: "cus_1".into(),
+            metadata: Some(json!({"tenant_id": "t1", "planTier": "pro", "seats": "3", "other": "x"})),
+            raw: Some(json!({"id": "cus_1"})),
+            ..Default::default()
+        };
+        let mut utx = uc.tx_open().unwrap();
+        c.tx_insert_set_pk_log_write(&mut utx, 1);
+        utx.tx_close().unwrap();
+
+        assert_eq!(get_md_cols(&file), vec![("cus_1".into(), Some("t1".into()), Some("pro".into()), Some(3))]);
+
+        // Values that do not parse to the column type are null.
+        c.metadata = Some(json!({"tenant_id": "t2", "seats": "three"}));
+        let mut utx = uc.tx_open().unwrap();
+        c.tx_update_log_write(&mut utx, 2, "id");
+        utx.tx_close().unwrap();
+    }).await;
+
+    assert_eq!(get_md_cols(&file), vec![("cus_1".into(), Some("t2".into()), None, None)]);
+
+    // Reading rows ignores the `md_` columns.
+    let c = Customer::get_all(&mut uc).pop().unwrap();
+    assert_eq!(c.raw, Some(json!({"id": "cus_1"})));
+}
+
+// The column type is set by the key's type; the key is the name in `metadata`.
+#[test]
+fn metadata_cols_typed() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    let objects = ObjectSelection::default();
+    let md = MetadataCols::try_from(&get_config(vec![("subscriptions", vec!["tier", "seats:integer", "rate:float", "trial:boolean"])])).unwrap();
+
+    let mut excluded = objects.get_excluded();
+    let mut extra = vec![];
+    md.add_tables(&mut excluded, &mut extra);
+    uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&excluded, &extra).unwrap();
+
+    let c = Connection::open(&file).unwrap();
+    let mut stmt = c.prepare("SELECT name, type FROM pragma_table_info('subscriptions') WHERE name LIKE 'md_%' ORDER BY cid").unwrap();
+    let cols: Vec<(String, String)> = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?))).unwrap().map(|x| x.unwrap()).collect();
+    assert_eq!(cols, vec![
+        ("md_tier".into(), "TEXT".into()),
+        ("md_seats".into(), "INTEGER".into()),
+        ("md_rate".into(), "REAL".into()),
+        ("md_trial".into(), "INTEGER".into()),
+    ]);
+
+    let x = json!({"seats": " 12 ", "rate": "0.5", "trial": "true"});
+    assert_eq!(md.get_vals("subscriptions", Some(&x)), vec![
+        ExtraVal::String(None),
+        ExtraVal::I64(Some(12)),
+        ExtraVal::F64(Some(0.5)),
+        ExtraVal::Bool(Some(true)),
+    ]);
+}
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::Utc;
+use stripe_client::http::http::ReqLog;
+
+use crate::{Cmds, HttpServer};
+use crate::http_server;
+use crate::metrics::{add_events_applied, observe_reqs, observe_tx_lock, set_heartbeat_now};
+use crate::util::JOB_LABEL;
+
+
+fn get_req(code: Option<u16>, duration_ms: u32) -> ReqLog {
+    ReqLog {
+        start: Utc::now(),
+        end: Utc::now(),
+        duration_ms,
+        bytes_rx: 0,
+        code,
+        net_error: code.is_none(),
+    }
+}
+
+#[tokio::main]
+#[test]
+async fn metrics_endpoint() {
+    JOB_LABEL.scope(Some("metrics_test".into()), async {
+        observe_reqs(&[get_req(Some(200), 80), get_req(Some(429), 300), get_req(None, 20_000)], 1);
+
+        let mut hm = HashMap::new();
+        hm.insert("customer.updated".to_string(), 2);
+        add_events_applied(&hm);
+        add_events_applied(&hm);
+
+        observe_tx_lock(Duration::from_millis(20));
+        set_heartbeat_now();
+    }).await;
+
+    let addr = http_server::start(&HttpServer { addr: "127.0.0.1:0".into(), ready_max_staleness_ms: 60_000 }, &Cmds::Many(vec![])).unwrap();
+    let body = reqwest::get(&format!("http://{}/metrics", addr)).await.unwrap().text().await.unwrap();
+
+    let lines: Vec<&str> = body.lines().filter(|x| x.contains("tdog_job=\"metrics_test\"")).collect();
+    let has = |x: &str| lines.contains(&x);
+
+    assert!(has("tdog_stripe_requests_total{tdog_job=\"metrics_test\",code=\"200\"} 1"));
+    assert!(has("tdog_stripe_requests_total{tdog_job=\"metrics_test\",code=\"net_error\"} 1"));
+    assert!(has("tdog_stripe_request_duration_seconds_bucket{tdog_job=\"metrics_test\",le=\"0.1\"} 1"));
+    assert!(has("tdog_stripe_request_duration_seconds_bucket{tdog_job=\"metrics_test\",le=\"10\"} 2"));
+    assert!(has("tdog_stripe_request_duration_seconds_bucket{tdog_job=\"metrics_test\",le=\"+Inf\"} 3"));
+    assert!(has("tdog_stripe_429_responses_total{tdog_job=\"metrics_test\"} 1"));
+    assert!(has("tdog_stripe_429_requests_retrying{tdog_job=\"metrics_test\"} 1"));
+    assert!(has("tdog_events_applied_total{tdog_job=\"metrics_test\",type=\"customer.updated\"} 4"));
+    assert!(has("tdog_apply_events_tx_lock_seconds_count{tdog_job=\"metrics_test\"} 1"));
+    assert!(lines.iter().any(|x| x.starts_with("tdog_seconds_since_heartbeat{tdog_job=\"metrics_test\"} ")));
+
+    let res = reqwest::get(&format!("http://{}/other", addr)).await.unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+}
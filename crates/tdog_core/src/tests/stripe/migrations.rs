@@ -0,0 +1,197 @@
+use rusqlite::Connection;
+use serde_json::Map;
+use stripe_client::http::http::StripeAccount;
+use unicon::traits::{*};
+use unicon::uc::UniCon;
+use unicon::utx::UniTx;
+
+use crate::Options;
+use crate::providers::stripe::history::get_history_tables;
+use crate::providers::stripe::migrations::{get_migrations, migrate_with, Migration, Step};
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::Customer;
+use crate::providers::stripe::schema_meta::{get_cli_version, get_semver_ints, TdMetadata};
+use crate::tests::stripe::util::get_unicon;
+use crate::util::{get_temp_file, get_unique_id};
+
+
+// A DB with history tables, changed to look like it was written by CLI version 0.7.0 (before `coupons` and `customers.email` existed).
+fn get_old_db(file: &str) -> UniCon {
+    let objects = ObjectSelection::default();
+    let mut uc = get_unicon(file);
+    uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&vec![], &get_history_tables(&objects)).unwrap();
+    TdMetadata::insert_cli_and_stripe_versions(&mut uc, &StripeAccount {
+        id: "acct_1".into(),
+        is_test: true,
+        account: Map::new(),
+    }, None);
+
+    let mut utx = uc.tx_open().unwrap();
+    Customer { id: "cus_1".into(), ..Default::default() }.tx_insert_set_pk(&mut utx);
+    utx.tx_close().unwrap();
+
+    let c = Connection::open(file).unwrap();
+    c.execute_batch("
+        UPDATE td_metadata SET cli_version = '0.7.0';
+        DROP TABLE coupons;
+        DROP TABLE coupons_history;
+        ALTER TABLE customers DROP COLUMN email;
+        ALTER TABLE customers_history DROP COLUMN email;
+        ALTER TABLE customers ADD COLUMN legacy TEXT;
+        DROP INDEX invoice;
+    ").unwrap();
+
+    uc
+}
+
+fn set_email(utx: &mut UniTx) {
+    if let UniTx::Rusqlite(tx) = utx {
+        tx.execute("UPDATE customers SET email = 'a@example.com'", []).unwrap();
+    }
+}
+
+fn get_test_migrations() -> Vec<Migration> {
+    vec![
+        // Already applied (the DB is at this version).
+        Migration {
+            version: "0.7.0",
+            name: "drop_customers",
+            steps: vec![Step::DropCol { table: "customers", col: "id" }],
+        },
+        Migration {
+            version: "0.8.0",
+            name: "coupons",
+            steps: vec![Step::AddTable("coupons")],
+        },
+        Migration {
+            version: "0.8.1",
+            name: "customer_email",
+            steps: vec![
+                Step::AddCol { table: "customers", col: "email", default: None },
+                Step::DropCol { table: "customers", col: "legacy" },
+                Step::AddIndex { table: "invoice_line_items", index: "invoice" },
+                Step::Backfill(set_email),
+            ],
+        },
+        // Newer than the CLI.
+        Migration {
+            version: "0.9.0",
+            name: "later",
+            steps: vec![Step::AddTable("not_a_table")],
+        },
+    ]
+}
+
+fn get_strings(file: &str, sql: &str) -> Vec<String> {
+    let c = Connection::open(file).unwrap();
+    let mut stmt = c.prepare(sql).unwrap();
+    let rows = stmt.query_map([], |r| r.get(0)).unwrap();
+    rows.map(|x| x.unwrap()).collect()
+}
+
+#[test]
+fn migrations_apply_once() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_old_db(&file);
+    let objects = ObjectSelection::default();
+    let history_tables = get_history_tables(&objects);
+
+    assert!(uc.schema_exists_with::<Db>(&vec![], &history_tables).is_err());
+
+    let applied = migrate_with(&mut uc, &objects, &Options::default(), &get_test_migrations(), "0.8.1").unwrap();
+    assert_eq!(applied, vec!["coupons", "customer_email"]);
+
+    assert!(uc.schema_exists_with::<Db>(&vec![], &history_tables).unwrap());
+    assert_eq!(get_strings(&file, "SELECT name FROM sqlite_master WHERE name IN ('coupons_history', 'invoice')"), vec!["coupons_history", "invoice"]);
+    assert_eq!(get_strings(&file, "SELECT email FROM customers"), vec!["a@example.com"]);
+    assert!(!get_strings(&file, "SELECT name FROM pragma_table_info('customers')").contains(&"legacy".to_string()));
+    assert!(get_strings(&file, "SELECT name FROM pragma_table_info('customers_history')").contains(&"email".to_string()));
+    assert_eq!(get_strings(&file, "SELECT from_cli_version || ' ' || version FROM td_migrations ORDER BY migration_id"), vec!["0.7.0 0.8.0", "0.7.0 0.8.1"]);
+    assert_eq!(TdMetadata::get_last(&mut uc, "id").unwrap().cli_version, "0.8.1");
+
+    // Up to date.
+    assert!(migrate_with(&mut uc, &objects, &Options::default(), &get_test_migrations(), "0.8.1").unwrap().is_empty());
+
+    // No migrations for this version: the DB stays at the last version that changed the schema.
+    assert!(migrate_with(&mut uc, &objects, &Options::default(), &get_test_migrations(), "0.8.2").unwrap().is_empty());
+    assert_eq!(TdMetadata::get_last(&mut uc, "id").unwrap().cli_version, "0.8.1");
+
+    // A DB that does not exist yet.
+    let mut uc = get_unicon(&get_temp_file(get_unique_id() + ".sqlite").unwrap());
+    assert!(migrate_with(&mut uc, &objects, &Options::default(), &get_test_migrations(), "0.8.1").unwrap().is_empty());
+}
+
+// The index was created by a run that did not record the migration (MySQL commits DDL implicitly).
+#[test]
+fn migrations_add_index_exists() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_old_db(&file);
+    let objects = ObjectSelection::default();
+
+    Connection::open(&file).unwrap().execute_batch("CREATE INDEX invoice ON invoice_line_items (invoice);").unwrap();
+
+    let applied = migrate_with(&mut uc, &objects, &Options::default(), &get_test_migrations(), "0.8.1").unwrap();
+    assert_eq!(applied, vec!["coupons", "customer_email"]);
+    assert_eq!(get_strings(&file, "SELECT name FROM sqlite_master WHERE type = 'index' AND name = 'invoice'"), vec!["invoice"]);
+}
+
+#[test]
+fn migrations_registry_is_ordered() {
+    let cli = get_semver_ints(get_cli_version());
+    let all = get_migrations();
+
+    for (i, m) in all.iter().enumerate() {
+        assert!(get_semver_ints(m.version) <= cli, "Migration `{}` is newer than the CLI.", m.name);
+        assert!(all.iter().filter(|x| x.name == m.name).count() == 1, "Migration `{}` is not unique.", m.name);
+        if i > 0 {
+            assert!(get_semver_ints(all[i - 1].version) <= get_semver_ints(m.version), "Migrations must be ordered by version.");
+        }
+    }
+}
+
+// DBs created before the flattened columns existed get them from the registry (computed by the engine for existing rows).
+#[test]
+fn migrations_flattened_cols() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_old_db(&file);
+    let objects = ObjectSelection::default();
+
+    let c = Connection::open(&file).unwrap();
+    c.execute_batch("
+        UPDATE customers SET address = '{\"country\": \"GB\"}';
+        ALTER TABLE customers DROP COLUMN address_country;
+        ALTER TABLE customers_history DROP COLUMN address_country;
+    ").unwrap();
+
+    // `coupons` and `customers.email` are only added by the test migrations.
+    let applied = migrate_with(&mut uc, &objects, &Options::default(), &get_migrations(), get_cli_version()).unwrap();
+    assert!(applied.contains(&"flattened_cols".to_string()));
+
+    assert_eq!(get_strings(&file, "SELECT address_country FROM customers"), vec!["GB"]);
+    assert!(get_strings(&file, "SELECT name FROM pragma_table_xinfo('customers_history')").contains(&"address_country".to_string()));
+}
+
+// Columns and tables of options added in the same version as the registry are added to DBs created before them, when the option is set.
+#[test]
+fn migrations_option_cols() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_old_db(&file);
+    let objects = ObjectSelection::default();
+    let opts = Options {
+        store_raw_json: true,
+        notify: true,
+        mrr_movements: true,
+        metadata_columns: vec![("customers".to_string(), vec!["tenant_id".to_string()])].into_iter().collect(),
+        ..Default::default()
+    };
+
+    let applied = migrate_with(&mut uc, &objects, &opts, &get_migrations(), get_cli_version()).unwrap();
+    assert_eq!(applied, get_migrations().iter().map(|x| x.name.to_string()).collect::<Vec<_>>());
+
+    let cols = get_strings(&file, "SELECT name FROM pragma_table_info('customers')");
+    assert!(cols.contains(&"raw".to_string()) && cols.contains(&"md_tenant_id".to_string()));
+    assert!(!get_strings(&file, "SELECT name FROM pragma_table_info('customers_history')").contains(&"raw".to_string()));
+    assert_eq!(get_strings(&file, "SELECT name FROM sqlite_master WHERE name IN ('customers_md_tenant_id', 'mrr_movements', 'td_changes') ORDER BY name"), vec!["customers_md_tenant_id", "mrr_movements", "td_changes"]);
+    assert_eq!(TdMetadata::get_last(&mut uc, "id").unwrap().cli_version, get_cli_version());
+}
@@ -1,2 +1,26 @@
 pub mod util;
-pub mod event_seq;
\ No newline at end of file
+pub mod event_seq;
+pub mod objects;
+pub mod created_gte;
+pub mod error;
+pub mod history;
+pub mod cdc;
+pub mod notify;
+pub mod hooks;
+pub mod metrics;
+pub mod http_server;
+pub mod dry_run;
+pub mod replay;
+pub mod event_archive;
+pub mod snapshot;
+pub mod raw_json;
+pub mod metadata_cols;
+pub mod flatten;
+pub mod views;
+pub mod mrr_movements;
+pub mod foreign_keys;
+pub mod schema_graph;
+pub mod check;
+pub mod migrations;
+pub mod schema_diff;
+pub mod shutdown;
@@ -0,0 +1,98 @@
+use unicon::traits::{*};
+
+use crate::providers::stripe::mrr_movements::{MrrMovement, tx_get_written_sub_ids, tx_seed, tx_write};
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::util::ToDT;
+use crate::providers::stripe::schema_meta::LogWrite;
+use crate::tests::stripe::util::{get_item, get_price, get_sub, get_unicon};
+use crate::util::{get_temp_file, get_unique_id};
+
+
+fn get_movements(uc: &mut unicon::uc::UniCon) -> Vec<(String, String, f64, f64, Option<String>)> {
+    MrrMovement::get_all(uc).into_iter().map(|x| (x.subscription, x.category, x.delta, x.mrr, x.event_id)).collect()
+}
+
+#[test]
+fn mrr_movements_ledger() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    let objects = ObjectSelection::default();
+    uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&objects.get_excluded(), &vec![<MrrMovement as TableStatic>::get_table().clone()]).unwrap();
+
+    get_price("price_m", 1000, "month", "licensed").insert(&mut uc);
+    get_price("price_y", 6000, "year", "licensed").insert(&mut uc);
+    get_sub("sub_1", "cus_1", "active", None).insert(&mut uc);
+    get_item("si_1", "sub_1", "price_m", Some(1)).insert(&mut uc);
+    get_sub("sub_2", "cus_1", "trialing", None).insert(&mut uc);
+    get_item("si_2", "sub_2", "price_y", Some(2)).insert(&mut uc);
+
+    let mut utx = uc.tx_open().unwrap();
+    tx_seed(&mut utx, 1, ToDT::to_dt(0));
+    utx.tx_close().unwrap();
+
+    // The trial has no MRR yet.
+    assert_eq!(get_movements(&mut uc), vec![("sub_1".into(), "starting".into(), 1000.0, 1000.0, None)]);
+
+    let write = |uc: &mut unicon::uc::UniCon, event_id: &str, sub_id: &str| {
+        let mut utx = uc.tx_open().unwrap();
+        tx_write(&mut utx, 2, event_id, ToDT::to_dt(1), sub_id);
+        utx.tx_close().unwrap();
+    };
+
+    // 2 * 6000 / 12
+    get_sub("sub_2", "cus_1", "active", None).update(&mut uc, "id");
+    write(&mut uc, "evt_1", "sub_2");
+
+    get_item("si_1", "sub_1", "price_m", Some(3)).update(&mut uc, "id");
+    write(&mut uc, "evt_2", "sub_1");
+
+    get_item("si_1", "sub_1", "price_m", Some(2)).update(&mut uc, "id");
+    write(&mut uc, "evt_3", "sub_1");
+
+    // No change in MRR.
+    write(&mut uc, "evt_4", "sub_1");
+
+    get_sub("sub_1", "cus_1", "canceled", None).update(&mut uc, "id");
+    write(&mut uc, "evt_5", "sub_1");
+
+    assert_eq!(get_movements(&mut uc)[1..], vec![
+        ("sub_2".into(), "new".into(), 1000.0, 1000.0, Some("evt_1".into())),
+        ("sub_1".into(), "expansion".into(), 2000.0, 3000.0, Some("evt_2".into())),
+        ("sub_1".into(), "contraction".into(), -1000.0, 2000.0, Some("evt_3".into())),
+        ("sub_1".into(), "churn".into(), -2000.0, 0.0, Some("evt_5".into())),
+    ]);
+}
+
+// Quantity and price changes are written as `subscription_items` rows (E.g. from a subscription event's `items`); they recompute the parent subscription.
+#[test]
+fn mrr_movements_item_writes() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    let objects = ObjectSelection::default();
+    uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&objects.get_excluded(), &vec![<MrrMovement as TableStatic>::get_table().clone()]).unwrap();
+
+    get_price("price_m", 1000, "month", "licensed").insert(&mut uc);
+    get_sub("sub_1", "cus_1", "active", None).insert(&mut uc);
+    get_item("si_1", "sub_1", "price_m", Some(1)).insert(&mut uc);
+
+    let mut utx = uc.tx_open().unwrap();
+    tx_seed(&mut utx, 1, ToDT::to_dt(0));
+
+    let mut p = get_price("price_x", 500, "month", "licensed");
+    let write_ids = vec![
+        get_item("si_1", "sub_1", "price_m", Some(3)).tx_update_log_write(&mut utx, 2, "id"),
+        p.tx_insert_set_pk_log_write(&mut utx, 2),
+    ];
+    let sub_ids = tx_get_written_sub_ids(&mut utx, &write_ids);
+    assert_eq!(sub_ids, vec!["sub_1".to_string()]);
+    for id in sub_ids {
+        tx_write(&mut utx, 2, "evt_1", ToDT::to_dt(1), &id);
+    }
+    utx.tx_close().unwrap();
+
+    assert_eq!(get_movements(&mut uc), vec![
+        ("sub_1".into(), "starting".into(), 1000.0, 1000.0, None),
+        ("sub_1".into(), "expansion".into(), 2000.0, 3000.0, Some("evt_1".into())),
+    ]);
+}
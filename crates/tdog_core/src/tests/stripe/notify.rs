@@ -0,0 +1,121 @@
+use rusqlite::Connection;
+use serde_json::{Map, Value};
+use stripe_client::http::http::StripeAccount;
+use unicon::uc::UniCon;
+
+use crate::Options;
+use crate::providers::stripe::migrations::migrate;
+use crate::providers::stripe::notify::{get_notify, get_payload, get_tables, tx_notify};
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::Customer;
+use crate::providers::stripe::schema_meta::{LogWrite, TdMetadata, TdStripeWrite};
+use crate::tests::stripe::util::get_unicon;
+use crate::util::{get_temp_file, get_unique_id};
+
+
+// (change_id, channel, payload)
+fn get_changes(file: &str) -> Vec<(i64, String, Value)> {
+    let c = Connection::open(file).unwrap();
+    let mut stmt = c.prepare("SELECT change_id, channel, payload FROM td_changes ORDER BY change_id").unwrap();
+    let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get::<_, String>(2)?))).unwrap();
+    rows.map(|x| x.unwrap()).map(|(a, b, c)| (a, b, serde_json::from_str(&c).unwrap())).collect()
+}
+
+#[test]
+fn notify_changes_table() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    uc.ensure_schema_and_tables_exist_and_writable::<Db>();
+
+    // Can be created again on an existing DB.
+    for _ in 0..2 {
+        let mut utx = uc.tx_open().unwrap();
+        get_notify().create_changes_table_if_not_exists(&mut utx);
+        utx.tx_close().unwrap();
+    }
+
+    let mut c = Customer {
+        id: "cus_1".into(),
+        ..Default::default()
+    };
+
+    let mut utx = uc.tx_open().unwrap();
+    c.tx_insert_set_pk_log_write(&mut utx, 1);
+    c.tx_update_log_write(&mut utx, 1, "id");
+    tx_notify(&mut utx, 1);
+
+    // No writes: nothing is sent.
+    tx_notify(&mut utx, 2);
+
+    c.tx_delete_log_write(&mut utx, 3, "id");
+    tx_notify(&mut utx, 3);
+    utx.tx_close().unwrap();
+
+    let changes = get_changes(&file);
+    assert_eq!(changes.len(), 2);
+    assert!(changes[0].0 < changes[1].0);
+    assert_eq!(changes[0].1, "tdog");
+    assert_eq!(changes[0].2, serde_json::json!({"run_id": 1, "tables": {"customers": ["cus_1"]}}));
+    assert_eq!(changes[1].2["run_id"], 3);
+}
+
+fn has_changes_table(uc: &mut UniCon) -> bool {
+    let mut utx = uc.tx_open().unwrap();
+    let x = utx.get_tables().contains(&"td_changes".to_string());
+    utx.tx_rollback().unwrap();
+    x
+}
+
+// New DBs create the table with the other tables; DBs created without `notify` get it from the `td_changes` migration, which runs again when the option is set later.
+#[test]
+fn notify_table_in_target_schema() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    let extra = get_tables(&uc);
+    uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&vec![], &extra).unwrap();
+    assert!(has_changes_table(&mut uc));
+    assert!(uc.get_schema_diff_with::<Db>(&vec![], &extra).unwrap().is_empty());
+
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+
+    let objects = ObjectSelection::default();
+    let opts = Options {
+        notify: true,
+        ..Default::default()
+    };
+
+    // Not created before the DB exists.
+    migrate(&mut uc, &objects, &opts).unwrap();
+    assert!(!has_changes_table(&mut uc));
+
+    uc.ensure_schema_and_tables_exist_and_writable::<Db>();
+    TdMetadata::insert_cli_and_stripe_versions(&mut uc, &StripeAccount {
+        id: "acct_1".into(),
+        is_test: true,
+        account: Map::new(),
+    }, None);
+
+    migrate(&mut uc, &objects, &Options::default()).unwrap();
+    assert!(!has_changes_table(&mut uc));
+
+    // The DB is up to date, so this is the option being set after the migration was applied.
+    assert!(migrate(&mut uc, &objects, &opts).unwrap().is_empty());
+    assert!(has_changes_table(&mut uc));
+    let (created_all, _) = uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&vec![], &extra).unwrap();
+    assert!(!created_all);
+}
+
+#[test]
+fn notify_payload_too_large() {
+    let writes: Vec<TdStripeWrite> = (0..1000).map(|i| TdStripeWrite {
+        table_name: "customers".into(),
+        obj_id: format!("cus_{}", i),
+        ..Default::default()
+    }).collect();
+
+    let x: Value = serde_json::from_str(&get_payload(1, &writes)).unwrap();
+    assert_eq!(x["tables"]["customers"], Value::Null);
+    assert!(x["tables"].as_object().unwrap().contains_key("customers"));
+}
@@ -0,0 +1,63 @@
+use std::convert::TryInto;
+
+use crate::Objects;
+use crate::error::TdError;
+use crate::providers::stripe::objects::ObjectSelection;
+
+
+#[test]
+fn objects_default_includes_all() {
+    let x: ObjectSelection = (&Objects::default()).try_into().unwrap();
+    assert_eq!(x.get_excluded().len(), 0);
+}
+
+#[test]
+fn objects_include_adds_tree_children() {
+    let x: ObjectSelection = (&Objects {
+        include: Some(vec!["customers".into(), "subscriptions".into(), "invoices".into(), "prices".into()]),
+        exclude: vec![],
+    }).try_into().unwrap();
+
+    // Written as part of the included tables.
+    for t in &["discounts", "payment_methods", "subscription_items", "invoice_line_items", "tax_ids"] {
+        assert!(x.is_included(t), "{}", t);
+    }
+
+    for t in &["orders", "skus", "balance_transactions", "charges"] {
+        assert!(!x.is_included(t), "{}", t);
+    }
+
+    // Always included.
+    assert!(x.is_included("notification_events"));
+    assert!(x.is_included("td_stripe_writes"));
+}
+
+#[test]
+fn objects_exclude() {
+    let x: ObjectSelection = (&Objects {
+        include: None,
+        exclude: vec!["orders".into(), "order_returns".into(), "skus".into()],
+    }).try_into().unwrap();
+
+    assert_eq!(x.get_excluded(), vec!["order_returns".to_string(), "orders".into(), "skus".into()]);
+}
+
+#[test]
+fn objects_exclude_tree_child_of_included() {
+    let x: Result<ObjectSelection, TdError> = (&Objects {
+        include: None,
+        exclude: vec!["order_returns".into()],
+    }).try_into();
+
+    assert!(matches!(x, Err(TdError::Config(_))));
+}
+
+#[test]
+fn objects_unknown_table() {
+    let x: Result<ObjectSelection, TdError> = (&Objects {
+        include: Some(vec!["customer".into()]),
+        exclude: vec![],
+    }).try_into();
+
+    assert!(matches!(x, Err(TdError::Config(_))));
+}
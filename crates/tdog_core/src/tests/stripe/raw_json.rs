@@ -0,0 +1,152 @@
+use serde_json::{json, Value};
+use stripe_client::types::types as API;
+use unicon::traits::{*};
+use unicon::uc::UniCon;
+
+use crate::Options;
+use crate::error::TdError;
+use crate::providers::stripe::apply_events::apply_events_body;
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::raw_json::{add_raw_tables, assert_raw_json_matches, get_raw_table_names};
+use crate::providers::stripe::schema::{Db, WriteTree};
+use crate::providers::stripe::schema::types::{Customer, Subscription, SubscriptionItem, Price};
+use crate::providers::stripe::schema_meta::LogWrite;
+use crate::providers::stripe::write_ctx::{with_write_ctx, WriteCtx};
+use crate::tests::stripe::util::{get_event_values, get_events, get_unicon};
+use crate::util::{get_temp_file, get_unique_id};
+
+
+fn create_raw_tables(file: &str) -> UniCon {
+    let mut uc = get_unicon(file);
+    let objects = ObjectSelection::default();
+
+    let mut excluded = objects.get_excluded();
+    let mut extra = vec![];
+    add_raw_tables(&objects, &mut excluded, &mut extra);
+    uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&excluded, &extra).unwrap();
+    uc
+}
+
+fn get_customer(e: &API::NotificationEvent) -> Customer {
+    match &*e.data.object {
+        API::UniNotificationEventDataObject::Customer(x) => x.as_ref().into(),
+        _ => unreachable!()
+    }
+}
+
+#[test]
+fn raw_tables_follow_objects() {
+    let names = get_raw_table_names(&ObjectSelection::default());
+
+    assert!(names.contains(&"customers".to_string()));
+    assert!(!names.contains(&"notification_events".to_string()));
+    assert!(!names.iter().any(|x| x.starts_with("td_")));
+}
+
+// `raw` is the JSON of the event's `data.object`, including fields the typed structs do not have.
+#[tokio::main]
+#[test]
+async fn raw_json_insert_and_update() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = create_raw_tables(&file);
+    let objects = ObjectSelection::default();
+
+    assert!(assert_raw_json_matches(&mut uc, &objects, true).is_ok());
+    assert!(matches!(assert_raw_json_matches(&mut uc, &objects, false), Err(TdError::SchemaConflict(_))));
+
+    let mut values = get_event_values("customer_1");
+    for x in &mut values {
+        x["data"]["object"]["not_modeled"] = json!("x");
+    }
+    let events: Vec<API::NotificationEvent> = serde_json::from_value(Value::Array(values.clone())).unwrap();
+
+    let opts = Options {
+        store_raw_json: true,
+        ..Default::default()
+    };
+    let ctx = WriteCtx::new(&opts).unwrap();
+    let index = ctx.get_raw_index().unwrap();
+    values.iter().for_each(|x| index.add(x));
+
+    with_write_ctx(ctx, async {
+        let mut utx = uc.tx_open().unwrap();
+        apply_events_body(&mut utx, 1, &events[0..1].to_vec(), &objects);
+        utx.tx_close().unwrap();
+
+        let raw = Customer::get_all(&mut uc).pop().unwrap().raw.unwrap();
+        assert_eq!(raw["object"], json!("customer"));
+        assert_eq!(raw["name"], json!("C1.customer_1 Test"));
+        assert_eq!(raw["not_modeled"], json!("x"));
+
+        // The update events keep `raw` in sync with the typed columns.
+        let mut utx = uc.tx_open().unwrap();
+        apply_events_body(&mut utx, 2, &events[1..3].to_vec(), &objects);
+        utx.tx_close().unwrap();
+    }).await;
+
+    let c = Customer::get_all(&mut uc).pop().unwrap();
+    let raw = c.raw.as_ref().unwrap();
+    assert_eq!(raw["name"], json!(c.name.as_ref().unwrap()));
+    assert_eq!(raw["email"], json!("update.1@gmail.com"));
+
+    // Each event's JSON is dropped once it is applied.
+    assert_eq!(index.len(), 1);
+
+    // `raw` is not part of the CDC/dry run JSON of a row.
+    assert!(serde_json::to_value(c).unwrap().get("raw").is_none());
+}
+
+// Nested objects (E.g. a subscription's items and their prices) are read from the JSON of the list item they were downloaded in.
+#[tokio::main]
+#[test]
+async fn raw_json_nested_objects() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = create_raw_tables(&file);
+
+    let mut sub = get_event_values("tax_rate_1").into_iter()
+        .find(|x| x["type"] == json!("customer.subscription.created"))
+        .unwrap()["data"]["object"].take();
+    sub["default_payment_method"] = Value::Null;
+    sub["items"]["data"][0]["not_modeled"] = json!("x");
+    let item_id = sub["items"]["data"][0]["id"].as_str().unwrap().to_string();
+    let price_id = sub["items"]["data"][0]["price"]["id"].as_str().unwrap().to_string();
+    let typed: API::Subscription = serde_json::from_value(sub.clone()).unwrap();
+
+    let ctx = WriteCtx::new(&Options { store_raw_json: true, ..Default::default() }).unwrap();
+    let index = ctx.get_raw_index().unwrap();
+    index.add(&json!({"object": "list", "data": [sub], "has_more": false}));
+
+    with_write_ctx(ctx, async {
+        let mut utx = uc.tx_open().unwrap();
+        Subscription::insert_tree(&mut utx, 1, &typed);
+        utx.tx_close().unwrap();
+    }).await;
+
+    assert!(index.is_empty());
+    assert_eq!(Subscription::get_all(&mut uc).pop().unwrap().raw.unwrap()["id"], json!(&typed.id));
+
+    let item = SubscriptionItem::get_all(&mut uc).pop().unwrap().raw.unwrap();
+    assert_eq!(item["id"], json!(item_id));
+    assert_eq!(item["not_modeled"], json!("x"));
+
+    let price = Price::get_all(&mut uc).into_iter().find(|x| x.id == price_id).unwrap();
+    assert_eq!(price.raw.unwrap()["object"], json!("price"));
+}
+
+// A DB created without `store_raw_json` has no `raw` column; rows are read with `raw: None`.
+#[test]
+fn raw_json_not_enabled() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    let objects = ObjectSelection::default();
+    uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&objects.get_excluded(), &vec![]).unwrap();
+
+    assert!(assert_raw_json_matches(&mut uc, &objects, false).is_ok());
+    assert!(matches!(assert_raw_json_matches(&mut uc, &objects, true), Err(TdError::SchemaConflict(_))));
+
+    let mut utx = uc.tx_open().unwrap();
+    get_customer(&get_events("customer_1")[0]).tx_insert_set_pk_log_write(&mut utx, 1);
+    utx.tx_close().unwrap();
+
+    assert_eq!(Customer::get_all(&mut uc).pop().unwrap().raw, None);
+}
@@ -0,0 +1,121 @@
+use rusqlite::Connection;
+use serde_json::Map;
+use stripe_client::http::http::{Config, StripeAccount, StripeClient};
+use stripe_client::types::types as API;
+use unicon::uc::{Engine, SQLiteCreate, UniCon};
+use unicon::traits::{*};
+
+use crate::{ForeignKeys, Options, Replay};
+use crate::fns::now_3;
+use crate::providers::stripe::foreign_keys::set_sqlite_enforcement;
+use crate::providers::stripe::replay::{get_ordered, replay};
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema_meta::{TdMetadata, TdRun};
+use crate::providers::stripe::write_ctx::{with_write_ctx, WriteCtx};
+use crate::tests::stripe::util::{get_events, get_unicon};
+use crate::util::{get_temp_file, get_unique_id};
+
+
+// A DB with an (empty) download, as if restored from a backup.
+fn get_downloaded_db(file: &str) -> UniCon {
+    let mut uc = get_unicon(file);
+    uc.ensure_schema_and_tables_exist_and_writable::<Db>();
+
+    let sa = StripeAccount {
+        id: "acct_1".into(),
+        is_test: true,
+        account: Map::new(),
+    };
+    TdMetadata::insert_cli_and_stripe_versions(&mut uc, &sa, None);
+
+    let mut utx = uc.tx_open().unwrap();
+    TdRun {
+        run_id: None,
+        r#type: "download".into(),
+        start_ts: now_3().into(),
+        end_ts: now_3().into(),
+    }.tx_insert_set_pk(&mut utx);
+    utx.tx_close().unwrap();
+
+    uc
+}
+
+fn get_count(file: &str, sql: &str) -> i64 {
+    Connection::open(file).unwrap().query_row(sql, [], |r| r.get(0)).unwrap()
+}
+
+#[tokio::main]
+#[test]
+async fn replay_jsonl_in_batches() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_downloaded_db(&file);
+
+    // Newest first, as returned by `/v1/events`.
+    let mut events = get_events("customer_1");
+    events.reverse();
+    let events_file = get_temp_file(get_unique_id() + ".jsonl").unwrap();
+    let lines: Vec<String> = events.iter().map(|x| serde_json::to_string(x).unwrap()).collect();
+    std::fs::write(&events_file, lines.join("\n")).unwrap();
+
+    let rp = Replay {
+        events: events_file,
+        to: Engine::SQLite(SQLiteCreate { file: file.clone() }),
+        from: None,
+        batch_size: 3,
+        options: Options::default(),
+    };
+
+    let sc = StripeClient::new(Config {
+        secret_key: "".into(),
+        is_test: true,
+        base: "".into(),
+        headers: None,
+        proxy: None,
+        timeout_ms: None,
+        retry: false,
+        log_requests: false,
+    });
+
+    set_sqlite_enforcement(ForeignKeys::Enforced, &mut uc);
+
+    // Running again skips events that have already been applied.
+    for _ in 0..2 {
+        with_write_ctx(WriteCtx::new(&rp.options).unwrap(), replay(&sc, &mut uc, &rp)).await.unwrap();
+
+        assert_eq!(get_count(&file, "SELECT COUNT(*) FROM td_stripe_apply_events"), 4);
+        assert_eq!(get_count(&file, "SELECT COUNT(*) FROM td_runs WHERE type = 'apply_events'"), 2);
+        assert_eq!(get_count(&file, "SELECT COUNT(*) FROM notification_events"), 4);
+    }
+
+    // Written with the job's `options.foreign_keys` (not checked by default, even on a connection that checks them).
+    if let UniCon::Rusqlite(x) = &uc {
+        let on: i64 = x.c.query_row("PRAGMA foreign_keys", [], |r| r.get(0)).unwrap();
+        assert_eq!(on, 0);
+    }
+
+    // First applied event is the oldest.
+    let first: String = Connection::open(&file).unwrap().query_row("SELECT event_id FROM td_stripe_apply_events ORDER BY apply_id LIMIT 1", [], |r| r.get(0)).unwrap();
+    assert_eq!(first, get_events("customer_1")[0].id);
+}
+
+#[test]
+fn replay_ordering() {
+    let events = get_events("customer_1");
+    let ids = |x: &Vec<API::NotificationEvent>| x.iter().map(|e| e.id.clone()).collect::<Vec<String>>();
+
+    // Oldest first, newest first, and duplicates.
+    assert_eq!(ids(&get_ordered(events.clone()).unwrap()), ids(&events));
+
+    let mut rev = events.clone();
+    rev.reverse();
+    assert_eq!(ids(&get_ordered(rev).unwrap()), ids(&events));
+
+    let mut dup = events.clone();
+    dup.push(events[0].clone());
+    assert_eq!(ids(&get_ordered(dup).unwrap()), ids(&events));
+
+    let mut unordered = events.clone();
+    unordered.swap(0, 3);
+    unordered.swap(1, 3);
+    assert!(get_ordered(unordered).is_err());
+}
@@ -0,0 +1,82 @@
+use rusqlite::Connection;
+use unicon::traits::{*};
+use unicon::uc::{ColCreate, SchemaDiff};
+
+use crate::providers::stripe::history::get_history_tables;
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::coupon::Coupon;
+use crate::providers::stripe::watch::assert_cols_compatible;
+use crate::tests::stripe::util::get_unicon;
+use crate::util::{get_temp_file, get_unique_id};
+
+
+fn get_col(name: &str, sql_type: &str, nullable: bool) -> ColCreate {
+    ColCreate {
+        name: name.into(),
+        sql_type: sql_type.into(),
+        nullable,
+        generated: false,
+    }
+}
+
+#[test]
+fn schema_diff_cols_sqlite() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    let history_tables = get_history_tables(&ObjectSelection::default());
+    uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&vec![], &history_tables).unwrap();
+
+    // Including generated (`flatten`) columns and history tables.
+    let diff = uc.get_schema_diff_with::<Db>(&vec![], &history_tables).unwrap();
+    assert_eq!(diff, SchemaDiff::default());
+
+    // `coupons.duration_in_months` changed to `TEXT`.
+    let create = <Coupon as TableStatic>::get_table().static_sql_strings.as_ref().unwrap().sqlite.create.create.clone();
+    let c = Connection::open(&file).unwrap();
+    c.execute_batch(&format!("
+        ALTER TABLE customers DROP COLUMN email;
+        ALTER TABLE customers ADD COLUMN note TEXT NOT NULL DEFAULT '';
+        DROP INDEX invoice;
+        DROP TABLE coupons;
+        {};
+    ", create.replace("duration_in_months INTEGER", "duration_in_months TEXT"))).unwrap();
+
+    let diff = uc.get_schema_diff_with::<Db>(&vec![], &history_tables).unwrap();
+    let names: Vec<&str> = diff.tables.iter().map(|x| x.name.as_str()).collect();
+    assert_eq!(names, vec!["coupons", "customers", "invoice_line_items"]);
+
+    assert_eq!(diff.tables[0].changed_cols, vec![(get_col("duration_in_months", "INTEGER", true), get_col("duration_in_months", "TEXT", true))]);
+    assert_eq!(diff.tables[1].missing_cols, vec![get_col("email", "TEXT", true)]);
+    assert_eq!(diff.tables[1].extra_cols, vec![get_col("note", "TEXT", false)]);
+    assert_eq!(diff.tables[2].missing_indexes, vec!["invoice".to_string()]);
+    assert!(matches!(assert_cols_compatible(&diff), Err(crate::error::TdError::SchemaConflict(_))));
+
+    // Extra columns, missing indexes and a different type are warnings.
+    let mut warnings = diff.clone();
+    warnings.tables.retain(|x| x.name != "customers");
+    warnings.tables[1].extra_cols.push(get_col("note", "TEXT", true));
+    assert!(assert_cols_compatible(&warnings).is_ok());
+
+    // A column tdog writes nulls to that is `NOT NULL`.
+    let mut not_null = diff;
+    not_null.tables.retain(|x| x.name == "coupons");
+    not_null.tables[0].changed_cols[0].1.nullable = false;
+    assert!(matches!(assert_cols_compatible(&not_null), Err(crate::error::TdError::SchemaConflict(_))));
+}
+
+// Table names are bound, not formatted into the SQL.
+#[test]
+fn schema_diff_cols_quoted_name() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+
+    let c = Connection::open(&file).unwrap();
+    c.execute_batch("CREATE TABLE \"a'b\" (id INTEGER NOT NULL, x TEXT);").unwrap();
+
+    let mut utx = uc.tx_open().unwrap();
+    assert_eq!(utx.get_columns("a'b").unwrap(), vec!["id", "x"]);
+    assert_eq!(utx.get_col_creates("a'b").unwrap(), vec![get_col("id", "INTEGER", false), get_col("x", "TEXT", true)]);
+    assert!(utx.get_columns("not_a_table").unwrap().is_empty());
+    utx.tx_rollback().unwrap();
+}
@@ -0,0 +1,38 @@
+use serde_json::Value;
+
+use crate::providers::stripe::schema_graph::{get_dbt_sources, get_graph, GraphFormat};
+
+
+#[test]
+fn schema_graph_formats() {
+    let x = get_graph(GraphFormat::Mermaid);
+    assert!(x.starts_with("erDiagram\n"));
+    assert!(x.contains("        string id UK\n"));
+    assert!(x.contains("    subscription_items }o--|| prices : \"price\"\n"));
+    assert!(x.contains("    customers }o..o| cards : \"default_source (polymorphic)\"\n"));
+    assert!(x.contains("    subscription_items }o..o{ tax_rates : \"tax_rates (JSON array)\"\n"));
+    assert!(!x.contains("td_"));
+
+    let x = get_graph(GraphFormat::Dot);
+    assert!(x.contains("    subscription_items:price -> prices:id [label=\"price\", style=solid];\n"));
+    assert!(x.contains("    subscriptions:customer -> customers:id [label=\"customer\", style=dashed];\n"));
+
+    let x: Value = serde_json::from_str(&get_graph(GraphFormat::Json)).unwrap();
+    let edges = x["edges"].as_array().unwrap();
+    let default_source = edges.iter().find(|e| e["copy_table"] == "customers" && e["copy_column"] == "default_source").unwrap();
+    assert_eq!(default_source["polymorphic"], true);
+    assert_eq!(default_source["owner_tables"].as_array().unwrap().len(), 3);
+
+    assert!("svg".parse::<GraphFormat>().is_err());
+}
+
+#[test]
+fn schema_graph_dbt() {
+    let x = get_dbt_sources(Some("main"));
+    assert!(x.starts_with("version: 2\n\nsources:\n  - name: stripe\n    schema: main\n    tables:\n"));
+    assert!(x.contains("          - name: id\n            tests:\n              - unique\n"));
+    assert!(x.contains("          - name: price\n            tests:\n              - relationships:\n                  to: source('stripe', 'prices')\n                  field: id\n"));
+
+    // Not enforced by the API.
+    assert!(!x.contains("to: source('stripe', 'customers')"));
+}
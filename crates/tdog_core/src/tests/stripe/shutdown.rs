@@ -0,0 +1,178 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use rusqlite::Connection;
+use serde_json::{json, Map, Value};
+use stripe_client::http::http::{Config, StripeAccount, StripeClient};
+use tokio::sync::oneshot;
+use unicon::traits::{*};
+
+use crate::{Download, Options};
+use crate::error::TdError;
+use crate::fns::now_3;
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema_meta::{TdMetadata, TdRun};
+use crate::providers::stripe::watch::{download_all, poll_apply_events};
+use crate::providers::stripe::write_ctx::{with_write_ctx, WriteCtx};
+use crate::tests::stripe::util::{get_event_values, get_unicon};
+use crate::util::{get_temp_file, get_unique_id};
+
+
+// A local server in place of the Stripe API.
+// - `body`: the response to every request, or None to never respond (the connection is held until the client closes it).
+// - The channel receives `GET <path>` for each request, and `closed <path>` when the client closes an unanswered request.
+// - The oneshot is sent on the first request, as if a signal was received while a request was in flight.
+struct MockStripe {
+    sc: StripeClient,
+    reqs: mpsc::Receiver<String>,
+    first_req: oneshot::Receiver<()>,
+}
+
+fn start_stripe(body: Option<Value>) -> MockStripe {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, reqs) = mpsc::channel();
+    let (first_tx, first_req) = oneshot::channel();
+    let first_tx = Arc::new(Mutex::new(Some(first_tx)));
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let (tx, body, first_tx) = (tx.clone(), body.clone(), first_tx.clone());
+
+            thread::spawn(move || {
+                let mut head = vec![];
+                let mut buf = [0; 1024];
+                while !String::from_utf8_lossy(&head).contains("\r\n\r\n") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => head.extend_from_slice(&buf[..n])
+                    }
+                }
+                let path = String::from_utf8_lossy(&head).split(' ').nth(1).unwrap_or_default().to_string();
+                let _ = tx.send(format!("GET {}", &path));
+                if let Some(x) = first_tx.lock().unwrap().take() {
+                    let _ = x.send(());
+                }
+
+                match body {
+                    Some(x) => {
+                        let x = x.to_string();
+                        let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", x.len(), x);
+                    }
+                    None => {
+                        while let Ok(n) = stream.read(&mut buf) {
+                            if n == 0 {
+                                break;
+                            }
+                        }
+                        let _ = tx.send(format!("closed {}", &path));
+                    }
+                }
+            });
+        }
+    });
+
+    let sc = StripeClient::new(Config {
+        secret_key: "sk_test_x".to_string(),
+        is_test: true,
+        base,
+        headers: None,
+        proxy: None,
+        timeout_ms: None,
+        retry: false,
+        log_requests: false,
+    });
+
+    MockStripe {
+        sc,
+        reqs,
+        first_req,
+    }
+}
+
+async fn stop_on(first_req: oneshot::Receiver<()>) -> &'static str {
+    first_req.await.unwrap();
+    "SIGTERM"
+}
+
+fn get_download(file: &str) -> Download {
+    serde_json::from_value(json!({
+        "from": {"stripe": {"secret_key": "sk_test_x"}},
+        "to": {"sqlite": {"file": file}}
+    })).unwrap()
+}
+
+fn get_count(file: &str, sql: &str) -> i64 {
+    Connection::open(file).unwrap().query_row(sql, [], |r| r.get(0)).unwrap()
+}
+
+// The download is stopped with its requests in flight: the tx is rolled back, the requests and the scheduler are dropped, and the job fails with exit code 75.
+#[tokio::main]
+#[test]
+async fn shutdown_during_download() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    uc.ensure_schema_and_tables_exist_and_writable::<Db>();
+
+    let m = start_stripe(None);
+    let dl = get_download(&file);
+    let objects = ObjectSelection::default();
+    let res = with_write_ctx(WriteCtx::new(&Options::default()).unwrap(), download_all(&m.sc, &mut uc, &dl, &objects, None, stop_on(m.first_req))).await;
+
+    let e = res.unwrap_err();
+    assert!(matches!(e, TdError::Interrupted(_)));
+    assert_eq!(e.get_exit_code(), 75);
+    assert_eq!(get_count(&file, "select count(*) from td_runs"), 0);
+
+    // Each in-flight request is closed, and no more are sent once the scheduler has been aborted.
+    let mut sent = 0;
+    let mut closed = 0;
+    while let Ok(x) = m.reqs.recv_timeout(Duration::from_millis(1500)) {
+        if x.starts_with("GET") { sent += 1 } else { closed += 1 }
+    }
+    assert!(sent > 0);
+    assert_eq!(sent, closed);
+}
+
+// A signal received while events are being applied: the tx is committed, then the loop stops without polling again.
+#[tokio::main]
+#[test]
+async fn shutdown_during_poll() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    uc.ensure_schema_and_tables_exist_and_writable::<Db>();
+    TdMetadata::insert_cli_and_stripe_versions(&mut uc, &StripeAccount {
+        id: "acct_1".into(),
+        is_test: true,
+        account: Map::new(),
+    }, None);
+    TdRun {
+        run_id: None,
+        r#type: "download".into(),
+        start_ts: now_3().into(),
+        end_ts: now_3().into(),
+    }.insert_set_pk(&mut uc);
+
+    let created = get_event_values("customer_1")[0].clone();
+    assert_eq!(created["type"], "customer.created");
+
+    let m = start_stripe(Some(json!({
+        "object": "list",
+        "url": "/v1/events",
+        "has_more": false,
+        "data": [created]
+    })));
+    let dl = get_download(&file);
+    let objects = ObjectSelection::default();
+    let res = with_write_ctx(WriteCtx::new(&Options::default()).unwrap(), poll_apply_events(&m.sc, &mut uc, 10, &objects, &dl, stop_on(m.first_req))).await;
+
+    assert!(res.is_ok());
+    assert_eq!(get_count(&file, "select count(*) from td_runs where type='apply_events' and end_ts is not null"), 1);
+    assert_eq!(get_count(&file, "select count(*) from customers"), 1);
+    assert_eq!(m.reqs.try_iter().count(), 1);
+}
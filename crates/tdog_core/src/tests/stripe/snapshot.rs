@@ -0,0 +1,164 @@
+use chrono::NaiveDate;
+use serde_json::Map;
+use stripe_client::http::http::StripeAccount;
+use stripe_client::types::types as API;
+use unicon::traits::{*};
+use unicon::uc::{Engine, SQLiteCreate, UniCon};
+
+use crate::{EventsExport, Options, Snapshot};
+use crate::error::TdError;
+use crate::providers::stripe::apply_events::apply_events_body;
+use crate::providers::stripe::event_archive::export;
+use crate::providers::stripe::history::get_history_tables;
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::{Customer, NotificationEvent};
+use crate::providers::stripe::schema_meta::{LogWrite, TdMetadata, TdRun};
+use crate::providers::stripe::snapshot::snapshot;
+use crate::providers::stripe::write_ctx::{with_write_ctx, WriteCtx};
+use crate::tests::stripe::util::{get_events, get_unicon};
+use crate::util::{get_temp_file, get_unique_id};
+
+
+fn get_customer(e: &API::NotificationEvent) -> Customer {
+    match &*e.data.object {
+        API::UniNotificationEventDataObject::Customer(x) => x.as_ref().into(),
+        _ => unreachable!()
+    }
+}
+
+// Downloaded at 17:11:50, after `customer.created` (17:11:44); then the update (17:11:56) and delete (17:12:08) events are applied.
+// - `cus_other` is never changed.
+fn get_source(file: &str, history: bool) -> UniCon {
+    let mut uc = get_unicon(file);
+    let history_tables = if history { get_history_tables(&ObjectSelection::default()) } else { vec![] };
+    uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&vec![], &history_tables).unwrap();
+    TdMetadata::insert_cli_and_stripe_versions(&mut uc, &StripeAccount {
+        id: "acct_1".into(),
+        is_test: true,
+        account: Map::new(),
+    }, None);
+
+    let events = get_events("customer_1");
+    let mut utx = uc.tx_open().unwrap();
+    let mut dl = TdRun {
+        run_id: None,
+        r#type: "download".into(),
+        start_ts: Some(NaiveDate::from_ymd(2022, 5, 11).and_hms(17, 11, 48).into()),
+        end_ts: Some(NaiveDate::from_ymd(2022, 5, 11).and_hms(17, 11, 50).into()),
+    };
+    dl.tx_insert_set_pk(&mut utx);
+
+    let mut other = get_customer(&events[0]);
+    other.id = "cus_other".into();
+    for mut x in [get_customer(&events[0]), other] {
+        x.tx_insert_set_pk_log_write(&mut utx, dl.run_id.unwrap());
+    }
+
+    let mut run = TdRun {
+        run_id: None,
+        r#type: "apply_events".into(),
+        start_ts: Some(NaiveDate::from_ymd(2022, 5, 11).and_hms(17, 12, 10).into()),
+        end_ts: Some(NaiveDate::from_ymd(2022, 5, 11).and_hms(17, 12, 10).into()),
+    };
+    run.tx_insert_set_pk(&mut utx);
+    apply_events_body(&mut utx, run.run_id.unwrap(), &events[1..].to_vec(), &ObjectSelection::default());
+    utx.tx_close().unwrap();
+
+    uc
+}
+
+fn get_snapshot(db: &str, as_of: &str) -> (Snapshot, UniCon) {
+    let to = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let x = Snapshot {
+        db: Engine::SQLite(SQLiteCreate { file: db.to_string() }),
+        as_of: as_of.into(),
+        to: Engine::SQLite(SQLiteCreate { file: to.clone() }),
+        events_dir: None,
+        options: Options::default(),
+    };
+    (x, get_unicon(&to))
+}
+
+fn get_names(uc: &mut UniCon) -> Vec<(String, Option<String>, Option<String>)> {
+    Customer::get_all(uc).into_iter().map(|x| (x.id, x.name, x.email)).collect()
+}
+
+#[test]
+fn snapshot_without_history() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut src = get_source(&file, false);
+    let id = get_customer(&get_events("customer_1")[0]).id;
+
+    // The download state of the customer was overwritten by events after `as_of`.
+    let (x, mut dst) = get_snapshot(&file, "2022-05-11T17:11:52Z");
+    assert!(matches!(snapshot(&mut src, &mut dst, &x), Err(TdError::SchemaConflict(_))));
+
+    // Before the download completed.
+    let (x, mut dst) = get_snapshot(&file, "2022-05-11T17:11:00Z");
+    assert!(matches!(snapshot(&mut src, &mut dst, &x), Err(TdError::Config(_))));
+
+    // Both updates are in the same second.
+    let (x, mut dst) = get_snapshot(&file, "2022-05-11T17:11:56Z");
+    assert_eq!(snapshot(&mut src, &mut dst, &x).unwrap(), 2);
+    assert_eq!(get_names(&mut dst), vec![
+        ("cus_other".into(), Some("C1.customer_1 Test".into()), None),
+        (id.clone(), Some("C1.customer_1 Test Update 0".into()), Some("update.1@gmail.com".into())),
+    ]);
+    assert_eq!(NotificationEvent::get_all(&mut dst).len(), 2);
+
+    // `to` must be empty.
+    assert!(matches!(snapshot(&mut src, &mut dst, &x), Err(TdError::SchemaConflict(_))));
+
+    let (x, mut dst) = get_snapshot(&file, "2022-05-11T17:12:08Z");
+    assert_eq!(snapshot(&mut src, &mut dst, &x).unwrap(), 3);
+    assert_eq!(get_names(&mut dst), vec![("cus_other".into(), Some("C1.customer_1 Test".into()), None)]);
+}
+
+#[tokio::main]
+#[test]
+async fn snapshot_from_history() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let opts = Options {
+        history: true,
+        ..Default::default()
+    };
+    let mut src = with_write_ctx(WriteCtx::new(&opts).unwrap(), async { get_source(&file, true) }).await;
+    let id = get_customer(&get_events("customer_1")[0]).id;
+
+    // The download versions are used, so any time after the download works.
+    let (x, mut dst) = get_snapshot(&file, "2022-05-11T17:11:52Z");
+    assert_eq!(snapshot(&mut src, &mut dst, &x).unwrap(), 0);
+
+    assert_eq!(get_names(&mut dst), vec![
+        (id, Some("C1.customer_1 Test".into()), None),
+        ("cus_other".into(), Some("C1.customer_1 Test".into()), None),
+    ]);
+}
+
+// Events deleted from the DB by `retention_days` are read from the `events_export` directory.
+#[test]
+fn snapshot_from_archive() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut src = get_source(&file, false);
+
+    let dir = get_temp_file(get_unique_id()).unwrap();
+    std::fs::remove_file(&dir).unwrap();
+    let ex = EventsExport {
+        db: Engine::SQLite(SQLiteCreate { file: file.clone() }),
+        dir: dir.clone(),
+        retention_days: Some(0),
+        options: Options::default(),
+    };
+    assert_eq!(export(&mut src, &ex).unwrap(), 3);
+    assert_eq!(NotificationEvent::get_all(&mut src).len(), 1);
+
+    let (x, mut dst) = get_snapshot(&file, "2022-05-11T17:12:08Z");
+    assert!(matches!(snapshot(&mut src, &mut dst, &x), Err(TdError::Config(_))));
+
+    let (mut x, mut dst) = get_snapshot(&file, "2022-05-11T17:12:08Z");
+    x.events_dir = Some(dir);
+    assert_eq!(snapshot(&mut src, &mut dst, &x).unwrap(), 3);
+    assert_eq!(get_names(&mut dst), vec![("cus_other".into(), Some("C1.customer_1 Test".into()), None)]);
+    assert_eq!(NotificationEvent::get_all(&mut dst).len(), 3);
+}
@@ -0,0 +1,155 @@
+use chrono::Utc;
+use rusqlite::Connection;
+use serde_json::json;
+use unicon::utx::UniTx;
+
+use crate::Options;
+use crate::providers::stripe::history::get_history_tables;
+use crate::providers::stripe::objects::ObjectSelection;
+use crate::providers::stripe::schema::Db;
+use crate::providers::stripe::schema::types::Customer;
+use crate::providers::stripe::schema::types::coupon::Coupon;
+use crate::providers::stripe::schema::types::discount::Discount;
+use crate::providers::stripe::schema::types::invoice::Invoice;
+use crate::providers::stripe::schema_meta::LogWrite;
+use crate::providers::stripe::views::get_views;
+use crate::providers::stripe::write_ctx::{with_write_ctx, WriteCtx};
+use crate::tests::stripe::util::{get_item, get_price, get_sub, get_unicon};
+use crate::util::{get_temp_file, get_unique_id};
+
+fn write<T: LogWrite>(utx: &mut UniTx, mut x: T) {
+    x.tx_insert_set_pk_log_write(utx, 1);
+}
+
+
+fn get_invoice(id: &str, customer: &str, paid: bool, status: &str) -> Invoice {
+    Invoice {
+        id: id.into(),
+        customer: customer.into(),
+        attempted: true,
+        paid,
+        status: Some(status.into()),
+        default_tax_rates: json!([]),
+        lines_newest_10: json!([]),
+        status_transitions: json!({}),
+        total_tax_amounts: json!([]),
+        ..Default::default()
+    }
+}
+
+fn query<T>(file: &str, sql: &str, f: fn(&rusqlite::Row) -> rusqlite::Result<T>) -> Vec<T> {
+    let c = Connection::open(file).unwrap();
+    let mut stmt = c.prepare(sql).unwrap();
+    let rows = stmt.query_map([], f).unwrap();
+    rows.map(|x| x.unwrap()).collect()
+}
+
+#[tokio::main]
+#[test]
+async fn views_sqlite() {
+    let file = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+    let mut uc = get_unicon(&file);
+    let objects = ObjectSelection::default();
+    uc.ensure_schema_and_tables_exist_and_writable_with::<Db>(&objects.get_excluded(), &get_history_tables(&objects)).unwrap();
+
+    let opts = Options {
+        history: true,
+        ..Default::default()
+    };
+    with_write_ctx(WriteCtx::new(&opts).unwrap(), async {
+        let mut utx = uc.tx_open().unwrap();
+
+        write(&mut utx, get_price("price_m", 1000, "month", "licensed"));
+        write(&mut utx, get_price("price_y", 12000, "year", "licensed"));
+        write(&mut utx, get_price("price_metered", 5, "month", "metered"));
+        write(&mut utx, Coupon { id: "co_half".into(), duration: "forever".into(), percent_off: Some(50.0), ..Default::default() });
+        write(&mut utx, Coupon { id: "co_once".into(), duration: "once".into(), amount_off: Some(100), ..Default::default() });
+        write(&mut utx, Discount { id: "di_1".into(), coupon: "co_half".into(), ..Default::default() });
+        write(&mut utx, Discount { id: "di_2".into(), coupon: "co_once".into(), ..Default::default() });
+
+        for x in ["cus_1", "cus_2", "cus_3"] {
+            write(&mut utx, Customer { id: x.into(), email: Some(format!("{}@example.com", x)), ..Default::default() });
+        }
+
+        // 2 * 1000 with 50% off; the metered item is not included.
+        write(&mut utx, get_sub("sub_1", "cus_1", "active", Some("di_1")));
+        write(&mut utx, get_item("si_1", "sub_1", "price_m", Some(2)));
+        write(&mut utx, get_item("si_2", "sub_1", "price_metered", Some(1)));
+
+        // 12000 / 12; the coupon only applied to the first invoice.
+        write(&mut utx, get_sub("sub_2", "cus_2", "past_due", Some("di_2")));
+        write(&mut utx, get_item("si_3", "sub_2", "price_y", None));
+
+        write(&mut utx, get_sub("sub_3", "cus_1", "canceled", None));
+        write(&mut utx, get_item("si_4", "sub_3", "price_m", Some(1)));
+
+        write(&mut utx, get_sub("sub_4", "cus_3", "trialing", None));
+
+        write(&mut utx, get_invoice("in_1", "cus_2", false, "open"));
+        write(&mut utx, get_invoice("in_2", "cus_1", true, "paid"));
+
+        utx.tx_close().unwrap();
+    }).await;
+
+    let mut utx = uc.tx_open().unwrap();
+    let views = get_views(&objects, true);
+    assert_eq!(views.create_or_replace(&mut utx).unwrap().len(), 5);
+    assert!(views.create_or_replace(&mut utx).unwrap().is_empty());
+    utx.tx_close().unwrap();
+
+    assert_eq!(
+        query(&file, "SELECT subscription, status, currency, mrr_gross, mrr FROM v_mrr_by_subscription ORDER BY subscription", |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?))),
+        vec![
+            ("sub_1".to_string(), "active".to_string(), "usd".to_string(), 2000.0, 1000.0),
+            ("sub_2".to_string(), "past_due".to_string(), "usd".to_string(), 1000.0, 1000.0),
+        ]
+    );
+
+    let today = Utc::now().format("%Y-%m-%d").to_string();
+    assert_eq!(
+        query(&file, "SELECT day, currency, mrr_gross, subscriptions FROM v_mrr_daily", |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?))),
+        vec![(today, "usd".to_string(), 3000.0, 2)]
+    );
+
+    assert_eq!(
+        query(&file, "SELECT customer, active_subscriptions FROM v_active_customers ORDER BY customer", |r| Ok((r.get(0)?, r.get(1)?))),
+        vec![("cus_1".to_string(), 1), ("cus_2".to_string(), 1), ("cus_3".to_string(), 1)]
+    );
+
+    assert_eq!(query(&file, "SELECT invoice FROM v_failed_invoices", |r| r.get::<_, String>(0)), vec!["in_1".to_string()]);
+
+    assert_eq!(
+        query(&file, "SELECT subscription, mrr_gross FROM v_churned_subscriptions", |r| Ok((r.get(0)?, r.get(1)?))),
+        vec![("sub_3".to_string(), 1000.0)]
+    );
+
+    // Without history, `v_mrr_daily` is dropped.
+    let mut utx = uc.tx_open().unwrap();
+    assert!(get_views(&objects, false).create_or_replace(&mut utx).unwrap().is_empty());
+    assert!(!utx.get_views().contains(&"v_mrr_daily".to_string()));
+    utx.tx_close().unwrap();
+
+    // A user's view on a replaced view is kept.
+    let mut views = get_views(&objects, false);
+    for v in views.views.iter_mut() {
+        v.version += 1;
+    }
+    let mut utx = uc.tx_open().unwrap();
+    utx.exec_one("CREATE VIEW user_churn AS SELECT subscription FROM v_churned_subscriptions");
+    assert_eq!(views.create_or_replace(&mut utx).unwrap().len(), 4);
+    utx.tx_close().unwrap();
+    assert_eq!(query(&file, "SELECT subscription FROM user_churn", |r| r.get::<_, String>(0)), vec!["sub_3".to_string()]);
+}
+
+#[test]
+fn views_follow_objects() {
+    let names = |x: &ObjectSelection, history: bool| get_views(x, history).views.into_iter().map(|v| v.name).collect::<Vec<String>>();
+
+    assert_eq!(names(&ObjectSelection::default(), false), vec!["v_mrr_by_subscription", "v_active_customers", "v_failed_invoices", "v_churned_subscriptions"]);
+    assert_eq!(names(&ObjectSelection::default(), true).len(), 5);
+
+    let v = get_views(&ObjectSelection::default(), true).views;
+    assert!(v[0].mysql.contains("JSON_UNQUOTE(JSON_EXTRACT(p.recurring, '$.interval'))"));
+    assert!(v[0].postgres.contains("ROUND((x.mrr_gross)::numeric, 2)"));
+    assert!(v[1].postgres.contains("generate_series("));
+}
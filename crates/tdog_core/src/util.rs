@@ -1,6 +1,9 @@
 use std::{env, fs};
 use std::fs::File;
+use std::future::Future;
 
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::future::AbortHandle;
 use uuid::Uuid;
 
 pub(crate) fn get_temp_file(f: String) -> std::io::Result<String> {
@@ -46,4 +49,50 @@ impl Redact for &str {
 }
 
 
+// Label of the download job that the current Tokio task is running (when more than one job is given in the config).
+// - A single process runs many (account, database) jobs; the label is included in each log line so the shared output can be read per job.
+// - Task local as `log!` macros have no way of passing context down from the caller.
+tokio::task_local! {
+    pub static JOB_LABEL: Option<String>;
+}
+
+pub fn get_job_label() -> Option<String> {
+    JOB_LABEL.try_with(|x| x.clone()).ok().flatten()
+}
+
+// Carry the current job label over into a new Tokio task (`tokio::spawn` does not inherit task locals).
+pub fn with_job_label<F: Future>(f: F) -> impl Future<Output=F::Output> {
+    JOB_LABEL.scope(get_job_label(), f)
+}
 
+
+// Abort background tasks when the owning scope ends, including when it ends via a panic.
+// - When one job out of many panics it is restarted in the same process; its looping background tasks must not keep running.
+pub struct AbortOnDrop(pub Vec<AbortHandle>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        for x in &self.0 {
+            x.abort();
+        }
+    }
+}
+
+
+// `options.created_gte` => unix timestamp.
+// - Accepts a date (`2021-01-01`), an RFC 3339 date time (`2021-01-01T00:00:00Z`), or a duration before `now` (`90days`, `1year 6months`).
+pub fn parse_created_gte(x: &str, now: DateTime<Utc>) -> Option<i64> {
+    if let Ok(d) = NaiveDate::parse_from_str(x, "%Y-%m-%d") {
+        return Some(d.and_hms(0, 0, 0).timestamp());
+    }
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(x) {
+        return Some(dt.timestamp());
+    }
+
+    if let Ok(d) = humantime::parse_duration(x) {
+        return Some(now.timestamp() - d.as_secs() as i64);
+    }
+
+    None
+}
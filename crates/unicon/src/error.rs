@@ -0,0 +1,69 @@
+use std::fmt;
+
+
+/// Errors from connecting to or writing to an engine.
+/// - Keeps the engine's own error so callers can match on it, instead of converting everything to a string.
+#[derive(Debug)]
+pub enum UniConError {
+    SQLite(rusqlite::Error),
+    MySQL(mysql::Error),
+    Postgres(postgres::Error),
+
+    // Invalid connection config (e.g. MySQL without `schema_name`, a missing `ca_cert_file`).
+    Config(String),
+
+    // A write or commit rejected by a foreign key (deferred constraints are checked at commit).
+    // - Keeps the engine's message, which names the constraint where the engine reports it.
+    ForeignKey(String),
+}
+
+impl UniConError {
+    // Separates foreign key violations from other engine errors.
+    pub fn from_commit<E: Into<UniConError>>(e: E) -> Self {
+        let e = e.into();
+        let is_fk = match &e {
+            UniConError::SQLite(rusqlite::Error::SqliteFailure(x, _)) => x.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_FOREIGNKEY,
+            UniConError::MySQL(mysql::Error::MySqlError(x)) => x.code == 1451 || x.code == 1452,
+            UniConError::Postgres(x) => x.code() == Some(&postgres::error::SqlState::FOREIGN_KEY_VIOLATION),
+            _ => false
+        };
+
+        if is_fk {
+            return UniConError::ForeignKey(e.to_string());
+        }
+
+        e
+    }
+}
+
+impl fmt::Display for UniConError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UniConError::SQLite(e) => write!(f, "SQLite: {}", e),
+            UniConError::MySQL(e) => write!(f, "MySQL: {}", e),
+            UniConError::Postgres(e) => write!(f, "Postgres: {}", e),
+            UniConError::Config(x) => write!(f, "{}", x),
+            UniConError::ForeignKey(x) => write!(f, "Foreign key violation: {}", x),
+        }
+    }
+}
+
+impl std::error::Error for UniConError {}
+
+impl From<rusqlite::Error> for UniConError {
+    fn from(e: rusqlite::Error) -> Self {
+        UniConError::SQLite(e)
+    }
+}
+
+impl From<mysql::Error> for UniConError {
+    fn from(e: mysql::Error) -> Self {
+        UniConError::MySQL(e)
+    }
+}
+
+impl From<postgres::Error> for UniConError {
+    fn from(e: postgres::Error) -> Self {
+        UniConError::Postgres(e)
+    }
+}
@@ -24,6 +24,7 @@ pub mod table;
 pub mod engines;
 pub mod uc;
 pub mod utx;
+pub mod error;
 pub mod traits;
 mod data;
 pub mod params;
@@ -193,3 +193,46 @@ impl<T1, T2, T3> ToWhereIndexedPostgres for ((&str, &T1), (&str, &T2), (&str, &T
         format!("{} = $1 AND {} = $2 AND {} = $3", &self.0.0, &self.1.0, &self.2.0)
     }
 }
+
+
+// A value for a column that is added to a table at runtime, so is not a field of the row struct (`Insert::tx_insert_with`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtraVal {
+    Json(Option<serde_json::Value>),
+    String(Option<String>),
+    I64(Option<i64>),
+    F64(Option<f64>),
+    Bool(Option<bool>),
+}
+
+impl ExtraVal {
+    pub fn to_sqlite(&self) -> &dyn ToSql {
+        match self {
+            ExtraVal::Json(x) => x,
+            ExtraVal::String(x) => x,
+            ExtraVal::I64(x) => x,
+            ExtraVal::F64(x) => x,
+            ExtraVal::Bool(x) => x,
+        }
+    }
+
+    pub fn to_mysql(&self) -> mysql::Value {
+        match self {
+            ExtraVal::Json(x) => x.as_ref().map(|x| x.to_string()).into(),
+            ExtraVal::String(x) => x.clone().into(),
+            ExtraVal::I64(x) => (*x).into(),
+            ExtraVal::F64(x) => (*x).into(),
+            ExtraVal::Bool(x) => (*x).into(),
+        }
+    }
+
+    pub fn to_postgres(&self) -> &(dyn postgres::types::ToSql + Sync) {
+        match self {
+            ExtraVal::Json(x) => x,
+            ExtraVal::String(x) => x,
+            ExtraVal::I64(x) => x,
+            ExtraVal::F64(x) => x,
+            ExtraVal::Bool(x) => x,
+        }
+    }
+}
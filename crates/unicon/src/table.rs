@@ -11,7 +11,7 @@ use syn::export::TokenStream2;
 use syn::{Field, Type, PathArguments, GenericArgument, DataStruct, Fields, DataEnum, Data};
 use quote::format_ident;
 use quote::quote;
-use crate::uc::{Schema, TableCreate, NameCreate};
+use crate::uc::{Schema, TableCreate, NameCreate, ColCreate};
 use std::collections::{HashSet, HashMap};
 use crate::data::mysql::MYSQL_RESERVED_KEYWORDS;
 use crate::data::postgres::POSTGRES_RESERVED_KEYWORDS;
@@ -107,6 +107,9 @@ pub struct StaticSQLStrings {
     pub create: CreateSQLObj,
     pub indexes: Vec<CreateSQLObj>,
     pub insert: String,
+
+    // Columns as the engine reports them (`UniTx::get_existing_schema`), to compare with an existing table.
+    pub cols: Vec<ColCreate>,
 }
 
 
@@ -118,12 +121,17 @@ impl From<StaticSQLStrings> for TableCreate {
         TableCreate {
             name: x.create.name,
             create: x.create.create.clone(),
-            indexes: x.indexes.iter().map(|x2| NameCreate {
+            indexes: x.indexes.iter().filter(|x2| x2.obj_type != ObjType::Constraint).map(|x2| NameCreate {
                 name: x2.name.clone(),
 
                 // Standard SQL
                 create: x2.create.clone(),
             }).collect(),
+            constraints: x.indexes.iter().filter(|x2| x2.obj_type == ObjType::Constraint).map(|x2| NameCreate {
+                name: x2.name.clone(),
+                create: x2.create.clone(),
+            }).collect(),
+            cols: x.cols,
         }
     }
 }
@@ -231,24 +239,38 @@ impl Table {
         true
     }
 
+    // Parse `#[extra_cols_prefix("x_")]` attribute, used on Rust structs.
+    // - Columns with this prefix may be added to the table at runtime (E.g. from config when the DB is created); they are ignored when reading rows instead of panicking.
+    pub fn get_extra_cols_prefix(ast: &syn::DeriveInput) -> Option<String> {
+        let attribute = ast.attrs.iter().filter(
+            |a| a.path.segments.len() == 1 && a.path.segments[0].ident == "extra_cols_prefix"
+        ).nth(0);
+
+        attribute.map(|x| x.tokens.to_string().trim_matches('(').trim_matches(')').trim_matches('"').to_string())
+    }
+
     // Intended to be run at macro time.
-    fn create_static_strings(&mut self) {
+    // - Also used at runtime for tables derived from a macro generated table (E.g. an app specific audit/history copy of a table).
+    pub fn create_static_strings(&mut self) {
         let sqlite = StaticSQLStrings {
             create: <Self as ToSQLString<SQLite>>::get_create_table(self),
             indexes: <Self as ToSQLString<SQLite>>::get_create_indexes(self),
             insert: <Self as ToSQLString<SQLite>>::get_insert(self),
+            cols: <Self as ToSQLString<SQLite>>::get_cols(self),
         };
 
         let mysql = StaticSQLStrings {
             create: <Self as ToSQLString<MySQL>>::get_create_table(self),
             indexes: <Self as ToSQLString<MySQL>>::get_create_indexes(self),
             insert: <Self as ToSQLString<MySQL>>::get_insert(self),
+            cols: <Self as ToSQLString<MySQL>>::get_cols(self),
         };
 
         let postgres = StaticSQLStrings {
             create: <Self as ToSQLString<Postgres>>::get_create_table(self),
             indexes: <Self as ToSQLString<Postgres>>::get_create_indexes(self),
             insert: <Self as ToSQLString<Postgres>>::get_insert(self),
+            cols: <Self as ToSQLString<Postgres>>::get_cols(self),
         };
 
         let all = AllEngineStrings {
@@ -260,6 +282,27 @@ impl Table {
         self.static_sql_strings = Some(all)
     }
 
+    // `pk_not_null`: the engine makes primary key columns `NOT NULL`.
+    // - Flattened columns are generated `TEXT` columns.
+    fn get_col_creates(&self, get_type: impl Fn(&Col) -> String, pk_not_null: bool) -> Vec<ColCreate> {
+        let cols = self.cols_not_skipped();
+        let mut v: Vec<ColCreate> = cols.iter().map(|c| ColCreate {
+            name: c.name.clone(),
+            sql_type: get_type(c),
+            nullable: c.nullable && !(c.primary && pk_not_null),
+            generated: false,
+        }).collect();
+
+        v.extend(cols.iter().flat_map(|c| c.get_flat_cols()).map(|(name, _)| ColCreate {
+            name,
+            sql_type: "TEXT".to_string(),
+            nullable: true,
+            generated: true,
+        }));
+
+        v
+    }
+
     // Returns the columns that conflict with SQL keywords, which would cause a runtime error to be thrown from the SQL engine.
     // - Taking the approach of "Rename keywords instead of quoting them".
     //      - Queries are easier to write without quoting identifiers.
@@ -321,6 +364,18 @@ pub struct Endpoint {
     pub fields: Vec<String>,
 }
 
+// A view over the tables, with the `SELECT` written in each engine's dialect (JSON functions and date series differ).
+// - Increment `version` when a `SELECT` changes; existing views are only replaced when their version differs (see `utx::Views`).
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct View {
+    pub name: String,
+    pub version: i64,
+    pub sqlite: String,
+    pub mysql: String,
+    pub postgres: String,
+}
+
 #[derive(Serialize, Deserialize)]
 #[derive(Debug, Clone, PartialEq)]
 pub struct Col {
@@ -345,10 +400,109 @@ pub struct Col {
     pub insert_ts: bool,
     pub update_ts: bool,
     pub attrs: Vec<AttrKey>,
+
+    // JSON paths to expose as generated columns (E.g. `address.country` on `shipping` = `shipping_address_country`).
+    // - Read only: computed by the engine from this column, so they are not in inserts or the Rust struct.
+    pub flatten: Vec<String>,
+
+    // A foreign key from this column (set on tables built at runtime; `#[fk]` is only used for compile time asserts).
+    pub references: Option<ColRef>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColRef {
+    pub table: String,
+    pub col: String,
+
+    // False: only declared for tools reading the schema (a comment on engines without unenforced constraints).
+    // True: a constraint checked at commit (`DEFERRABLE INITIALLY DEFERRED`), so rows can be written in any order within a tx.
+    // - MySQL does not support deferred constraints.
+    pub enforced: bool,
 }
 
 impl Col {
-    fn to_sqlite_type(&self) -> &str {
+    // A column for tables that are built at runtime (not derived from a struct).
+    pub fn new(name: &str, t: RustType, nullable: bool) -> Col {
+        Col {
+            name: name.to_string(),
+            name_raw: name.to_string(),
+            t,
+            nullable,
+            primary: false,
+            unique: false,
+            is_used_in_index: false,
+            skip: false,
+            insert_ts: false,
+            update_ts: false,
+            attrs: vec![],
+            flatten: vec![],
+            references: None,
+        }
+    }
+
+    // (generated column name, JSON path keys) for each `flatten` path.
+    pub fn get_flat_cols(&self) -> Vec<(String, Vec<String>)> {
+        self.flatten.iter().map(|x| {
+            let keys: Vec<String> = x.split('.').map(|k| k.to_string()).collect();
+            (format!("{}_{}", &self.name, keys.join("_")), keys)
+        }).collect()
+    }
+
+    // E.g. `address_country TEXT GENERATED ALWAYS AS (json_extract(address, '$.country')) VIRTUAL`
+    pub(crate) fn get_flat_cols_sqlite(&self) -> Vec<String> {
+        self.get_flat_cols().into_iter().map(|(name, keys)| {
+            format!("{} TEXT GENERATED ALWAYS AS (json_extract({}, '$.{}')) VIRTUAL", name, &self.name, keys.join("."))
+        }).collect()
+    }
+
+    // Note: `JSON_UNQUOTE` converts a JSON `null` to the string `null`.
+    pub(crate) fn get_flat_cols_mysql(&self) -> Vec<String> {
+        self.get_flat_cols().into_iter().map(|(name, keys)| {
+            let x = format!("JSON_EXTRACT({}, '$.{}')", &self.name, keys.join("."));
+            format!("{} TEXT GENERATED ALWAYS AS (CASE WHEN JSON_TYPE({}) = 'NULL' THEN NULL ELSE JSON_UNQUOTE({}) END) VIRTUAL", name, &x, &x)
+        }).collect()
+    }
+
+    // Postgres only supports stored generated columns.
+    pub(crate) fn get_flat_cols_postgres(&self) -> Vec<String> {
+        self.get_flat_cols().into_iter().map(|(name, keys)| {
+            let (last, parents) = keys.split_last().unwrap();
+            let x = parents.iter().map(|k| format!("->'{}'", k)).collect::<Vec<String>>().join("");
+            format!("{} TEXT GENERATED ALWAYS AS ({}{}->>'{}') STORED", name, &self.name, x, last)
+        }).collect()
+    }
+
+    // E.g. `References customers(id)`.
+    fn get_ref_comment(&self) -> Option<String> {
+        self.references.as_ref().map(|r| format!("References {}({})", &r.table, &r.col))
+    }
+
+    // SQLite cannot add constraints to an existing table; the clause is always `DEFERRABLE`, and enforcement is set per connection (`PRAGMA foreign_keys`).
+    fn get_fk_sqlite(&self) -> Option<String> {
+        self.references.as_ref().map(|r| format!("FOREIGN KEY ({}) REFERENCES {} ({}) DEFERRABLE INITIALLY DEFERRED", &self.name, &r.table, &r.col))
+    }
+
+    // Run after all tables are created (the referenced table must exist).
+    // - Postgres `NOT VALID` constraints still check new rows, so unenforced references are comments.
+    fn get_fk_postgres(&self, table_name: &str) -> Option<CreateSQLObj> {
+        let r = self.references.as_ref()?;
+        let name = format!("{}_{}_fkey", table_name, &self.name);
+
+        let create = if r.enforced {
+            format!("ALTER TABLE {} ADD CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {} ({}) DEFERRABLE INITIALLY DEFERRED", table_name, &name, &self.name, &r.table, &r.col)
+        } else {
+            format!("COMMENT ON COLUMN {}.{} IS '{}'", table_name, &self.name, self.get_ref_comment().unwrap())
+        };
+
+        Some(CreateSQLObj {
+            obj_type: ObjType::Constraint,
+            name,
+            create,
+        })
+    }
+
+    pub(crate) fn to_sqlite_type(&self) -> &str {
         // Note: `INT` != `INTEGER` in the context of `PRIMARY KEY`
         // @see https://stackoverflow.com/questions/20289410/difference-between-int-primary-key-and-integer-primary-key-sqlite/20289487#:~:text=Yes%2C%20there%20is%20a%20difference,separate%20primary%20key%20is%20created.
         let t = match self.t {
@@ -376,7 +530,7 @@ impl Col {
         t
     }
 
-    fn to_mysql_type(&self) -> &str {
+    pub(crate) fn to_mysql_type(&self) -> &str {
         // Note: `INT` != `INTEGER` in the context of `PRIMARY KEY`
         // @see https://stackoverflow.com/questions/20289410/difference-between-int-primary-key-and-integer-primary-key-sqlite/20289487#:~:text=Yes%2C%20there%20is%20a%20difference,separate%20primary%20key%20is%20created.
 
@@ -438,7 +592,7 @@ impl Col {
         t
     }
 
-    fn to_postgres_type(&self) -> &str {
+    pub(crate) fn to_postgres_type(&self) -> &str {
         // Note: `INT` != `INTEGER` in the context of `PRIMARY KEY`
         // @see https://stackoverflow.com/questions/20289410/difference-between-int-primary-key-and-integer-primary-key-sqlite/20289487#:~:text=Yes%2C%20there%20is%20a%20difference,separate%20primary%20key%20is%20created.
 
@@ -496,6 +650,7 @@ pub trait ToSQLString<T> {
     fn get_create_table(&self) -> CreateSQLObj;
     fn get_create_indexes(&self) -> Vec<CreateSQLObj>;
     fn get_insert(&self) -> String;
+    fn get_cols(&self) -> Vec<ColCreate>;
 }
 
 // Strings that do not require state from a given Rust struct (that represents a row).
@@ -537,6 +692,8 @@ pub enum ObjType {
     Schema,
     Table,
     Index,
+    // Foreign keys/comments added after all tables exist.
+    Constraint,
 }
 
 
@@ -559,7 +716,10 @@ impl ToSQLString<SQLite> for Table {
             if c.unique { v.push("UNIQUE".to_string()) }
 
             v.join(" ")
-        }).collect::<Vec<String>>().join(",\n");
+        })
+            .chain(self.cols_not_skipped().iter().flat_map(|c| c.get_flat_cols_sqlite()))
+            .chain(self.cols_not_skipped().iter().filter_map(|c| c.get_fk_sqlite()))
+            .collect::<Vec<String>>().join(",\n");
 
         CreateSQLObj {
             obj_type: ObjType::Table,
@@ -581,6 +741,11 @@ impl ToSQLString<SQLite> for Table {
         v
     }
 
+    // `INTEGER PRIMARY KEY` is nullable unless declared `NOT NULL`.
+    fn get_cols(&self) -> Vec<ColCreate> {
+        self.get_col_creates(|c| c.to_sqlite_type().to_string(), false)
+    }
+
     fn get_insert(&self) -> String {
         let mut kv: Vec<(String, String)> = vec![];
 
@@ -636,8 +801,13 @@ impl ToSQLString<MySQL> for Table {
             if c.primary { v.push("PRIMARY KEY AUTO_INCREMENT".to_string()) }
             if c.unique { v.push("UNIQUE".to_string()) }
 
+            // MySQL has no unenforced or deferred foreign keys, so references are always comments.
+            if let Some(x) = c.get_ref_comment() {
+                v.push(format!("COMMENT '{}'", x));
+            }
+
             v.join(" ")
-        }).collect::<Vec<String>>().join(",\n");
+        }).chain(self.cols_not_skipped().iter().flat_map(|c| c.get_flat_cols_mysql())).collect::<Vec<String>>().join(",\n");
 
         CreateSQLObj {
             obj_type: ObjType::Table,
@@ -659,6 +829,10 @@ impl ToSQLString<MySQL> for Table {
         v
     }
 
+    fn get_cols(&self) -> Vec<ColCreate> {
+        self.get_col_creates(|c| c.to_mysql_type().to_string(), true)
+    }
+
     // Note: `mysql crate, src/engines`: MySql itself doesn't have named parameters support, so it's implemented on the client side. One should use `:name` as a placeholder syntax for a named parameter.
     // - https://docs.rs/mysql/20.1.0/mysql/params/enum.Params.html
     //      - Named takes a `String` for every `Value`, does this double the memory needed? Is it better to use Vec<>?
@@ -731,7 +905,7 @@ impl ToSQLString<Postgres> for Table {
             if c.unique { v.push("UNIQUE".to_string()) }
 
             v.join(" ")
-        }).collect::<Vec<String>>().join(",\n");
+        }).chain(self.cols_not_skipped().iter().flat_map(|c| c.get_flat_cols_postgres())).collect::<Vec<String>>().join(",\n");
 
         CreateSQLObj {
             obj_type: ObjType::Table,
@@ -750,9 +924,16 @@ impl ToSQLString<Postgres> for Table {
             });
         }
 
+        v.extend(self.cols_not_skipped().iter().filter_map(|c| c.get_fk_postgres(&self.name)));
+
         v
     }
 
+    // `BIGSERIAL` is a `BIGINT` with a sequence default.
+    fn get_cols(&self) -> Vec<ColCreate> {
+        self.get_col_creates(|c| if c.primary { "BIGINT".to_string() } else { c.to_postgres_type().to_string() }, true)
+    }
+
     // Postgres does not have named parameters support (libraries may emulate it).
     fn get_insert(&self) -> String {
         let mut kv: Vec<(String, String)> = vec![];
@@ -858,6 +1039,7 @@ impl From<&Field> for Col {
         let mut update_ts = false;
         // let mut attr_type = None;
         let mut attrs = vec![];
+        let mut flatten = vec![];
 
         f.attrs.iter().for_each(|a| {
             let attr = a.path.segments.first().unwrap().ident.to_string();
@@ -869,6 +1051,7 @@ impl From<&Field> for Col {
                 AttrKey::PrimaryKey => primary = true,
                 AttrKey::Unique => unique = true,
                 AttrKey::Skip => skip = true,
+                AttrKey::OptionalCol => skip = true,
                 AttrKey::InsertTs => {
                     assert_eq!(t, RustType::DT3, "Rust type for DateTime should be chrono `NaiveDateTime` wrapped with DT3");
                     insert_ts = true
@@ -898,6 +1081,16 @@ impl From<&Field> for Col {
                     //     }
                     // }
                 }
+                AttrKey::Flatten => {
+                    // E.g. `#[flatten("country", "address.country")]`
+                    lazy_static! {
+                        static ref RE: Regex = Regex::new(r#""([\w.]+)""#).unwrap();
+                    }
+
+                    assert_eq!(t, RustType::Value, "`flatten` is only valid on JSON `Value` fields.");
+                    flatten = RE.captures_iter(&a.tokens.to_string()).map(|c| c[1].to_string()).collect();
+                }
+                AttrKey::Serde => {}
             }
         });
 
@@ -915,6 +1108,8 @@ impl From<&Field> for Col {
             insert_ts,
             update_ts,
             attrs,
+            flatten,
+            references: None,
         }
     }
 }
@@ -929,6 +1124,16 @@ pub enum AttrKey {
     InsertTs,
     UpdateTs,
     ColType,
+
+    // `#[optional_col]`: only some DBs have this column (E.g. set by a runtime option when the DB is created).
+    // - Not in the static schema or insert/update SQL, but read into the struct field when a `SELECT *` returns it.
+    OptionalCol,
+
+    // `#[flatten("key", "nested.key")]`: generated columns for JSON paths of this column.
+    Flatten,
+
+    // `#[serde(...)]`: used by the `Serialize`/`Deserialize` derives on the same struct.
+    Serde,
     // @todo/low `ColType(DataStruct)`. Is there a better way to convert the attribute AST into strongly typed structs?
 }
 
@@ -941,6 +1146,9 @@ impl From<String> for AttrKey {
             "col_type" => Self::ColType,
             "insert_ts" => Self::InsertTs,
             "update_ts" => Self::UpdateTs,
+            "optional_col" => Self::OptionalCol,
+            "flatten" => Self::Flatten,
+            "serde" => Self::Serde,
             x => panic!("AttrKey does not have variant for key {}", x)
         }
     }
@@ -17,14 +17,14 @@ use rusqlite::types::{FromSql, FromSqlResult, ToSqlOutput, Value, ValueRef};
 use serde::{Deserialize, Deserializer, Serialize};
 use twox_hash::XxHash;
 use crate::engines::placeholder::{PlaceholderString, PlaceholderFuncStd, PlaceholderFuncStdStatic};
-use crate::engines::mysql::{MySQLFuncX, MySQLFuncXStatic};
+use crate::engines::mysql::{MySQLFuncX, MySQLFuncXStatic, MySQLKV};
 use crate::engines::sqlite::{SQLiteFuncRusqlite, SQLiteFuncRusqliteStatic};
 use crate::table::{Table, ObjType, CreateSQLObj, StaticSQLStrings, ToSQLString};
 use crate::utx::UniTx;
 use crate::uc::{UniCon, Schema};
 use crate::uc::UniCon::Postgres;
 use crate::engines::postgres::{PostgresFuncX, PostgresFuncXStatic};
-use crate::params::{ToVecParamsMySQL, ToVecParamsSQLite, ToWhere, ToWhereIndexedSQLite, ToVecParamsPostgres, ToWhereIndexedPostgres};
+use crate::params::{ExtraVal, ToVecParamsMySQL, ToVecParamsSQLite, ToWhere, ToWhereIndexedSQLite, ToVecParamsPostgres, ToWhereIndexedPostgres};
 
 
 // type MySQLTransaction<'a> = mysql::conn::transaction::Transaction<'a>;
@@ -216,6 +216,49 @@ pub trait Insert: SQLiteFuncRusqlite + MySQLFuncX + PostgresFuncX + PlaceholderF
         last_id
     }
 
+    /// Inserts the row into `t`: a copy of this struct's table with extra columns added after its columns at runtime (E.g. from config).
+    /// - `extra`: the values of the extra columns, in column order. They are written in the same `INSERT`.
+    fn tx_insert_with(&self, utx: &mut UniTx, t: &Table, extra: &[ExtraVal]) -> i64 {
+        let cols = get_extra_cols(SQLiteFuncRusqlite::to_kv_writable_only(self).len(), t, extra);
+        let all = t.static_sql_strings.as_ref().unwrap();
+
+        match utx {
+            UniTx::Rusqlite(tx) => {
+                {
+                    let keys: Vec<String> = cols.iter().map(|x| format!(":{}", x)).collect();
+                    let mut params: Vec<(&str, &dyn ToSql)> = SQLiteFuncRusqlite::to_kv_writable_only(self).into_iter().map(|x| (x.key_param, x.val)).collect();
+                    params.extend(keys.iter().zip(extra).map(|(k, v)| (k.as_str(), v.to_sqlite())));
+
+                    let mut stmt = tx.prepare_cached(&all.sqlite.insert).unwrap();
+                    stmt.execute_named(&params).unwrap();
+                }
+                utx.get_last_id()
+            }
+            UniTx::MySQL(tx) => {
+                let mut kv = MySQLFuncX::to_kv_writable_only(self);
+                kv.extend(cols.into_iter().zip(extra).map(|(key, v)| MySQLKV { key, val: v.to_mysql() }));
+                tx.exec_drop(&all.mysql.insert, Params::Named(MySQLKV::into_hm(kv))).unwrap();
+                utx.get_last_id()
+            }
+            UniTx::Postgres(tx) => {
+                // Positional: the extra columns are last in the insert.
+                let mut params: Vec<&(dyn postgres::types::ToSql + Sync)> = PostgresFuncX::to_kv_writable_only(self).into_iter().map(|x| x.1).collect();
+                params.extend(extra.iter().map(|x| x.to_postgres()));
+                tx.query(all.postgres.insert.as_str(), &params[..]).unwrap().first().unwrap().get(0)
+            }
+            UniTx::PlaceholderLibA(x) => {
+                dbg!(self.get_vals());
+                unimplemented!();
+            }
+        }
+    }
+
+    fn tx_insert_set_pk_with(&mut self, utx: &mut UniTx, t: &Table, extra: &[ExtraVal]) -> i64 {
+        let last_id = self.tx_insert_with(utx, t, extra);
+        self.set_pk(last_id);
+        last_id
+    }
+
 
     // @todo/maybe fn `insert_then_select_insert_ts` - When inserting the insert ts is set by the SQL server, but the Rust struct field is always `None`.
 
@@ -291,6 +334,51 @@ pub trait Insert: SQLiteFuncRusqlite + MySQLFuncX + PostgresFuncX + PlaceholderF
         }
     }
 
+    /// `tx_update` for a row of `t`; the extra columns are set in the same `UPDATE` (see `tx_insert_with`).
+    fn tx_update_with(&self, utx: &mut UniTx, t: &Table, w: &'static str, extra: &[ExtraVal]) -> u64 {
+        let cols = get_extra_cols(SQLiteFuncRusqlite::to_kv_writable_only(self).len(), t, extra);
+
+        match utx {
+            UniTx::Rusqlite(tx) => {
+                let (update, params) = SQLiteFuncRusqlite::get_update_and_params(self, w);
+                let keys: Vec<String> = cols.iter().map(|x| format!(":{}", x)).collect();
+                let mut params: Vec<(&str, &dyn ToSql)> = params;
+                params.extend(keys.iter().zip(extra).map(|(k, v)| (k.as_str(), v.to_sqlite())));
+
+                let set: Vec<String> = cols.iter().map(|x| format!("{}=:{}", x, x)).collect();
+                let mut stmt = tx.prepare_cached(&add_to_set(&update, &set)).unwrap();
+                stmt.execute_named(&params).unwrap() as u64
+            }
+            UniTx::MySQL(tx) => {
+                let (update, params) = MySQLFuncX::get_update_and_params(self, w);
+                let mut hm = match params {
+                    Params::Named(x) => x,
+                    _ => unreachable!()
+                };
+
+                let set: Vec<String> = cols.iter().map(|x| format!("{}=:{}", x, x)).collect();
+                for (key, v) in cols.into_iter().zip(extra) {
+                    hm.insert(key, v.to_mysql());
+                }
+
+                tx.exec_drop(add_to_set(&update, &set), Params::Named(hm)).unwrap();
+                tx.affected_rows()
+            }
+            UniTx::Postgres(tx) => {
+                // The where param is last in `update`; the extra params are numbered after it.
+                let (update, mut params) = PostgresFuncX::get_update_and_params_vec(self, w);
+                let set: Vec<String> = cols.iter().enumerate().map(|(i, x)| format!("{} = ${}", x, params.len() + i + 1)).collect();
+                params.extend(extra.iter().map(|x| x.to_postgres()));
+
+                tx.execute(add_to_set(&update, &set).as_str(), &params[..]).unwrap()
+            }
+            UniTx::PlaceholderLibA(x) => {
+                dbg!(self.get_vals());
+                0
+            }
+        }
+    }
+
     fn tx_update_pk(&self, utx: &mut UniTx) -> u64 {
         self.tx_update(utx, self.get_key_pk())
     }
@@ -508,7 +596,12 @@ pub trait QueryByStatic: SQLiteFuncRusqliteStatic + MySQLFuncXStatic + PostgresF
     // {}
 
 
-    // @todo/low Impl `utx.get_vec_from_sql` like above for transactions.
+    fn tx_get_all(utx: &mut UniTx) -> Vec<Self> where Self: Sized {
+        let (table, pk_col) = (Self::get_table_name_static(), Self::get_table().get_primary_key_col_name());
+        let std_sql = format!("SELECT * FROM {} ORDER BY {} ASC", table, pk_col);
+        utx.get_vec_from_sql(std_sql.as_str())
+    }
+
     // @todo/low Search for all `match utx`, `match uc` in this code and in the Stripe code, and extract general patterns (there is probably some amount of duplication that can be standardised into a re-usable trait interface).
     fn tx_get_last(utx: &mut UniTx, w: &'static str) -> Option<Self> where Self: Sized {
         // @todo/low Check `w` is a field of the struct at compile time.
@@ -564,6 +657,24 @@ pub trait OracleString {
 // - Keep functions calls static/compile time by avoiding dyn.
 // - Enable both generic/dyn APIs so each one can be used when needed.
 //      - Prefer generic when possible (the types are concrete).
+// The names of the columns `t` has after the first `n` writable columns (the struct's).
+fn get_extra_cols(n: usize, t: &Table, extra: &[ExtraVal]) -> Vec<String> {
+    let cols: Vec<String> = t.cols_writable_only().into_iter().skip(n).map(|x| x.name).collect();
+    assert_eq!(cols.len(), extra.len(), "Table {} has {} columns after the struct's columns, but {} values were given.", &t.name, cols.len(), extra.len());
+    cols
+}
+
+// `UPDATE t SET a=:a WHERE id=:id` -> `UPDATE t SET a=:a, b=:b WHERE id=:id`
+fn add_to_set(update: &str, set: &[String]) -> String {
+    if set.is_empty() {
+        return update.to_string();
+    }
+
+    let i = update.rfind(" WHERE ").unwrap();
+    format!("{}, {}{}", &update[..i], set.join(", "), &update[i..])
+}
+
+
 pub trait InserterConcrete {
     // @todo/low When reading/writing JSON serde_json::Value, ensure that only the Value is only an object or array (not scalar).
     fn insert<T: SQLiteFuncRusqlite + PlaceholderFuncStd>(&self, r: &T) -> bool;
@@ -31,6 +31,7 @@ use std::time::Duration;
 use postgres::{Client, NoTls};
 use core::fmt;
 use crate::engines::postgres::PostgresFuncXStatic;
+use crate::error::UniConError;
 
 
 use postgres::config::SslMode;
@@ -159,15 +160,18 @@ pub struct MySQLCreate {
 
 impl MySQLCreate {
     // Basic logic for testing if this is a valid config (beyond just types).
-    pub fn assert_is_valid(&self) {
+    pub fn is_valid(&self) -> std::result::Result<(), UniConError> {
         match &self.tls {
             None => {}
             Some(x) => {
                 if let Some(f) = &x.ca_cert_file {
-                    assert!(Path::new(f.as_str()).exists(), "`ca_cert_file` - file does not exist.");
+                    if !Path::new(f.as_str()).exists() {
+                        return Err(UniConError::Config(format!("`ca_cert_file` - file does not exist: {}", f)));
+                    }
                 }
             }
         }
+        Ok(())
     }
 }
 
@@ -282,7 +286,7 @@ impl fmt::Debug for ConMetaPostgres {
 
 
 impl UniCon {
-    pub fn new(ucc: &UniConCreate) -> Self {
+    pub fn new(ucc: &UniConCreate) -> std::result::Result<Self, UniConError> {
 
         // error!("error Creating UniCon");
         // warn!("warn Creating UniCon");
@@ -292,7 +296,7 @@ impl UniCon {
 
         match &ucc.engine {
             Engine::SQLite(create) => {
-                let c = Connection::open(&create.file).unwrap();
+                let c = Connection::open(&create.file)?;
 
                 c.busy_timeout(Duration::from_millis(30 * 1000));
 
@@ -317,15 +321,19 @@ impl UniCon {
                 //     true
                 // })).unwrap();
 
-                UniCon::Rusqlite(ConMetaSQLite {
+                Ok(UniCon::Rusqlite(ConMetaSQLite {
                     create: (*create).clone(),
                     c,
-                })
+                }))
             }
             Engine::MySQL(create) => {
-                create.assert_is_valid();
-                assert!(create.db_name.is_none(), "MySQL: use `schema_name` instead of `db_name`. These have identical meanings in MySQL, but Postgres and other SQL engines use a database/schema/table nesting.");
-                assert!(create.schema_name.is_some(), "MySQL: `schema_name` must be set.");
+                create.is_valid()?;
+                if create.db_name.is_some() {
+                    return Err(UniConError::Config("MySQL: use `schema_name` instead of `db_name`. These have identical meanings in MySQL, but Postgres and other SQL engines use a database/schema/table nesting.".into()));
+                }
+                if create.schema_name.is_none() {
+                    return Err(UniConError::Config("MySQL: `schema_name` must be set.".into()));
+                }
 
                 let get_ops_builder = || {
                     let mut x = OptsBuilder::new();
@@ -403,31 +411,37 @@ impl UniCon {
                     x
                 };
 
-                // Note: `Conn::new` panics when db_name is set but does not exist.
+                // Note: `Conn::new` errors when db_name is set but does not exist.
                 let mut x = get_ops_builder();
-                let mut c = Conn::new(x).unwrap();
+                let mut c = Conn::new(x)?;
 
                 // When strings > 255 are inserted into `varchar(255)`, throw error.
                 // - See `ToSQLString<MySQL> for Table` comment.
                 // @see https://dev.mysql.com/doc/refman/5.7/en/sql-mode.html#sqlmode_strict_all_tables
-                c.query_drop("SET SESSION sql_mode = 'STRICT_ALL_TABLES'").unwrap();
-                c.query_drop("SET SESSION default_storage_engine = INNODB;").unwrap();
-                c.query_drop("SET SESSION innodb_strict_mode = 'ON'").unwrap();
+                c.query_drop("SET SESSION sql_mode = 'STRICT_ALL_TABLES'")?;
+                c.query_drop("SET SESSION default_storage_engine = INNODB;")?;
+                c.query_drop("SET SESSION innodb_strict_mode = 'ON'")?;
 
-                UniCon::MySQL(ConMetaMySQL {
+                Ok(UniCon::MySQL(ConMetaMySQL {
                     create: (*create).clone(),
                     c,
-                })
+                }))
             }
             Engine::Postgres(create) => {
-                create.assert_is_valid();
-                assert!(create.schema_name.is_some(), "Postgres: `schema_name` must be set.");
+                create.is_valid()?;
+                if create.schema_name.is_none() {
+                    return Err(UniConError::Config("Postgres: `schema_name` must be set.".into()));
+                }
 
                 let c2 = create.clone();
                 let mut config = Client::configure();
 
-                config.user(c2.user.unwrap().as_str());
-                config.password(c2.pass.unwrap().as_str());
+                let (user, pass) = match (c2.user, c2.pass) {
+                    (Some(user), Some(pass)) => (user, pass),
+                    _ => return Err(UniConError::Config("Postgres: `user` and `pass` must be set.".into()))
+                };
+                config.user(user.as_str());
+                config.password(pass.as_str());
 
                 match c2.addr {
                     Addr::IP(x) => {
@@ -436,7 +450,9 @@ impl UniCon {
                     }
                     Addr::Socket(x) => {
                         // Issue: Type error when compiling for `x86_64-pc-windows-gnu` (method is only included for unix).
-                        assert!(cfg!(unix), "Postgres socket is only supported on Unix. Use IP and port instead. Incorrect socket usage: {}", x.socket.as_str());
+                        if !cfg!(unix) {
+                            return Err(UniConError::Config(format!("Postgres socket is only supported on Unix. Use IP and port instead. Incorrect socket usage: {}", x.socket.as_str())));
+                        }
 
                         #[cfg(unix)]
                             {
@@ -454,13 +470,14 @@ impl UniCon {
                 }
 
 
-                let mut get_con = || {
+                let mut get_con = || -> std::result::Result<Client, UniConError> {
                     if let Some(tls) = &create.tls {
+                        let tls_err = |e: openssl::error::ErrorStack| UniConError::Config(format!("Postgres TLS: {}", e));
                         // Using openssl instead of `native-tls` as macOS is much stricter in rejecting ca certs.
-                        let mut builder = SslConnector::builder(SslMethod::tls_client()).unwrap();
+                        let mut builder = SslConnector::builder(SslMethod::tls_client()).map_err(tls_err)?;
 
                         if let Some(file) = &tls.ca_cert_file {
-                            builder.set_ca_file(file.as_str()).unwrap();
+                            builder.set_ca_file(file.as_str()).map_err(tls_err)?;
                         } else {
                             // Use the systems keychain (official CA's like Lets Encrypt).
                         }
@@ -494,10 +511,10 @@ impl UniCon {
                         }
 
                         config.ssl_mode(SslMode::Require);
-                        return config.connect(connector_openssl).unwrap();
+                        return Ok(config.connect(connector_openssl)?);
                     }
 
-                    config.connect(NoTls).unwrap()
+                    Ok(config.connect(NoTls)?)
                 };
 
 
@@ -506,10 +523,10 @@ impl UniCon {
                 //      - Same as SQLite.
                 //      - Differs to MySQL by default.
 
-                UniCon::Postgres(ConMetaPostgres {
+                Ok(UniCon::Postgres(ConMetaPostgres {
                     create: create.clone(),
-                    c: get_con(),
-                })
+                    c: get_con()?,
+                }))
             }
         }
     }
@@ -520,29 +537,86 @@ impl UniCon {
     /// @todo/low Some type of migration system to check if the tables already exist when moving the next version of an app.
     /// - Fix, temp: Each app should implement its own system (e.g. for user-read-only tables, store drop statements in the database at create time, the next version of the binary will read, apply drop statements and then re-create all tables without having to know which tables existed in the previous version).
     pub fn ensure_schema_and_tables_exist_and_writable<T>(&mut self) -> (bool, Schema) where T: DbStatic {
-        let mut created_all = false;
+        self.ensure_schema_and_tables_exist_and_writable_excluding::<T>(&vec![]).unwrap()
+    }
+
+    /// Same as `ensure_schema_and_tables_exist_and_writable`, but `exclude_tables` are not part of the target schema (they are not created or required to exist).
+    /// - Allows an app to only use a subset of the tables in `T`.
+    /// - Err when only some of the tables exist (the existing schema conflicts with the target schema); nothing is written.
+    pub fn ensure_schema_and_tables_exist_and_writable_excluding<T>(&mut self, exclude_tables: &Vec<String>) -> Result<(bool, Schema), String> where T: DbStatic {
+        self.ensure_schema_and_tables_exist_and_writable_with::<T>(exclude_tables, &vec![])
+    }
 
+    // The schema and tables this program expects to write to.
+    pub fn get_target_schema_with<T>(&self, exclude_tables: &Vec<String>, extra_tables: &Vec<Table>) -> Schema where T: DbStatic {
         let schema_opt = self.get_user_defined_schema();
 
-        // Get the target schema (schema and tables this program expects to write to).
-        let target_schema = {
-            let mut x = match self {
-                UniCon::Rusqlite(_) => T::get_target_schema_sqlite(),
-                UniCon::MySQL(_) => T::get_target_schema_mysql(),
-                UniCon::Postgres(_) => T::get_target_schema_postgres(),
+        let mut x = match self {
+            UniCon::Rusqlite(_) => T::get_target_schema_sqlite(),
+            UniCon::MySQL(_) => T::get_target_schema_mysql(),
+            UniCon::Postgres(_) => T::get_target_schema_postgres(),
+            UniCon::PlaceholderLibA(_) => unreachable!()
+        };
+
+        x.tables.retain(|t| !exclude_tables.contains(&t.name));
+
+        for t in extra_tables {
+            let all = t.static_sql_strings.as_ref().unwrap();
+            x.tables.push(match self {
+                UniCon::Rusqlite(_) => all.sqlite.clone().into(),
+                UniCon::MySQL(_) => all.mysql.clone().into(),
+                UniCon::Postgres(_) => all.postgres.clone().into(),
                 UniCon::PlaceholderLibA(_) => unreachable!()
-            };
+            });
+        }
 
-            // Add user defined schema name from config.
-            if let Some(x2) = &schema_opt {
-                x.schema = Some(NameCreate {
-                    name: x2.name.clone(),
-                    create: x2.create.clone(),
-                })
-            }
+        // Add user defined schema name from config.
+        if let Some(x2) = &schema_opt {
+            x.schema = Some(NameCreate {
+                name: x2.name.clone(),
+                create: x2.create.clone(),
+            })
+        }
 
-            x
-        };
+        x
+    }
+
+    // Ok(true) when all of the tables exist, Ok(false) when none exist; nothing is created (E.g. for a dry run).
+    pub fn schema_exists_with<T>(&mut self, exclude_tables: &Vec<String>, extra_tables: &Vec<Table>) -> std::result::Result<bool, String> where T: DbStatic {
+        let schema_opt = self.get_user_defined_schema();
+        let target_schema = self.get_target_schema_with::<T>(exclude_tables, extra_tables);
+
+        let mut utx = self.tx_open().unwrap();
+        let existing_schema = utx.get_existing_schema(schema_opt.as_ref().and_then(|x| x.name.as_str().into()))?;
+        utx.tx_rollback().unwrap();
+
+        let (all_exist, none_exist, diff) = Schema::diff(&target_schema, &existing_schema);
+        if !(all_exist || none_exist) {
+            return Err(format!("Some tables do not exist. These tables are required: {:?}", &diff));
+        }
+
+        Ok(all_exist)
+    }
+
+    // Column level differences between the tables of `T` (and `extra_tables`) and the existing tables with the same names.
+    pub fn get_schema_diff_with<T>(&mut self, exclude_tables: &Vec<String>, extra_tables: &Vec<Table>) -> std::result::Result<SchemaDiff, String> where T: DbStatic {
+        let schema_opt = self.get_user_defined_schema();
+        let target_schema = self.get_target_schema_with::<T>(exclude_tables, extra_tables);
+
+        let mut utx = self.tx_open().unwrap();
+        let existing_schema = utx.get_existing_schema(schema_opt.as_ref().and_then(|x| x.name.as_str().into()))?;
+        utx.tx_rollback().unwrap();
+
+        Ok(Schema::diff_cols(&target_schema, &existing_schema))
+    }
+
+    /// Same as `ensure_schema_and_tables_exist_and_writable_excluding`, but `extra_tables` are added to the target schema (created and required to exist along with the tables of `T`).
+    /// - For tables that are built at runtime (not derived from a struct). Each table must have `static_sql_strings` set.
+    pub fn ensure_schema_and_tables_exist_and_writable_with<T>(&mut self, exclude_tables: &Vec<String>, extra_tables: &Vec<Table>) -> Result<(bool, Schema), String> where T: DbStatic {
+        let mut created_all = false;
+
+        let schema_opt = self.get_user_defined_schema();
+        let target_schema = self.get_target_schema_with::<T>(exclude_tables, extra_tables);
 
         // Before: Set connection-level options before creating the schema.
         {
@@ -570,44 +644,17 @@ impl UniCon {
         {
             let mut utx = self.tx_open().unwrap();
 
-            let existing_schema = utx.get_existing_schema(schema_opt.as_ref().and_then(|x| x.name.as_str().into()));
+            let existing_schema = utx.get_existing_schema(schema_opt.as_ref().and_then(|x| x.name.as_str().into()))?;
             let (all_exist, none_exist, diff) = Schema::diff(&target_schema, &existing_schema);
 
             // When: User is trying to write to an existing schema with table conflicts. Different db/cli versions.
-            assert!(all_exist || none_exist, "Some tables do not exist. These tables are required: {:?}", &diff);
+            if !(all_exist || none_exist) {
+                utx.tx_rollback().unwrap();
+                return Err(format!("Some tables do not exist. These tables are required: {:?}", &diff));
+            }
 
             if none_exist {
-                let tbls_indexes = target_schema.get_create_tables_and_indexes();
-
-                match utx {
-                    UniTx::Rusqlite(ref tx) => {
-                        assert_eq!(target_schema.schema, None);
-
-                        for x in tbls_indexes {
-                            let changes = tx.execute(&x, NO_PARAMS).unwrap();
-                        }
-                    }
-                    UniTx::MySQL(_) | UniTx::Postgres(_) => {
-                        let ts = target_schema.schema.as_ref().unwrap();
-
-                        // When: (schema exists && tables do not) (E.g. empty schema pre-created, or schema with another non-td processes tables in it).
-                        if existing_schema.schema.is_none() {
-                            utx.exec_one(&ts.create);
-                        }
-
-                        // Note:
-                        // - MySQL: This sets the active schema at the connection level (no tx isolation).
-                        // - Postgres: If commit, persists on connection, if rollback does not persist.
-                        utx.set_active_schema(&ts.name);
-
-                        for x in tbls_indexes {
-                            utx.exec_one(&x);
-                        }
-                    }
-                    UniTx::PlaceholderLibA(_) => {}
-                }
-
-
+                utx.create_schema_and_tables(&target_schema, existing_schema.schema.is_some());
                 created_all = true;
             }
 
@@ -640,7 +687,7 @@ impl UniCon {
         }
 
 
-        (created_all, target_schema)
+        Ok((created_all, target_schema))
     }
 
     pub fn get_last_id(&self) -> i64 {
@@ -660,6 +707,22 @@ impl UniCon {
         }
     }
 
+    // Round trip to the DB server (E.g. for a health check); Err when the connection is no longer usable.
+    pub fn ping(&mut self) -> std::result::Result<(), String> {
+        match self {
+            UniCon::Rusqlite(x) => {
+                x.c.query_row("SELECT 1", [], |_| Ok(())).map_err(|e| e.to_string())
+            }
+            UniCon::MySQL(x) => {
+                x.c.query_drop("SELECT 1").map_err(|e| e.to_string())
+            }
+            UniCon::Postgres(x) => {
+                x.c.simple_query("SELECT 1").map(|_| ()).map_err(|e| e.to_string())
+            }
+            UniCon::PlaceholderLibA(_) => Ok(())
+        }
+    }
+
     /// @todo/med DB may be locked; impl retry.
     /// @todo/high Merge errors from each lib into single normalised error (same for tx close too).
     pub fn tx_open(&mut self) -> Result<UniTx> {
@@ -783,6 +846,82 @@ pub struct TableCreate {
     pub name: String,
     pub create: String,
     pub indexes: Vec<NameCreate>,
+
+    // Run after all tables are created (E.g. Postgres foreign keys).
+    pub constraints: Vec<NameCreate>,
+
+    pub cols: Vec<ColCreate>,
+}
+
+// `sql_type` uses the type names of the create statements (E.g. Postgres `character varying(255)` is read as `VARCHAR(255)`).
+#[derive(Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct ColCreate {
+    pub name: String,
+    pub sql_type: String,
+    pub nullable: bool,
+
+    // E.g. flattened JSON columns.
+    pub generated: bool,
+}
+
+
+// Differences between the columns and indexes of tables that exist in both the target and existing schema.
+// - Only tables with differences are included.
+#[derive(Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct SchemaDiff {
+    pub tables: Vec<TableDiff>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct TableDiff {
+    pub name: String,
+
+    // In the target, not in the existing table.
+    pub missing_cols: Vec<ColCreate>,
+
+    // In the existing table, not in the target (E.g. added by a user).
+    pub extra_cols: Vec<ColCreate>,
+
+    // (target, existing) with a different type, nullability or generated expression.
+    pub changed_cols: Vec<(ColCreate, ColCreate)>,
+
+    // Index names; unique and primary key indexes are not compared.
+    pub missing_indexes: Vec<String>,
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+}
+
+impl TableDiff {
+    fn is_empty(&self) -> bool {
+        self.missing_cols.is_empty() && self.extra_cols.is_empty() && self.changed_cols.is_empty() && self.missing_indexes.is_empty()
+    }
+
+    // One line per difference, for logs.
+    pub fn get_msgs(&self) -> Vec<String> {
+        let fmt = |c: &ColCreate| format!("{} {}{}{}", &c.name, &c.sql_type, if c.nullable { "" } else { " NOT NULL" }, if c.generated { " GENERATED" } else { "" });
+
+        let mut v = vec![];
+        for c in &self.missing_cols {
+            v.push(format!("{}: missing column `{}`.", &self.name, fmt(c)));
+        }
+        for c in &self.extra_cols {
+            v.push(format!("{}: extra column `{}`.", &self.name, fmt(c)));
+        }
+        for (a, b) in &self.changed_cols {
+            v.push(format!("{}: column `{}` is `{}`.", &self.name, fmt(a), fmt(b)));
+        }
+        for x in &self.missing_indexes {
+            v.push(format!("{}: missing index `{}`.", &self.name, x));
+        }
+        v
+    }
 }
 
 
@@ -814,6 +953,38 @@ impl Schema {
     }
 
 
+    // Column level comparison of the tables in both schemas (`diff` compares table names).
+    // - `existing` must be read with `UniTx::get_existing_schema`.
+    pub fn diff_cols(target: &Schema, existing: &Schema) -> SchemaDiff {
+        let mut tables = vec![];
+
+        for t in &target.tables {
+            let e = match existing.tables.iter().find(|x| x.name == t.name) {
+                Some(x) => x,
+                None => continue
+            };
+
+            let find = |cols: &Vec<ColCreate>, name: &str| cols.iter().find(|c| c.name == name).cloned();
+
+            let d = TableDiff {
+                name: t.name.clone(),
+                missing_cols: t.cols.iter().filter(|c| find(&e.cols, &c.name).is_none()).cloned().collect(),
+                extra_cols: e.cols.iter().filter(|c| find(&t.cols, &c.name).is_none()).cloned().collect(),
+                changed_cols: t.cols.iter().filter_map(|c| find(&e.cols, &c.name).filter(|x| x != c).map(|x| (c.clone(), x))).collect(),
+                missing_indexes: t.indexes.iter().filter(|x| !e.indexes.iter().any(|x2| x2.name == x.name)).map(|x| x.name.clone()).collect(),
+            };
+
+            if !d.is_empty() {
+                tables.push(d);
+            }
+        }
+
+        SchemaDiff {
+            tables
+        }
+    }
+
+    // Constraints are last as they reference other tables.
     pub fn get_create_tables_and_indexes(&self) -> Vec<String> {
         let mut all = vec![];
 
@@ -825,6 +996,8 @@ impl Schema {
             }
         }
 
+        let constraints = self.tables.iter().flat_map(|x| x.constraints.iter().map(|x2| x2.create.clone()));
+        all.extend(constraints);
         all
     }
 }
@@ -17,10 +17,13 @@ use twox_hash::XxHash;
 use crate::engines::placeholder::{PlaceholderString, PlaceholderFuncStd, PlaceholderFuncStdStatic};
 use crate::engines::mysql::{MySQLFuncX, MySQLFuncXStatic};
 use crate::engines::sqlite::{SQLiteFuncRusqlite, SQLiteFuncRusqliteStatic};
-use crate::table::{Table, ObjType, CreateSQLObj, StaticSQLStrings};
-use crate::uc::{TableCreate, Schema, NameCreate};
+use crate::engines::postgres::PostgresFuncXStatic;
+use crate::table::{Table, ObjType, CreateSQLObj, StaticSQLStrings, Col, RustType, ToSQLString, SQLite, MySQL, Postgres, View};
+use crate::error::UniConError;
+use crate::uc::{TableCreate, Schema, NameCreate, ColCreate};
 use std::hint::unreachable_unchecked;
 use log::debug;
+use lazy_static::lazy_static;
 
 pub enum UniTx<'a> {
     Rusqlite(Transaction<'a>),
@@ -31,19 +34,17 @@ pub enum UniTx<'a> {
 
 impl UniTx<'_> {
     /// Note: Rusqlite defaults to ROLLBACK on drop, ensure other libs have the same meaning.
-    pub fn tx_close(mut self) -> Result<()> {
+    // A commit can fail (E.g. deferred foreign keys are checked at commit), in which case the tx is rolled back.
+    pub fn tx_close(mut self) -> std::result::Result<(), UniConError> {
         match self {
             UniTx::Rusqlite(tx) => {
-                tx.commit();
-                Ok(())
+                tx.commit().map_err(UniConError::from_commit)
             }
             UniTx::MySQL(tx) => {
-                tx.commit();
-                Ok(())
+                tx.commit().map_err(UniConError::from_commit)
             }
             UniTx::Postgres(tx) => {
-                tx.commit();
-                Ok(())
+                tx.commit().map_err(UniConError::from_commit)
             }
             UniTx::PlaceholderLibA(_) => {
                 0;
@@ -74,6 +75,40 @@ impl UniTx<'_> {
     }
 
 
+    // Same as `UniCon::get_vec_from_sql`, inside the tx.
+    pub fn get_vec_from_sql<T>(&mut self, std_sql: &str) -> Vec<T>
+        where T: SQLiteFuncRusqliteStatic + MySQLFuncXStatic + PostgresFuncXStatic {
+        let mut v = vec![];
+
+        match self {
+            UniTx::Rusqlite(tx) => {
+                let mut stmt = tx.prepare_cached(std_sql).unwrap();
+                let mut rows = stmt.query(NO_PARAMS).unwrap();
+                while let Some(x2) = rows.next().unwrap() {
+                    v.push(<T as SQLiteFuncRusqliteStatic>::row_to_ins(&x2));
+                }
+            }
+            UniTx::MySQL(tx) => {
+                let res = tx.exec(std_sql, Params::Empty).unwrap();
+                for mut x2 in res {
+                    v.push(<T as MySQLFuncXStatic>::row_to_ins(&mut x2))
+                }
+            }
+            UniTx::Postgres(tx) => {
+                let res = tx.query(std_sql, &[]).unwrap();
+                for mut x2 in res {
+                    v.push(<T as PostgresFuncXStatic>::row_to_ins(&mut x2))
+                }
+            }
+            UniTx::PlaceholderLibA(_) => {
+                unreachable!()
+            }
+        }
+
+        v
+    }
+
+
     // Executes one SQL statement that does not need params (E.g. create table|index).
     // - fn return indicates no error;
     pub fn exec_one(&mut self, sql: &str) {
@@ -169,6 +204,40 @@ impl UniTx<'_> {
         }
     }
 
+    // Creates the schema (unless `schema_exists`), tables and indexes.
+    // - SQLite and Postgres DDL is transactional, so this can be previewed in a tx that is rolled back; MySQL commits each statement implicitly.
+    pub fn create_schema_and_tables(&mut self, target_schema: &Schema, schema_exists: bool) {
+        let tbls_indexes = target_schema.get_create_tables_and_indexes();
+
+        match self {
+            UniTx::Rusqlite(tx) => {
+                assert_eq!(target_schema.schema, None);
+
+                for x in tbls_indexes {
+                    tx.execute(&x, NO_PARAMS).unwrap();
+                }
+            }
+            UniTx::MySQL(_) | UniTx::Postgres(_) => {
+                let ts = target_schema.schema.as_ref().unwrap();
+
+                // When: (schema exists && tables do not) (E.g. empty schema pre-created, or schema with another non-td processes tables in it).
+                if !schema_exists {
+                    self.exec_one(&ts.create);
+                }
+
+                // Note:
+                // - MySQL: This sets the active schema at the connection level (no tx isolation).
+                // - Postgres: If commit, persists on connection, if rollback does not persist.
+                self.set_active_schema(&ts.name);
+
+                for x in tbls_indexes {
+                    self.exec_one(&x);
+                }
+            }
+            UniTx::PlaceholderLibA(_) => {}
+        }
+    }
+
     pub fn get_schemas(&mut self) -> Vec<String> {
         match self {
             UniTx::Rusqlite(tx) => {
@@ -228,27 +297,182 @@ impl UniTx<'_> {
     }
 
 
+    // Column names of a table in the active schema (empty when the table does not exist).
+    pub fn get_columns(&mut self, table: &str) -> Result<Vec<String>, String> {
+        let target_schema = self.get_active_schema();
+
+        match self {
+            UniTx::Rusqlite(tx) => {
+                let mut stmt = tx.prepare_cached("SELECT name FROM pragma_table_info(?)").map_err(|e| e.to_string())?;
+                let rows = stmt.query_map(params![table], |r| r.get(0)).map_err(|e| e.to_string())?;
+                rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+            }
+            UniTx::MySQL(tx) => {
+                let q = "SELECT column_name FROM information_schema.columns WHERE table_schema = DATABASE() AND table_name = ? ORDER BY ordinal_position";
+                tx.exec(q, (table,)).map_err(|e| e.to_string())
+            }
+            UniTx::Postgres(tx) => {
+                let schema = target_schema.ok_or("Postgres should have an active schema readable from search_path prefix.")?;
+                let q = "SELECT column_name::text FROM information_schema.columns WHERE table_schema = $1 AND table_name = $2 ORDER BY ordinal_position";
+                let rows = tx.query(q, &[&schema, &table]).map_err(|e| e.to_string())?;
+                Ok(rows.iter().map(|x| x.get(0)).collect::<Vec<String>>())
+            }
+            UniTx::PlaceholderLibA(_) => Err("Reading columns is not implemented for PlaceholderLibA.".into())
+        }
+    }
+
+
+    // Columns of a table in the active schema, with the type names used in create statements (see `ColCreate`).
+    pub fn get_col_creates(&mut self, table: &str) -> Result<Vec<ColCreate>, String> {
+        let target_schema = self.get_active_schema();
+
+        match self {
+            UniTx::Rusqlite(tx) => {
+                // `table_xinfo` includes generated columns (`hidden` = 2 virtual, 3 stored), with the type `TEXT GENERATED ALWAYS`.
+                let mut stmt = tx.prepare_cached("SELECT name, type, \"notnull\", hidden FROM pragma_table_xinfo(?)").map_err(|e| e.to_string())?;
+                let rows = stmt.query_map(params![table], |r| {
+                    let hidden: i64 = r.get(3)?;
+                    Ok(ColCreate {
+                        name: r.get(0)?,
+                        sql_type: r.get::<_, String>(1)?.to_uppercase().trim_end_matches(" GENERATED ALWAYS").to_string(),
+                        nullable: r.get::<_, i64>(2)? == 0,
+                        generated: hidden == 2 || hidden == 3,
+                    })
+                }).map_err(|e| e.to_string())?;
+
+                rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+            }
+            UniTx::MySQL(tx) => {
+                let q = "SELECT column_name, column_type, is_nullable, extra FROM information_schema.columns WHERE table_schema = DATABASE() AND table_name = ? ORDER BY ordinal_position";
+                let rows: Vec<(String, String, String, String)> = tx.exec(q, (table,)).map_err(|e| e.to_string())?;
+                Ok(rows.into_iter().map(|(name, t, nullable, extra)| ColCreate {
+                    name,
+                    sql_type: get_mysql_type_name(&t),
+                    nullable: nullable == "YES",
+                    generated: extra.to_uppercase().contains("GENERATED"),
+                }).collect())
+            }
+            UniTx::Postgres(tx) => {
+                let schema = target_schema.ok_or("Postgres should have an active schema readable from search_path prefix.")?;
+
+                // `attgenerated` is only in Postgres 12+ (read via JSON so the query works on older versions).
+                let q = "SELECT a.attname::text, format_type(a.atttypid, a.atttypmod), a.attnotnull, coalesce(to_jsonb(a)->>'attgenerated', '') <> ''
+                    FROM pg_catalog.pg_attribute a
+                    JOIN pg_catalog.pg_class c ON c.oid = a.attrelid
+                    JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+                    WHERE n.nspname = $1 AND c.relname = $2 AND a.attnum > 0 AND NOT a.attisdropped
+                    ORDER BY a.attnum";
+                let rows = tx.query(q, &[&schema, &table]).map_err(|e| e.to_string())?;
+                Ok(rows.iter().map(|x| ColCreate {
+                    name: x.get(0),
+                    sql_type: get_postgres_type_name(x.get(1)),
+                    nullable: !x.get::<_, bool>(2),
+                    generated: x.get(3),
+                }).collect())
+            }
+            UniTx::PlaceholderLibA(_) => Err("Reading columns is not implemented for PlaceholderLibA.".into())
+        }
+    }
+
+    // Index names of a table in the active schema (including the indexes of unique and primary key columns on MySQL and Postgres).
+    pub fn get_index_names(&mut self, table: &str) -> Result<Vec<String>, String> {
+        let target_schema = self.get_active_schema();
+
+        match self {
+            UniTx::Rusqlite(tx) => {
+                // `sql` is null for the automatic indexes of unique columns.
+                let mut stmt = tx.prepare_cached("SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = ? AND sql IS NOT NULL").map_err(|e| e.to_string())?;
+                let rows = stmt.query_map(params![table], |r| r.get(0)).map_err(|e| e.to_string())?;
+                rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+            }
+            UniTx::MySQL(tx) => {
+                let q = "SELECT DISTINCT index_name FROM information_schema.statistics WHERE table_schema = DATABASE() AND table_name = ?";
+                tx.exec(q, (table,)).map_err(|e| e.to_string())
+            }
+            UniTx::Postgres(tx) => {
+                let schema = target_schema.ok_or("Postgres should have an active schema readable from search_path prefix.")?;
+                let q = "SELECT indexname::text FROM pg_catalog.pg_indexes WHERE schemaname = $1 AND tablename = $2";
+                let rows = tx.query(q, &[&schema, &table]).map_err(|e| e.to_string())?;
+                Ok(rows.iter().map(|x| x.get(0)).collect::<Vec<String>>())
+            }
+            UniTx::PlaceholderLibA(_) => Err("Reading indexes is not implemented for PlaceholderLibA.".into())
+        }
+    }
+
+
+    // Adds a column to an existing table (E.g. a schema migration).
+    // - `default` is an SQL literal for the existing rows; `NOT NULL` columns need one.
+    // - Unique and primary key columns cannot be added by SQLite; add a unique index instead.
+    pub fn add_col(&mut self, table: &str, c: &Col, default: Option<&str>) {
+        assert!(!c.primary && !c.unique, "Cannot add unique column {}.{}; add the column and a unique index instead.", table, &c.name);
+        assert!(c.nullable || default.is_some(), "Cannot add NOT NULL column {}.{} without a default for the existing rows.", table, &c.name);
+
+        let t = match self {
+            UniTx::Rusqlite(_) => c.to_sqlite_type(),
+            UniTx::MySQL(_) => c.to_mysql_type(),
+            UniTx::Postgres(_) => c.to_postgres_type(),
+            UniTx::PlaceholderLibA(_) => unreachable!()
+        };
+
+        let mut v = vec![format!("ALTER TABLE {} ADD COLUMN {} {}", table, &c.name, t)];
+        if !c.nullable { v.push("NOT NULL".to_string()) }
+        if let Some(x) = default { v.push(format!("DEFAULT {}", x)) }
+
+        self.exec_one(&v.join(" "));
+    }
+
+    // Adds the generated columns for `c.flatten` that the table does not have (E.g. a DB created before they existed).
+    // - Postgres rewrites the table to compute `STORED` columns for the existing rows.
+    pub fn add_flat_cols(&mut self, table: &str, c: &Col) -> Result<(), String> {
+        let existing: Vec<String> = self.get_col_creates(table)?.into_iter().map(|x| x.name).collect();
+        let creates = match self {
+            UniTx::Rusqlite(_) => c.get_flat_cols_sqlite(),
+            UniTx::MySQL(_) => c.get_flat_cols_mysql(),
+            UniTx::Postgres(_) => c.get_flat_cols_postgres(),
+            UniTx::PlaceholderLibA(_) => unreachable!()
+        };
+
+        for ((name, _), create) in c.get_flat_cols().into_iter().zip(creates) {
+            if !existing.contains(&name) {
+                self.exec_one(&format!("ALTER TABLE {} ADD COLUMN {}", table, create));
+            }
+        }
+
+        Ok(())
+    }
+
+    // SQLite (>= 3.35) cannot drop a column that is used in an index; drop the index first.
+    pub fn drop_col(&mut self, table: &str, col: &str) {
+        self.exec_one(&format!("ALTER TABLE {} DROP COLUMN {}", table, col));
+    }
+
+
     // Reads (schema, tables and indexes) from a given schema.
     // - Used when comparing a target schema with a possibly existing one.
-    pub fn get_existing_schema(&mut self, target_schema_opt: Option<&str>) -> Schema {
-        let get_tables = |utx: &mut UniTx| -> Vec<TableCreate> {
-            utx.get_tables().iter().map(|x| TableCreate {
+    pub fn get_existing_schema(&mut self, target_schema_opt: Option<&str>) -> Result<Schema, String> {
+        let get_tables = |utx: &mut UniTx| -> Result<Vec<TableCreate>, String> {
+            utx.get_tables().iter().map(|x| Ok(TableCreate {
                 name: x.clone(),
 
-                // @todo/low if needed in the future (only doing basic name-name compare atm, not structure comparison).
+                // Compared by name and columns (`Schema::diff_cols`), not by create statement.
                 create: "".to_string(),
-                indexes: vec![],
-            }).collect()
+                indexes: utx.get_index_names(x)?.into_iter().map(|name| NameCreate {
+                    name,
+                    create: "".to_string(),
+                }).collect(),
+                constraints: vec![],
+                cols: utx.get_col_creates(x)?,
+            })).collect()
         };
 
         match self {
             UniTx::Rusqlite(tx) => {
                 assert_eq!(target_schema_opt, None);
 
-                return Schema {
+                return Ok(Schema {
                     schema: None,
-                    tables: get_tables(self),
-                };
+                    tables: get_tables(self)?,
+                });
             }
             UniTx::MySQL(_) | UniTx::Postgres(_) => {
                 let target_schema = target_schema_opt.unwrap().to_string();
@@ -260,23 +484,17 @@ impl UniTx<'_> {
 
                 // At this point the schema may not have been created.
                 if !schema_exists {
-                    return Schema {
+                    return Ok(Schema {
                         schema: None,
                         tables: vec![],
-                    };
+                    });
                 }
 
                 if orig_active != Some(target_schema.clone()) {
                     self.set_active_schema(&target_schema);
                 }
 
-                let s = Schema {
-                    schema: Some(NameCreate {
-                        name: target_schema.clone(),
-                        create: "".to_string(),
-                    }),
-                    tables: get_tables(self),
-                };
+                let tables = get_tables(self);
 
 
                 // Revert to previous `USE x`.
@@ -288,9 +506,359 @@ impl UniTx<'_> {
                     self.set_active_schema(&x);
                 }
 
-                s
+                Ok(Schema {
+                    schema: Some(NameCreate {
+                        name: target_schema,
+                        create: "".to_string(),
+                    }),
+                    tables: tables?,
+                })
             }
             UniTx::PlaceholderLibA(_) => unreachable!()
         }
     }
-}
\ No newline at end of file
+}
+
+
+// MySQL `COLUMN_TYPE` => type name in create statements.
+// - E.g. `bigint(20)` => `BIGINT` (5.7 includes a display width for integers; 8.0.19+ only for `tinyint(1)`).
+fn get_mysql_type_name(x: &str) -> String {
+    lazy_static! {
+        static ref INT_WIDTH: Regex = Regex::new(r"^(TINYINT|SMALLINT|MEDIUMINT|INT|BIGINT)\(\d+\)").unwrap();
+    }
+
+    let x = x.to_uppercase();
+    if x.starts_with("TINYINT(1)") {
+        return x;
+    }
+    INT_WIDTH.replace(&x, "$1").to_string()
+}
+
+// Postgres `format_type` => type name in create statements.
+fn get_postgres_type_name(x: &str) -> String {
+    lazy_static! {
+        static ref WITH_SIZE: Regex = Regex::new(r"^(character varying|numeric)\((\d+)(,0)?\)$").unwrap();
+    }
+
+    if let Some(c) = WITH_SIZE.captures(x) {
+        let t = if &c[1] == "numeric" { "NUMERIC" } else { "VARCHAR" };
+        return format!("{}({})", t, &c[2]);
+    }
+
+    match x {
+        "integer" => "INT".into(),
+        "timestamp without time zone" => "TIMESTAMP".into(),
+        _ => x.to_uppercase()
+    }
+}
+
+
+// Notifications sent from inside a tx; consumers only see them when the tx commits.
+// - Postgres: `pg_notify(channel, payload)`, received with `LISTEN channel`.
+//      - Postgres limits the payload to 8000 bytes.
+// - Engines without notifications (SQLite, MySQL): the payload is inserted into `changes_table`.
+//      - Consumers poll with `WHERE change_id > last_change_id`; `change_id` always increases.
+//      - Only the last `keep_rows` rows are kept.
+#[derive(Debug, Clone)]
+pub struct Notify {
+    pub channel: String,
+    pub changes_table: String,
+    pub keep_rows: i64,
+}
+
+impl Notify {
+    pub fn get_changes_table(&self) -> Table {
+        let mut change_id = Col::new("change_id", RustType::I64, false);
+        change_id.primary = true;
+
+        let mut insert_ts = Col::new("insert_ts", RustType::DT3, false);
+        insert_ts.insert_ts = true;
+
+        let mut t = Table {
+            struct_name: "Change".into(),
+            name: self.changes_table.clone(),
+            cols: vec![
+                change_id,
+                Col::new("channel", RustType::String, false),
+                Col::new("payload", RustType::Value, false),
+                insert_ts,
+            ],
+            indexes: vec![],
+            foreign_keys: vec![],
+            static_sql_strings: None,
+        };
+        t.create_static_strings();
+        t
+    }
+
+    // For DBs created before notifications were enabled (an app includes `get_changes_table` in the target schema of new DBs).
+    pub fn create_changes_table_if_not_exists(&self, utx: &mut UniTx) {
+        let t = self.get_changes_table();
+
+        match utx {
+            UniTx::Rusqlite(_) => utx.exec_one(&<Table as ToSQLString<SQLite>>::get_create_table(&t).create),
+            UniTx::MySQL(_) => utx.exec_one(&<Table as ToSQLString<MySQL>>::get_create_table(&t).create),
+            UniTx::Postgres(_) => {}
+            UniTx::PlaceholderLibA(_) => unreachable!()
+        }
+    }
+}
+
+impl UniTx<'_> {
+    // Returns the `change_id` when written to the changes table.
+    pub fn notify(&mut self, n: &Notify, payload: &str) -> Option<i64> {
+        let insert = format!("INSERT INTO {} (channel, payload, insert_ts) VALUES (?, ?, {})", &n.changes_table, match self {
+            UniTx::Rusqlite(_) => "STRFTIME('%Y-%m-%d %H:%M:%f', 'NOW')",
+            _ => "UTC_TIMESTAMP(3)"
+        });
+        let delete = format!("DELETE FROM {} WHERE change_id <= ?", &n.changes_table);
+
+        let change_id = match self {
+            UniTx::Rusqlite(tx) => {
+                tx.prepare_cached(&insert).unwrap().execute(params![&n.channel, payload]).unwrap();
+                tx.last_insert_rowid()
+            }
+            UniTx::MySQL(tx) => {
+                tx.exec_drop(&insert, (&n.channel, payload)).unwrap();
+                i64::try_from(tx.last_insert_id().unwrap()).unwrap()
+            }
+            UniTx::Postgres(tx) => {
+                tx.execute("SELECT pg_notify($1, $2)", &[&n.channel, &payload]).unwrap();
+                return None;
+            }
+            UniTx::PlaceholderLibA(_) => unreachable!()
+        };
+
+        let oldest_kept = change_id - n.keep_rows;
+        if oldest_kept > 0 {
+            match self {
+                UniTx::Rusqlite(tx) => {
+                    tx.prepare_cached(&delete).unwrap().execute(params![oldest_kept]).unwrap();
+                }
+                UniTx::MySQL(tx) => {
+                    tx.exec_drop(&delete, (oldest_kept,)).unwrap();
+                }
+                _ => {}
+            }
+        }
+
+        Some(change_id)
+    }
+}
+
+
+// Views created alongside the tables.
+// - Not part of an app's target schema, so that views can be enabled for an existing DB.
+// - The version of each created view is stored in `versions_table`; a view is (re)created when it does not exist or its version differs.
+//      - Views that are in `versions_table` but no longer in `views` are dropped.
+// - Views must only select from tables (not other views), so they can be replaced in any order.
+// - A view with the same columns is replaced in place, so views created by users that select from it are kept.
+//      - Postgres cannot drop a view that other views depend on; when the columns change this returns an error naming them (they are not dropped with `CASCADE`).
+#[derive(Debug, Clone)]
+pub struct Views {
+    pub versions_table: String,
+    pub views: Vec<View>,
+}
+
+impl Views {
+    pub fn get_versions_table(&self) -> Table {
+        let mut view_id = Col::new("view_id", RustType::I64, false);
+        view_id.primary = true;
+
+        let mut name = Col::new("name", RustType::String, false);
+        name.is_used_in_index = true;
+
+        Table {
+            struct_name: "ViewVersion".into(),
+            name: self.versions_table.clone(),
+            cols: vec![
+                view_id,
+                name,
+                Col::new("version", RustType::I64, false),
+            ],
+            indexes: vec![],
+            foreign_keys: vec![],
+            static_sql_strings: None,
+        }
+    }
+
+    // Returns the names of the views that were created or replaced.
+    pub fn create_or_replace(&self, utx: &mut UniTx) -> Result<Vec<String>, String> {
+        let t = self.get_versions_table();
+        let create = match utx {
+            UniTx::Rusqlite(_) => <Table as ToSQLString<SQLite>>::get_create_table(&t).create,
+            UniTx::MySQL(_) => <Table as ToSQLString<MySQL>>::get_create_table(&t).create,
+            UniTx::Postgres(_) => <Table as ToSQLString<Postgres>>::get_create_table(&t).create,
+            UniTx::PlaceholderLibA(_) => unreachable!()
+        };
+        utx.exec_one(&create);
+
+        let versions = utx.get_view_versions(&self.versions_table);
+        let existing = utx.get_views();
+
+        for (name, _) in &versions {
+            if !self.views.iter().any(|v| &v.name == name) {
+                if existing.contains(name) {
+                    utx.drop_view(name)?;
+                }
+                utx.set_view_version(&self.versions_table, name, None);
+            }
+        }
+
+        let mut changed = vec![];
+        for v in &self.views {
+            if existing.contains(&v.name) && versions.get(&v.name) == Some(&v.version) {
+                continue;
+            }
+
+            match utx {
+                UniTx::Rusqlite(_) => {
+                    // SQLite views are bound when queried, so dependent views are not checked.
+                    utx.exec_one(&format!("DROP VIEW IF EXISTS {}", &v.name));
+                    utx.exec_one(&format!("CREATE VIEW {} AS\n{}", &v.name, &v.sqlite));
+                }
+                UniTx::MySQL(_) => {
+                    utx.exec_one(&format!("CREATE OR REPLACE VIEW {} AS\n{}", &v.name, &v.mysql));
+                }
+                UniTx::Postgres(_) => {
+                    // `CREATE OR REPLACE` fails when the columns change.
+                    if existing.contains(&v.name) && utx.get_query_cols(&format!("SELECT * FROM {}", &v.name)) != utx.get_query_cols(&v.postgres) {
+                        utx.drop_view(&v.name)?;
+                    }
+                    utx.exec_one(&format!("CREATE OR REPLACE VIEW {} AS\n{}", &v.name, &v.postgres));
+                }
+                UniTx::PlaceholderLibA(_) => unreachable!()
+            }
+
+            utx.set_view_version(&self.versions_table, &v.name, Some(v.version));
+            changed.push(v.name.clone());
+        }
+
+        Ok(changed)
+    }
+}
+
+impl UniTx<'_> {
+    // View names in the active schema.
+    pub fn get_views(&mut self) -> Vec<String> {
+        let target_schema = self.get_active_schema();
+
+        match self {
+            UniTx::Rusqlite(tx) => {
+                let mut stmt = tx.prepare_cached("SELECT name FROM sqlite_master WHERE type='view'").unwrap();
+                let rows = stmt.query_map(NO_PARAMS, |r| {
+                    r.get(0)
+                }).unwrap();
+
+                rows.map(|x| x.unwrap()).collect()
+            }
+            UniTx::MySQL(tx) => {
+                tx.query("SELECT table_name FROM information_schema.views WHERE table_schema = DATABASE()").unwrap()
+            }
+            UniTx::Postgres(tx) => {
+                let schema = target_schema.expect("Postgres should have an active schema readable from search_path prefix.");
+                let rows = tx.query("SELECT viewname::text FROM pg_catalog.pg_views WHERE schemaname = $1", &[&schema]).unwrap();
+                rows.iter().map(|x| x.get(0)).collect::<Vec<String>>()
+            }
+            UniTx::PlaceholderLibA(_) => {
+                unimplemented!()
+            }
+        }
+    }
+
+    // Postgres: (name, type) of each column a query returns, without running it.
+    fn get_query_cols(&mut self, q: &str) -> Vec<(String, String)> {
+        match self {
+            UniTx::Postgres(tx) => {
+                let stmt = tx.prepare(q).unwrap();
+                stmt.columns().iter().map(|c| (c.name().to_string(), c.type_().name().to_string())).collect()
+            }
+            _ => unreachable!()
+        }
+    }
+
+    // Views in the active schema that select from `name`.
+    fn get_dependent_views(&mut self, name: &str) -> Vec<String> {
+        let target_schema = self.get_active_schema();
+
+        match self {
+            UniTx::Postgres(tx) => {
+                let schema = target_schema.expect("Postgres should have an active schema readable from search_path prefix.");
+                let q = "SELECT DISTINCT n2.nspname::text || '.' || dv.relname::text
+                    FROM pg_catalog.pg_depend d
+                    JOIN pg_catalog.pg_rewrite r ON r.oid = d.objid
+                    JOIN pg_catalog.pg_class dv ON dv.oid = r.ev_class
+                    JOIN pg_catalog.pg_namespace n2 ON n2.oid = dv.relnamespace
+                    JOIN pg_catalog.pg_class v ON v.oid = d.refobjid
+                    JOIN pg_catalog.pg_namespace n ON n.oid = v.relnamespace
+                    WHERE n.nspname = $1 AND v.relname = $2 AND dv.oid <> v.oid
+                    ORDER BY 1";
+                let rows = tx.query(q, &[&schema, &name]).unwrap();
+                rows.iter().map(|x| x.get(0)).collect()
+            }
+            _ => vec![]
+        }
+    }
+
+    fn drop_view(&mut self, name: &str) -> Result<(), String> {
+        let deps = self.get_dependent_views(name);
+        if !deps.is_empty() {
+            return Err(format!("View {} cannot be replaced as its columns changed and other views depend on it: {}. Drop them, and create them again after the views are replaced.", name, deps.join(", ")));
+        }
+
+        self.exec_one(&format!("DROP VIEW IF EXISTS {}", name));
+        Ok(())
+    }
+
+    fn get_view_versions(&mut self, versions_table: &str) -> HashMap<String, i64> {
+        let q = format!("SELECT name, version FROM {}", versions_table);
+
+        match self {
+            UniTx::Rusqlite(tx) => {
+                let mut stmt = tx.prepare(&q).unwrap();
+                let rows = stmt.query_map(NO_PARAMS, |r| {
+                    Ok((r.get(0)?, r.get(1)?))
+                }).unwrap();
+
+                rows.map(|x| x.unwrap()).collect()
+            }
+            UniTx::MySQL(tx) => {
+                let rows: Vec<(String, i64)> = tx.query(q).unwrap();
+                rows.into_iter().collect()
+            }
+            UniTx::Postgres(tx) => {
+                let rows = tx.query(q.as_str(), &[]).unwrap();
+                rows.iter().map(|x| (x.get(0), x.get(1))).collect()
+            }
+            UniTx::PlaceholderLibA(_) => unreachable!()
+        }
+    }
+
+    // `None` removes the view from the versions table.
+    fn set_view_version(&mut self, versions_table: &str, name: &str, version: Option<i64>) {
+        let delete = format!("DELETE FROM {} WHERE name = ?", versions_table);
+        let insert = format!("INSERT INTO {} (name, version) VALUES (?, ?)", versions_table);
+
+        match self {
+            UniTx::Rusqlite(tx) => {
+                tx.execute(&delete, params![name]).unwrap();
+                if let Some(v) = version {
+                    tx.execute(&insert, params![name, v]).unwrap();
+                }
+            }
+            UniTx::MySQL(tx) => {
+                tx.exec_drop(&delete, (name,)).unwrap();
+                if let Some(v) = version {
+                    tx.exec_drop(&insert, (name, v)).unwrap();
+                }
+            }
+            UniTx::Postgres(tx) => {
+                tx.execute(delete.replace("?", "$1").as_str(), &[&name]).unwrap();
+                if let Some(v) = version {
+                    tx.execute(format!("INSERT INTO {} (name, version) VALUES ($1, $2)", versions_table).as_str(), &[&name, &v]).unwrap();
+                }
+            }
+            UniTx::PlaceholderLibA(_) => unreachable!()
+        }
+    }
+}
@@ -291,7 +291,7 @@ mod test {
             engine: Engine::SQLite(SQLiteCreate {
                 file: f.clone()
             })
-        });
+        }).unwrap();
 
         uc.ensure_schema_and_tables_exist_and_writable::<DbTest>();
 
@@ -464,6 +464,40 @@ mod test {
         }
     }
 
+    // Invalid configs are returned as errors before connecting.
+    #[test]
+    fn test_new_invalid_config() {
+        let create = MySQLCreate {
+            addr: Addr::IP(IPPort { ip: "127.0.0.1".into(), port: 3306 }),
+            user: Some("root".into()),
+            pass: Some("my-secret-pw".into()),
+            db_name: None,
+            schema_name: None,
+            tls: None,
+        };
+        let res = UniCon::new(&UniConCreate { engine: Engine::MySQL(create) });
+        assert!(matches!(res, Err(unicon::error::UniConError::Config(_))));
+
+        let f = std::env::temp_dir().join(get_unique_id()).join("a.sqlite").to_str().unwrap().to_string();
+        let res = UniCon::new(&UniConCreate { engine: Engine::SQLite(SQLiteCreate { file: f }) });
+        assert!(matches!(res, Err(unicon::error::UniConError::SQLite(_))));
+    }
+
+    // Tables created in a tx are gone after a rollback (E.g. a dry run).
+    #[test]
+    fn test_create_tables_rollback() {
+        let f = get_temp_file(get_unique_id() + ".sqlite").unwrap();
+        let mut uc = UniCon::new(&UniConCreate { engine: Engine::SQLite(SQLiteCreate { file: f }) }).unwrap();
+        let target = uc.get_target_schema_with::<DbTest>(&vec![], &vec![]);
+
+        let mut utx = uc.tx_open().unwrap();
+        utx.create_schema_and_tables(&target, false);
+        assert_eq!(utx.get_tables().len(), target.tables.len());
+        utx.tx_rollback().unwrap();
+
+        assert!(!uc.schema_exists_with::<DbTest>(&vec![], &vec![]).unwrap());
+    }
+
     #[test]
     #[should_panic]
     fn test_drop_all_on_empty_db() {
@@ -557,7 +591,7 @@ mod test {
                 engine: Engine::SQLite(SQLiteCreate {
                     file: f.clone()
                 })
-            });
+            }).unwrap();
             create_schema(&mut uc, "sqlite", "local", &f);
             x.push(uc);
         }
@@ -580,7 +614,7 @@ mod test {
                     schema_name: schema_local.clone().into(),
                     tls: None,
                 })
-            });
+            }).unwrap();
             create_schema(&mut uc, "mysql", "local", &schema_local);
             x.push(uc);
 
@@ -600,7 +634,7 @@ mod test {
                         verify_server_cert_hostname: None,
                     }),
                 })
-            });
+            }).unwrap();
             create_schema(&mut uc, "mysql", "local-tls", &schema_name);
             x.push(uc);
 
@@ -643,7 +677,7 @@ mod test {
                     // schema_name: "abc".to_string().into()
                     tls: None,
                 })
-            });
+            }).unwrap();
             create_schema(&mut uc, "postgres", "local", &schema_local);
             x.push(uc);
 
@@ -669,7 +703,7 @@ mod test {
                         verify_server_cert_hostname: None,
                     }),
                 })
-            });
+            }).unwrap();
             create_schema(&mut uc, "postgres", "local-tls", &schema_name);
             x.push(uc);
 
@@ -692,7 +726,7 @@ mod test {
                         verify_server_cert_hostname: None,
                     }),
                 })
-            });
+            }).unwrap();
             create_schema(&mut uc, "postgres", "remote-tls-supabase", &schema_name);
             x.push(uc);
 
@@ -742,7 +776,7 @@ mod test {
                         verify_server_cert_hostname: None,
                     }),
                 })
-            });
+            }).unwrap();
             create_schema(&mut uc, "postgres", "remote-tls-cockroachlabs", &schema_name);
             x.push(uc);
         }
@@ -29,7 +29,7 @@ use syn::export::TokenStream2;
 use unicon::table::*;
 
 
-#[proc_macro_derive(Insert, attributes(table_name_plural, primary_key, unique, index, col_type, skip, update_ts, insert_ts, fk))]
+#[proc_macro_derive(Insert, attributes(table_name_plural, extra_cols_prefix, primary_key, unique, index, col_type, skip, optional_col, flatten, update_ts, insert_ts, fk))]
 pub fn insert(input: TokenStream) -> TokenStream {
     let ast = syn::parse(input).unwrap();
     impl_insert(&ast)
@@ -411,6 +411,25 @@ fn cols_to_vec_mysql(cols: Vec<Col>) -> Vec<TokenStream2> {
 }
 
 
+// Columns that are not fields of the struct:
+// - `#[flatten(...)]`: generated columns.
+// - `#[extra_cols_prefix("x_")]`: runtime added columns.
+fn get_extra_cols_match(ast: &syn::DeriveInput, t: &Table) -> TokenStream2 {
+    let flat: Vec<String> = t.cols.iter().flat_map(|c| c.get_flat_cols()).map(|(name, _)| name).collect();
+    let flat_match = if flat.is_empty() { quote! {} } else { quote! { #( #flat )|* => {}, } };
+
+    let prefix_match = match Table::get_extra_cols_prefix(ast) {
+        Some(p) => quote! { x if x.starts_with(#p) => {}, },
+        None => quote! {}
+    };
+
+    quote! {
+        #flat_match
+        #prefix_match
+    }
+}
+
+
 fn impl_sqlite_func_rusqlite(ast: &syn::DeriveInput) -> TokenStream {
     let s_name = format_ident!("{}", &ast.ident.to_string());
 
@@ -423,6 +442,7 @@ fn impl_sqlite_func_rusqlite(ast: &syn::DeriveInput) -> TokenStream {
     let cols_all = cols_to_vec_sqlite(t.cols_not_skipped());
     let cols_writable = cols_to_vec_sqlite(t.cols_writable_only());
 
+    let extra_cols_match = get_extra_cols_match(ast, &t);
     let field_matches = t.cols.iter().map(|x| {
         let name = &x.name;
         let field = format_ident!("{}", x.name_raw);
@@ -461,6 +481,7 @@ fn impl_sqlite_func_rusqlite(ast: &syn::DeriveInput) -> TokenStream {
                 for col_name in r.column_names() {
                     match col_name {
                         #( #field_matches )*
+                        #extra_cols_match
                         x => panic!(format!("SQL table field does not exist in Rust struct {}", x))
                     }
                 }
@@ -521,6 +542,7 @@ fn impl_mysql_func_x(ast: &syn::DeriveInput) -> TokenStream {
     let cols_writable = cols_to_vec_mysql(t.cols_writable_only());
 
 
+    let extra_cols_match = get_extra_cols_match(ast, &t);
     let field_matches = t.cols.iter().map(|x| {
         let name = &x.name;
         let field = format_ident!("{}", x.name_raw);
@@ -568,6 +590,7 @@ fn impl_mysql_func_x(ast: &syn::DeriveInput) -> TokenStream {
                     let col_name: &str = cow.deref();
                     match col_name {
                         #( #field_matches )*
+                        #extra_cols_match
                         x => panic!(format!("SQL table field does not exist in Rust struct {}", x))
                     }
                 }
@@ -614,6 +637,7 @@ fn impl_postgres_func_x(ast: &syn::DeriveInput) -> TokenStream {
     let cols_all = cols_to_vec_postgres(t.cols_not_skipped());
     let cols_writable = cols_to_vec_postgres(t.cols_writable_only());
 
+    let extra_cols_match = get_extra_cols_match(ast, &t);
     let field_matches = t.cols.iter().map(|x| {
         let name = &x.name;
         let field = format_ident!("{}", x.name_raw);
@@ -659,6 +683,7 @@ fn impl_postgres_func_x(ast: &syn::DeriveInput) -> TokenStream {
 
                     match col_name {
                         #( #field_matches )*
+                        #extra_cols_match
                         x => panic!(format!("SQL table field does not exist in Rust struct {}", x))
                     }
                 }
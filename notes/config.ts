@@ -9,7 +9,11 @@ interface Config {
 }
 
 type Cmd =
-    { fn: "download", args: DownloadArgs };
+    { fn: "download", args: DownloadArgs } |
+    { fn: "replay", args: ReplayArgs } |
+    { fn: "events_export", args: EventsExportArgs } |
+    { fn: "events_import", args: EventsImportArgs } |
+    { fn: "snapshot", args: SnapshotArgs };
 
 interface DownloadArgs {
     from: From,
@@ -17,6 +21,43 @@ interface DownloadArgs {
     options?: Options
 }
 
+interface ReplayArgs {
+    // A directory of `.json` event files, or a `.jsonl` file (one event per line).
+    events: string,
+    to: To,
+    // Only needed when the DB was created with `options.created_gte`.
+    from?: From,
+    // Events per transaction, defaults to 500.
+    batch_size?: number,
+    options?: Options
+}
+
+interface EventsExportArgs {
+    db: To,
+    // Gzipped JSONL segments, one per UTC day (`events-2021-01-31.jsonl.gz`).
+    dir: string,
+    // After exporting, delete exported events older than this from `db`.
+    retention_days?: number,
+    options?: Options
+}
+
+interface EventsImportArgs {
+    dir: string,
+    to: To,
+    options?: Options
+}
+
+interface SnapshotArgs {
+    db: To,
+    // A date (`2021-01-31`), an RFC 3339 date time, or a duration before now (`30days`).
+    as_of: string,
+    // Must be empty.
+    to: To,
+    // An `events_export` directory, for events deleted from `db` by `retention_days`.
+    events_dir?: string,
+    options?: Options
+}
+
 type From =
     { stripe: Stripe };
 